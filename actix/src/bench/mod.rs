@@ -66,4 +66,7 @@ impl BenchmarkFactory for Factory {
     fn atomic_broadcast(&self) -> Result<Box<dyn AbstractDistributedBenchmark>, NotImplementedError> {
         Err(NotImplementedError::FutureWork)
     }
+    fn replicated_kv(&self) -> Result<Box<dyn AbstractDistributedBenchmark>, NotImplementedError> {
+        Err(NotImplementedError::FutureWork)
+    }
 }
@@ -72,4 +72,7 @@ class BenchmarkRunnerImpl extends BenchmarkRunnerGrpc.BenchmarkRunner {
   override def atomicBroadcast(request: AtomicBroadcastRequest): Future[TestResult] = {
     Future.successful(NotImplemented());
   }
+  override def replicatedKV(request: ReplicatedKVRequest): Future[TestResult] = {
+    Future.successful(NotImplemented());
+  }
 }
@@ -70,4 +70,7 @@ class TypedBenchmarkRunnerImpl extends BenchmarkRunnerGrpc.BenchmarkRunner {
   override def atomicBroadcast(request: AtomicBroadcastRequest): Future[TestResult] = {
     Future.successful(NotImplemented());
   }
+  override def replicatedKV(request: ReplicatedKVRequest): Future[TestResult] = {
+    Future.successful(NotImplemented());
+  }
 }
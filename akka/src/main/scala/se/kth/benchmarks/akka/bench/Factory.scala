@@ -13,4 +13,5 @@ object Factory extends BenchmarkFactory {
   override def chameneos(): se.kth.benchmarks.Benchmark = Chameneos;
   override def fibonacci: se.kth.benchmarks.Benchmark = Fibonacci;
   override def atomicBroadcast(): DistributedBenchmark = ???
+  override def replicatedKV(): DistributedBenchmark = ???
 }
@@ -9,6 +9,33 @@ use std::fmt;
 use std::sync::{Arc, Weak};
 use synchronoise::CountdownEvent;
 
+/// Compares the distributed result against the sequential Floyd-Warshall `reference`.
+fn check_result(reference: &Graph<f64>, result: Option<Graph<f64>>) -> Result<(), BenchmarkError> {
+    let result = result.ok_or_else(|| {
+        BenchmarkError::ValidationFailed("No APSP result was assembled!".to_string())
+    })?;
+    if result.num_nodes() != reference.num_nodes() {
+        return Err(BenchmarkError::ValidationFailed(format!(
+            "APSP result has {} nodes, but the graph has {}",
+            result.num_nodes(),
+            reference.num_nodes()
+        )));
+    }
+    for i in 0usize..reference.num_nodes() {
+        for j in 0usize..reference.num_nodes() {
+            let expected = reference.get(i, j);
+            let actual = result.get(i, j);
+            if actual != expected {
+                return Err(BenchmarkError::ValidationFailed(format!(
+                    "APSP distance ({}, {}) was computed as {}, but should be {}",
+                    i, j, actual, expected
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
 pub mod actor_apsp {
     use super::*;
 
@@ -20,7 +47,7 @@ pub mod actor_apsp {
         type Instance = AllPairsShortestPathI;
 
         fn msg_to_conf(msg: Box<dyn (::protobuf::Message)>) -> Result<Self::Conf, BenchmarkError> {
-            downcast_msg!(msg; APSPRequest)
+            downcast_kompact_msg!(msg; APSPRequest)
         }
 
         fn new_instance() -> Self::Instance {
@@ -34,8 +61,10 @@ pub mod actor_apsp {
         num_nodes: Option<usize>,
         block_size: Option<usize>,
         system: Option<KompactSystem>,
+        system_config: Option<String>,
         manager: Option<Arc<Component<ManagerActor>>>,
         graph: Option<Arc<Graph<f64>>>,
+        reference: Option<Graph<f64>>,
         latch: Option<Arc<CountdownEvent>>,
     }
 
@@ -45,8 +74,10 @@ pub mod actor_apsp {
                 num_nodes: None,
                 block_size: None,
                 system: None,
+                system_config: None,
                 manager: None,
                 graph: None,
+                reference: None,
                 latch: None,
             }
         }
@@ -66,8 +97,11 @@ pub mod actor_apsp {
                 .try_into()
                 .expect("Block Size should fit into usize");
             self.block_size = Some(block_size);
-            let system = crate::kompact_system_provider::global().new_system("apsp");
-            let manager = system.create(|| ManagerActor::with(block_size));
+            let provider = crate::kompact_system_provider::for_request(c.get_system_overrides());
+            self.system_config = Some(provider.describe());
+            let system = provider.new_system("apsp");
+            let keep_result = c.validate;
+            let manager = system.create(|| ManagerActor::with(block_size, keep_result));
             let manager_f = system.start_notify(&manager);
             manager_f
                 .wait_timeout(Duration::from_millis(1000))
@@ -75,9 +109,18 @@ pub mod actor_apsp {
             self.manager = Some(manager);
             self.system = Some(system);
             let graph = generate_graph(num_nodes);
+            if c.validate {
+                let mut reference = graph.clone();
+                reference.compute_floyd_warshall();
+                self.reference = Some(reference);
+            }
             self.graph = Some(Arc::new(graph));
         }
 
+        fn system_config(&self) -> Option<String> {
+            self.system_config.clone()
+        }
+
         fn prepare_iteration(&mut self) -> () {
             self.latch = Some(Arc::new(CountdownEvent::new(1)));
         }
@@ -99,6 +142,21 @@ pub mod actor_apsp {
             }
         }
 
+        fn validate_iteration(&mut self) -> Result<(), BenchmarkError> {
+            if let Some(ref reference) = self.reference {
+                if let Some(ref manager) = self.manager {
+                    let result = manager.on_definition(|m| m.last_result.take());
+                    check_result(reference, result)
+                } else {
+                    Err(BenchmarkError::ValidationFailed(
+                        "No APSP manager to take the result from!".to_string(),
+                    ))
+                }
+            } else {
+                Ok(())
+            }
+        }
+
         fn cleanup_iteration(&mut self, last_iteration: bool, _exec_time_millis: f64) -> () {
             let system = self.system.take().unwrap();
 
@@ -109,6 +167,7 @@ pub mod actor_apsp {
                         .expect("Manager never died!");
                 }
                 self.graph = None;
+                self.reference = None;
 
                 system
                     .shutdown()
@@ -160,12 +219,14 @@ pub mod actor_apsp {
         latch: Option<Arc<CountdownEvent>>,
         assembly: Option<Vec<Vec<Arc<Block<f64>>>>>,
         missing_blocks: Option<usize>,
+        keep_result: bool,
+        last_result: Option<Graph<f64>>,
         #[cfg(test)]
         result: Option<Graph<f64>>,
     }
 
     impl ManagerActor {
-        fn with(block_size: usize) -> ManagerActor {
+        fn with(block_size: usize, keep_result: bool) -> ManagerActor {
             ManagerActor {
                 ctx: ComponentContext::uninitialised(),
                 block_size,
@@ -173,6 +234,8 @@ pub mod actor_apsp {
                 latch: None,
                 assembly: None,
                 missing_blocks: None,
+                keep_result,
+                last_result: None,
                 #[cfg(test)]
                 result: None,
             }
@@ -276,7 +339,6 @@ pub mod actor_apsp {
                                         .collect()
                                 })
                                 .collect();
-                            #[allow(unused_variables)] // used during test
                             let result = Graph::assemble_from_blocks(blocks_owned);
                             self.latch
                                 .take()
@@ -289,6 +351,9 @@ pub mod actor_apsp {
                             self.missing_blocks = None;
                             #[cfg(test)]
                             assert_eq!(self.result.take().unwrap(), result, "Wrong APSP result!");
+                            if self.keep_result {
+                                self.last_result = Some(result);
+                            }
                         } else {
                             debug!(
                                 self.ctx.log(),
@@ -451,7 +516,7 @@ pub mod component_apsp {
         type Instance = AllPairsShortestPathI;
 
         fn msg_to_conf(msg: Box<dyn (::protobuf::Message)>) -> Result<Self::Conf, BenchmarkError> {
-            downcast_msg!(msg; APSPRequest)
+            downcast_kompact_msg!(msg; APSPRequest)
         }
 
         fn new_instance() -> Self::Instance {
@@ -465,8 +530,10 @@ pub mod component_apsp {
         num_nodes: Option<usize>,
         block_size: Option<usize>,
         system: Option<KompactSystem>,
+        system_config: Option<String>,
         manager: Option<Arc<Component<ManagerActor>>>,
         graph: Option<Arc<Graph<f64>>>,
+        reference: Option<Graph<f64>>,
         latch: Option<Arc<CountdownEvent>>,
     }
 
@@ -476,8 +543,10 @@ pub mod component_apsp {
                 num_nodes: None,
                 block_size: None,
                 system: None,
+                system_config: None,
                 manager: None,
                 graph: None,
+                reference: None,
                 latch: None,
             }
         }
@@ -497,8 +566,11 @@ pub mod component_apsp {
                 .try_into()
                 .expect("Block Size should fit into usize");
             self.block_size = Some(block_size);
-            let system = crate::kompact_system_provider::global().new_system("apsp");
-            let manager = system.create(|| ManagerActor::with(block_size));
+            let provider = crate::kompact_system_provider::for_request(c.get_system_overrides());
+            self.system_config = Some(provider.describe());
+            let system = provider.new_system("apsp");
+            let keep_result = c.validate;
+            let manager = system.create(|| ManagerActor::with(block_size, keep_result));
             let manager_f = system.start_notify(&manager);
             manager_f
                 .wait_timeout(Duration::from_millis(1000))
@@ -506,9 +578,18 @@ pub mod component_apsp {
             self.manager = Some(manager);
             self.system = Some(system);
             let graph = generate_graph(num_nodes);
+            if c.validate {
+                let mut reference = graph.clone();
+                reference.compute_floyd_warshall();
+                self.reference = Some(reference);
+            }
             self.graph = Some(Arc::new(graph));
         }
 
+        fn system_config(&self) -> Option<String> {
+            self.system_config.clone()
+        }
+
         fn prepare_iteration(&mut self) -> () {
             self.latch = Some(Arc::new(CountdownEvent::new(1)));
         }
@@ -536,6 +617,21 @@ pub mod component_apsp {
             }
         }
 
+        fn validate_iteration(&mut self) -> Result<(), BenchmarkError> {
+            if let Some(ref reference) = self.reference {
+                if let Some(ref manager) = self.manager {
+                    let result = manager.on_definition(|m| m.last_result.take());
+                    check_result(reference, result)
+                } else {
+                    Err(BenchmarkError::ValidationFailed(
+                        "No APSP manager to take the result from!".to_string(),
+                    ))
+                }
+            } else {
+                Ok(())
+            }
+        }
+
         fn cleanup_iteration(&mut self, last_iteration: bool, _exec_time_millis: f64) -> () {
             let system = self.system.take().unwrap();
 
@@ -546,6 +642,7 @@ pub mod component_apsp {
                         .expect("Manager never died!");
                 }
                 self.graph = None;
+                self.reference = None;
 
                 system
                     .shutdown()
@@ -611,12 +708,14 @@ pub mod component_apsp {
         latch: Option<Arc<CountdownEvent>>,
         assembly: Option<Vec<Vec<Arc<Block<f64>>>>>,
         missing_blocks: Option<usize>,
+        keep_result: bool,
+        last_result: Option<Graph<f64>>,
         #[cfg(test)]
         result: Option<Graph<f64>>,
     }
 
     impl ManagerActor {
-        fn with(block_size: usize) -> ManagerActor {
+        fn with(block_size: usize, keep_result: bool) -> ManagerActor {
             ManagerActor {
                 ctx: ComponentContext::uninitialised(),
                 manager_port: ProvidedPort::uninitialised(),
@@ -625,6 +724,8 @@ pub mod component_apsp {
                 latch: None,
                 assembly: None,
                 missing_blocks: None,
+                keep_result,
+                last_result: None,
                 #[cfg(test)]
                 result: None,
             }
@@ -725,7 +826,6 @@ pub mod component_apsp {
                                         .collect()
                                 })
                                 .collect();
-                            #[allow(unused_variables)] // used during test
                             let result = Graph::assemble_from_blocks(blocks_owned);
                             self.latch
                                 .take()
@@ -738,6 +838,9 @@ pub mod component_apsp {
                             self.missing_blocks = None;
                             #[cfg(test)]
                             assert_eq!(self.result.take().unwrap(), result, "Wrong APSP result!");
+                            if self.keep_result {
+                                self.last_result = Some(result);
+                            }
                         } else {
                             debug!(
                                 self.ctx.log(),
@@ -1,12 +1,32 @@
 extern crate raft as tikv_raft;
 
 use super::super::*;
-use super::client::{Client, LocalClientMessage};
-use super::paxos::{PaxosComp, ReconfigurationPolicy as PaxosReconfigurationPolicy};
+use super::client::{
+    Arrivals, Client, LocalClientMessage, MetaResults, OpenLoop, PayloadSize, ThroughputSeries,
+};
+use super::fault::{FaultSchedule, FaultTarget, NetworkRule};
+use super::handover::TransferSchedule;
+use super::kv::{KvConfig, ReadMode};
+use super::messages::{Clients, RemoteWorkload, CLIENT_ID_SHIFT};
+use super::paxos::{
+    PaxosComp, PaxosOptions, Quorums, ReconfigurationPolicy as PaxosReconfigurationPolicy,
+};
 use super::raft::{RaftComp, ReconfigurationPolicy as RaftReconfigurationPolicy};
-use super::storage::paxos::{MemorySequence, MemoryState};
+use super::reconfig::ReconfigSchedule;
+use super::remote_client::{ClientHost, ClientHostLocal, RemoteClients, RemoteClientsMsg};
+use super::results::{
+    append_line, file_name, latencies_json, throughput_json, IterationRecord, LatencySummary,
+};
+use super::storage::paxos::{DiskSequence, DiskState, MemorySequence, MemoryState};
+use super::storage::{CompactionPolicy, DiskConfig, FsyncPolicy, StorageKind};
+use crate::kompact_system_provider::{
+    is_missing, read_optional, read_positive, BufferSettings, KompactSystemProvider, WithOverrides,
+};
 use crate::partitioning_actor::IterationControlMsg;
-use benchmark_suite_shared::kompics_benchmarks::benchmarks::AtomicBroadcastRequest;
+use benchmark_suite_shared::kompics_benchmarks::benchmarks::{
+    AtomicBroadcastRequest, PaxosOptions as PaxosOptionsPB,
+};
+use benchmark_suite_shared::test_utils::all_linearizable;
 use hashbrown::HashMap;
 use hdrhistogram::Histogram;
 use kompact::prelude::*;
@@ -15,35 +35,60 @@ use std::str::FromStr;
 use std::sync::Arc;
 use synchronoise::CountdownEvent;
 
-#[allow(unused_imports)]
 use super::storage::raft::DiskStorage;
-use crate::bench::atomic_broadcast::paxos::PaxosCompMsg;
+use crate::bench::atomic_broadcast::paxos::{PaxosCompMsg, PaxosStateTraits, SequenceTraits};
 use crate::bench::atomic_broadcast::raft::RaftCompMsg;
-use hocon::HoconLoader;
+use crate::bench::atomic_broadcast::storage::raft::RaftStorage;
+use hocon::{Hocon, HoconLoader};
 use kompact::net::buffers::BufferConfig;
-use std::fs::{create_dir_all, OpenOptions};
+use std::fs::{create_dir_all, File};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 use tikv_raft::storage::MemStorage;
 
-const CONFIG_PATH: &str = "./configs/atomic_broadcast.conf";
+pub(super) const CONFIG_PATH: &str = "./configs/atomic_broadcast.conf";
+const DEFAULT_META_RESULTS_PATH: &str = "./meta_results";
 const PAXOS_PATH: &str = "paxos_replica";
 const RAFT_PATH: &str = "raft_replica";
 const REGISTER_TIMEOUT: Duration = Duration::from_secs(5);
+const TCP_NODELAY: bool = true;
 
 #[derive(Debug, Clone)]
 pub struct ClientParams {
     algorithm: String,
     last_node_id: u64,
     reconfig_policy: String,
+    disk: Option<DiskConfig>, // None if the replicas keep their storage in memory
+    compaction: CompactionPolicy,
+    kv: Option<ReadMode>, // replicas apply the decided commands to a key-value store
+    paxos_options: PaxosOptions,
+    initial_leader: u64, // the node that should win the first election, 0 if any
+    config: ExperimentConfig,
 }
 
 impl ClientParams {
-    fn with(algorithm: String, last_node_id: u64, reconfig_policy: String) -> ClientParams {
+    fn with(
+        algorithm: String,
+        last_node_id: u64,
+        reconfig_policy: String,
+        disk: Option<DiskConfig>,
+        compaction: CompactionPolicy,
+        kv: Option<ReadMode>,
+        paxos_options: PaxosOptions,
+        initial_leader: u64,
+        config: ExperimentConfig,
+    ) -> ClientParams {
         ClientParams {
             algorithm,
             last_node_id,
             reconfig_policy,
+            disk,
+            compaction,
+            kv,
+            paxos_options,
+            initial_leader,
+            config,
         }
     }
 }
@@ -51,13 +96,20 @@ impl ClientParams {
 #[derive(Debug, Clone)]
 pub struct Done;
 
+/// The paths a benchmark client process gives the master: its replica and the host of its client components.
+#[derive(Debug, Clone)]
+pub struct ClientProcess {
+    replica: ActorPath,
+    client_host: ActorPath,
+}
+
 #[derive(Default)]
 pub struct AtomicBroadcast;
 
 impl DistributedBenchmark for AtomicBroadcast {
     type MasterConf = AtomicBroadcastRequest;
-    type ClientConf = ClientParams;
-    type ClientData = ActorPath;
+    type ClientConf = WithOverrides<ClientParams>;
+    type ClientData = ClientProcess;
     type Master = AtomicBroadcastMaster;
     type Client = AtomicBroadcastClient;
     const LABEL: &'static str = "AtomicBroadcast";
@@ -69,7 +121,7 @@ impl DistributedBenchmark for AtomicBroadcast {
     fn msg_to_master_conf(
         msg: Box<dyn (::protobuf::Message)>,
     ) -> Result<Self::MasterConf, BenchmarkError> {
-        downcast_msg!(msg; AtomicBroadcastRequest)
+        downcast_kompact_msg!(msg; AtomicBroadcastRequest)
     }
 
     fn new_client() -> Self::Client {
@@ -77,38 +129,115 @@ impl DistributedBenchmark for AtomicBroadcast {
     }
 
     fn str_to_client_conf(s: String) -> Result<Self::ClientConf, BenchmarkError> {
-        let split: Vec<_> = s.split(',').collect();
-        if split.len() != 3 {
-            Err(BenchmarkError::InvalidMessage(format!(
-                "String '{}' does not represent a client conf! Split length should be 3",
-                s
-            )))
-        } else {
-            let algorithm = split[0].to_lowercase();
-            let last_node_id = split[1].parse::<u64>().map_err(|e| {
-                BenchmarkError::InvalidMessage(format!(
-                    "String to ClientConf error: '{}' does not represent a node id: {:?}",
-                    split[1], e
+        WithOverrides::deserialise(s, |s| {
+            let split: Vec<_> = s.splitn(9, ',').collect();
+            if split.len() != 9 {
+                Err(BenchmarkError::InvalidMessage(format!(
+                    "String '{}' does not represent a client conf! Split length should be 9",
+                    s
+                )))
+            } else {
+                let algorithm = split[0].to_lowercase();
+                let last_node_id = split[1].parse::<u64>().map_err(|e| {
+                    BenchmarkError::InvalidMessage(format!(
+                        "String to ClientConf error: '{}' does not represent a node id: {:?}",
+                        split[1], e
+                    ))
+                })?;
+                let reconfig_policy = split[2].to_lowercase();
+                let storage = StorageKind::parse(split[3]).map_err(|e| {
+                    BenchmarkError::InvalidMessage(format!("String to ClientConf error: {}", e))
+                })?;
+                let compaction = CompactionPolicy::parse(split[4]).map_err(|e| {
+                    BenchmarkError::InvalidMessage(format!("String to ClientConf error: {}", e))
+                })?;
+                let kv = match split[5] {
+                    "none" => None,
+                    read_mode => Some(ReadMode::parse(read_mode).map_err(|e| {
+                        BenchmarkError::InvalidMessage(format!("String to ClientConf error: {}", e))
+                    })?),
+                };
+                let paxos_options = PaxosOptions::parse(split[6], last_node_id).map_err(|e| {
+                    BenchmarkError::InvalidMessage(format!("String to ClientConf error: {}", e))
+                })?;
+                let initial_leader = split[7].parse::<u64>().map_err(|e| {
+                    BenchmarkError::InvalidMessage(format!(
+                        "String to ClientConf error: '{}' does not represent a node id: {:?}",
+                        split[7], e
+                    ))
+                })?;
+                let config = ExperimentConfig::load(None, split[8]).map_err(|e| {
+                    BenchmarkError::InvalidMessage(format!("String to ClientConf error: {}", e))
+                })?;
+                let disk = match storage {
+                    StorageKind::Memory => None,
+                    StorageKind::Disk => Some(
+                        ExperimentConfig::load_disk_config(None, split[8]).map_err(|e| {
+                            BenchmarkError::InvalidMessage(format!(
+                                "String to ClientConf error: {}",
+                                e
+                            ))
+                        })?,
+                    ),
+                };
+                Ok(ClientParams::with(
+                    algorithm,
+                    last_node_id,
+                    reconfig_policy,
+                    disk,
+                    compaction,
+                    kv,
+                    paxos_options,
+                    initial_leader,
+                    config,
                 ))
-            })?;
-            let reconfig_policy = split[2].to_lowercase();
-            Ok(ClientParams::with(algorithm, last_node_id, reconfig_policy))
-        }
+            }
+        })
     }
 
     fn str_to_client_data(str: String) -> Result<Self::ClientData, BenchmarkError> {
-        let res = ActorPath::from_str(&str);
-        res.map_err(|e| {
-            BenchmarkError::InvalidMessage(format!("Could not read client data: {}", e))
+        let read_path = |s: &str| {
+            ActorPath::from_str(s).map_err(|e| {
+                BenchmarkError::InvalidMessage(format!("Could not read client data: {}", e))
+            })
+        };
+        let split: Vec<_> = str.split(' ').collect();
+        if split.len() != 2 {
+            return Err(BenchmarkError::InvalidMessage(format!(
+                "String '{}' does not represent client data! Expected '<replica> <client host>'",
+                str
+            )));
+        }
+        Ok(ClientProcess {
+            replica: read_path(split[0])?,
+            client_host: read_path(split[1])?,
         })
     }
 
     fn client_conf_to_str(c: Self::ClientConf) -> String {
-        format!("{},{},{}", c.algorithm, c.last_node_id, c.reconfig_policy)
+        c.serialise(|c| {
+            let (storage, disk_hocon) = match &c.disk {
+                Some(disk) => (StorageKind::Disk, format!("\n{}", disk.to_hocon())),
+                None => (StorageKind::Memory, String::new()),
+            };
+            format!(
+                "{},{},{},{},{},{},{},{},{}{}",
+                c.algorithm,
+                c.last_node_id,
+                c.reconfig_policy,
+                storage.as_str(),
+                c.compaction,
+                c.kv.map_or("none", |read_mode| read_mode.as_str()),
+                c.paxos_options,
+                c.initial_leader,
+                c.config.to_hocon(), // last, as it contains commas
+                disk_hocon
+            )
+        })
     }
 
     fn client_data_to_str(d: Self::ClientData) -> String {
-        d.to_string()
+        format!("{} {}", d.replica, d.client_host) // paths contain no spaces
     }
 }
 
@@ -121,89 +250,199 @@ fn get_initial_conf(last_node_id: u64) -> (Vec<u64>, Vec<u64>) {
     initial_conf
 }
 
-fn get_reconfig_data(
-    s: &str,
-    n: u64,
-) -> Result<(u64, Option<(Vec<u64>, Vec<u64>)>), BenchmarkError> {
-    match s.to_lowercase().as_ref() {
-        "off" => Ok((0, None)),
-        "single" => {
-            let reconfig: Vec<u64> = (2..=n + 1).collect();
-            let new_followers: Vec<u64> = vec![];
-            let reconfiguration = Some((reconfig, new_followers));
-            Ok((1, reconfiguration))
-        }
-        "majority" => {
-            let majority = n / 2 + 1;
-            let mut reconfig: Vec<u64> = (1..majority).collect(); // minority i.e. continued node ids
-            for i in 1..=majority {
-                let new_node_id = n + i;
-                reconfig.push(new_node_id);
-            }
-            assert_eq!(n, reconfig.len() as u64);
-            let new_followers: Vec<u64> = vec![];
-            let reconfiguration = Some((reconfig, new_followers));
-            Ok((majority, reconfiguration))
-        }
-        _ => Err(BenchmarkError::InvalidMessage(String::from(
-            "Got unknown reconfiguration parameter",
-        ))),
-    }
-}
-type Storage = MemStorage;
-
+/// The settings of the replicas, sent to every client so that all nodes of an experiment use the same ones.
+#[derive(Clone, Debug, PartialEq)]
 pub struct ExperimentConfig {
     pub election_timeout: u64,
     pub outgoing_period: Duration,
     pub max_inflight: usize,
     pub initial_election_factor: u64,
+    pub ble_delta: u64,
+    pub ble_tick_period: u64,
+    pub get_decided_period: Duration,
+    pub transfer_timeout: Duration,
+    pub prio_start_round: u64,
+    pub request_acceptsync_timer: Duration,
+    pub raft_tick_period: u64,
+    pub raft_leader_hb_period: u64,
+    pub raft_max_batch_size: u64,
+    pub buffer: BufferSettings,
 }
 
 impl ExperimentConfig {
-    pub fn new(
-        election_timeout: u64,
-        outgoing_period: Duration,
-        max_inflight: usize,
-        initial_election_factor: u64,
-    ) -> ExperimentConfig {
-        ExperimentConfig {
-            election_timeout,
-            outgoing_period,
-            max_inflight,
-            initial_election_factor,
-        }
-    }
-
-    pub fn load_from_file<P>(path: P) -> ExperimentConfig
-    where
-        P: Into<PathBuf>,
-    {
-        let p: PathBuf = path.into();
-        let config = HoconLoader::new()
-            .load_file(p)
-            .expect("Failed to load file")
-            .hocon()
-            .expect("Failed to load as HOCON");
-        let election_timeout = config["experiment"]["election_timeout"]
-            .as_i64()
-            .expect("Failed to load election_timeout") as u64;
-        let outgoing_period = config["experiment"]["outgoing_period"]
-            .as_duration()
-            .expect("Failed to load outgoing_period");
-        let max_inflight = config["experiment"]["max_inflight"]
-            .as_i64()
-            .expect("Failed to load max_inflight") as usize;
-        let initial_election_factor = config["experiment"]["initial_election_factor"]
-            .as_i64()
-            .expect("Failed to load initial_election_factor")
-            as u64;
-        ExperimentConfig::new(
-            election_timeout,
-            outgoing_period,
-            max_inflight,
-            initial_election_factor,
+    /// Reads the config from `file` (if it exists) with the HOCON `overrides` of a request applied on top.
+    ///
+    /// Every setting must be given by one of them.
+    pub fn load(file: Option<&str>, overrides: &str) -> Result<ExperimentConfig, String> {
+        let hocon = load_hocon(file, overrides)?;
+        ExperimentConfig::from_hocon(&hocon)
+    }
+
+    fn from_hocon(h: &Hocon) -> Result<ExperimentConfig, String> {
+        let e = &h["experiment"];
+        let p = &h["paxos"];
+        let r = &h["raft"];
+        let b = &h["buffer_config"];
+        let initial_election_factor = read_positive(
+            &e["initial_election_factor"],
+            "experiment.initial_election_factor",
+        )?;
+        Ok(ExperimentConfig {
+            election_timeout: read_positive(&e["election_timeout"], "experiment.election_timeout")?
+                as u64,
+            outgoing_period: read_duration(&e["outgoing_period"], "experiment.outgoing_period")?,
+            max_inflight: read_positive(&e["max_inflight"], "experiment.max_inflight")?,
+            initial_election_factor: initial_election_factor as u64,
+            ble_delta: read_positive(&p["ble_delta"], "paxos.ble_delta")? as u64,
+            ble_tick_period: read_positive(&p["ble_tick_period"], "paxos.ble_tick_period")? as u64,
+            get_decided_period: read_duration(
+                &p["get_decided_period"],
+                "paxos.get_decided_period",
+            )?,
+            transfer_timeout: read_duration(&p["transfer_timeout"], "paxos.transfer_timeout")?,
+            prio_start_round: read_positive(&p["prio_start_round"], "paxos.prio_start_round")?
+                as u64,
+            request_acceptsync_timer: read_duration(
+                &p["request_acceptsync_timer"],
+                "paxos.request_acceptsync_timer",
+            )?,
+            raft_tick_period: read_positive(&r["tick_period"], "raft.tick_period")? as u64,
+            raft_leader_hb_period: read_positive(&r["leader_hb_period"], "raft.leader_hb_period")?
+                as u64,
+            raft_max_batch_size: read_positive(&r["max_batch_size"], "raft.max_batch_size")? as u64,
+            buffer: BufferSettings {
+                chunk_size: read_optional(&b["chunk_size"], "buffer_config.chunk_size")?,
+                initial_chunk_count: read_optional(
+                    &b["initial_chunk_count"],
+                    "buffer_config.initial_chunk_count",
+                )?,
+                max_chunk_count: read_optional(
+                    &b["max_chunk_count"],
+                    "buffer_config.max_chunk_count",
+                )?,
+                encode_buf_min_free_space: read_optional(
+                    &b["encode_buf_min_free_space"],
+                    "buffer_config.encode_buf_min_free_space",
+                )?,
+            },
+        })
+    }
+
+    /// The config as HOCON, which is what the replicas read through their component config.
+    pub fn to_hocon(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Some(v) = self.buffer.chunk_size {
+            buffer.push(format!("chunk_size = {}", v));
+        }
+        if let Some(v) = self.buffer.initial_chunk_count {
+            buffer.push(format!("initial_chunk_count = {}", v));
+        }
+        if let Some(v) = self.buffer.max_chunk_count {
+            buffer.push(format!("max_chunk_count = {}", v));
+        }
+        if let Some(v) = self.buffer.encode_buf_min_free_space {
+            buffer.push(format!("encode_buf_min_free_space = {}", v));
+        }
+        format!(
+            "experiment {{ election_timeout = {}, outgoing_period = {} ns, max_inflight = {}, initial_election_factor = {} }}\n\
+             paxos {{ ble_delta = {}, ble_tick_period = {}, get_decided_period = {} ns, transfer_timeout = {} ns, prio_start_round = {}, request_acceptsync_timer = {} ns }}\n\
+             raft {{ tick_period = {}, leader_hb_period = {}, max_batch_size = {} }}\n\
+             buffer_config {{ {} }}",
+            self.election_timeout,
+            self.outgoing_period.as_nanos(),
+            self.max_inflight,
+            self.initial_election_factor,
+            self.ble_delta,
+            self.ble_tick_period,
+            self.get_decided_period.as_nanos(),
+            self.transfer_timeout.as_nanos(),
+            self.prio_start_round,
+            self.request_acceptsync_timer.as_nanos(),
+            self.raft_tick_period,
+            self.raft_leader_hb_period,
+            self.raft_max_batch_size,
+            buffer.join(", ")
         )
     }
+
+    /// A system config in which the component config is this config.
+    fn kompact_config(&self) -> KompactConfig {
+        let mut conf = KompactConfig::default();
+        conf.load_config_str(self.to_hocon());
+        conf
+    }
+
+    fn buffer_config(&self) -> BufferConfig {
+        let mut bc = BufferConfig::default();
+        if let Some(v) = self.buffer.chunk_size {
+            bc.chunk_size(v);
+        }
+        if let Some(v) = self.buffer.initial_chunk_count {
+            bc.initial_chunk_count(v);
+        }
+        if let Some(v) = self.buffer.max_chunk_count {
+            bc.max_chunk_count(v);
+        }
+        if let Some(v) = self.buffer.encode_buf_min_free_space {
+            bc.encode_buf_min_free_space(v);
+        }
+        bc.validate();
+        bc
+    }
+
+    /// Reads the `storage` section of `file` (if it exists) with the HOCON `overrides` applied on top,
+    /// which configures disk-backed storage.
+    pub fn load_disk_config(file: Option<&str>, overrides: &str) -> Result<DiskConfig, String> {
+        let config = load_hocon(file, overrides)?;
+        let s = &config["storage"];
+        let dir = s["directory"].as_string().ok_or_else(|| {
+            format!(
+                "storage.directory should be a string, but was {:?}",
+                s["directory"]
+            )
+        })?;
+        let batch_size =
+            read_optional(&s["fsync_batch_size"], "storage.fsync_batch_size")?.unwrap_or(0);
+        let fsync = s["fsync"]
+            .as_string()
+            .ok_or_else(|| format!("storage.fsync should be a string, but was {:?}", s["fsync"]))?;
+        let fsync = FsyncPolicy::parse(&fsync, batch_size)?;
+        Ok(DiskConfig::with(PathBuf::from(dir), fsync))
+    }
+}
+
+/// Loads `file` (if it exists) with the HOCON `overrides` applied on top.
+pub(super) fn load_hocon(file: Option<&str>, overrides: &str) -> Result<Hocon, String> {
+    let mut loader = HoconLoader::new();
+    if let Some(path) = file {
+        if Path::new(path).exists() {
+            loader = loader
+                .load_file(path)
+                .map_err(|e| format!("Could not load {}: {:?}", path, e))?;
+        }
+    }
+    if !overrides.trim().is_empty() {
+        loader = loader
+            .load_str(overrides)
+            .map_err(|e| format!("Could not load overrides '{}': {:?}", overrides, e))?;
+    }
+    loader
+        .hocon()
+        .map_err(|e| format!("Config is not valid HOCON: {:?}", e))
+}
+
+fn read_duration(v: &Hocon, key: &str) -> Result<Duration, String> {
+    v.as_duration()
+        .ok_or_else(|| format!("{} should be a duration, but was {:?}", key, v))
+}
+
+/// The settings of the master, which are not needed by the replicas.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BenchmarkConfig {
+    pub client_timeout: Duration,
+    pub meta_results_path: String,
+    pub check_total_order: bool,
+    pub throughput_window: Option<Duration>, // None if no throughput series should be recorded
 }
 
 pub struct AtomicBroadcastMaster {
@@ -211,44 +450,74 @@ pub struct AtomicBroadcastMaster {
     num_nodes: Option<u64>,
     num_proposals: Option<u64>,
     concurrent_proposals: Option<u64>,
-    reconfiguration: Option<(Vec<u64>, Vec<u64>)>,
+    num_clients: Option<u64>,       // in-process and remote
+    num_remote_clients: u64, // the clients with the highest ids, on the benchmark client processes
+    num_initial_nodes: Option<u64>, // the nodes 1..=num_initial_nodes start in the configuration
+    reconfiguration: ReconfigSchedule,
     system: Option<KompactSystem>,
+    system_config: Option<String>, // description of `system`, which is attached to the result
+    /// Extra systems in the master's process, one for each in-process client but the primary, which runs on `system`.
+    /// They are not DistributedBenchmarkClients, all clients share the master's machine.
+    in_process_client_systems: Vec<KompactSystem>,
     finished_latch: Option<Arc<CountdownEvent>>,
     iteration_id: u32,
-    client_comp: Option<Arc<Component<Client>>>,
+    client_comps: Vec<Arc<Component<Client>>>, // in-process
+    remote_clients: Option<Arc<Component<RemoteClients>>>, // talks to the client hosts, if there are remote clients
+    meta_results: Option<MetaResults>, // of the clients of the last iteration, once they were stopped
     partitioning_actor: Option<Arc<Component<PartitioningActor>>>,
-    num_written_latency: usize, // used to calculate avg of median latency
-    latency_hist: Option<Histogram<u64>>,
-    num_timed_out: Vec<u64>,
     experiment_str: Option<String>,
+    experiment_params: Vec<(&'static str, String)>, // the columns of experiment_str
     meta_results_path: Option<String>,
+    check_total_order: bool,
+    fault_schedule: FaultSchedule,
+    leader_transfers: TransferSchedule,
+    open_loop: Option<OpenLoop>,
+    payload_size: Option<PayloadSize>,
+    kv: Option<KvConfig>,
+    experiment_config: Option<ExperimentConfig>,
+    client_timeout: Option<Duration>,
+    throughput_window: Option<Duration>,
 }
 
 impl AtomicBroadcastMaster {
-    fn new() -> AtomicBroadcastMaster {
+    pub(super) fn new() -> AtomicBroadcastMaster {
         AtomicBroadcastMaster {
             algorithm: None,
             num_nodes: None,
             num_proposals: None,
             concurrent_proposals: None,
-            reconfiguration: None,
+            num_clients: None,
+            num_remote_clients: 0,
+            num_initial_nodes: None,
+            reconfiguration: ReconfigSchedule::default(),
             system: None,
+            system_config: None,
+            in_process_client_systems: vec![],
             finished_latch: None,
             iteration_id: 0,
-            client_comp: None,
+            client_comps: vec![],
+            remote_clients: None,
+            meta_results: None,
             partitioning_actor: None,
-            num_written_latency: 0,
-            latency_hist: None,
-            num_timed_out: vec![],
             experiment_str: None,
+            experiment_params: vec![],
             meta_results_path: None,
+            check_total_order: false,
+            fault_schedule: FaultSchedule::default(),
+            leader_transfers: TransferSchedule::default(),
+            open_loop: None,
+            payload_size: None,
+            kv: None,
+            experiment_config: None,
+            client_timeout: None,
+            throughput_window: None,
         }
     }
 
     fn initialise_iteration(
         &self,
         nodes: Vec<ActorPath>,
-        client: ActorPath,
+        clients: &[ActorPath],
     ) -> Arc<Component<PartitioningActor>> {
         let system = self.system.as_ref().unwrap();
         let prepare_latch = Arc::new(CountdownEvent::new(1));
@@ -265,38 +534,83 @@ impl AtomicBroadcastMaster {
         partitioning_actor_f
             .wait_timeout(Duration::from_millis(1000))
             .expect("PartitioningComp never started!");
-        let mut ser_client = Vec::<u8>::new();
-        client
-            .serialise(&mut ser_client)
-            .expect("Failed to serialise ClientComp actorpath");
+        let ser_clients =
+            Clients::serialise(clients).expect("Failed to serialise ClientComp actorpaths");
         partitioning_actor
             .actor_ref()
-            .tell(IterationControlMsg::Prepare(Some(ser_client)));
+            .tell(IterationControlMsg::Prepare(Some(ser_clients)));
         prepare_latch.wait();
         partitioning_actor
     }
 
+    fn create_system(
+        provider: &KompactSystemProvider,
+        name: &str,
+        threads: usize,
+        config: &ExperimentConfig,
+    ) -> KompactSystem {
+        provider.new_remote_system_with_threads_config(
+            name,
+            threads,
+            config.kompact_config(),
+            config.buffer_config(),
+            TCP_NODELAY,
+        )
+    }
+
+    fn client_system(&self, id: u64) -> &KompactSystem {
+        match id {
+            0 => self.system.as_ref().unwrap(),
+            _ => &self.in_process_client_systems[id as usize - 1],
+        }
+    }
+
+    /// Splits `total` evenly over the clients, the first clients get the remainder.
+    fn client_share(&self, id: u64, total: u64) -> u64 {
+        let num_clients = self.num_clients.unwrap();
+        total / num_clients + if id < total % num_clients { 1 } else { 0 }
+    }
+
     fn create_client(
         &self,
+        id: u64,
         nodes_id: HashMap<u64, ActorPath>,
         client_timeout: Duration,
-        reconfig: Option<(Vec<u64>, Vec<u64>)>,
+        reconfig_schedule: ReconfigSchedule,
         leader_election_latch: Arc<CountdownEvent>,
     ) -> (Arc<Component<Client>>, ActorPath) {
-        let system = self.system.as_ref().unwrap();
+        let system = self.client_system(id);
         let finished_latch = self.finished_latch.clone().unwrap();
         /*** Setup client ***/
-        let initial_config: Vec<_> = (1..=self.num_nodes.unwrap()).map(|x| x as u64).collect();
+        let initial_config: Vec<_> = (1..=self.num_initial_nodes.unwrap()).collect();
+        let num_proposals = self.client_share(id, self.num_proposals.unwrap());
+        // only the primary client injects faults and transfers the leadership
+        let (fault_schedule, leader_transfers) = match id {
+            0 => (self.fault_schedule.clone(), self.leader_transfers.clone()),
+            _ => (FaultSchedule::default(), TransferSchedule::default()),
+        };
+        let open_loop = self.open_loop.map(|ol| OpenLoop {
+            rate: self.client_share(id, ol.rate),
+            arrivals: ol.arrivals,
+        });
         let (client_comp, unique_reg_f) = system.create_and_register(|| {
             Client::with(
+                id,
                 initial_config,
-                self.num_proposals.unwrap(),
+                num_proposals,
                 self.concurrent_proposals.unwrap(),
                 nodes_id,
-                reconfig,
+                reconfig_schedule,
                 client_timeout,
                 leader_election_latch,
                 finished_latch,
+                self.check_total_order,
+                fault_schedule,
+                leader_transfers,
+                open_loop,
+                self.payload_size.unwrap(),
+                self.kv,
+                self.throughput_window,
             )
         });
         unique_reg_f.wait_expect(REGISTER_TIMEOUT, "Client failed to register!");
@@ -305,121 +619,474 @@ impl AtomicBroadcastMaster {
             .wait_timeout(REGISTER_TIMEOUT)
             .expect("ClientComp never started!");
         let client_path = system
-            .register_by_alias(&client_comp, format!("client{}", &self.iteration_id))
+            .register_by_alias(&client_comp, format!("client{}_{}", &self.iteration_id, id))
             .wait_expect(REGISTER_TIMEOUT, "Failed to register alias for ClientComp");
         (client_comp, client_path)
     }
 
+    /// Spreads the remote clients over the client hosts in contiguous ranges of ids, the first hosts get the
+    /// remainder. Hosts without a client get no workload.
+    fn remote_workloads(
+        &self,
+        hosts: &[ActorPath],
+        nodes_id: &HashMap<u64, ActorPath>,
+        client_timeout: Duration,
+    ) -> Vec<(ActorPath, RemoteWorkload)> {
+        let num_hosts = hosts.len() as u64;
+        let num_remote = self.num_remote_clients;
+        let mut next_id = self.num_clients.unwrap() - num_remote;
+        let mut workloads = vec![];
+        for (i, host) in hosts.iter().enumerate() {
+            let n = num_remote / num_hosts + ((i as u64) < num_remote % num_hosts) as u64;
+            if n == 0 {
+                break;
+            }
+            let clients = (next_id..next_id + n)
+                .map(|id| {
+                    let num_proposals = self.client_share(id, self.num_proposals.unwrap());
+                    let rate = self
+                        .open_loop
+                        .map_or(0, |ol| self.client_share(id, ol.rate));
+                    (id, num_proposals, rate)
+                })
+                .collect();
+            next_id += n;
+            let workload = RemoteWorkload {
+                iteration_id: self.iteration_id,
+                clients,
+                concurrent_proposals: self.concurrent_proposals.unwrap(),
+                num_initial_nodes: self.num_initial_nodes.unwrap(),
+                nodes: nodes_id
+                    .iter()
+                    .map(|(pid, ap)| (*pid, ap.clone()))
+                    .collect(),
+                timeout: client_timeout,
+                check_total_order: self.check_total_order,
+                arrivals: self.open_loop.map_or(Arrivals::Constant, |ol| ol.arrivals),
+                payload_size: self.payload_size.unwrap(),
+                throughput_window: self.throughput_window,
+            };
+            workloads.push((host.clone(), workload));
+        }
+        workloads
+    }
+
     fn validate_experiment_params(
         &mut self,
         c: &AtomicBroadcastRequest,
         num_clients: u32,
     ) -> Result<(), BenchmarkError> {
-        // TODO reconfiguration
         if c.concurrent_proposals > c.number_of_proposals {
             return Err(BenchmarkError::InvalidTest(format!(
                 "Concurrent proposals: {} should be less or equal to number of proposals: {}",
                 c.concurrent_proposals, c.number_of_proposals
             )));
         }
-        match &c.algorithm.to_lowercase() {
-            paxos if paxos == "paxos" => match c.reconfiguration.to_lowercase().as_ref() {
-                "off" => {
-                    if c.reconfig_policy.to_lowercase() != "none" {
-                        return Err(BenchmarkError::InvalidTest(format!(
-                            "Reconfiguration is off, transfer policy should be none, but found: {}",
-                            &c.reconfig_policy
-                        )));
-                    }
-                }
-                s if s == "single" || s == "majority" => {
-                    let reconfig_policy: &str = &c.reconfig_policy.to_lowercase();
-                    if reconfig_policy != "eager" && reconfig_policy != "pull" {
-                        return Err(BenchmarkError::InvalidTest(format!(
-                            "Unimplemented Paxos transfer policy: {}",
-                            &c.reconfig_policy
-                        )));
-                    }
-                }
-                _ => {
+        let algorithm = c.algorithm.to_lowercase();
+        if algorithm != "paxos" && algorithm != "raft" {
+            return Err(BenchmarkError::InvalidTest(format!(
+                "Unimplemented atomic broadcast algorithm: {}",
+                &c.algorithm
+            )));
+        }
+        let reconfiguration = ReconfigSchedule::parse(&c.reconfiguration, c.number_of_nodes)
+            .map_err(BenchmarkError::InvalidTest)?;
+        let reconfig_policy: &str = &c.reconfig_policy.to_lowercase();
+        if reconfiguration.is_empty() {
+            if reconfig_policy != "none" {
+                return Err(BenchmarkError::InvalidTest(format!(
+                    "Reconfiguration is off, transfer policy should be none, but found: {}",
+                    &c.reconfig_policy
+                )));
+            }
+        } else if algorithm == "paxos" {
+            if reconfig_policy != "eager" && reconfig_policy != "pull" {
+                return Err(BenchmarkError::InvalidTest(format!(
+                    "Unimplemented Paxos transfer policy: {}",
+                    &c.reconfig_policy
+                )));
+            }
+            if reconfiguration.has_learners() {
+                // a stopsign only holds the voters of the next configuration
+                return Err(BenchmarkError::InvalidTest(format!(
+                    "Paxos has no learners, but found: {}",
+                    &c.reconfiguration
+                )));
+            }
+        } else {
+            // the replace policies turn the requested configuration into a single replacement
+            let valid_policy = reconfig_policy == "exact"
+                || (ReconfigSchedule::is_alias(&c.reconfiguration)
+                    && (reconfig_policy == "replace-leader"
+                        || reconfig_policy == "replace-follower"));
+            if !valid_policy {
+                return Err(BenchmarkError::InvalidTest(format!(
+                    "Unimplemented Raft transfer policy: {} for reconfiguration: {}",
+                    &c.reconfig_policy, &c.reconfiguration
+                )));
+            }
+        }
+        let n = reconfiguration
+            .validate(c.number_of_nodes, c.number_of_proposals)
+            .map_err(BenchmarkError::InvalidTest)?;
+        if (num_clients as u64) < n {
+            return Err(BenchmarkError::InvalidTest(format!(
+                "Not enough clients: {}, Required: {}",
+                num_clients, n
+            )));
+        }
+        self.reconfiguration = reconfiguration;
+        self.num_nodes = Some(n);
+        self.num_initial_nodes = Some(c.number_of_nodes);
+        Ok(())
+    }
+
+    /// The number of in-process and remote clients.
+    fn validate_clients(c: &AtomicBroadcastRequest) -> Result<u64, BenchmarkError> {
+        let num_clients = c.in_process_clients.max(1).saturating_add(c.remote_clients);
+        if num_clients == 1 {
+            return Ok(num_clients);
+        }
+        if num_clients >= 1 << (64 - CLIENT_ID_SHIFT) {
+            return Err(BenchmarkError::InvalidTest(format!(
+                "Too many clients: {}",
+                num_clients
+            )));
+        }
+        if c.number_of_proposals / num_clients < c.concurrent_proposals.max(1) {
+            return Err(BenchmarkError::InvalidTest(format!(
+                "Each of the {} clients should get at least {} proposals, but there are only {}",
+                num_clients,
+                c.concurrent_proposals.max(1),
+                c.number_of_proposals
+            )));
+        }
+        if c.target_rate > 0 && c.target_rate < num_clients {
+            return Err(BenchmarkError::InvalidTest(format!(
+                "target_rate: {} can not be split over {} clients",
+                c.target_rate, num_clients
+            )));
+        }
+        if !ReconfigSchedule::is_off(&c.reconfiguration) {
+            // the primary client would reconfigure while the others keep proposing in the old configuration
+            return Err(BenchmarkError::InvalidTest(
+                "Multiple clients can not be combined with reconfiguration".to_string(),
+            ));
+        }
+        Ok(num_clients)
+    }
+
+    /// The optimisations and quorums of the Paxos replicas. If the request does not set any optimisations, the ones of
+    /// the default build are used.
+    fn validate_paxos_options(c: &AtomicBroadcastRequest) -> Result<PaxosOptions, BenchmarkError> {
+        let flexible_quorums = c.phase1_quorum != 0 || c.phase2_quorum != 0;
+        if !c.algorithm.to_lowercase().starts_with("paxos") {
+            if c.has_paxos_options() || flexible_quorums {
+                return Err(BenchmarkError::InvalidTest(format!(
+                    "paxos_options and quorums can not be used with algorithm: {}",
+                    c.algorithm
+                )));
+            }
+            return Ok(PaxosOptions::default());
+        }
+        if flexible_quorums && !ReconfigSchedule::is_off(&c.reconfiguration) {
+            // the sizes are fixed for number_of_nodes, but a reconfiguration changes the number of replicas
+            return Err(BenchmarkError::InvalidTest(
+                "Flexible quorums can not be combined with reconfiguration".to_string(),
+            ));
+        }
+        let mut options = if c.has_paxos_options() {
+            let o: &PaxosOptionsPB = c.get_paxos_options();
+            PaxosOptions {
+                batch_accept: o.batch_accept,
+                latest_decide: o.latest_decide,
+                latest_accepted: o.latest_accepted,
+                max_accsync: o.max_accsync,
+                headstart_ble: o.headstart_ble,
+                quorums: Quorums::Majority,
+            }
+        } else {
+            PaxosOptions::default()
+        };
+        options.quorums = Quorums::with(c.phase1_quorum, c.phase2_quorum, c.number_of_nodes)
+            .map_err(BenchmarkError::InvalidTest)?;
+        Ok(options)
+    }
+
+    fn validate_open_loop(c: &AtomicBroadcastRequest) -> Result<Option<OpenLoop>, BenchmarkError> {
+        let arrivals = Arrivals::parse(&c.arrivals).map_err(BenchmarkError::InvalidTest)?;
+        if c.target_rate == 0 {
+            if arrivals != Arrivals::Constant {
+                return Err(BenchmarkError::InvalidTest(format!(
+                    "{} arrivals require a target_rate",
+                    arrivals.as_str()
+                )));
+            }
+            return Ok(None);
+        }
+        if !ReconfigSchedule::is_off(&c.reconfiguration) {
+            // the reconfigurations are proposed after a number of responses of a closed loop
+            return Err(BenchmarkError::InvalidTest(
+                "An open-loop client can not be combined with reconfiguration".to_string(),
+            ));
+        }
+        Ok(Some(OpenLoop {
+            rate: c.target_rate,
+            arrivals,
+        }))
+    }
+
+    /// Whether the latency of every proposal is measured.
+    fn tracks_latency(&self) -> bool {
+        self.concurrent_proposals == Some(1)
+            || self.open_loop.is_some()
+            || (!self.reconfiguration.is_empty() && cfg!(feature = "track_reconfig_latency"))
+    }
+
+    fn validate_fault_schedule(
+        c: &AtomicBroadcastRequest,
+        storage: StorageKind,
+    ) -> Result<FaultSchedule, BenchmarkError> {
+        let schedule =
+            FaultSchedule::parse(&c.fault_schedule).map_err(BenchmarkError::InvalidTest)?;
+        if schedule.is_empty() {
+            return Ok(schedule);
+        }
+        if !ReconfigSchedule::is_off(&c.reconfiguration) {
+            return Err(BenchmarkError::InvalidTest(
+                "Fault injection can not be combined with reconfiguration".to_string(),
+            ));
+        }
+        if schedule.has_restarts() && storage != StorageKind::Disk {
+            return Err(BenchmarkError::InvalidTest(format!(
+                "Restarting crashed nodes requires disk storage, but found: {}",
+                storage.as_str()
+            )));
+        }
+        let targets = schedule.faults.iter().map(|f| f.target).chain(
+            schedule
+                .network_faults
+                .iter()
+                .flat_map(|f| f.rule.targets()),
+        );
+        for target in targets {
+            if let FaultTarget::Node(pid) = target {
+                if pid > c.number_of_nodes {
                     return Err(BenchmarkError::InvalidTest(format!(
-                        "Unimplemented Paxos reconfiguration: {}",
-                        &c.reconfiguration
+                        "Fault targets node {}, but there are only {} nodes",
+                        pid, c.number_of_nodes
                     )));
                 }
-            },
-            raft if raft == "raft" => match c.reconfiguration.to_lowercase().as_ref() {
-                "off" => {
-                    if c.reconfig_policy.to_lowercase() != "none" {
-                        return Err(BenchmarkError::InvalidTest(format!(
-                            "Reconfiguration is off, transfer policy should be none, but found: {}",
-                            &c.reconfig_policy
-                        )));
-                    }
-                }
-                s if s == "single" || s == "majority" => {
-                    let reconfig_policy: &str = &c.reconfig_policy.to_lowercase();
-                    if reconfig_policy != "replace-leader" && reconfig_policy != "replace-follower"
-                    {
+            }
+        }
+        if c.algorithm.to_lowercase() == "paxos" {
+            // Paxos relies on links that deliver messages once and in order
+            for fault in &schedule.network_faults {
+                if let NetworkRule::Link(effect, _) = &fault.rule {
+                    if !effect.is_fifo() {
                         return Err(BenchmarkError::InvalidTest(format!(
-                            "Unimplemented Raft transfer policy: {}",
-                            &c.reconfig_policy
+                            "Paxos only supports partitions, oneway, loss and delay, but found: {}",
+                            fault
                         )));
                     }
                 }
-                _ => {
-                    return Err(BenchmarkError::InvalidTest(format!(
-                        "Unimplemented Raft reconfiguration: {}",
-                        &c.reconfiguration
-                    )));
-                }
-            },
-            _ => {
-                return Err(BenchmarkError::InvalidTest(format!(
-                    "Unimplemented atomic broadcast algorithm: {}",
-                    &c.algorithm
-                )));
             }
+        }
+        Ok(schedule)
+    }
+
+    /// The scheduled leader transfers, which hand over to nodes of the initial configuration.
+    fn validate_leader_transfers(
+        c: &AtomicBroadcastRequest,
+    ) -> Result<TransferSchedule, BenchmarkError> {
+        let schedule =
+            TransferSchedule::parse(&c.leader_transfers).map_err(BenchmarkError::InvalidTest)?;
+        if c.initial_leader > c.number_of_nodes {
+            return Err(BenchmarkError::InvalidTest(format!(
+                "initial_leader is node {}, but there are only {} nodes",
+                c.initial_leader, c.number_of_nodes
+            )));
+        }
+        if !schedule.is_empty() && !ReconfigSchedule::is_off(&c.reconfiguration) {
+            // the targets are nodes of the initial configuration
+            return Err(BenchmarkError::InvalidTest(
+                "Leader transfers can not be combined with reconfiguration".to_string(),
+            ));
+        }
+        schedule
+            .validate(c.number_of_nodes, c.number_of_proposals)
+            .map_err(BenchmarkError::InvalidTest)?;
+        Ok(schedule)
+    }
+
+    /// The file `<meta_results_path>/<dir>/<experiment>.<ext>`, with the experiment made safe as a file name.
+    fn experiment_file(&self, dir: &str, ext: &str) -> PathBuf {
+        let meta_path = self.meta_results_path.as_ref().expect("No meta path!");
+        let experiment = self.experiment_str.as_ref().expect("No experiment");
+        Path::new(meta_path)
+            .join(dir)
+            .join(format!("{}.{}", file_name(experiment), ext))
+    }
+
+    /// Appends the parameters and results of the iteration as one JSON object per line to
+    /// `<meta_results_path>/results/<experiment>.jsonl`.
+    fn write_iteration_record(
+        &self,
+        meta_results: &MetaResults,
+        latency: Option<LatencySummary>,
+        exec_time_millis: f64,
+    ) {
+        let experiment = self.experiment_str.as_ref().unwrap();
+        let record = IterationRecord {
+            experiment,
+            params: &self.experiment_params,
+            iteration: self.iteration_id,
+            exec_time_millis,
+            num_timed_out: meta_results.num_timed_out,
+            leader_changes: &meta_results.leader_changes,
+            latency,
+            reconfigurations: &meta_results.reconfigurations,
+            leader_transfers: &meta_results.leader_transfers,
+            faults: &meta_results.faults,
+            network_faults: &meta_results.network_faults,
+            open_loop: meta_results.open_loop.as_ref(),
+            clients: &meta_results.clients,
+            kv: meta_results.kv.as_ref(),
+            storage_io: &meta_results.io_stats,
         };
-        match get_reconfig_data(&c.reconfiguration, c.number_of_nodes) {
-            Ok((additional_n, reconfig)) => {
-                let n = c.number_of_nodes + additional_n;
-                if (num_clients as u64) < n {
-                    return Err(BenchmarkError::InvalidTest(format!(
-                        "Not enough clients: {}, Required: {}",
-                        num_clients, n
-                    )));
-                }
-                self.reconfiguration = reconfig;
-                self.num_nodes = Some(n);
-            }
-            Err(e) => return Err(e),
+        let path = self.experiment_file("results", "jsonl");
+        append_line(&path, &record.to_json())
+            .unwrap_or_else(|e| panic!("Failed to write results to {:?}: {}", path, e));
+    }
+
+    /// Appends the decided proposals per window and the events of the iteration as one JSON object per line to
+    /// `<meta_results_path>/throughput/<experiment>.jsonl`.
+    fn write_throughput_series(&self, throughput: &ThroughputSeries) {
+        let path = self.experiment_file("throughput", "jsonl");
+        append_line(&path, &throughput_json(self.iteration_id, throughput))
+            .unwrap_or_else(|e| panic!("Failed to write throughput to {:?}: {}", path, e));
+    }
+
+    /// Appends the latency of each decided proposal of the iteration as one JSON object per line to
+    /// `<meta_results_path>/latency/<experiment>.jsonl`.
+    fn write_latencies(&self, latencies: &[(u64, Duration)]) {
+        let path = self.experiment_file("latency", "jsonl");
+        append_line(&path, &latencies_json(self.iteration_id, latencies))
+            .unwrap_or_else(|e| panic!("Failed to write latencies to {:?}: {}", path, e));
+    }
+
+    /// Sets up a run in which the replicas are a key-value store and the clients issue `kv` operations.
+    pub(super) fn setup_kv(
+        &mut self,
+        c: AtomicBroadcastRequest,
+        kv: KvConfig,
+        m: &DeploymentMetaData,
+    ) -> Result<WithOverrides<ClientParams>, BenchmarkError> {
+        self.kv = Some(kv);
+        let res = self.setup(c, m);
+        if res.is_err() {
+            self.kv = None;
         }
-        Ok(())
+        res
+    }
+
+    fn stop_client(client: &Arc<Component<Client>>) -> MetaResults {
+        client
+            .actor_ref()
+            .ask(|promise| LocalClientMessage::Stop(Ask::new(promise, ())))
+            .wait()
     }
 
-    pub fn load_benchmark_config<P>(path: P) -> (Duration, Option<String>)
-    where
-        P: Into<PathBuf>,
-    {
-        let p: PathBuf = path.into();
-        let config = HoconLoader::new()
-            .load_file(p)
-            .expect("Failed to load file")
-            .hocon()
-            .expect("Failed to load as HOCON");
-        let client_timeout = config["experiment"]["client_timeout"]
-            .as_duration()
-            .expect("Failed to load client timeout");
-        let meta_results_path = config["experiment"]["meta_results_path"].as_string();
-        (client_timeout, meta_results_path)
+    /// Stops the clients of the iteration, merges their results and checks the total order with the proposals of
+    /// all of them.
+    fn stop_clients(&self) -> MetaResults {
+        let (primary_client, secondary_clients) =
+            self.client_comps.split_first().expect("No client found!");
+        // the primary client stops the replicas, so it is stopped last
+        let mut secondary_results: Vec<_> =
+            secondary_clients.iter().map(Self::stop_client).collect();
+        if let Some(remote_clients) = &self.remote_clients {
+            let remote_results = remote_clients
+                .actor_ref()
+                .ask(|promise| RemoteClientsMsg::Stop(Ask::new(promise, ())))
+                .wait();
+            secondary_results.extend(remote_results);
+        }
+        let mut meta_results = Self::stop_client(primary_client);
+        for res in secondary_results {
+            meta_results.merge(res);
+        }
+        meta_results.check_total_order();
+        meta_results
+    }
+
+    /// Reads the settings of the master from `file` (if it exists) with the HOCON `overrides` applied on top.
+    pub fn load_benchmark_config(
+        file: Option<&str>,
+        overrides: &str,
+    ) -> Result<BenchmarkConfig, String> {
+        let config = load_hocon(file, overrides)?;
+        let e = &config["experiment"];
+        let client_timeout = read_duration(&e["client_timeout"], "experiment.client_timeout")?;
+        let meta_results_path = match &e["meta_results_path"] {
+            v if is_missing(v) => DEFAULT_META_RESULTS_PATH.to_string(),
+            v => v.as_string().ok_or_else(|| {
+                format!(
+                    "experiment.meta_results_path should be a string, but was {:?}",
+                    v
+                )
+            })?,
+        };
+        let check_total_order = match &e["check_total_order"] {
+            v if is_missing(v) => false,
+            v => v.as_bool().ok_or_else(|| {
+                format!(
+                    "experiment.check_total_order should be a boolean, but was {:?}",
+                    v
+                )
+            })?,
+        };
+        let throughput_window = match &e["throughput_window"] {
+            v if is_missing(v) => None,
+            v => match read_duration(v, "experiment.throughput_window")? {
+                w if w == Duration::from_millis(0) => None,
+                w => Some(w),
+            },
+        };
+        Ok(BenchmarkConfig {
+            client_timeout,
+            meta_results_path,
+            check_total_order,
+            throughput_window,
+        })
+    }
+
+    /// Writes the effective config of the experiment to `<meta_results_path>/config/<experiment>.conf`.
+    fn write_experiment_config(&self) {
+        if self.meta_results_path.is_none() {
+            return;
+        }
+        let path = self.experiment_file("config", "conf");
+        let dir = path.parent().expect("No config directory");
+        create_dir_all(dir)
+            .unwrap_or_else(|_| panic!("Failed to create given directory: {:?}", dir));
+        let mut file = File::create(&path).expect("Failed to create experiment config file");
+        writeln!(
+            file,
+            "{}\nexperiment {{ client_timeout = {} ms, check_total_order = {}, throughput_window = {} ns }}",
+            self.experiment_config.as_ref().unwrap().to_hocon(),
+            self.client_timeout.unwrap().as_millis(),
+            self.check_total_order,
+            self.throughput_window.map_or(0, |w| w.as_nanos())
+        )
+        .expect("Failed to write experiment config");
     }
 }
 
 impl DistributedBenchmarkMaster for AtomicBroadcastMaster {
     type MasterConf = AtomicBroadcastRequest;
-    type ClientConf = ClientParams;
-    type ClientData = ActorPath;
+    type ClientConf = WithOverrides<ClientParams>;
+    type ClientData = ClientProcess;
 
     fn setup(
         &mut self,
@@ -428,35 +1095,154 @@ impl DistributedBenchmarkMaster for AtomicBroadcastMaster {
     ) -> Result<Self::ClientConf, BenchmarkError> {
         println!("Setting up Atomic Broadcast (Master)");
         self.validate_experiment_params(&c, m.number_of_clients())?;
-        let experiment_str = format!(
-            "{},{},{},{},{},{}",
-            c.algorithm,
-            c.number_of_nodes,
-            c.concurrent_proposals,
-            c.number_of_proposals,
-            c.reconfiguration,
-            c.reconfig_policy
-        );
-        self.experiment_str = Some(experiment_str);
+        let storage = StorageKind::parse(&c.storage).map_err(BenchmarkError::InvalidTest)?;
+        let compaction = CompactionPolicy::with(c.compaction_entries, c.compaction_bytes);
+        let invalid_config =
+            |e| BenchmarkError::InvalidTest(format!("Invalid experiment config: {}", e));
+        let experiment_config = ExperimentConfig::load(Some(CONFIG_PATH), &c.experiment_config)
+            .map_err(invalid_config)?;
+        let disk = match storage {
+            StorageKind::Memory => None,
+            StorageKind::Disk => Some(
+                ExperimentConfig::load_disk_config(Some(CONFIG_PATH), &c.experiment_config)
+                    .map_err(invalid_config)?,
+            ),
+        };
+        let BenchmarkConfig {
+            client_timeout,
+            meta_results_path,
+            check_total_order,
+            throughput_window,
+        } = Self::load_benchmark_config(Some(CONFIG_PATH), &c.experiment_config)
+            .map_err(invalid_config)?;
+        if compaction.is_enabled()
+            && check_total_order
+            && !ReconfigSchedule::is_off(&c.reconfiguration)
+        {
+            // compacted replicas only return the suffix of their sequence, without the stopsigns before it
+            return Err(BenchmarkError::InvalidTest(
+                "Log compaction can not be combined with check_total_order and reconfiguration"
+                    .to_string(),
+            ));
+        }
+        let fault_schedule = Self::validate_fault_schedule(&c, storage)?;
+        if compaction.is_enabled()
+            && c.algorithm.to_lowercase().starts_with("paxos")
+            && fault_schedule.has_permanent_crashes()
+        {
+            // without snapshot transfer, Paxos only compacts what every node has decided
+            return Err(BenchmarkError::InvalidTest(
+                "Paxos log compaction can not be combined with crashes without restart".to_string(),
+            ));
+        }
+        let open_loop = Self::validate_open_loop(&c)?;
+        let num_clients = Self::validate_clients(&c)?;
+        let payload_size =
+            PayloadSize::parse(&c.payload_size).map_err(BenchmarkError::InvalidTest)?;
+        let paxos_options = Self::validate_paxos_options(&c)?;
+        let leader_transfers = Self::validate_leader_transfers(&c)?;
+        let mut params = vec![];
+        if let Some(kv) = self.kv {
+            params.push(("workload", kv.workload.as_str().to_string()));
+            params.push(("number_of_keys", kv.num_keys.to_string()));
+            params.push(("read_mode", kv.read_mode.as_str().to_string()));
+        }
+        params.extend(vec![
+            ("algorithm", c.algorithm.clone()),
+            ("number_of_nodes", c.number_of_nodes.to_string()),
+            ("concurrent_proposals", c.concurrent_proposals.to_string()),
+            ("number_of_proposals", c.number_of_proposals.to_string()),
+            ("reconfiguration", c.reconfiguration.clone()),
+            ("reconfig_policy", c.reconfig_policy.clone()),
+            ("storage", storage.as_str().to_string()),
+            ("compaction", compaction.to_string()),
+            (
+                "load",
+                open_loop.map_or("closed".to_string(), |ol| ol.to_string()),
+            ),
+            (
+                "in_process_clients",
+                (num_clients - c.remote_clients).to_string(),
+            ),
+            ("remote_clients", c.remote_clients.to_string()),
+            ("payload_size", payload_size.to_string()),
+            (
+                "paxos_options",
+                if c.algorithm.to_lowercase().starts_with("paxos") {
+                    paxos_options.to_string()
+                } else {
+                    "-".to_string()
+                },
+            ),
+            ("initial_leader", c.initial_leader.to_string()),
+            ("leader_transfers", leader_transfers.to_string()),
+            ("fault_schedule", fault_schedule.to_string()), // last, as it may contain commas
+        ]);
+        let experiment_str: Vec<&str> = params.iter().map(|(_, v)| v.as_str()).collect();
+        self.experiment_str = Some(experiment_str.join(","));
+        self.experiment_params = params;
+        self.fault_schedule = fault_schedule;
+        self.leader_transfers = leader_transfers;
         self.algorithm = Some(c.algorithm.clone());
         self.num_proposals = Some(c.number_of_proposals);
         self.concurrent_proposals = Some(c.concurrent_proposals);
-        if c.concurrent_proposals == 1
-            || (self.reconfiguration.is_some() && cfg!(feature = "track_reconfig_latency"))
-        {
-            self.latency_hist =
-                Some(Histogram::<u64>::new(4).expect("Failed to create latency histogram"));
+        self.open_loop = open_loop;
+        self.num_clients = Some(num_clients);
+        self.num_remote_clients = c.remote_clients;
+        self.payload_size = Some(payload_size);
+        self.meta_results_path = Some(meta_results_path);
+        self.check_total_order = check_total_order;
+        self.client_timeout = Some(client_timeout);
+        self.throughput_window = throughput_window;
+        self.experiment_config = Some(experiment_config.clone());
+        self.write_experiment_config();
+        let system_overrides = c.get_system_overrides().to_string();
+        let provider = crate::kompact_system_provider::for_request(&system_overrides);
+        self.system = Some(Self::create_system(
+            &provider,
+            "atomicbroadcast",
+            4,
+            &experiment_config,
+        ));
+        self.system_config =
+            Some(provider.describe_system(4, &experiment_config.buffer, TCP_NODELAY));
+        // the other in-process clients get their own systems, so that they do not share a socket with the primary
+        self.in_process_client_systems = (1..num_clients - c.remote_clients)
+            .map(|id| {
+                Self::create_system(
+                    &provider,
+                    &format!("atomicbroadcast_client{}", id),
+                    2,
+                    &experiment_config,
+                )
+            })
+            .collect();
+        if c.remote_clients > 0 {
+            let system = self.system.as_ref().unwrap();
+            let (remote_clients, unique_reg_f) = system.create_and_register(RemoteClients::new);
+            unique_reg_f.wait_expect(REGISTER_TIMEOUT, "RemoteClients failed to register!");
+            system
+                .start_notify(&remote_clients)
+                .wait_timeout(REGISTER_TIMEOUT)
+                .expect("RemoteClients never started!");
+            self.remote_clients = Some(remote_clients);
         }
-        let mut conf = KompactConfig::default();
-        conf.load_config_file(CONFIG_PATH);
-        let bc = BufferConfig::from_config_file(CONFIG_PATH);
-        bc.validate();
-        let tcp_no_delay = true;
-        let system = crate::kompact_system_provider::global()
-            .new_remote_system_with_threads_config("atomicbroadcast", 4, conf, bc, tcp_no_delay);
-        self.system = Some(system);
-        let params = ClientParams::with(c.algorithm, c.number_of_nodes, c.reconfig_policy);
-        Ok(params)
+        let params = ClientParams::with(
+            c.algorithm,
+            c.number_of_nodes,
+            c.reconfig_policy,
+            disk,
+            compaction,
+            self.kv.map(|kv| kv.read_mode),
+            paxos_options,
+            c.initial_leader,
+            experiment_config,
+        );
+        Ok(WithOverrides::new(system_overrides, params))
+    }
+
+    fn system_config(&self) -> Option<String> {
+        self.system_config.clone()
     }
 
     fn prepare_iteration(&mut self, d: Vec<Self::ClientData>) -> () {
@@ -464,87 +1250,160 @@ impl DistributedBenchmarkMaster for AtomicBroadcastMaster {
         if self.system.is_none() {
             panic!("No KompactSystem found!")
         }
-        let finished_latch = Arc::new(CountdownEvent::new(1));
+        let num_clients = self.num_clients.expect("No cached num_clients");
+        let num_in_process_clients = num_clients - self.num_remote_clients;
+        let finished_latch = Arc::new(CountdownEvent::new(num_in_process_clients as usize));
         self.finished_latch = Some(finished_latch);
         self.iteration_id += 1;
         let mut nodes_id: HashMap<u64, ActorPath> = HashMap::new();
         let num_nodes_needed = self.num_nodes.expect("No cached num_nodes") as usize;
-        let mut nodes = d;
+        let client_hosts: Vec<ActorPath> = d.iter().map(|p| p.client_host.clone()).collect();
+        let mut nodes: Vec<ActorPath> = d.into_iter().map(|p| p.replica).collect();
         nodes.truncate(num_nodes_needed);
         for (id, ap) in nodes.iter().enumerate() {
             nodes_id.insert(id as u64 + 1, ap.clone());
         }
-        let (client_timeout, meta_path) = Self::load_benchmark_config(CONFIG_PATH);
-        self.meta_results_path = meta_path;
-        let leader_election_latch = Arc::new(CountdownEvent::new(1));
-        let (client_comp, client_path) = self.create_client(
-            nodes_id,
-            client_timeout,
-            self.reconfiguration.clone(),
-            leader_election_latch.clone(),
-        );
-        let partitioning_actor = self.initialise_iteration(nodes, client_path);
+        let client_timeout = self.client_timeout.expect("No cached client timeout");
+        let mut client_comps = Vec::with_capacity(num_clients as usize);
+        let mut client_paths = Vec::with_capacity(num_clients as usize);
+        let mut leader_election_latches = Vec::with_capacity(num_clients as usize);
+        for id in 0..num_in_process_clients {
+            let leader_election_latch = Arc::new(CountdownEvent::new(1));
+            let (client_comp, client_path) = self.create_client(
+                id,
+                nodes_id.clone(),
+                client_timeout,
+                self.reconfiguration.clone(),
+                leader_election_latch.clone(),
+            );
+            client_comps.push(client_comp);
+            client_paths.push(client_path);
+            leader_election_latches.push(leader_election_latch);
+        }
+        if let Some(remote_clients) = &self.remote_clients {
+            let workloads = self.remote_workloads(&client_hosts, &nodes_id, client_timeout);
+            remote_clients
+                .actor_ref()
+                .tell(RemoteClientsMsg::Prepare(workloads));
+            let remote_paths = remote_clients
+                .actor_ref()
+                .ask(|promise| RemoteClientsMsg::AwaitPrepared(Ask::new(promise, ())))
+                .wait();
+            client_paths.extend(remote_paths); // by id, after the in-process clients
+        }
+        let partitioning_actor = self.initialise_iteration(nodes, &client_paths);
         partitioning_actor
             .actor_ref()
             .tell(IterationControlMsg::Run);
-        leader_election_latch.wait(); // wait until leader is established
+        for leader_election_latch in leader_election_latches {
+            leader_election_latch.wait(); // wait until every client knows the leader
+        }
+        if let Some(remote_clients) = &self.remote_clients {
+            remote_clients
+                .actor_ref()
+                .ask(|promise| RemoteClientsMsg::AwaitLeaders(Ask::new(promise, ())))
+                .wait();
+        }
         self.partitioning_actor = Some(partitioning_actor);
-        self.client_comp = Some(client_comp);
+        self.client_comps = client_comps;
     }
 
     fn run_iteration(&mut self) -> () {
         println!("Running Atomic Broadcast experiment!");
-        match self.client_comp {
-            Some(ref client_comp) => {
-                client_comp.actor_ref().tell(LocalClientMessage::Run);
-                let finished_latch = self.finished_latch.take().unwrap();
-                finished_latch.wait();
-            }
-            _ => panic!("No client found!"),
+        if self.client_comps.is_empty() {
+            panic!("No client found!");
+        }
+        // a shared start aligns the throughput windows of the clients
+        let run_start = SystemTime::now();
+        for client_comp in &self.client_comps {
+            client_comp
+                .actor_ref()
+                .tell(LocalClientMessage::Run(run_start));
+        }
+        if let Some(remote_clients) = &self.remote_clients {
+            remote_clients
+                .actor_ref()
+                .tell(RemoteClientsMsg::Run(run_start));
+        }
+        let finished_latch = self.finished_latch.take().unwrap();
+        finished_latch.wait();
+        if let Some(remote_clients) = &self.remote_clients {
+            remote_clients
+                .actor_ref()
+                .ask(|promise| RemoteClientsMsg::AwaitFinished(Ask::new(promise, ())))
+                .wait();
         }
     }
 
+    fn validate_iteration(&mut self) -> Result<(), BenchmarkError> {
+        let meta_results = self.stop_clients();
+        let res = match &meta_results.total_order {
+            Some(Err(violation)) => Err(BenchmarkError::ValidationFailed(format!(
+                "Total order violated in iteration {}: {:?}",
+                self.iteration_id, violation
+            ))),
+            _ => match meta_results
+                .kv
+                .as_ref()
+                .and_then(|kv| kv.timestamps.as_ref())
+            {
+                Some(timestamps) if !all_linearizable(timestamps) => {
+                    Err(BenchmarkError::ValidationFailed(format!(
+                        "Linearizability violated in iteration {}",
+                        self.iteration_id
+                    )))
+                }
+                _ => Ok(()),
+            },
+        };
+        self.meta_results = Some(meta_results);
+        res
+    }
+
     fn cleanup_iteration(&mut self, last_iteration: bool, exec_time_millis: f64) -> () {
         println!(
             "Cleaning up Atomic Broadcast (master) iteration {}. Exec_time: {}",
             self.iteration_id, exec_time_millis
         );
         let system = self.system.take().unwrap();
-        let client = self.client_comp.take().unwrap();
-        let meta_results = client
-            .actor_ref()
-            .ask(|promise| LocalClientMessage::Stop(Ask::new(promise, ())))
-            .wait();
-        self.num_timed_out.push(meta_results.num_timed_out);
-        if self.concurrent_proposals == Some(1)
-            || (self.reconfiguration.is_some() && cfg!(feature = "track_reconfig_latency"))
-        {
-            let meta_path = self.meta_results_path.as_ref().expect("No meta path!");
-            let dir = format!("{}/latency/", meta_path);
-            create_dir_all(&dir)
-                .unwrap_or_else(|_| panic!("Failed to create given directory: {}", &dir));
-            let mut file = OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(format!(
-                    "{}raw_{}.data",
-                    &dir,
-                    self.experiment_str.as_ref().unwrap()
-                ))
-                .expect("Failed to open latency file");
-            let histo = self.latency_hist.as_mut().unwrap();
-            for (_, l) in meta_results.latencies {
-                let latency = l.as_micros() as u64;
-                writeln!(file, "{}", latency).expect("Failed to write raw latency");
-                histo.record(latency).expect("Failed to record histogram");
+        let meta_results = match self.meta_results.take() {
+            Some(meta_results) => meta_results,
+            None => self.stop_clients(),
+        };
+        let mut secondary_clients = std::mem::take(&mut self.client_comps);
+        let primary_client = secondary_clients.remove(0);
+        let latency = if let Some(open_loop) = &meta_results.open_loop {
+            Some(LatencySummary::from_histogram(&open_loop.latency_hist))
+        } else if self.tracks_latency() {
+            let mut histo = Histogram::<u64>::new(4).expect("Failed to create latency histogram");
+            for (_, l) in &meta_results.latencies {
+                histo
+                    .record(l.as_micros() as u64)
+                    .expect("Failed to record histogram");
             }
-            writeln!(file, "").expect("Failed to write raw latency"); // separate each run with empty line. TODO handle in plotting
+            self.write_latencies(&meta_results.latencies);
+            Some(LatencySummary::from_histogram(&histo))
+        } else {
+            None
+        };
+        self.write_iteration_record(&meta_results, latency, exec_time_millis);
+        if let Some(throughput) = &meta_results.throughput {
+            self.write_throughput_series(throughput);
         }
 
-        let kill_client_f = system.kill_notify(client);
+        let kill_client_f = system.kill_notify(primary_client);
         kill_client_f
             .wait_timeout(REGISTER_TIMEOUT)
             .expect("Client never died");
+        for (client, client_system) in secondary_clients
+            .into_iter()
+            .zip(&self.in_process_client_systems)
+        {
+            client_system
+                .kill_notify(client)
+                .wait_timeout(REGISTER_TIMEOUT)
+                .expect("Client never died");
+        }
 
         if let Some(partitioning_actor) = self.partitioning_actor.take() {
             let kill_pactor_f = system.kill_notify(partitioning_actor);
@@ -555,96 +1414,36 @@ impl DistributedBenchmarkMaster for AtomicBroadcastMaster {
 
         if last_iteration {
             println!("Cleaning up last iteration");
-            let meta_path = self.meta_results_path.as_ref().expect("No meta path!");
-            let sum: u64 = self.num_timed_out.iter().sum();
-            if sum > 0 {
-                let summary_file_path = format!("{}/summary.out", meta_path);
-                create_dir_all(meta_path).unwrap_or_else(|_| {
-                    panic!("Failed to create given directory: {}", summary_file_path)
-                });
-                let mut summary_file = OpenOptions::new()
-                    .create(true)
-                    .append(true)
-                    .open(summary_file_path)
-                    .expect("Failed to open meta summary file");
-                let len = self.num_timed_out.len();
-                let timed_out_len = self.num_timed_out.iter().filter(|x| **x > 0).count();
-                self.num_timed_out.sort();
-                let min = self.num_timed_out.first().unwrap();
-                let max = self.num_timed_out.last().unwrap();
-                let avg = sum / (self.iteration_id as u64);
-                let median = self.num_timed_out[len / 2];
-                let summary_str = format!(
-                    "{}/{} runs had timeouts. sum: {}, avg: {}, med: {}, min: {}, max: {}",
-                    timed_out_len, len, sum, avg, median, min, max
-                );
-                writeln!(summary_file, "{}", self.experiment_str.as_ref().unwrap())
-                    .expect("Failed to write meta summary file");
-                writeln!(summary_file, "{}", summary_str).expect(&format!(
-                    "Failed to write meta summary file: {}",
-                    summary_str
-                ));
-                summary_file.flush().expect("Failed to flush meta file");
-            }
-            if self.concurrent_proposals == Some(1)
-                || (self.reconfiguration.is_some() && cfg!(feature = "track_reconfig_latency"))
-            {
-                let dir = format!("{}/latency/", meta_path);
-                create_dir_all(&dir)
-                    .unwrap_or_else(|_| panic!("Failed to create given directory: {}", dir));
-                let mut file = OpenOptions::new()
-                    .create(true)
-                    .append(true)
-                    .open(format!(
-                        "{}summary_{}.out",
-                        &dir,
-                        self.experiment_str.as_ref().unwrap()
-                    ))
-                    .expect("Failed to open latency file");
-                let hist = std::mem::take(&mut self.latency_hist).unwrap();
-                let quantiles = [
-                    0.001, 0.01, 0.005, 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9, 0.95, 0.99,
-                    0.999,
-                ];
-                for q in &quantiles {
-                    writeln!(
-                        file,
-                        "Value at quantile {}: {} micro s",
-                        q,
-                        hist.value_at_quantile(*q)
-                    )
-                    .expect("Failed to write summary latency file");
-                }
-                let max = hist.max();
-                writeln!(
-                    file,
-                    "Min: {} micro s, Max: {} micro s, Average: {} micro s",
-                    hist.min(),
-                    max,
-                    hist.mean()
-                )
-                .expect("Failed to write histogram summary");
-                writeln!(file, "Total elements: {}", hist.len())
-                    .expect("Failed to write histogram summary");
-                file.flush().expect("Failed to flush histogram file");
-                // flush raw file
-                let raw_file_path =
-                    format!("{}raw_{}.data", dir, self.experiment_str.as_ref().unwrap());
-                let mut raw_file = OpenOptions::new()
-                    .create(true)
-                    .append(true)
-                    .open(raw_file_path)
-                    .expect("Failed to open raw latency file");
-                raw_file.flush().expect("Failed to flush raw latency file");
+            // the remote clients themselves are killed by the cleanup of their benchmark client process
+            if let Some(remote_clients) = self.remote_clients.take() {
+                system
+                    .kill_notify(remote_clients)
+                    .wait_timeout(REGISTER_TIMEOUT)
+                    .expect("RemoteClients never died!");
             }
             self.algorithm = None;
             self.num_nodes = None;
-            self.reconfiguration = None;
+            self.num_initial_nodes = None;
+            self.reconfiguration = ReconfigSchedule::default();
             self.concurrent_proposals = None;
             self.num_proposals = None;
             self.experiment_str = None;
-            self.num_timed_out.clear();
-            self.num_written_latency = 0;
+            self.experiment_params.clear();
+            self.fault_schedule = FaultSchedule::default();
+            self.leader_transfers = TransferSchedule::default();
+            self.open_loop = None;
+            self.num_clients = None;
+            self.num_remote_clients = 0;
+            self.payload_size = None;
+            self.kv = None;
+            self.experiment_config = None;
+            self.client_timeout = None;
+            self.throughput_window = None;
+            for client_system in self.in_process_client_systems.drain(..) {
+                client_system
+                    .shutdown()
+                    .expect("Kompact didn't shut down properly");
+            }
             self.iteration_id = 0;
             system
                 .shutdown()
@@ -655,36 +1454,160 @@ impl DistributedBenchmarkMaster for AtomicBroadcastMaster {
     }
 }
 
+/// The replica of a client, for each algorithm and storage backend.
+enum Replica {
+    PaxosMemory(Arc<Component<PaxosComp<MemorySequence, MemoryState>>>),
+    PaxosDisk(Arc<Component<PaxosComp<DiskSequence, DiskState>>>),
+    RaftMemory(Arc<Component<RaftComp<MemStorage>>>),
+    RaftDisk(Arc<Component<RaftComp<DiskStorage>>>),
+}
+
+impl Replica {
+    fn kill_components(&self) {
+        match self {
+            Replica::PaxosMemory(paxos) => kill_paxos_components(paxos),
+            Replica::PaxosDisk(paxos) => kill_paxos_components(paxos),
+            Replica::RaftMemory(raft) => kill_raft_components(raft),
+            Replica::RaftDisk(raft) => kill_raft_components(raft),
+        }
+    }
+
+    fn kill(self, system: &KompactSystem) {
+        let (kill_replica_f, algorithm) = match self {
+            Replica::PaxosMemory(paxos) => (system.kill_notify(paxos), "Paxos"),
+            Replica::PaxosDisk(paxos) => (system.kill_notify(paxos), "Paxos"),
+            Replica::RaftMemory(raft) => (system.kill_notify(raft), "Raft"),
+            Replica::RaftDisk(raft) => (system.kill_notify(raft), "Raft"),
+        };
+        kill_replica_f
+            .wait_timeout(REGISTER_TIMEOUT)
+            .unwrap_or_else(|_| panic!("{} Replica never died!", algorithm));
+    }
+}
+
+fn kill_paxos_components<S, P>(paxos: &Arc<Component<PaxosComp<S, P>>>)
+where
+    S: SequenceTraits,
+    P: PaxosStateTraits,
+{
+    paxos
+        .actor_ref()
+        .ask(|p| PaxosCompMsg::KillComponents(Ask::new(p, ())))
+        .wait();
+}
+
+fn kill_raft_components<S>(raft: &Arc<Component<RaftComp<S>>>)
+where
+    S: RaftStorage + Send + Clone + 'static,
+{
+    raft.actor_ref()
+        .ask(|p| RaftCompMsg::KillComponents(Ask::new(p, ())))
+        .wait();
+}
+
+fn create_paxos_replica<S, P>(
+    system: &KompactSystem,
+    initial_config: Vec<u64>,
+    reconfig_policy: PaxosReconfigurationPolicy,
+    disk_config: Option<DiskConfig>,
+    compaction: CompactionPolicy,
+    kv: bool,
+    options: PaxosOptions,
+    initial_leader: u64,
+    experiment_config: ExperimentConfig,
+) -> (Arc<Component<PaxosComp<S, P>>>, ActorPath)
+where
+    S: SequenceTraits,
+    P: PaxosStateTraits,
+{
+    let (paxos_replica, unique_reg_f) = system.create_and_register(|| {
+        PaxosComp::with(
+            initial_config,
+            reconfig_policy,
+            experiment_config,
+            disk_config,
+            compaction,
+            kv,
+            options,
+            initial_leader,
+        )
+    });
+    unique_reg_f.wait_expect(REGISTER_TIMEOUT, "ReplicaComp failed to register!");
+    let self_path = system
+        .register_by_alias(&paxos_replica, PAXOS_PATH)
+        .wait_expect(REGISTER_TIMEOUT, "Failed to register alias for ReplicaComp");
+    let paxos_replica_f = system.start_notify(&paxos_replica);
+    paxos_replica_f
+        .wait_timeout(REGISTER_TIMEOUT)
+        .expect("ReplicaComp never started!");
+    (paxos_replica, self_path)
+}
+
+fn create_raft_replica<S>(
+    system: &KompactSystem,
+    initial_config: Vec<u64>,
+    reconfig_policy: RaftReconfigurationPolicy,
+    storage_dir: Option<String>,
+    compaction: CompactionPolicy,
+    kv: Option<ReadMode>,
+    initial_leader: u64,
+) -> (Arc<Component<RaftComp<S>>>, ActorPath)
+where
+    S: RaftStorage + Send + Clone + 'static,
+{
+    let (raft_replica, unique_reg_f) = system.create_and_register(|| {
+        RaftComp::<S>::with(
+            initial_config,
+            reconfig_policy,
+            storage_dir,
+            compaction,
+            kv,
+            initial_leader,
+        )
+    });
+    unique_reg_f.wait_expect(REGISTER_TIMEOUT, "RaftComp failed to register!");
+    let self_path = system
+        .register_by_alias(&raft_replica, RAFT_PATH)
+        .wait_expect(REGISTER_TIMEOUT, "Communicator failed to register!");
+    let raft_replica_f = system.start_notify(&raft_replica);
+    raft_replica_f
+        .wait_timeout(REGISTER_TIMEOUT)
+        .expect("RaftComp never started!");
+    (raft_replica, self_path)
+}
+
 pub struct AtomicBroadcastClient {
     system: Option<KompactSystem>,
-    paxos_comp: Option<Arc<Component<PaxosComp<MemorySequence, MemoryState>>>>,
-    raft_comp: Option<Arc<Component<RaftComp<Storage>>>>,
+    replica: Option<Replica>,
+    client_host: Option<Arc<Component<ClientHost>>>, // runs the remote clients the master assigns to this process
 }
 
 impl AtomicBroadcastClient {
-    fn new() -> AtomicBroadcastClient {
+    pub(super) fn new() -> AtomicBroadcastClient {
         AtomicBroadcastClient {
             system: None,
-            paxos_comp: None,
-            raft_comp: None,
+            replica: None,
+            client_host: None,
         }
     }
 }
 
 impl DistributedBenchmarkClient for AtomicBroadcastClient {
-    type ClientConf = ClientParams;
-    type ClientData = ActorPath;
+    type ClientConf = WithOverrides<ClientParams>;
+    type ClientData = ClientProcess;
 
     fn setup(&mut self, c: Self::ClientConf) -> Self::ClientData {
         println!("Setting up Atomic Broadcast (client)");
-        let mut conf = KompactConfig::default();
-        conf.load_config_file(CONFIG_PATH);
-        let bc = BufferConfig::from_config_file(CONFIG_PATH);
-        bc.validate();
-        let tcp_no_delay = true;
-        let system = crate::kompact_system_provider::global()
-            .new_remote_system_with_threads_config("atomicbroadcast", 4, conf, bc, tcp_no_delay);
-        let named_path = match &c.algorithm {
+        let provider = c.provider();
+        let c = c.conf;
+        let system = provider.new_remote_system_with_threads_config(
+            "atomicbroadcast",
+            4,
+            c.config.kompact_config(),
+            c.config.buffer_config(),
+            TCP_NODELAY,
+        );
+        let (replica, named_path) = match &c.algorithm {
             paxos if paxos == "paxos" || paxos == "paxos-batch" => {
                 let initial_config = get_initial_conf(c.last_node_id).0;
                 let reconfig_policy = match c.reconfig_policy.as_ref() {
@@ -693,24 +1616,37 @@ impl DistributedBenchmarkClient for AtomicBroadcastClient {
                     "pull" => Some(PaxosReconfigurationPolicy::Pull),
                     unknown => panic!("Got unknown Paxos transfer policy: {}", unknown),
                 };
-                let experiment_config = ExperimentConfig::load_from_file(CONFIG_PATH);
-                let (paxos_replica, unique_reg_f) = system.create_and_register(|| {
-                    PaxosComp::with(
-                        initial_config,
-                        reconfig_policy.unwrap_or(PaxosReconfigurationPolicy::Pull),
-                        experiment_config,
-                    )
-                });
-                unique_reg_f.wait_expect(REGISTER_TIMEOUT, "ReplicaComp failed to register!");
-                let self_path = system
-                    .register_by_alias(&paxos_replica, PAXOS_PATH)
-                    .wait_expect(REGISTER_TIMEOUT, "Failed to register alias for ReplicaComp");
-                let paxos_replica_f = system.start_notify(&paxos_replica);
-                paxos_replica_f
-                    .wait_timeout(REGISTER_TIMEOUT)
-                    .expect("ReplicaComp never started!");
-                self.paxos_comp = Some(paxos_replica);
-                self_path
+                let reconfig_policy = reconfig_policy.unwrap_or(PaxosReconfigurationPolicy::Pull);
+                match c.disk {
+                    None => {
+                        let (paxos, path) = create_paxos_replica::<MemorySequence, MemoryState>(
+                            &system,
+                            initial_config,
+                            reconfig_policy,
+                            None,
+                            c.compaction,
+                            c.kv.is_some(),
+                            c.paxos_options,
+                            c.initial_leader,
+                            c.config.clone(),
+                        );
+                        (Replica::PaxosMemory(paxos), path)
+                    }
+                    Some(disk_config) => {
+                        let (paxos, path) = create_paxos_replica::<DiskSequence, DiskState>(
+                            &system,
+                            initial_config,
+                            reconfig_policy,
+                            Some(disk_config),
+                            c.compaction,
+                            c.kv.is_some(),
+                            c.paxos_options,
+                            c.initial_leader,
+                            c.config.clone(),
+                        );
+                        (Replica::PaxosDisk(paxos), path)
+                    }
+                }
             }
             raft if raft == "raft" => {
                 let conf_state = get_initial_conf(c.last_node_id);
@@ -718,35 +1654,58 @@ impl DistributedBenchmarkClient for AtomicBroadcastClient {
                     "none" => None,
                     "replace-leader" => Some(RaftReconfigurationPolicy::ReplaceLeader),
                     "replace-follower" => Some(RaftReconfigurationPolicy::ReplaceFollower),
+                    "exact" => Some(RaftReconfigurationPolicy::Exact),
                     unknown => panic!("Got unknown Raft transfer policy: {}", unknown),
                 };
-                //                let storage = MemStorage::new_with_conf_state(conf_state);
-                //                let dir = "./diskstorage";
-                //                let storage = DiskStorage::new_with_conf_state(dir, conf_state);
+                let reconfig_policy =
+                    reconfig_policy.unwrap_or(RaftReconfigurationPolicy::ReplaceFollower);
                 /*** Setup RaftComp ***/
-                let (raft_replica, unique_reg_f) = system.create_and_register(|| {
-                    RaftComp::<Storage>::with(
-                        conf_state.0,
-                        reconfig_policy.unwrap_or(RaftReconfigurationPolicy::ReplaceFollower),
-                    )
-                });
-                unique_reg_f.wait_expect(REGISTER_TIMEOUT, "RaftComp failed to register!");
-                let self_path = system
-                    .register_by_alias(&raft_replica, RAFT_PATH)
-                    .wait_expect(REGISTER_TIMEOUT, "Communicator failed to register!");
-                let raft_replica_f = system.start_notify(&raft_replica);
-                raft_replica_f
-                    .wait_timeout(REGISTER_TIMEOUT)
-                    .expect("RaftComp never started!");
-
-                self.raft_comp = Some(raft_replica);
-                self_path
+                match c.disk {
+                    None => {
+                        let (raft, path) = create_raft_replica::<MemStorage>(
+                            &system,
+                            conf_state.0,
+                            reconfig_policy,
+                            None,
+                            c.compaction,
+                            c.kv,
+                            c.initial_leader,
+                        );
+                        (Replica::RaftMemory(raft), path)
+                    }
+                    Some(disk_config) => {
+                        let (raft, path) = create_raft_replica::<DiskStorage>(
+                            &system,
+                            conf_state.0,
+                            reconfig_policy,
+                            Some(disk_config.dir.to_string_lossy().into_owned()),
+                            c.compaction,
+                            c.kv,
+                            c.initial_leader,
+                        );
+                        (Replica::RaftDisk(raft), path)
+                    }
+                }
             }
             unknown => panic!("Got unknown algorithm: {}", unknown),
         };
+        let (client_host, unique_reg_f) = system.create_and_register(ClientHost::new);
+        unique_reg_f.wait_expect(REGISTER_TIMEOUT, "ClientHost failed to register!");
+        let client_host_path = system
+            .register_by_alias(&client_host, "client_host")
+            .wait_expect(REGISTER_TIMEOUT, "Failed to register alias for ClientHost");
+        system
+            .start_notify(&client_host)
+            .wait_timeout(REGISTER_TIMEOUT)
+            .expect("ClientHost never started!");
+        self.replica = Some(replica);
+        self.client_host = Some(client_host);
         self.system = Some(system);
         println!("Got path for Atomic Broadcast actor: {}", named_path);
-        named_path
+        ClientProcess {
+            replica: named_path,
+            client_host: client_host_path,
+        }
     }
 
     fn prepare_iteration(&mut self) -> () {
@@ -755,31 +1714,25 @@ impl DistributedBenchmarkClient for AtomicBroadcastClient {
 
     fn cleanup_iteration(&mut self, last_iteration: bool) -> () {
         println!("Cleaning up Atomic Broadcast (client)");
-        if let Some(paxos) = &self.paxos_comp {
-            let kill_comps_f = paxos
-                .actor_ref()
-                .ask(|p| PaxosCompMsg::KillComponents(Ask::new(p, ())));
-            kill_comps_f.wait();
+        if let Some(replica) = &self.replica {
+            replica.kill_components();
         }
-        if let Some(raft) = &self.raft_comp {
-            let kill_comps_f = raft
+        if let Some(client_host) = &self.client_host {
+            client_host
                 .actor_ref()
-                .ask(|p| RaftCompMsg::KillComponents(Ask::new(p, ())));
-            kill_comps_f.wait();
+                .ask(|promise| ClientHostLocal::Kill(Ask::new(promise, ())))
+                .wait();
         }
         if last_iteration {
             let system = self.system.take().unwrap();
-            if let Some(replica) = self.paxos_comp.take() {
-                let kill_replica_f = system.kill_notify(replica);
-                kill_replica_f
-                    .wait_timeout(REGISTER_TIMEOUT)
-                    .expect("Paxos Replica never died!");
+            if let Some(replica) = self.replica.take() {
+                replica.kill(&system);
             }
-            if let Some(raft_replica) = self.raft_comp.take() {
-                let kill_raft_f = system.kill_notify(raft_replica);
-                kill_raft_f
+            if let Some(client_host) = self.client_host.take() {
+                system
+                    .kill_notify(client_host)
                     .wait_timeout(REGISTER_TIMEOUT)
-                    .expect("Raft Replica never died!");
+                    .expect("ClientHost never died");
             }
             system
                 .shutdown()
@@ -787,3 +1740,71 @@ impl DistributedBenchmarkClient for AtomicBroadcastClient {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn experiment_config_test() {
+        let file_config =
+            ExperimentConfig::load(Some(CONFIG_PATH), "").expect("Invalid config file");
+        let overridden = ExperimentConfig::load(
+            Some(CONFIG_PATH),
+            "experiment.election_timeout = 1000\nraft { tick_period = 10 }",
+        )
+        .unwrap();
+        assert_eq!(overridden.election_timeout, 1000);
+        assert_eq!(overridden.raft_tick_period, 10);
+        assert_eq!(overridden.max_inflight, file_config.max_inflight);
+        // the clients only get the request-supplied config
+        let sent = ExperimentConfig::load(None, &overridden.to_hocon()).unwrap();
+        assert_eq!(sent, overridden);
+        assert!(ExperimentConfig::load(None, "experiment.election_timeout = 1000").is_err());
+        assert!(
+            ExperimentConfig::load(Some(CONFIG_PATH), "experiment.max_inflight = many").is_err()
+        );
+    }
+
+    #[test]
+    fn disk_config_test() {
+        let file_config = ExperimentConfig::load_disk_config(Some(CONFIG_PATH), "")
+            .expect("Invalid storage config");
+        let overridden = ExperimentConfig::load_disk_config(
+            Some(CONFIG_PATH),
+            "storage { directory = \"/tmp/replicas\", fsync = always }",
+        )
+        .unwrap();
+        assert_eq!(overridden.dir, PathBuf::from("/tmp/replicas"));
+        assert_eq!(overridden.fsync, FsyncPolicy::Always);
+        // the clients only get the config the master loaded
+        let sent = ExperimentConfig::load_disk_config(None, &file_config.to_hocon()).unwrap();
+        assert_eq!(sent.dir, file_config.dir);
+        assert_eq!(sent.fsync, file_config.fsync);
+        assert!(ExperimentConfig::load_disk_config(None, "").is_err());
+        assert!(ExperimentConfig::load_disk_config(
+            Some(CONFIG_PATH),
+            "storage { fsync = batched, fsync_batch_size = 0 }"
+        )
+        .is_err());
+        assert!(
+            ExperimentConfig::load_disk_config(Some(CONFIG_PATH), "storage.fsync = sometimes")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn benchmark_config_test() {
+        let load = |overrides| AtomicBroadcastMaster::load_benchmark_config(None, overrides);
+        let config =
+            load("experiment { client_timeout = 5 s, throughput_window = 10 ms }").unwrap();
+        assert_eq!(config.client_timeout, Duration::from_secs(5));
+        assert_eq!(config.meta_results_path, DEFAULT_META_RESULTS_PATH);
+        assert!(!config.check_total_order);
+        assert_eq!(config.throughput_window, Some(Duration::from_millis(10)));
+        let disabled =
+            load("experiment { client_timeout = 5 s, throughput_window = 0 ms }").unwrap();
+        assert_eq!(disabled.throughput_window, None);
+        assert!(load("experiment { client_timeout = 5 s, throughput_window = often }").is_err());
+    }
+}
@@ -1,26 +1,43 @@
+use super::fault::{
+    Fault, FaultRecord, FaultSchedule, FaultTarget, LinkFault, NetworkFault, NetworkFaultRecord,
+};
+use super::handover::{LeaderTransfer, TransferRecord, TransferSchedule};
+use super::kv::{KvClient, KvConfig, KvResults};
 use super::messages::{
-    AtomicBroadcastDeser, AtomicBroadcastMsg, Proposal, StopMsg as NetStopMsg, StopMsgDeser,
-    RECONFIG_ID,
+    AtomicBroadcastDeser, AtomicBroadcastMsg, FaultMsg, Proposal, StopMsg as NetStopMsg,
+    StopMsgDeser, TestMessage, TestMessageSer, CLIENT_ID_SHIFT, PROPOSAL_ID_LEN, RECONFIG_ID,
+};
+use super::reconfig::{ReconfigSchedule, ReconfigStep};
+use super::storage::IoStats;
+use benchmark_suite_shared::test_utils::{
+    check_total_order, DecidedSequence, ProposalLog, TotalOrderViolation,
 };
 use hashbrown::HashMap;
+use hdrhistogram::Histogram;
 use kompact::prelude::*;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::fmt;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 use synchronoise::event::CountdownError;
 use synchronoise::CountdownEvent;
 
+const ISSUE_PERIOD: Duration = Duration::from_millis(1);
+
 #[derive(PartialEq)]
 enum ExperimentState {
     LeaderElection,
     Running,
     ProposedReconfiguration,
     ReconfigurationElection,
+    FaultElection,
     Finished,
 }
 
 #[derive(Debug)]
 pub enum LocalClientMessage {
-    Run,
+    Run(SystemTime), // the start of the run, shared by all clients of the master
     Stop(Ask<(), MetaResults>), // (num_timed_out, latency)
 }
 
@@ -35,6 +52,239 @@ struct ProposalMetaData {
     timer: ScheduledTimer,
 }
 
+/// A network fault that has not healed yet.
+#[derive(Debug)]
+struct ActiveNetworkFault {
+    id: u32,
+    record: usize, // index in network_fault_records
+    start: SystemTime,
+    last_decided: SystemTime,
+}
+
+/// How an open-loop client spaces its proposals.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Arrivals {
+    Constant,
+    Poisson,
+}
+
+impl Arrivals {
+    pub fn parse(s: &str) -> Result<Arrivals, String> {
+        match s.to_lowercase().as_ref() {
+            "" | "constant" => Ok(Arrivals::Constant),
+            "poisson" => Ok(Arrivals::Poisson),
+            unknown => Err(format!("Unknown arrivals: {}", unknown)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Arrivals::Constant => "constant",
+            Arrivals::Poisson => "poisson",
+        }
+    }
+}
+
+/// Issue proposals at `rate` per second, regardless of how many are in flight.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OpenLoop {
+    pub rate: u64,
+    pub arrivals: Arrivals,
+}
+
+impl OpenLoop {
+    fn interarrival<R: Rng>(&self, rng: &mut R) -> Duration {
+        let mean = 1.0 / self.rate as f64;
+        match self.arrivals {
+            Arrivals::Constant => Duration::from_secs_f64(mean),
+            Arrivals::Poisson => {
+                let u: f64 = rng.gen();
+                Duration::from_secs_f64(-(1.0 - u).ln() * mean)
+            }
+        }
+    }
+}
+
+impl fmt::Display for OpenLoop {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.arrivals.as_str(), self.rate)
+    }
+}
+
+/// Size of the proposals in bytes, including the id.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PayloadSize {
+    Fixed(usize),
+    /// Drawn uniformly from `[min, max]`.
+    Uniform(usize, usize),
+    /// Drawn from an exponential distribution with the given mean, capped at `EXP_CAP_FACTOR` times the mean.
+    Exponential(usize),
+}
+
+const EXP_CAP_FACTOR: usize = 10;
+/// The largest proposal size, as every client allocates a payload buffer of the largest size it produces.
+const MAX_PAYLOAD_SIZE: usize = 64 * 1024 * 1024;
+
+impl PayloadSize {
+    /// Parses `<bytes>`, `uniform(<min>,<max>)` or `exp(<mean>)`. Sizes below the id length are padded up to it.
+    /// The bounds of `uniform` may also be separated by `-` like they are displayed, which keeps commas out of the
+    /// experiment string.
+    pub fn parse(s: &str) -> Result<PayloadSize, String> {
+        let s = s.trim().to_lowercase();
+        let parse_bytes = |b: &str| {
+            b.trim()
+                .parse::<usize>()
+                .map_err(|e| format!("Invalid payload size {}: {}", b, e))
+        };
+        if s.is_empty() {
+            return Ok(PayloadSize::Fixed(PROPOSAL_ID_LEN));
+        }
+        let open = match s.find('(') {
+            Some(i) if s.ends_with(')') => i,
+            _ => return PayloadSize::Fixed(parse_bytes(&s)?).validated(),
+        };
+        let args = &s[open + 1..s.len() - 1];
+        let size = match &s[..open] {
+            "uniform" => {
+                let bounds: Vec<&str> = args.split(|c| c == ',' || c == '-').collect();
+                if bounds.len() != 2 {
+                    return Err(format!("Expected uniform(<min>,<max>) but got: {}", s));
+                }
+                PayloadSize::Uniform(parse_bytes(bounds[0])?, parse_bytes(bounds[1])?)
+            }
+            "exp" => PayloadSize::Exponential(parse_bytes(args)?),
+            unknown => return Err(format!("Unknown payload size distribution: {}", unknown)),
+        };
+        size.validated()
+    }
+
+    /// Checks that the sizes form a range and stay within `MAX_PAYLOAD_SIZE`.
+    pub fn validated(self) -> Result<PayloadSize, String> {
+        if let PayloadSize::Uniform(min, max) = self {
+            if min > max {
+                return Err(format!("Empty payload size range: {}", self));
+            }
+        }
+        let max = match self {
+            PayloadSize::Fixed(size) | PayloadSize::Uniform(_, size) => Some(size),
+            PayloadSize::Exponential(mean) => mean.checked_mul(EXP_CAP_FACTOR),
+        };
+        match max {
+            Some(max) if max <= MAX_PAYLOAD_SIZE => Ok(self),
+            _ => Err(format!(
+                "Payload size {} can exceed the maximum of {} bytes",
+                self, MAX_PAYLOAD_SIZE
+            )),
+        }
+    }
+
+    /// The largest size this can produce.
+    fn max_size(&self) -> usize {
+        let max = match *self {
+            PayloadSize::Fixed(size) => size,
+            PayloadSize::Uniform(_, max) => max,
+            PayloadSize::Exponential(mean) => mean.saturating_mul(EXP_CAP_FACTOR),
+        };
+        max.max(PROPOSAL_ID_LEN)
+    }
+
+    /// The size of proposal `id`. It is derived from the id, so a retried proposal keeps its size.
+    fn size_of(&self, id: u64) -> usize {
+        let size = match *self {
+            PayloadSize::Fixed(size) => size,
+            PayloadSize::Uniform(min, max) => {
+                SmallRng::seed_from_u64(id).gen_range(min as u64, max as u64 + 1) as usize
+            }
+            PayloadSize::Exponential(mean) => {
+                let u: f64 = SmallRng::seed_from_u64(id).gen();
+                let size = (-(1.0 - u).ln() * mean as f64) as usize;
+                size.min(mean.saturating_mul(EXP_CAP_FACTOR))
+            }
+        };
+        size.max(PROPOSAL_ID_LEN)
+    }
+}
+
+impl fmt::Display for PayloadSize {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PayloadSize::Fixed(size) => write!(f, "{}", size),
+            PayloadSize::Uniform(min, max) => write!(f, "uniform({}-{})", min, max),
+            PayloadSize::Exponential(mean) => write!(f, "exp({})", mean),
+        }
+    }
+}
+
+/// Schedule and measurements of an open-loop run.
+struct OpenLoopState {
+    config: OpenLoop,
+    rng: SmallRng,
+    start: SystemTime,
+    next_send: SystemTime, // scheduled send time of the next proposal
+    last_issued: Option<SystemTime>,
+    last_decided: Option<SystemTime>,
+    timer: Option<ScheduledTimer>,
+    latency_hist: Histogram<u64>, // in micros, from the scheduled send time
+}
+
+impl OpenLoopState {
+    fn with(config: OpenLoop) -> OpenLoopState {
+        let now = SystemTime::now();
+        OpenLoopState {
+            config,
+            rng: SmallRng::from_entropy(),
+            start: now,
+            next_send: now,
+            last_issued: None,
+            last_decided: None,
+            timer: None,
+            latency_hist: Histogram::new(4).expect("Failed to create latency histogram"),
+        }
+    }
+
+    fn rate(&self, count: u64, end: Option<SystemTime>) -> f64 {
+        match end.and_then(|t| t.duration_since(self.start).ok()) {
+            Some(d) if d > Duration::from_millis(0) => count as f64 / d.as_secs_f64(),
+            _ => 0.0,
+        }
+    }
+
+    fn into_results(self, issued: u64, decided: u64) -> OpenLoopResults {
+        // the first proposal is issued at the start, so the issue span covers one less
+        let offered_rate = self.rate(issued.saturating_sub(1), self.last_issued);
+        let achieved_rate = self.rate(decided, self.last_decided);
+        OpenLoopResults {
+            target_rate: self.config.rate,
+            offered_rate,
+            achieved_rate,
+            decided,
+            latency_hist: self.latency_hist,
+        }
+    }
+}
+
+impl OpenLoopResults {
+    /// Adds the results of another client of the same run.
+    fn merge(&mut self, other: OpenLoopResults) {
+        self.target_rate += other.target_rate;
+        self.offered_rate += other.offered_rate;
+        self.achieved_rate += other.achieved_rate;
+        self.decided += other.decided;
+        self.latency_hist
+            .add(&other.latency_hist)
+            .expect("Failed to merge latency histogram");
+    }
+}
+
+#[derive(Debug)]
+pub struct OpenLoopResults {
+    pub target_rate: u64,
+    pub offered_rate: f64,  // proposals per second actually issued
+    pub achieved_rate: f64, // decided proposals per second
+    pub decided: u64,
+    pub latency_hist: Histogram<u64>,
+}
+
 impl ProposalMetaData {
     fn with(start_time: Option<SystemTime>, timer: ScheduledTimer) -> ProposalMetaData {
         ProposalMetaData { start_time, timer }
@@ -45,17 +295,188 @@ impl ProposalMetaData {
     }
 }
 
+/// How much a single client got decided, to compare the clients of a run.
+#[derive(Debug)]
+pub struct ClientStats {
+    pub decided: u64,
+    pub throughput: f64, // decided proposals per second
+    pub decided_bytes: u64,
+    pub byte_throughput: f64, // decided bytes per second
+}
+
+/// When a reconfiguration of a run was proposed and how long it took until the client got its response.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReconfigTiming {
+    pub proposed_at: Duration,           // since the start of the run
+    pub decided_after: Option<Duration>, // None if the reconfiguration was never decided
+}
+
+/// Something that happened during a run, marked on the throughput timeline.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimelineEvent {
+    LeaderChange(u64),
+    Crash(u64),
+    NetworkFault(String),
+    ReconfigProposed,
+    ReconfigDecided,
+    LeaderTransfer(u64),
+}
+
+impl TimelineEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimelineEvent::LeaderChange(_) => "leader_change",
+            TimelineEvent::Crash(_) => "crash",
+            TimelineEvent::NetworkFault(_) => "network_fault",
+            TimelineEvent::ReconfigProposed => "reconfig_proposed",
+            TimelineEvent::ReconfigDecided => "reconfig_decided",
+            TimelineEvent::LeaderTransfer(_) => "leader_transfer",
+        }
+    }
+}
+
+/// The number of decided proposals in each window of a run, and the events of the run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThroughputSeries {
+    pub window: Duration,
+    pub decided: Vec<u64>, // index i counts the proposals decided in [i * window, (i + 1) * window) of the run
+    pub events: Vec<(Duration, TimelineEvent)>, // since the start of the run
+}
+
+impl ThroughputSeries {
+    pub fn with(window: Duration) -> ThroughputSeries {
+        assert!(window > Duration::from_millis(0), "Empty throughput window");
+        ThroughputSeries {
+            window,
+            decided: vec![],
+            events: vec![],
+        }
+    }
+
+    pub fn record_decided(&mut self, since_start: Duration) {
+        let idx = (since_start.as_nanos() / self.window.as_nanos()) as usize;
+        if idx >= self.decided.len() {
+            self.decided.resize(idx + 1, 0);
+        }
+        self.decided[idx] += 1;
+    }
+
+    pub fn record_event(&mut self, since_start: Duration, event: TimelineEvent) {
+        self.events.push((since_start, event));
+    }
+
+    /// Adds the decided proposals of another client of the same run, whose windows start at the same time.
+    /// The events are those of the primary client.
+    fn merge(&mut self, other: ThroughputSeries) {
+        if other.decided.len() > self.decided.len() {
+            self.decided.resize(other.decided.len(), 0);
+        }
+        for (count, other_count) in self.decided.iter_mut().zip(other.decided) {
+            *count += other_count;
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct MetaResults {
     pub num_timed_out: u64,
     pub latencies: Vec<(u64, Duration)>,
+    pub leader_changes: Vec<u64>, // the leader after each change, as seen by the primary client
+    pub reconfigurations: Vec<ReconfigTiming>, // in the order they were proposed
+    pub throughput: Option<ThroughputSeries>,
+    pub total_order: Option<Result<(), TotalOrderViolation>>, // None until `check_total_order` of the merged results
+    pub proposal_log: Option<ProposalLog>, // of all merged clients, None if the total order is not checked
+    pub decided_sequences: std::collections::HashMap<u64, DecidedSequence>, // only collected by the primary client
+    pub faults: Vec<FaultRecord>,
+    pub network_faults: Vec<NetworkFaultRecord>,
+    pub leader_transfers: Vec<TransferRecord>,
+    pub open_loop: Option<OpenLoopResults>,
+    pub clients: Vec<ClientStats>,
+    pub kv: Option<KvResults>,
+    pub io_stats: Vec<(u64, IoStats)>, // (pid, I/O of its storage), only collected by the primary client
 }
 
 impl MetaResults {
-    pub fn with(num_timed_out: u64, latencies: Vec<(u64, Duration)>) -> Self {
+    pub fn with(
+        num_timed_out: u64,
+        latencies: Vec<(u64, Duration)>,
+        leader_changes: Vec<u64>,
+        reconfigurations: Vec<ReconfigTiming>,
+        throughput: Option<ThroughputSeries>,
+        proposal_log: Option<ProposalLog>,
+        decided_sequences: std::collections::HashMap<u64, DecidedSequence>,
+        faults: Vec<FaultRecord>,
+        network_faults: Vec<NetworkFaultRecord>,
+        leader_transfers: Vec<TransferRecord>,
+        open_loop: Option<OpenLoopResults>,
+        client_stats: ClientStats,
+        kv: Option<KvResults>,
+        io_stats: Vec<(u64, IoStats)>,
+    ) -> Self {
         MetaResults {
             num_timed_out,
             latencies,
+            leader_changes,
+            reconfigurations,
+            throughput,
+            total_order: None,
+            proposal_log,
+            decided_sequences,
+            faults,
+            network_faults,
+            leader_transfers,
+            open_loop,
+            clients: vec![client_stats],
+            kv,
+            io_stats,
+        }
+    }
+
+    /// Adds the results of another client of the same run. Only the primary client collects the decided sequences,
+    /// reconfigures and transfers the leadership, and the leader changes it observed are kept.
+    pub fn merge(&mut self, other: MetaResults) {
+        self.num_timed_out += other.num_timed_out;
+        self.latencies.extend(other.latencies);
+        self.throughput = match (self.throughput.take(), other.throughput) {
+            (Some(mut t), Some(other_t)) => {
+                t.merge(other_t);
+                Some(t)
+            }
+            (t, other_t) => t.or(other_t),
+        };
+        self.proposal_log = match (self.proposal_log.take(), other.proposal_log) {
+            (Some(mut log), Some(other_log)) => {
+                log.merge(other_log);
+                Some(log)
+            }
+            (log, other_log) => log.or(other_log),
+        };
+        self.decided_sequences.extend(other.decided_sequences);
+        self.faults.extend(other.faults);
+        self.network_faults.extend(other.network_faults);
+        self.open_loop = match (self.open_loop.take(), other.open_loop) {
+            (Some(mut ol), Some(other_ol)) => {
+                ol.merge(other_ol);
+                Some(ol)
+            }
+            (ol, other_ol) => ol.or(other_ol),
+        };
+        self.clients.extend(other.clients);
+        self.kv = match (self.kv.take(), other.kv) {
+            (Some(mut kv), Some(other_kv)) => {
+                kv.merge(other_kv);
+                Some(kv)
+            }
+            (kv, other_kv) => kv.or(other_kv),
+        };
+        self.io_stats.extend(other.io_stats);
+    }
+
+    /// Checks the decided sequences against the proposals of all clients, once their results were merged.
+    pub fn check_total_order(&mut self) {
+        if let Some(log) = self.proposal_log.take() {
+            let sequences = std::mem::take(&mut self.decided_sequences);
+            self.total_order = Some(check_total_order(&sequences, &log));
         }
     }
 }
@@ -63,10 +484,12 @@ impl MetaResults {
 #[derive(ComponentDefinition)]
 pub struct Client {
     ctx: ComponentContext<Self>,
+    id: u64, // index of this client, the primary client is 0
     num_proposals: u64,
     num_concurrent_proposals: u64,
     nodes: HashMap<u64, ActorPath>,
-    reconfig: Option<(Vec<u64>, Vec<u64>)>,
+    reconfigs: Vec<ReconfigStep>, // not proposed yet, in the order they should be
+    pending_reconfig: Option<(Vec<u64>, Vec<u64>)>, // (voters, learners) proposed but not decided yet
     leader_election_latch: Arc<CountdownEvent>,
     finished_latch: Arc<CountdownEvent>,
     latest_proposal_id: u64,
@@ -76,11 +499,35 @@ pub struct Client {
     current_leader: u64,
     state: ExperimentState,
     current_config: Vec<u64>,
+    current_learners: Vec<u64>,
     num_timed_out: u64,
     leader_changes: Vec<u64>,
     first_proposal_after_reconfig: Option<u64>,
+    reconfig_times: Vec<(SystemTime, Option<SystemTime>)>, // (proposed, decided) of each reconfiguration
     retry_proposals: Vec<(u64, Option<SystemTime>)>,
     stop_ask: Option<Ask<(), MetaResults>>,
+    proposal_log: Option<ProposalLog>, // only kept if the total order should be checked
+    decided_sequences: HashMap<u64, DecidedSequence>,
+    stopped_nodes: HashMap<u64, ActorPath>, // asked for their I/O once all nodes stopped
+    io_stats: Vec<(u64, IoStats)>,
+    faults: Vec<Fault>, // not injected yet, in the order they should be
+    fault_records: Vec<FaultRecord>,
+    ongoing_crash: Option<(usize, SystemTime)>, // (index in fault_records, crash time) until proposals are decided again
+    network_faults: Vec<NetworkFault>,          // not injected yet, in the order they should be
+    network_fault_records: Vec<NetworkFaultRecord>,
+    active_network_faults: Vec<ActiveNetworkFault>,
+    next_link_fault_id: u32,
+    leader_transfers: Vec<LeaderTransfer>, // not requested yet, in the order they should be
+    transfer_records: Vec<TransferRecord>,
+    ongoing_transfer: Option<(usize, SystemTime)>, // (index in transfer_records, request time) until proposals are decided under the new leader
+    open_loop: Option<OpenLoopState>,
+    payload_size: PayloadSize,
+    payload: Vec<u8>, // random bytes that the payload of each proposal is taken from
+    decided_bytes: u64,
+    run_start: Option<SystemTime>,
+    run_end: Option<SystemTime>,
+    throughput: Option<ThroughputSeries>,
+    kv: Option<KvClient>, // proposes key-value commands instead of payloads
     #[cfg(feature = "track_timeouts")]
     timeouts: Vec<u64>,
     #[cfg(feature = "track_timeouts")]
@@ -89,21 +536,38 @@ pub struct Client {
 
 impl Client {
     pub fn with(
+        id: u64,
         initial_config: Vec<u64>,
         num_proposals: u64,
         num_concurrent_proposals: u64,
         nodes: HashMap<u64, ActorPath>,
-        reconfig: Option<(Vec<u64>, Vec<u64>)>,
+        reconfig_schedule: ReconfigSchedule,
         timeout: Duration,
         leader_election_latch: Arc<CountdownEvent>,
         finished_latch: Arc<CountdownEvent>,
+        check_total_order: bool,
+        fault_schedule: FaultSchedule,
+        leader_transfers: TransferSchedule,
+        open_loop: Option<OpenLoop>,
+        payload_size: PayloadSize,
+        kv: Option<KvConfig>,
+        throughput_window: Option<Duration>,
     ) -> Client {
+        let mut payload = vec![0; payload_size.max_size() - PROPOSAL_ID_LEN];
+        SmallRng::seed_from_u64(id).fill(payload.as_mut_slice());
+        let proposal_log = if check_total_order {
+            Some(ProposalLog::default())
+        } else {
+            None
+        };
         Client {
             ctx: ComponentContext::uninitialised(),
+            id,
             num_proposals,
             num_concurrent_proposals,
             nodes,
-            reconfig,
+            reconfigs: reconfig_schedule.steps,
+            pending_reconfig: None,
             leader_election_latch,
             finished_latch,
             latest_proposal_id: 0,
@@ -113,11 +577,35 @@ impl Client {
             current_leader: 0,
             state: ExperimentState::LeaderElection,
             current_config: initial_config,
+            current_learners: vec![],
             num_timed_out: 0,
             leader_changes: vec![],
             first_proposal_after_reconfig: None,
+            reconfig_times: vec![],
             retry_proposals: Vec::with_capacity(num_concurrent_proposals as usize),
             stop_ask: None,
+            proposal_log,
+            decided_sequences: HashMap::new(),
+            stopped_nodes: HashMap::new(),
+            io_stats: vec![],
+            faults: fault_schedule.faults,
+            fault_records: vec![],
+            ongoing_crash: None,
+            network_faults: fault_schedule.network_faults,
+            network_fault_records: vec![],
+            active_network_faults: vec![],
+            next_link_fault_id: 0,
+            leader_transfers: leader_transfers.transfers,
+            transfer_records: vec![],
+            ongoing_transfer: None,
+            open_loop: open_loop.map(OpenLoopState::with),
+            payload_size,
+            payload,
+            decided_bytes: 0,
+            run_start: None,
+            run_end: None,
+            throughput: throughput_window.map(ThroughputSeries::with),
+            kv: kv.map(|config| KvClient::with(config, id)),
             #[cfg(feature = "track_timeouts")]
             timeouts: vec![],
             #[cfg(feature = "track_timeouts")]
@@ -125,16 +613,30 @@ impl Client {
         }
     }
 
-    fn propose_normal(&self, id: u64, node: &ActorPath) {
-        let mut data: Vec<u8> = Vec::with_capacity(8);
-        data.put_u64(id);
+    /// The id of proposal `id` of this client in the decided sequences, unique among all clients.
+    fn global_id(&self, id: u64) -> u64 {
+        (self.id << CLIENT_ID_SHIFT) + id
+    }
+
+    fn propose_normal(&mut self, id: u64, node: &ActorPath) {
+        let proposal_id = self.global_id(id);
+        let data = match self.kv.as_mut() {
+            Some(kv) => kv.command(id).serialise(proposal_id),
+            None => {
+                let size = self.payload_size.size_of(id);
+                let mut data: Vec<u8> = Vec::with_capacity(size);
+                data.put_u64(proposal_id);
+                data.extend_from_slice(&self.payload[..size - PROPOSAL_ID_LEN]);
+                data
+            }
+        };
         let p = Proposal::normal(data);
         node.tell_serialised(AtomicBroadcastMsg::Proposal(p), self)
             .expect("Should serialise Proposal");
     }
 
     fn propose_reconfiguration(&self, node: &ActorPath) {
-        let reconfig = self.reconfig.as_ref().unwrap();
+        let reconfig = self.pending_reconfig.as_ref().unwrap();
         debug!(
             self.ctx.log(),
             "{}",
@@ -153,6 +655,13 @@ impl Client {
     }
 
     fn send_concurrent_proposals(&mut self) {
+        if self.open_loop.is_some() {
+            // proposals are issued by the schedule, only send those waiting for a leader
+            if self.current_leader != 0 && !self.retry_proposals.is_empty() {
+                self.send_held_back_proposals(self.retry_proposals.len());
+            }
+            return;
+        }
         let num_inflight = self.pending_proposals.len() as u64;
         assert!(num_inflight <= self.num_concurrent_proposals);
         let available_n = self.num_concurrent_proposals - num_inflight;
@@ -192,32 +701,103 @@ impl Client {
             } else {
                 num_retry_proposals
             };
-            let retry_proposals: Vec<_> = self.retry_proposals.drain(0..n).collect();
-            #[cfg(feature = "track_timeouts")]
-            {
-                let min = retry_proposals.iter().min();
-                let max = retry_proposals.iter().max();
-                let count = retry_proposals.len();
-                let num_pending = self.pending_proposals.len();
-                info!(self.ctx.log(), "Retrying proposals to node {}. Count: {}, min: {:?}, max: {:?}, num_pending: {}", self.current_leader, count, min, max, num_pending);
-            }
-            for (id, start_time) in retry_proposals {
-                self.propose_normal(id, &leader);
-                let timer = self.schedule_once(self.timeout, move |c, _| c.proposal_timeout(id));
-                let meta = ProposalMetaData::with(start_time, timer);
-                self.pending_proposals.insert(id, meta);
-            }
+            self.send_held_back_proposals(n);
         }
     }
 
+    fn send_held_back_proposals(&mut self, n: usize) {
+        let leader = self.nodes.get(&self.current_leader).unwrap().clone();
+        let retry_proposals: Vec<_> = self.retry_proposals.drain(0..n).collect();
+        #[cfg(feature = "track_timeouts")]
+        {
+            let min = retry_proposals.iter().min();
+            let max = retry_proposals.iter().max();
+            let count = retry_proposals.len();
+            let num_pending = self.pending_proposals.len();
+            info!(
+                self.ctx.log(),
+                "Retrying proposals to node {}. Count: {}, min: {:?}, max: {:?}, num_pending: {}",
+                self.current_leader,
+                count,
+                min,
+                max,
+                num_pending
+            );
+        }
+        for (id, start_time) in retry_proposals {
+            self.propose_normal(id, &leader);
+            let timer = self.schedule_once(self.timeout, move |c, _| c.proposal_timeout(id));
+            let meta = ProposalMetaData::with(start_time, timer);
+            self.pending_proposals.insert(id, meta);
+        }
+    }
+
+    fn start_open_loop(&mut self) {
+        let now = SystemTime::now();
+        if let Some(ol) = self.open_loop.as_mut() {
+            ol.start = now;
+            ol.next_send = now;
+        }
+        let timer = self.schedule_periodic(Duration::from_millis(0), ISSUE_PERIOD, |c, _| {
+            c.issue_scheduled_proposals()
+        });
+        self.open_loop.as_mut().unwrap().timer = Some(timer);
+    }
+
+    /// Issues every proposal whose scheduled send time has passed, whether or not the earlier ones were decided.
+    /// Latencies are measured from the scheduled time so that a slow system can not hide its queueing delay.
+    fn issue_scheduled_proposals(&mut self) -> Handled {
+        if self.state == ExperimentState::Finished {
+            return Handled::Ok;
+        }
+        let now = SystemTime::now();
+        let ol = self
+            .open_loop
+            .as_mut()
+            .expect("Issuing scheduled proposals without open loop");
+        while self.latest_proposal_id < self.num_proposals && ol.next_send <= now {
+            self.latest_proposal_id += 1;
+            self.retry_proposals
+                .push((self.latest_proposal_id, Some(ol.next_send)));
+            ol.last_issued = Some(ol.next_send);
+            ol.next_send += ol.config.interarrival(&mut ol.rng);
+        }
+        let finished_issuing = if self.latest_proposal_id == self.num_proposals {
+            ol.timer.take()
+        } else {
+            None
+        };
+        if let Some(timer) = finished_issuing {
+            self.cancel_timer(timer);
+        }
+        if self.state == ExperimentState::Running {
+            self.send_concurrent_proposals();
+        }
+        Handled::Ok
+    }
+
     fn handle_normal_response(&mut self, id: u64, latency_res: Option<Duration>) {
+        if let (Some(ol), Some(latency)) = (self.open_loop.as_mut(), latency_res) {
+            ol.latency_hist
+                .record(latency.as_micros() as u64)
+                .expect("Failed to record latency");
+        }
         self.responses.insert(id, latency_res);
         let received_count = self.responses.len() as u64;
-        if received_count == self.num_proposals && self.reconfig.is_none() {
+        if received_count == self.num_proposals && self.pending_reconfig.is_none() {
+            if !self.reconfigs.is_empty() {
+                warn!(
+                    self.ctx.log(),
+                    "{} reconfigurations were never proposed, as the previous one took until the end of the run",
+                    self.reconfigs.len()
+                );
+            }
             self.state = ExperimentState::Finished;
+            self.run_end = Some(SystemTime::now());
             self.finished_latch
                 .decrement()
                 .expect("Failed to countdown finished latch");
+            self.close_network_faults();
             if self.num_timed_out > 0 {
                 info!(self.ctx.log(), "Got all responses with {} timeouts, Number of leader changes: {}, {:?}, Last leader was: {}", self.num_timed_out, self.leader_changes.len(), self.leader_changes, self.current_leader);
                 #[cfg(feature = "track_timeouts")]
@@ -245,24 +825,322 @@ impl Client {
                     self.current_leader
                 );
             }
-        } else if received_count == self.num_proposals / 2 && self.reconfig.is_some() {
-            if let Some(leader) = self.nodes.get(&self.current_leader) {
-                self.propose_reconfiguration(&leader);
-                #[cfg(feature = "track_reconfig_latency")]
-                {
-                    self.state = ExperimentState::ProposedReconfiguration;
+        } else if self.reconfiguration_due(received_count) {
+            self.start_reconfiguration();
+        }
+        if self.state == ExperimentState::Running {
+            self.inject_faults(received_count);
+            self.transfer_leadership(received_count);
+        }
+    }
+
+    /// If the next reconfiguration should be proposed. A reconfiguration is only proposed once the previous one is
+    /// decided and the client knows the leader of the new configuration, so a late one is proposed as soon as possible.
+    fn reconfiguration_due(&self, received_count: u64) -> bool {
+        match self.reconfigs.first() {
+            Some(step) => {
+                received_count >= step.trigger_count(self.num_proposals)
+                    && self.pending_reconfig.is_none()
+                    && self.state == ExperimentState::Running
+                    && self.current_leader != 0
+            }
+            None => false,
+        }
+    }
+
+    /// Proposes the next reconfiguration, with its changes applied to the current configuration and leader.
+    fn start_reconfiguration(&mut self) {
+        let step = self.reconfigs.remove(0);
+        let reconfig = step.apply(
+            &self.current_config,
+            &self.current_learners,
+            self.current_leader,
+        );
+        info!(
+            self.ctx.log(),
+            "Reconfiguring with {} from {:?} to {:?}", step, self.current_config, reconfig
+        );
+        self.pending_reconfig = Some(reconfig);
+        if let Some(leader) = self.nodes.get(&self.current_leader) {
+            self.propose_reconfiguration(&leader);
+            #[cfg(feature = "track_reconfig_latency")]
+            {
+                self.state = ExperimentState::ProposedReconfiguration;
+            }
+        }
+        self.reconfig_times.push((SystemTime::now(), None));
+        self.mark_event(TimelineEvent::ReconfigProposed);
+        let timer = self.schedule_once(self.timeout, move |c, _| c.proposal_timeout(RECONFIG_ID));
+        let proposal_meta = ProposalMetaData::with(None, timer);
+        self.pending_proposals.insert(RECONFIG_ID, proposal_meta);
+    }
+
+    fn inject_faults(&mut self, received_count: u64) {
+        while let Some(fault) = self.network_faults.first() {
+            if received_count < fault.trigger_count(self.num_proposals) {
+                break;
+            }
+            let fault = self.network_faults.remove(0);
+            self.inject_network_fault(fault);
+        }
+        while let Some(fault) = self.faults.first() {
+            if received_count < fault.trigger_count(self.num_proposals)
+                || self.state != ExperimentState::Running
+            {
+                return;
+            }
+            let fault = self.faults.remove(0);
+            self.inject_fault(fault);
+        }
+    }
+
+    fn inject_fault(&mut self, fault: Fault) {
+        let pid = match fault.target {
+            FaultTarget::Leader => self.current_leader,
+            FaultTarget::Node(pid) => pid,
+        };
+        let node = match self.nodes.get(&pid) {
+            Some(node) => node,
+            None => {
+                warn!(self.ctx.log(), "Skipping fault {}: no node {}", fault, pid);
+                return;
+            }
+        };
+        let was_leader = pid == self.current_leader;
+        info!(
+            self.ctx.log(),
+            "Injecting fault {} at node {}. Leader: {}", fault, pid, was_leader
+        );
+        node.tell_serialised(FaultMsg::Crash(fault.downtime), self)
+            .expect("Should serialise FaultMsg");
+        self.mark_event(TimelineEvent::Crash(pid));
+        self.fault_records.push(FaultRecord {
+            pid,
+            was_leader,
+            downtime: fault.downtime,
+            election_time: None,
+            unavailability: None,
+        });
+        self.ongoing_crash = Some((self.fault_records.len() - 1, SystemTime::now()));
+        if was_leader {
+            // the proposals are lost with the leader, propose them again to the next one
+            self.hold_back_pending_proposals();
+            let pending: Vec<u64> = self
+                .retry_proposals
+                .iter()
+                .map(|(id, _)| self.global_id(*id))
+                .collect();
+            if let Some(log) = self.proposal_log.as_mut() {
+                // unless the leader replicated them before it crashed
+                log.pending_at_crash.extend(pending);
+            }
+            self.current_leader = 0;
+            self.state = ExperimentState::FaultElection;
+        }
+    }
+
+    fn inject_network_fault(&mut self, fault: NetworkFault) {
+        self.next_link_fault_id += 1;
+        let id = self.next_link_fault_id;
+        info!(
+            self.ctx.log(),
+            "Injecting network fault {}. Leader: {}", fault, self.current_leader
+        );
+        for (pid, to, effect) in fault
+            .rule
+            .resolve(self.current_leader, &self.current_config)
+        {
+            if to.is_empty() {
+                continue;
+            }
+            match self.nodes.get(&pid) {
+                Some(node) => node
+                    .tell_serialised(FaultMsg::Link(LinkFault { id, to, effect }), self)
+                    .expect("Should serialise FaultMsg"),
+                None => warn!(
+                    self.ctx.log(),
+                    "Skipping network fault {} at node {}: no such node", fault, pid
+                ),
+            }
+        }
+        self.mark_event(TimelineEvent::NetworkFault(fault.to_string()));
+        let now = SystemTime::now();
+        self.network_fault_records.push(NetworkFaultRecord {
+            fault: fault.to_string(),
+            window: Duration::from_millis(0),
+            leader_changes: 0,
+            decided: 0,
+            timed_out: 0,
+            max_stall: Duration::from_millis(0),
+        });
+        self.active_network_faults.push(ActiveNetworkFault {
+            id,
+            record: self.network_fault_records.len() - 1,
+            start: now,
+            last_decided: now,
+        });
+        if let Some(duration) = fault.duration {
+            self.schedule_once(duration, move |c, _| c.heal_network_fault(id));
+        }
+    }
+
+    /// Asks the target of each due leader transfer to take over the leadership.
+    fn transfer_leadership(&mut self, received_count: u64) {
+        while let Some(transfer) = self.leader_transfers.first() {
+            if received_count < transfer.trigger_count(self.num_proposals)
+                || self.state != ExperimentState::Running
+                || self.current_leader == 0
+            {
+                return;
+            }
+            let transfer = self.leader_transfers.remove(0);
+            let pid = transfer
+                .target
+                .resolve(self.current_leader, &self.current_config);
+            if pid == self.current_leader {
+                info!(
+                    self.ctx.log(),
+                    "Skipping leader transfer {}: node {} already leads", transfer, pid
+                );
+                continue;
+            }
+            let node = match self.nodes.get(&pid) {
+                Some(node) => node,
+                None => {
+                    warn!(
+                        self.ctx.log(),
+                        "Skipping leader transfer {}: no node {}", transfer, pid
+                    );
+                    continue;
                 }
+            };
+            info!(
+                self.ctx.log(),
+                "Transferring the leadership from {} to {}", self.current_leader, pid
+            );
+            node.tell_serialised(FaultMsg::TransferLeadership, self)
+                .expect("Should serialise FaultMsg");
+            self.mark_event(TimelineEvent::LeaderTransfer(pid));
+            self.transfer_records.push(TransferRecord {
+                from: self.current_leader,
+                to: pid,
+                requested_at: self.since_run_start().unwrap_or_default(),
+                elected_after: None,
+                resumed_after: None,
+            });
+            self.ongoing_transfer = Some((self.transfer_records.len() - 1, SystemTime::now()));
+        }
+    }
+
+    /// Ends the latest leader transfer when a proposal is decided under its target.
+    fn transfer_progress(&mut self, leader: u64) {
+        if let Some((idx, request_time)) = self.ongoing_transfer {
+            let record = &mut self.transfer_records[idx];
+            if record.elected_after.is_some() && leader == record.to {
+                record.resumed_after = Some(
+                    request_time
+                        .elapsed()
+                        .expect("Failed to get elapsed duration"),
+                );
+                self.ongoing_transfer = None;
+            }
+        }
+    }
+
+    fn heal_network_fault(&mut self, id: u32) -> Handled {
+        info!(self.ctx.log(), "Healing network fault {}", id);
+        for node in self.nodes.values() {
+            node.tell_serialised(FaultMsg::Heal(id), self)
+                .expect("Should serialise FaultMsg");
+        }
+        if let Some(i) = self.active_network_faults.iter().position(|f| f.id == id) {
+            let active = self.active_network_faults.remove(i);
+            self.close_network_fault(active);
+        }
+        Handled::Ok
+    }
+
+    /// Ends the window of the network faults that last until the end of the run.
+    fn close_network_faults(&mut self) {
+        for active in std::mem::take(&mut self.active_network_faults) {
+            self.close_network_fault(active);
+        }
+    }
+
+    fn close_network_fault(&mut self, active: ActiveNetworkFault) {
+        let record = &mut self.network_fault_records[active.record];
+        record.window = active
+            .start
+            .elapsed()
+            .expect("Failed to get elapsed duration");
+        let stall = active
+            .last_decided
+            .elapsed()
+            .expect("Failed to get elapsed duration");
+        record.max_stall = record.max_stall.max(stall);
+    }
+
+    /// Tracks the longest time without decided proposals for the ongoing network faults.
+    fn network_fault_progress(&mut self) {
+        if self.active_network_faults.is_empty() {
+            return;
+        }
+        let now = SystemTime::now();
+        for active in &mut self.active_network_faults {
+            let record = &mut self.network_fault_records[active.record];
+            record.decided += 1;
+            let stall = now.duration_since(active.last_decided).unwrap_or_default();
+            record.max_stall = record.max_stall.max(stall);
+            active.last_decided = now;
+        }
+    }
+
+    fn since_run_start(&self) -> Option<Duration> {
+        self.run_start
+            .map(|start| start.elapsed().unwrap_or_default())
+    }
+
+    /// Marks `event` on the throughput timeline, if the run has started.
+    fn mark_event(&mut self, event: TimelineEvent) {
+        if let Some(since_start) = self.since_run_start() {
+            if let Some(throughput) = self.throughput.as_mut() {
+                throughput.record_event(since_start, event);
             }
-            let timer =
-                self.schedule_once(self.timeout, move |c, _| c.proposal_timeout(RECONFIG_ID));
-            let proposal_meta = ProposalMetaData::with(None, timer);
-            self.pending_proposals.insert(RECONFIG_ID, proposal_meta);
+        }
+    }
+
+    fn record_leader_change(&mut self, pid: u64) {
+        self.leader_changes.push(pid);
+        self.mark_event(TimelineEvent::LeaderChange(pid));
+        for active in &self.active_network_faults {
+            self.network_fault_records[active.record].leader_changes += 1;
+        }
+        if let Some((idx, request_time)) = self.ongoing_transfer {
+            let record = &mut self.transfer_records[idx];
+            if record.to == pid && record.elected_after.is_none() {
+                record.elected_after = Some(
+                    request_time
+                        .elapsed()
+                        .expect("Failed to get elapsed duration"),
+                );
+            }
+        }
+    }
+
+    /// Ends the unavailability window of the latest crash when a proposal is decided after it.
+    fn end_unavailability(&mut self) {
+        if let Some((idx, crash_time)) = self.ongoing_crash.take() {
+            let elapsed = crash_time
+                .elapsed()
+                .expect("Failed to get elapsed duration");
+            self.fault_records[idx].unavailability = Some(elapsed);
         }
     }
 
     fn proposal_timeout(&mut self, id: u64) -> Handled {
         if self.responses.contains_key(&id)
             || self.state == ExperimentState::ReconfigurationElection
+            || self.state == ExperimentState::FaultElection
         {
             return Handled::Ok;
         }
@@ -283,6 +1161,9 @@ impl Client {
             proposal_meta.set_timer(timer);
         } else {
             self.num_timed_out += 1;
+            for active in &self.active_network_faults {
+                self.network_fault_records[active.record].timed_out += 1;
+            }
             let proposal_meta = self
                 .pending_proposals
                 .remove(&id)
@@ -317,6 +1198,17 @@ impl Client {
         }
     }
 
+    fn hold_back_pending_proposals(&mut self) {
+        let pending_proposals = std::mem::take(&mut self.pending_proposals);
+        let mut held_back = Vec::with_capacity(pending_proposals.len());
+        for (id, ProposalMetaData { start_time, timer }) in pending_proposals {
+            self.cancel_timer(timer);
+            held_back.push((id, start_time));
+        }
+        held_back.sort_by_key(|(id, _)| *id);
+        self.retry_proposals.append(&mut held_back);
+    }
+
     fn deserialise_response(data: &mut dyn Buf) -> Response {
         match data.get_u64() {
             RECONFIG_ID => {
@@ -327,7 +1219,7 @@ impl Client {
                 }
                 Response::Reconfiguration(config)
             }
-            n => Response::Normal(n),
+            n => Response::Normal(n & ((1 << CLIENT_ID_SHIFT) - 1)), // strip the client index
         }
     }
 
@@ -338,7 +1230,62 @@ impl Client {
         }
     }
 
+    fn request_io_stats(&self) {
+        for ap in self.stopped_nodes.values() {
+            ap.tell((TestMessage::IoStatsReq, TestMessageSer), self);
+        }
+    }
+
+    fn request_sequences(&self) {
+        for ap in self.nodes.values() {
+            ap.tell((TestMessage::SequenceReq, TestMessageSer), self);
+        }
+    }
+
+    /// The proposals of this client with the ones it got a response for, if the total order is checked.
+    fn take_proposal_log(&mut self) -> Option<ProposalLog> {
+        let mut log = self.proposal_log.take()?;
+        log.proposed = (1..=self.latest_proposal_id)
+            .map(|id| self.global_id(id))
+            .collect();
+        Some(log)
+    }
+
+    fn client_stats(&self, decided: u64) -> ClientStats {
+        let run_time = match (self.run_start, self.run_end.unwrap_or_else(SystemTime::now)) {
+            (Some(start), end) => end.duration_since(start).unwrap_or_default(),
+            _ => Duration::from_millis(0),
+        };
+        let per_sec = |count: u64| {
+            if run_time > Duration::from_millis(0) {
+                count as f64 / run_time.as_secs_f64()
+            } else {
+                0.0
+            }
+        };
+        ClientStats {
+            decided,
+            throughput: per_sec(decided),
+            decided_bytes: self.decided_bytes,
+            byte_throughput: per_sec(self.decided_bytes),
+        }
+    }
+
+    fn reconfig_timings(&self) -> Vec<ReconfigTiming> {
+        let since = |t: SystemTime, start: SystemTime| t.duration_since(start).unwrap_or_default();
+        self.reconfig_times
+            .iter()
+            .map(|(proposed, decided)| ReconfigTiming {
+                proposed_at: self
+                    .run_start
+                    .map_or(Duration::from_millis(0), |start| since(*proposed, start)),
+                decided_after: decided.map(|t| since(t, *proposed)),
+            })
+            .collect()
+    }
+
     fn reply_stop_ask(&mut self) {
+        let decided = (self.responses.len() as u64).saturating_sub(self.num_timed_out);
         let l = std::mem::take(&mut self.responses);
         let mut v: Vec<_> = l
             .into_iter()
@@ -349,7 +1296,32 @@ impl Client {
             .into_iter()
             .map(|(id, latency)| (id, latency.unwrap()))
             .collect();
-        let meta_results = MetaResults::with(self.num_timed_out, latencies);
+        let proposal_log = self.take_proposal_log();
+        let faults = std::mem::take(&mut self.fault_records);
+        self.close_network_faults();
+        let network_faults = std::mem::take(&mut self.network_fault_records);
+        let leader_transfers = std::mem::take(&mut self.transfer_records);
+        let client_stats = self.client_stats(decided);
+        let open_loop = self
+            .open_loop
+            .take()
+            .map(|ol| ol.into_results(self.latest_proposal_id, decided));
+        let meta_results = MetaResults::with(
+            self.num_timed_out,
+            latencies,
+            std::mem::take(&mut self.leader_changes),
+            self.reconfig_timings(),
+            self.throughput.take(),
+            proposal_log,
+            self.decided_sequences.drain().collect(),
+            faults,
+            network_faults,
+            leader_transfers,
+            open_loop,
+            client_stats,
+            self.kv.take().map(KvClient::into_results),
+            std::mem::take(&mut self.io_stats),
+        );
         self.stop_ask
             .take()
             .expect("No stop promise!")
@@ -365,18 +1337,34 @@ impl Actor for Client {
 
     fn receive_local(&mut self, msg: Self::Message) -> Handled {
         match msg {
-            LocalClientMessage::Run => {
+            LocalClientMessage::Run(run_start) => {
                 self.state = ExperimentState::Running;
+                self.run_start = Some(run_start);
                 assert_ne!(self.current_leader, 0);
-                self.send_concurrent_proposals();
+                if self.open_loop.is_some() {
+                    self.start_open_loop();
+                } else {
+                    self.send_concurrent_proposals();
+                }
             }
             LocalClientMessage::Stop(a) => {
                 let pending_proposals = std::mem::take(&mut self.pending_proposals);
                 for proposal_meta in pending_proposals {
                     self.cancel_timer(proposal_meta.1.timer);
                 }
-                self.send_stop();
+                if let Some(timer) = self.open_loop.as_mut().and_then(|ol| ol.timer.take()) {
+                    self.cancel_timer(timer);
+                }
                 self.stop_ask = Some(a);
+                if self.id != 0 {
+                    // only the primary client stops the replicas
+                    self.reply_stop_ask();
+                } else if self.proposal_log.is_some() {
+                    // collect the sequences before the replicas are stopped
+                    self.request_sequences();
+                } else {
+                    self.send_stop();
+                }
             }
         }
         Handled::Ok
@@ -408,7 +1396,7 @@ impl Actor for Client {
                                 if self.current_leader != pid {
                                     // info!(self.ctx.log(), "Got leader in ReconfigElection: {}. old: {}", pid, self.current_leader);
                                     self.current_leader = pid;
-                                    self.leader_changes.push(pid);
+                                    self.record_leader_change(pid);
                                 }
                                 self.state = ExperimentState::Running;
                                 if self.retry_proposals.is_empty() {
@@ -416,13 +1404,31 @@ impl Actor for Client {
                                 }
                                 self.send_concurrent_proposals();
                             },
+                            ExperimentState::FaultElection => {
+                                if let Some((idx, crash_time)) = self.ongoing_crash {
+                                    let elapsed = crash_time.elapsed().expect("Failed to get elapsed duration");
+                                    self.fault_records[idx].election_time = Some(elapsed);
+                                }
+                                info!(self.ctx.log(), "Got new leader after crash: {}", pid);
+                                self.current_leader = pid;
+                                self.record_leader_change(pid);
+                                self.state = ExperimentState::Running;
+                                self.send_concurrent_proposals();
+                            },
+                            ExperimentState::Running => {
+                                if self.current_leader != pid {
+                                    self.current_leader = pid;
+                                    self.record_leader_change(pid);
+                                }
+                            },
                             _ => {},
                         }
                     },
                     AtomicBroadcastMsg::ProposalResp(pr) => {
                         if self.state == ExperimentState::Finished || self.state == ExperimentState::LeaderElection { return Handled::Ok; }
                         let data = pr.data;
-                        let response = Self::deserialise_response(&mut data.as_slice());
+                        let mut buf = data.as_slice();
+                        let response = Self::deserialise_response(&mut buf);
                         match response {
                             Response::Normal(id) => {
                                 if let Some(proposal_meta) = self.pending_proposals.remove(&id) {
@@ -431,11 +1437,30 @@ impl Actor for Client {
                                         _ => None,
                                     };
                                     self.cancel_timer(proposal_meta.timer);
+                                    let global_id = self.global_id(id);
+                                    if let Some(log) = self.proposal_log.as_mut() {
+                                        log.acked.push(global_id);
+                                    }
+                                    if self.state == ExperimentState::Running {
+                                        self.end_unavailability();
+                                    }
+                                    self.network_fault_progress();
+                                    if let Some(ol) = self.open_loop.as_mut() {
+                                        ol.last_decided = Some(SystemTime::now());
+                                    }
+                                    self.decided_bytes += self.payload_size.size_of(id) as u64;
+                                    if let (Some(since_start), Some(throughput)) = (self.since_run_start(), self.throughput.as_mut()) {
+                                        throughput.record_decided(since_start);
+                                    }
+                                    if let Some(kv) = self.kv.as_mut() {
+                                        kv.complete(id, &mut buf);
+                                    }
                                     if self.current_config.contains(&pr.latest_leader) && self.current_leader != pr.latest_leader && self.state != ExperimentState::ReconfigurationElection {
                                         // info!(self.ctx.log(), "Got leader in normal response: {}. old: {}", pr.latest_leader, self.current_leader);
                                         self.current_leader = pr.latest_leader;
-                                        self.leader_changes.push(pr.latest_leader);
+                                        self.record_leader_change(pr.latest_leader);
                                     }
+                                    self.transfer_progress(pr.latest_leader);
                                     self.handle_normal_response(id, latency);
                                     if self.state != ExperimentState::ReconfigurationElection {
                                         self.send_concurrent_proposals();
@@ -453,13 +1478,22 @@ impl Actor for Client {
                             Response::Reconfiguration(new_config) => {
                                 if let Some(proposal_meta) = self.pending_proposals.remove(&RECONFIG_ID) {
                                     self.cancel_timer(proposal_meta.timer);
+                                    if let Some((_, decided)) = self.reconfig_times.last_mut() {
+                                        *decided = Some(SystemTime::now());
+                                    }
+                                    self.mark_event(TimelineEvent::ReconfigDecided);
+                                    if let Some(log) = self.proposal_log.as_mut() {
+                                        log.reconfigs.push((log.acked.len(), self.latest_proposal_id));
+                                    }
+                                    let (_, learners) = self.pending_reconfig.take().expect("Decided a reconfiguration that was not proposed");
                                     if self.responses.len() as u64 == self.num_proposals {
                                         self.state = ExperimentState::Finished;
+                                        self.run_end = Some(SystemTime::now());
                                         self.finished_latch.decrement().expect("Failed to countdown finished latch");
                                         info!(self.ctx.log(), "Got reconfig at last. {} proposals timed out. Leader changes: {}, {:?}, Last leader was: {}", self.num_timed_out, self.leader_changes.len(), self.leader_changes, self.current_leader);
                                     } else {
-                                        self.reconfig = None;
                                         self.current_config = new_config;
+                                        self.current_learners = learners;
                                         let leader_changed = self.current_leader != pr.latest_leader;
                                         info!(self.ctx.log(), "Reconfig OK, leader: {}, old: {}, current_config: {:?}", pr.latest_leader, self.current_leader, self.current_config);
                                         self.current_leader = pr.latest_leader;
@@ -469,7 +1503,7 @@ impl Actor for Client {
                                             self.state = ExperimentState::Running;
                                             self.send_concurrent_proposals();
                                             if leader_changed {
-                                                self.leader_changes.push(pr.latest_leader);
+                                                self.record_leader_change(pr.latest_leader);
                                             }
                                         }
                                     }
@@ -481,7 +1515,7 @@ impl Actor for Client {
                         let dropped_proposal = data.as_slice().get_u64();
                         match self.state {
                             ExperimentState::Running => {
-                                if self.reconfig.is_some() {    // still running in old config
+                                if self.pending_reconfig.is_some() {    // still running in old config
                                     self.hold_back_proposals(dropped_proposal, self.latest_proposal_id);
                                     self.state = ExperimentState::ReconfigurationElection;  // wait for FirstLeader in new configuration before proposing more
                                 } else {    // already running in new configuration
@@ -505,10 +1539,29 @@ impl Actor for Client {
             },
             stop: NetStopMsg [StopMsgDeser] => {
                 if let NetStopMsg::Peer(pid) = stop {
-                    self.nodes.remove(&pid).expect(&format!("Got stop from unknown pid {}", pid));
+                    let node = self.nodes.remove(&pid).expect(&format!("Got stop from unknown pid {}", pid));
+                    self.stopped_nodes.insert(pid, node);
                     if self.nodes.is_empty() {
-                        self.reply_stop_ask();
+                        self.request_io_stats();
+                    }
+                }
+            },
+            tm: TestMessage [TestMessageSer] => {
+                match tm {
+                    TestMessage::SequenceResp(sr) => {
+                        self.decided_sequences.insert(sr.node_id, sr.sequence);
+                        if self.decided_sequences.len() == self.nodes.len() {
+                            self.send_stop();
+                        }
                     }
+                    TestMessage::IoStatsResp(pid, io_stats) => {
+                        self.io_stats.push((pid, io_stats));
+                        if self.io_stats.len() == self.stopped_nodes.len() {
+                            self.io_stats.sort_by_key(|(pid, _)| *pid);
+                            self.reply_stop_ask();
+                        }
+                    }
+                    _ => error!(self.ctx.log(), "Client received unexpected TestMessage: {:?}", tm),
                 }
             },
             !Err(e) => error!(self.ctx.log(), "{}", &format!("Client failed to deserialise msg: {:?}", e)),
@@ -521,7 +1574,7 @@ impl Actor for Client {
 #[cfg(test)]
 pub mod tests {
     use super::*;
-    use crate::bench::atomic_broadcast::messages::{TestMessage, TestMessageSer};
+    use benchmark_suite_shared::test_utils::DecidedEntry;
 
     #[derive(ComponentDefinition)]
     pub struct TestClient {
@@ -720,7 +1773,11 @@ pub mod tests {
                     tm: TestMessage [TestMessageSer] => {
                         match tm {
                             TestMessage::SequenceResp(sr) => {
-                                self.test_results.insert(sr.node_id, sr.sequence);
+                                let sequence = sr.sequence.entries.into_iter().filter_map(|e| match e {
+                                    DecidedEntry::Normal(id) => Some(id),
+                                    _ => None,
+                                }).collect();
+                                self.test_results.insert(sr.node_id, sequence);
                                 if (self.test_results.len()) == self.nodes.len() + 1 {    // got sequences from everybody, we're done
                                     info!(self.ctx.log(), "Got all sequences");
                                     self.finished_promise
@@ -737,4 +1794,28 @@ pub mod tests {
                 }
         }
     }
+
+    #[test]
+    fn payload_size_test() {
+        for size in &[
+            PayloadSize::Fixed(256),
+            PayloadSize::Uniform(64, 1024),
+            PayloadSize::Exponential(512),
+        ] {
+            assert_eq!(Ok(*size), PayloadSize::parse(&size.to_string()));
+        }
+        assert_eq!(
+            Ok(PayloadSize::Uniform(64, 1024)),
+            PayloadSize::parse("uniform(64, 1024)")
+        );
+        assert_eq!(
+            Ok(PayloadSize::Fixed(PROPOSAL_ID_LEN)),
+            PayloadSize::parse("")
+        );
+        assert!(PayloadSize::parse("uniform(1024,64)").is_err());
+        assert!(PayloadSize::parse("uniform(64)").is_err());
+        assert!(PayloadSize::parse(&(MAX_PAYLOAD_SIZE + 1).to_string()).is_err());
+        assert!(PayloadSize::parse(&format!("exp({})", usize::MAX / 2)).is_err());
+        assert!(PayloadSize::parse(&format!("exp({})", MAX_PAYLOAD_SIZE / EXP_CAP_FACTOR)).is_ok());
+    }
 }
@@ -1,13 +1,15 @@
 extern crate raft as tikv_raft;
 
+use super::fault::{Delivery, LinkFaultMsg, LinkFilter};
 use super::messages::paxos::Message as RawPaxosMsg;
 use crate::bench::atomic_broadcast::messages::raft::RaftMsg;
 use crate::bench::atomic_broadcast::messages::{paxos::PaxosSer, raft::RawRaftSer};
 use crate::bench::atomic_broadcast::messages::{
-    AtomicBroadcastMsg, ProposalResp, StopMsg as NetStopMsg, StopMsgDeser,
+    AtomicBroadcastMsg, Clients, ProposalResp, StopMsg as NetStopMsg, StopMsgDeser,
 };
-use hashbrown::HashMap;
+use hashbrown::{HashMap, HashSet};
 use kompact::prelude::*;
+use std::time::{Duration, Instant};
 use tikv_raft::prelude::Message as RawRaftMsg;
 
 #[derive(Clone, Debug)]
@@ -15,6 +17,9 @@ pub enum AtomicBroadcastCompMsg {
     RawRaftMsg(RawRaftMsg),
     RawPaxosMsg(RawPaxosMsg),
     StopMsg(u64),
+    /// A network fault dropped a message to the peer. Later messages to it are dropped until `LinkUp`.
+    LinkDown(u64),
+    LinkUp(u64),
 }
 
 #[derive(Clone, Debug)]
@@ -37,18 +42,100 @@ pub struct Communicator {
     ctx: ComponentContext<Communicator>,
     atomic_broadcast_port: ProvidedPort<CommunicationPort>,
     peers: HashMap<u64, ActorPath>, // tikv raft node id -> actorpath
-    client: ActorPath,              // cached client to send SequenceResp to
+    clients: Clients,               // cached clients to send responses to
+    link_filter: LinkFilter,
+    /// Treat links like sessions: once a message is dropped, the link stays down until the fault heals.
+    sessions: bool,
+    down_links: HashSet<u64>,
+    /// When the last delayed message to each peer is delivered.
+    delayed_until: HashMap<u64, Instant>,
 }
 
 impl Communicator {
-    pub fn with(peers: HashMap<u64, ActorPath>, client: ActorPath) -> Communicator {
+    pub fn with(peers: HashMap<u64, ActorPath>, clients: Clients, sessions: bool) -> Communicator {
         Communicator {
             ctx: ComponentContext::uninitialised(),
             atomic_broadcast_port: ProvidedPort::uninitialised(),
             peers,
-            client,
+            clients,
+            link_filter: LinkFilter::default(),
+            sessions,
+            down_links: HashSet::new(),
+            delayed_until: HashMap::new(),
         }
     }
+
+    /// Sends a replica message to a peer through the injected network faults, if any.
+    fn send_to_peer<M>(&mut self, to: u64, msg: M)
+    where
+        M: Serialisable + Clone + Send + 'static,
+    {
+        if self.down_links.contains(&to) {
+            return;
+        }
+        let (copies, delay) = match self.link_filter.delivery(to) {
+            Delivery::Drop => {
+                if self.sessions {
+                    self.down_links.insert(to);
+                    self.atomic_broadcast_port
+                        .trigger(AtomicBroadcastCompMsg::LinkDown(to));
+                }
+                return;
+            }
+            Delivery::Send {
+                copies,
+                delay,
+                in_order: true,
+            } => (copies, self.in_order_delay(to, delay)),
+            Delivery::Send { copies, delay, .. } => (copies, delay),
+        };
+        for _ in 1..copies {
+            self.deliver(to, msg.clone(), delay);
+        }
+        self.deliver(to, msg, delay);
+    }
+
+    /// Never lets a message overtake an earlier delayed message to the same peer, so that delays alone do not reorder messages.
+    fn in_order_delay(&mut self, to: u64, delay: Option<Duration>) -> Option<Duration> {
+        if delay.is_none() && self.delayed_until.is_empty() {
+            return None;
+        }
+        let now = Instant::now();
+        let mut deliver_at = now + delay.unwrap_or_default();
+        if let Some(prev) = self.delayed_until.get(&to) {
+            deliver_at = deliver_at.max(*prev);
+        }
+        if deliver_at > now {
+            self.delayed_until.insert(to, deliver_at);
+            Some(deliver_at - now)
+        } else {
+            self.delayed_until.remove(&to);
+            None
+        }
+    }
+
+    fn deliver<M>(&mut self, to: u64, msg: M, delay: Option<Duration>)
+    where
+        M: Serialisable + Send + 'static,
+    {
+        match delay {
+            Some(delay) => {
+                self.schedule_once(delay, move |c, _| {
+                    c.tell_peer(to, msg);
+                    Handled::Ok
+                });
+            }
+            None => self.tell_peer(to, msg),
+        }
+    }
+
+    fn tell_peer<M: Serialisable + 'static>(&self, to: u64, msg: M) {
+        self.peers
+            .get(&to)
+            .expect("Peer was checked when sending")
+            .tell_serialised(msg, self)
+            .expect("Should serialise message to peer");
+    }
 }
 
 ignore_lifecycle!(Communicator);
@@ -57,29 +144,29 @@ impl Provide<CommunicationPort> for Communicator {
     fn handle(&mut self, msg: CommunicatorMsg) -> Handled {
         match msg {
             CommunicatorMsg::RawRaftMsg(rm) => {
-                let receiver = self.peers.get(&rm.get_to()).unwrap_or_else(|| {
+                let to = rm.get_to();
+                if !self.peers.contains_key(&to) {
                     panic!(
                         "Could not find actorpath for id={}. Known peers: {:?}. RaftMsg: {:?}",
-                        &rm.get_to(),
+                        to,
                         self.peers.keys(),
                         rm
                     )
-                });
-                receiver
-                    .tell_serialised(RaftMsg(rm), self)
-                    .expect("Should serialise RaftMsg");
+                }
+                self.send_to_peer(to, RaftMsg(rm));
             }
             CommunicatorMsg::RawPaxosMsg(pm) => {
                 trace!(self.ctx.log(), "sending {:?}", pm);
-                let receiver = self.peers.get(&pm.to).unwrap_or_else(|| panic!("RawPaxosMsg: Could not find actorpath for id={}. Known peers: {:?}. PaxosMsg: {:?}", &pm.to, self.peers.keys(), pm));
-                receiver
-                    .tell_serialised(pm, self)
-                    .expect("Should serialise RawPaxosMsg");
+                if !self.peers.contains_key(&pm.to) {
+                    panic!("RawPaxosMsg: Could not find actorpath for id={}. Known peers: {:?}. PaxosMsg: {:?}", &pm.to, self.peers.keys(), pm)
+                }
+                self.send_to_peer(pm.to, pm);
             }
             CommunicatorMsg::ProposalResponse(pr) => {
                 trace!(self.ctx.log(), "ProposalResp: {:?}", pr);
+                let client = self.clients.of_response(&pr.data);
                 let am = AtomicBroadcastMsg::ProposalResp(pr);
-                self.client
+                client
                     .tell_serialised(am, self)
                     .expect("Should serialise ProposalResp");
             }
@@ -90,7 +177,8 @@ impl Provide<CommunicationPort> for Communicator {
                         .expect("Should serialise StopMsg")
                 }
                 if ack_client {
-                    self.client
+                    self.clients
+                        .primary()
                         .tell_serialised(NetStopMsg::Peer(my_pid), self)
                         .expect("Should serialise StopMsg")
                 }
@@ -101,10 +189,21 @@ impl Provide<CommunicationPort> for Communicator {
 }
 
 impl Actor for Communicator {
-    type Message = ();
+    type Message = LinkFaultMsg;
 
-    fn receive_local(&mut self, _msg: Self::Message) -> Handled {
-        // ignore
+    fn receive_local(&mut self, msg: Self::Message) -> Handled {
+        self.link_filter.handle(msg);
+        let healed: Vec<u64> = self
+            .down_links
+            .iter()
+            .filter(|pid| !self.link_filter.is_lossy(**pid))
+            .copied()
+            .collect();
+        for pid in healed {
+            self.down_links.remove(&pid);
+            self.atomic_broadcast_port
+                .trigger(AtomicBroadcastCompMsg::LinkUp(pid));
+        }
         Handled::Ok
     }
 
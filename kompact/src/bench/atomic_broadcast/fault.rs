@@ -0,0 +1,734 @@
+use rand::Rng;
+use std::fmt;
+use std::time::Duration;
+
+/// Longest time in ms that a reordered message is held back.
+const REORDER_WINDOW_MS: u64 = 10;
+
+/// Which node a fault crashes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FaultTarget {
+    /// Whichever node the client considers leader when the fault is injected.
+    Leader,
+    Node(u64),
+}
+
+impl FaultTarget {
+    pub(super) fn parse(s: &str) -> Result<FaultTarget, String> {
+        match s.trim().to_lowercase().as_ref() {
+            "leader" => Ok(FaultTarget::Leader),
+            pid => match pid.parse::<u64>() {
+                Ok(pid) if pid > 0 => Ok(FaultTarget::Node(pid)),
+                _ => Err(format!("Invalid fault target: {}", s)),
+            },
+        }
+    }
+
+    pub(super) fn resolve(&self, leader: u64) -> u64 {
+        match self {
+            FaultTarget::Leader => leader,
+            FaultTarget::Node(pid) => *pid,
+        }
+    }
+}
+
+impl fmt::Display for FaultTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FaultTarget::Leader => write!(f, "leader"),
+            FaultTarget::Node(pid) => write!(f, "{}", pid),
+        }
+    }
+}
+
+/// Parses `<fraction of proposals>[:<duration in ms>]`, the part after the `@` of a fault.
+fn parse_timing(s: &str) -> Result<(f64, Option<Duration>), String> {
+    let (at, duration) = match s.find(':') {
+        Some(i) => (&s[..i], Some(&s[i + 1..])),
+        None => (s, None),
+    };
+    let at = match at.parse::<f64>() {
+        Ok(at) if (0.0..1.0).contains(&at) => at,
+        _ => {
+            return Err(format!(
+                "Invalid fault fraction, should be in [0, 1): {}",
+                at
+            ))
+        }
+    };
+    let duration = match duration {
+        Some(ms) => Some(parse_millis(ms)?),
+        None => None,
+    };
+    Ok((at, duration))
+}
+
+fn parse_millis(s: &str) -> Result<Duration, String> {
+    s.trim()
+        .parse::<u64>()
+        .map(Duration::from_millis)
+        .map_err(|e| format!("Invalid fault duration '{}': {}", s, e))
+}
+
+fn parse_percent(s: &str) -> Result<u8, String> {
+    match s.trim().parse::<u8>() {
+        Ok(pct) if pct <= 100 => Ok(pct),
+        _ => Err(format!("Invalid percentage, should be in [0, 100]: {}", s)),
+    }
+}
+
+/// Splits `<from>><to>`.
+fn split_link(s: &str) -> Result<(&str, &str), String> {
+    match s.find('>') {
+        Some(i) => Ok((&s[..i], &s[i + 1..])),
+        None => Err(format!("Link '{}' should look like <from>><to>", s)),
+    }
+}
+
+/// Crashes a node once a fraction of the proposals has been decided.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fault {
+    pub target: FaultTarget,
+    pub at: f64,
+    /// How long the node stays down before it restarts from its storage. `None` if it never restarts.
+    pub downtime: Option<Duration>,
+}
+
+impl Fault {
+    /// Parses `<leader|node id>@<fraction of proposals>[:<downtime in ms>]`.
+    fn parse(s: &str) -> Result<Fault, String> {
+        let (target, rest) = match s.find('@') {
+            Some(i) => (&s[..i], &s[i + 1..]),
+            None => return Err(format!("Fault '{}' has no '@'", s)),
+        };
+        let target = FaultTarget::parse(target)?;
+        let (at, downtime) = parse_timing(rest)?;
+        Ok(Fault {
+            target,
+            at,
+            downtime,
+        })
+    }
+
+    /// Number of decided proposals after which the fault is injected.
+    pub fn trigger_count(&self, num_proposals: u64) -> u64 {
+        (self.at * num_proposals as f64).ceil() as u64
+    }
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.target, self.at)?;
+        match self.downtime {
+            Some(d) => write!(f, ":{}", d.as_millis()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// What a network fault does to the messages sent over a link.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LinkEffect {
+    /// Drops the given percentage of messages.
+    Loss(u8),
+    Delay(Duration),
+    /// Sends the given percentage of messages twice.
+    Duplicate(u8),
+    /// Holds back the given percentage of messages for a few ms, so that later messages overtake them.
+    Reorder(u8),
+}
+
+impl LinkEffect {
+    /// If the messages that are delivered still arrive once and in order.
+    pub fn is_fifo(&self) -> bool {
+        match self {
+            LinkEffect::Loss(_) | LinkEffect::Delay(_) => true,
+            LinkEffect::Duplicate(_) | LinkEffect::Reorder(_) => false,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            LinkEffect::Loss(_) => "loss",
+            LinkEffect::Delay(_) => "delay",
+            LinkEffect::Duplicate(_) => "duplicate",
+            LinkEffect::Reorder(_) => "reorder",
+        }
+    }
+
+    /// The percentage of messages, or the delay in ms.
+    fn value(&self) -> u128 {
+        match self {
+            LinkEffect::Loss(pct) | LinkEffect::Duplicate(pct) | LinkEffect::Reorder(pct) => {
+                *pct as u128
+            }
+            LinkEffect::Delay(d) => d.as_millis(),
+        }
+    }
+}
+
+impl fmt::Display for LinkEffect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({})", self.name(), self.value())
+    }
+}
+
+/// The nodes on one side of a network fault.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NodeGroup {
+    Nodes(Vec<FaultTarget>),
+    /// `*`: every node that is not named elsewhere in the fault.
+    Rest,
+}
+
+impl NodeGroup {
+    fn parse(s: &str) -> Result<NodeGroup, String> {
+        if s.trim() == "*" {
+            return Ok(NodeGroup::Rest);
+        }
+        s.split(',')
+            .map(FaultTarget::parse)
+            .collect::<Result<Vec<_>, _>>()
+            .map(NodeGroup::Nodes)
+    }
+}
+
+impl fmt::Display for NodeGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NodeGroup::Nodes(nodes) => {
+                let nodes: Vec<_> = nodes.iter().map(|n| n.to_string()).collect();
+                write!(f, "{}", nodes.join(","))
+            }
+            NodeGroup::Rest => write!(f, "*"),
+        }
+    }
+}
+
+/// Which links a network fault affects and how.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NetworkRule {
+    /// `partition(1,2|3,4,5)`: nodes only reach the nodes in their own group.
+    Partition(Vec<NodeGroup>),
+    /// `oneway(leader>*)`: messages from the node to the group are dropped, but not the other way around.
+    OneWay(FaultTarget, NodeGroup),
+    /// `loss(10)`, `delay(50,1>2)`, ...: the effect on every link, or only on the link from one node to another.
+    Link(LinkEffect, Option<(FaultTarget, FaultTarget)>),
+}
+
+impl NetworkRule {
+    fn parse(s: &str) -> Result<NetworkRule, String> {
+        let open = match s.find('(') {
+            Some(i) if s.ends_with(')') => i,
+            _ => {
+                return Err(format!(
+                    "Network fault '{}' should look like <rule>(<args>)",
+                    s
+                ))
+            }
+        };
+        let args = &s[open + 1..s.len() - 1];
+        match s[..open].trim().to_lowercase().as_ref() {
+            "partition" => {
+                let groups = args
+                    .split('|')
+                    .map(NodeGroup::parse)
+                    .collect::<Result<Vec<_>, _>>()?;
+                if groups.len() < 2 {
+                    return Err(format!("A partition needs at least two groups: {}", s));
+                }
+                Ok(NetworkRule::Partition(groups))
+            }
+            "oneway" => {
+                let (from, to) = split_link(args)?;
+                Ok(NetworkRule::OneWay(
+                    FaultTarget::parse(from)?,
+                    NodeGroup::parse(to)?,
+                ))
+            }
+            name => {
+                let (value, link) = match args.find(',') {
+                    Some(i) => (&args[..i], Some(&args[i + 1..])),
+                    None => (args, None),
+                };
+                let effect = match name {
+                    "loss" => LinkEffect::Loss(parse_percent(value)?),
+                    "delay" => LinkEffect::Delay(parse_millis(value)?),
+                    "duplicate" => LinkEffect::Duplicate(parse_percent(value)?),
+                    "reorder" => LinkEffect::Reorder(parse_percent(value)?),
+                    _ => return Err(format!("Unknown network fault: {}", s)),
+                };
+                let link = match link {
+                    Some(link) => {
+                        let (from, to) = split_link(link)?;
+                        Some((FaultTarget::parse(from)?, FaultTarget::parse(to)?))
+                    }
+                    None => None,
+                };
+                Ok(NetworkRule::Link(effect, link))
+            }
+        }
+    }
+
+    /// All nodes named in the rule.
+    pub fn targets(&self) -> Vec<FaultTarget> {
+        let group = |g: &NodeGroup| match g {
+            NodeGroup::Nodes(nodes) => nodes.clone(),
+            NodeGroup::Rest => vec![],
+        };
+        match self {
+            NetworkRule::Partition(groups) => groups.iter().flat_map(group).collect(),
+            NetworkRule::OneWay(from, to) => {
+                let mut targets = group(to);
+                targets.push(*from);
+                targets
+            }
+            NetworkRule::Link(_, Some((from, to))) => vec![*from, *to],
+            NetworkRule::Link(_, None) => vec![],
+        }
+    }
+
+    /// Which outgoing links of which node the rule affects, as `(node, receivers, effect)`.
+    pub fn resolve(&self, leader: u64, nodes: &[u64]) -> Vec<(u64, Vec<u64>, LinkEffect)> {
+        match self {
+            NetworkRule::Partition(groups) => {
+                let named: Vec<u64> = self.targets().iter().map(|t| t.resolve(leader)).collect();
+                let members: Vec<Vec<u64>> = groups
+                    .iter()
+                    .map(|g| match g {
+                        NodeGroup::Nodes(targets) => {
+                            targets.iter().map(|t| t.resolve(leader)).collect()
+                        }
+                        NodeGroup::Rest => nodes
+                            .iter()
+                            .filter(|pid| !named.contains(pid))
+                            .copied()
+                            .collect(),
+                    })
+                    .collect();
+                let mut links = vec![];
+                for (i, group) in members.iter().enumerate() {
+                    let others: Vec<u64> = members
+                        .iter()
+                        .enumerate()
+                        .filter(|(j, _)| *j != i)
+                        .flat_map(|(_, other)| other.iter().copied())
+                        .filter(|pid| !group.contains(pid))
+                        .collect();
+                    for pid in group {
+                        links.push((*pid, others.clone(), LinkEffect::Loss(100)));
+                    }
+                }
+                links
+            }
+            NetworkRule::OneWay(from, to) => {
+                let from = from.resolve(leader);
+                let to = match to {
+                    NodeGroup::Nodes(targets) => targets
+                        .iter()
+                        .map(|t| t.resolve(leader))
+                        .filter(|pid| *pid != from)
+                        .collect(),
+                    NodeGroup::Rest => nodes.iter().copied().filter(|pid| *pid != from).collect(),
+                };
+                vec![(from, to, LinkEffect::Loss(100))]
+            }
+            NetworkRule::Link(effect, Some((from, to))) => {
+                vec![(from.resolve(leader), vec![to.resolve(leader)], *effect)]
+            }
+            NetworkRule::Link(effect, None) => nodes
+                .iter()
+                .map(|pid| {
+                    let others = nodes.iter().copied().filter(|p| p != pid).collect();
+                    (*pid, others, *effect)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for NetworkRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkRule::Partition(groups) => {
+                let groups: Vec<_> = groups.iter().map(|g| g.to_string()).collect();
+                write!(f, "partition({})", groups.join("|"))
+            }
+            NetworkRule::OneWay(from, to) => write!(f, "oneway({}>{})", from, to),
+            NetworkRule::Link(effect, None) => write!(f, "{}", effect),
+            NetworkRule::Link(effect, Some((from, to))) => {
+                write!(f, "{}({},{}>{})", effect.name(), effect.value(), from, to)
+            }
+        }
+    }
+}
+
+/// Changes the network between the nodes once a fraction of the proposals has been decided.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NetworkFault {
+    pub rule: NetworkRule,
+    pub at: f64,
+    /// How long until the network heals. `None` if it lasts until the end of the run.
+    pub duration: Option<Duration>,
+}
+
+impl NetworkFault {
+    /// Parses `<rule>(<args>)@<fraction of proposals>[:<duration in ms>]`.
+    fn parse(s: &str) -> Result<NetworkFault, String> {
+        let (rule, rest) = match s.find('@') {
+            Some(i) => (&s[..i], &s[i + 1..]),
+            None => return Err(format!("Fault '{}' has no '@'", s)),
+        };
+        let rule = NetworkRule::parse(rule.trim())?;
+        let (at, duration) = parse_timing(rest)?;
+        Ok(NetworkFault { rule, at, duration })
+    }
+
+    pub fn trigger_count(&self, num_proposals: u64) -> u64 {
+        (self.at * num_proposals as f64).ceil() as u64
+    }
+}
+
+impl fmt::Display for NetworkFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.rule, self.at)?;
+        match self.duration {
+            Some(d) => write!(f, ":{}", d.as_millis()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The faults injected in an atomic broadcast experiment, ordered by when they happen.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FaultSchedule {
+    pub faults: Vec<Fault>,
+    pub network_faults: Vec<NetworkFault>,
+}
+
+impl FaultSchedule {
+    /// Parses `;`-separated crashes and network faults. An empty string or `none` is a schedule without faults.
+    pub fn parse(s: &str) -> Result<FaultSchedule, String> {
+        let s = s.trim();
+        if s.is_empty() || s.eq_ignore_ascii_case("none") {
+            return Ok(FaultSchedule::default());
+        }
+        let mut schedule = FaultSchedule::default();
+        for fault in s.split(';').map(str::trim) {
+            if fault.contains('(') {
+                schedule.network_faults.push(NetworkFault::parse(fault)?);
+            } else {
+                schedule.faults.push(Fault::parse(fault)?);
+            }
+        }
+        schedule
+            .faults
+            .sort_by(|a, b| a.at.partial_cmp(&b.at).expect("Fault fractions are finite"));
+        schedule
+            .network_faults
+            .sort_by(|a, b| a.at.partial_cmp(&b.at).expect("Fault fractions are finite"));
+        Ok(schedule)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.faults.is_empty() && self.network_faults.is_empty()
+    }
+
+    /// If any of the crashed nodes restarts, which needs persistent storage.
+    pub fn has_restarts(&self) -> bool {
+        self.faults.iter().any(|f| f.downtime.is_some())
+    }
+
+    /// If any of the crashed nodes never restarts.
+    pub fn has_permanent_crashes(&self) -> bool {
+        self.faults.iter().any(|f| f.downtime.is_none())
+    }
+}
+
+impl fmt::Display for FaultSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "none");
+        }
+        let faults: Vec<_> = self
+            .faults
+            .iter()
+            .map(|fault| fault.to_string())
+            .chain(self.network_faults.iter().map(|fault| fault.to_string()))
+            .collect();
+        write!(f, "{}", faults.join(";"))
+    }
+}
+
+/// What the client observed for a single injected crash.
+#[derive(Clone, Debug)]
+pub struct FaultRecord {
+    pub pid: u64,
+    pub was_leader: bool,
+    pub downtime: Option<Duration>,
+    /// From the crash until the client learned about a new leader. Only set if the leader crashed.
+    pub election_time: Option<Duration>,
+    /// From the crash until the first proposal was decided again.
+    pub unavailability: Option<Duration>,
+}
+
+/// What the client observed while a network fault was in place.
+#[derive(Clone, Debug)]
+pub struct NetworkFaultRecord {
+    pub fault: String,
+    /// From injecting the fault until it healed or the run ended.
+    pub window: Duration,
+    pub leader_changes: u64,
+    pub decided: u64,
+    pub timed_out: u64,
+    /// Longest time without any decided proposal.
+    pub max_stall: Duration,
+}
+
+/// A network fault as applied by a single node to its outgoing links.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinkFault {
+    pub id: u32,
+    pub to: Vec<u64>,
+    pub effect: LinkEffect,
+}
+
+#[derive(Clone, Debug)]
+pub enum LinkFaultMsg {
+    Apply(LinkFault),
+    Heal(u32),
+}
+
+/// What to do with a message according to the applied link faults.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Delivery {
+    Drop,
+    Send {
+        copies: usize,
+        delay: Option<Duration>,
+        /// False if the message was held back to let later messages overtake it.
+        in_order: bool,
+    },
+}
+
+/// The link faults a node applies to the messages it sends to its peers.
+#[derive(Debug, Default)]
+pub struct LinkFilter {
+    faults: Vec<LinkFault>,
+}
+
+impl LinkFilter {
+    pub fn handle(&mut self, msg: LinkFaultMsg) {
+        match msg {
+            LinkFaultMsg::Apply(fault) => self.faults.push(fault),
+            LinkFaultMsg::Heal(id) => self.faults.retain(|f| f.id != id),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.faults.is_empty()
+    }
+
+    /// If messages to `to` may currently be dropped.
+    pub fn is_lossy(&self, to: u64) -> bool {
+        self.faults
+            .iter()
+            .filter(|f| f.to.contains(&to))
+            .any(|f| match f.effect {
+                LinkEffect::Loss(pct) => pct > 0,
+                _ => false,
+            })
+    }
+
+    pub fn delivery(&self, to: u64) -> Delivery {
+        let mut copies = 1;
+        let mut delay = Duration::from_millis(0);
+        let mut in_order = true;
+        if !self.faults.is_empty() {
+            let mut rng = rand::thread_rng();
+            for fault in self.faults.iter().filter(|f| f.to.contains(&to)) {
+                match fault.effect {
+                    LinkEffect::Loss(pct) => {
+                        if rng.gen_range(0, 100) < pct {
+                            return Delivery::Drop;
+                        }
+                    }
+                    LinkEffect::Delay(d) => delay += d,
+                    LinkEffect::Duplicate(pct) => {
+                        if rng.gen_range(0, 100) < pct {
+                            copies += 1;
+                        }
+                    }
+                    LinkEffect::Reorder(pct) => {
+                        if rng.gen_range(0, 100) < pct {
+                            delay += Duration::from_millis(rng.gen_range(1, REORDER_WINDOW_MS + 1));
+                            in_order = false;
+                        }
+                    }
+                }
+            }
+        }
+        Delivery::Send {
+            copies,
+            delay: if delay > Duration::from_millis(0) {
+                Some(delay)
+            } else {
+                None
+            },
+            in_order,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_fault_schedule_test() {
+        let schedule = FaultSchedule::parse("3@0.5:2000; leader@0.3").expect("Should parse");
+        assert_eq!(
+            vec![
+                Fault {
+                    target: FaultTarget::Leader,
+                    at: 0.3,
+                    downtime: None
+                },
+                Fault {
+                    target: FaultTarget::Node(3),
+                    at: 0.5,
+                    downtime: Some(Duration::from_millis(2000))
+                }
+            ],
+            schedule.faults
+        );
+        assert!(schedule.has_restarts());
+        assert!(schedule.has_permanent_crashes());
+        assert!(!FaultSchedule::parse("3@0.5:2000")
+            .expect("Should parse")
+            .has_permanent_crashes());
+        assert_eq!("leader@0.3;3@0.5:2000", schedule.to_string());
+        assert_eq!(30, schedule.faults[0].trigger_count(100));
+        assert!(FaultSchedule::parse("").expect("Should parse").is_empty());
+        assert!(FaultSchedule::parse("none")
+            .expect("Should parse")
+            .is_empty());
+        assert!(FaultSchedule::parse("leader@1.5").is_err());
+        assert!(FaultSchedule::parse("0@0.5").is_err());
+        assert!(FaultSchedule::parse("leader:2000").is_err());
+    }
+
+    #[test]
+    fn parse_network_fault_test() {
+        let schedule = FaultSchedule::parse(
+            "loss(10,1>2)@0.6; partition(1,2|3,4,5)@0.2:1000; oneway(leader>*)@0.4; 2@0.1",
+        )
+        .expect("Should parse");
+        assert_eq!(1, schedule.faults.len());
+        assert_eq!(
+            vec![
+                NetworkFault {
+                    rule: NetworkRule::Partition(vec![
+                        NodeGroup::Nodes(vec![FaultTarget::Node(1), FaultTarget::Node(2)]),
+                        NodeGroup::Nodes(vec![
+                            FaultTarget::Node(3),
+                            FaultTarget::Node(4),
+                            FaultTarget::Node(5)
+                        ]),
+                    ]),
+                    at: 0.2,
+                    duration: Some(Duration::from_millis(1000))
+                },
+                NetworkFault {
+                    rule: NetworkRule::OneWay(FaultTarget::Leader, NodeGroup::Rest),
+                    at: 0.4,
+                    duration: None
+                },
+                NetworkFault {
+                    rule: NetworkRule::Link(
+                        LinkEffect::Loss(10),
+                        Some((FaultTarget::Node(1), FaultTarget::Node(2)))
+                    ),
+                    at: 0.6,
+                    duration: None
+                },
+            ],
+            schedule.network_faults
+        );
+        assert_eq!(
+            "2@0.1;partition(1,2|3,4,5)@0.2:1000;oneway(leader>*)@0.4;loss(10,1>2)@0.6",
+            schedule.to_string()
+        );
+        assert_eq!(
+            "delay(50)",
+            LinkEffect::Delay(Duration::from_millis(50)).to_string()
+        );
+        assert_eq!(
+            "reorder(20)@0.5",
+            FaultSchedule::parse("reorder(20)@0.5")
+                .expect("Should parse")
+                .to_string()
+        );
+        assert!(FaultSchedule::parse("delay(50)@0.5").is_ok());
+        assert!(FaultSchedule::parse("loss(101)@0.5").is_err());
+        assert!(FaultSchedule::parse("partition(1,2)@0.5").is_err());
+        assert!(FaultSchedule::parse("oneway(1,2)@0.5").is_err());
+        assert!(FaultSchedule::parse("flood(10)@0.5").is_err());
+    }
+
+    #[test]
+    fn resolve_network_rule_test() {
+        let nodes = vec![1, 2, 3];
+        let isolate_leader = NetworkRule::Partition(vec![
+            NodeGroup::Nodes(vec![FaultTarget::Leader]),
+            NodeGroup::Rest,
+        ]);
+        assert_eq!(
+            vec![
+                (2, vec![1, 3], LinkEffect::Loss(100)),
+                (1, vec![2], LinkEffect::Loss(100)),
+                (3, vec![2], LinkEffect::Loss(100)),
+            ],
+            isolate_leader.resolve(2, &nodes)
+        );
+        let oneway = NetworkRule::OneWay(FaultTarget::Leader, NodeGroup::Rest);
+        assert_eq!(
+            vec![(3, vec![1, 2], LinkEffect::Loss(100))],
+            oneway.resolve(3, &nodes)
+        );
+    }
+
+    #[test]
+    fn link_filter_test() {
+        let mut filter = LinkFilter::default();
+        let send = Delivery::Send {
+            copies: 1,
+            delay: None,
+            in_order: true,
+        };
+        assert_eq!(send, filter.delivery(2));
+        filter.handle(LinkFaultMsg::Apply(LinkFault {
+            id: 1,
+            to: vec![2],
+            effect: LinkEffect::Loss(100),
+        }));
+        filter.handle(LinkFaultMsg::Apply(LinkFault {
+            id: 2,
+            to: vec![3],
+            effect: LinkEffect::Delay(Duration::from_millis(20)),
+        }));
+        assert_eq!(Delivery::Drop, filter.delivery(2));
+        assert!(filter.is_lossy(2));
+        assert_eq!(
+            Delivery::Send {
+                copies: 1,
+                delay: Some(Duration::from_millis(20)),
+                in_order: true
+            },
+            filter.delivery(3)
+        );
+        filter.handle(LinkFaultMsg::Heal(1));
+        assert_eq!(send, filter.delivery(2));
+        assert!(!filter.is_lossy(2));
+    }
+}
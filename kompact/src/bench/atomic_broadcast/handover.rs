@@ -0,0 +1,211 @@
+use super::reconfig::ReconfigOffset;
+use std::fmt;
+use std::time::Duration;
+
+/// Which node a leader transfer hands the leadership over to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransferTarget {
+    Node(u64),
+    /// The node with the next higher id after the current leader, wrapping around to the lowest.
+    Next,
+}
+
+impl TransferTarget {
+    fn parse(s: &str) -> Result<TransferTarget, String> {
+        match s.trim().to_lowercase().as_ref() {
+            "next" => Ok(TransferTarget::Next),
+            pid => match pid.parse::<u64>() {
+                Ok(pid) if pid > 0 => Ok(TransferTarget::Node(pid)),
+                _ => Err(format!("Invalid transfer target: {}", s)),
+            },
+        }
+    }
+
+    /// The node to hand over to from `leader` in the configuration `nodes`.
+    pub fn resolve(&self, leader: u64, nodes: &[u64]) -> u64 {
+        match self {
+            TransferTarget::Node(pid) => *pid,
+            TransferTarget::Next => {
+                let mut nodes = nodes.to_vec();
+                nodes.sort_unstable();
+                nodes
+                    .iter()
+                    .find(|pid| **pid > leader)
+                    .or_else(|| nodes.first())
+                    .copied()
+                    .unwrap_or(leader)
+            }
+        }
+    }
+}
+
+impl fmt::Display for TransferTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransferTarget::Node(pid) => write!(f, "{}", pid),
+            TransferTarget::Next => write!(f, "next"),
+        }
+    }
+}
+
+/// Hands the leadership over to another node once some of the proposals have been decided.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LeaderTransfer {
+    pub target: TransferTarget,
+    pub at: ReconfigOffset,
+}
+
+impl LeaderTransfer {
+    /// Parses `<node id|next>@<offset>`.
+    fn parse(s: &str) -> Result<LeaderTransfer, String> {
+        let (target, at) = match s.find('@') {
+            Some(i) => (&s[..i], &s[i + 1..]),
+            None => return Err(format!("Leader transfer '{}' has no '@'", s)),
+        };
+        Ok(LeaderTransfer {
+            target: TransferTarget::parse(target)?,
+            at: ReconfigOffset::parse(at)?,
+        })
+    }
+
+    /// Number of decided proposals after which the leadership is transferred.
+    pub fn trigger_count(&self, num_proposals: u64) -> u64 {
+        self.at.trigger_count(num_proposals)
+    }
+}
+
+impl fmt::Display for LeaderTransfer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.target, self.at)
+    }
+}
+
+/// The leader transfers of an atomic broadcast experiment, in the order they happen.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TransferSchedule {
+    pub transfers: Vec<LeaderTransfer>,
+}
+
+impl TransferSchedule {
+    /// Parses `;`-separated leader transfers, e.g. `2@0.3;next@5000`. An empty string or `none` is a schedule without
+    /// transfers.
+    pub fn parse(s: &str) -> Result<TransferSchedule, String> {
+        let s = s.trim();
+        if s.is_empty() || s.eq_ignore_ascii_case("none") {
+            return Ok(TransferSchedule::default());
+        }
+        let transfers = s
+            .split(';')
+            .map(|t| LeaderTransfer::parse(t.trim()))
+            .collect::<Result<_, _>>()?;
+        Ok(TransferSchedule { transfers })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transfers.is_empty()
+    }
+
+    /// Checks that the transfers happen in order before the end of the run and only hand over to the `num_nodes`
+    /// nodes of the experiment.
+    pub fn validate(&self, num_nodes: u64, num_proposals: u64) -> Result<(), String> {
+        let mut prev_trigger = None;
+        for transfer in &self.transfers {
+            if let TransferTarget::Node(pid) = transfer.target {
+                if pid > num_nodes {
+                    return Err(format!(
+                        "Leader transfer {} targets a node that is not one of the {} nodes",
+                        transfer, num_nodes
+                    ));
+                }
+            }
+            let trigger = transfer.trigger_count(num_proposals);
+            if trigger >= num_proposals {
+                return Err(format!(
+                    "Leader transfer {} happens after all {} proposals",
+                    transfer, num_proposals
+                ));
+            }
+            if prev_trigger.map_or(false, |prev| trigger <= prev) {
+                return Err(format!(
+                    "Leader transfer {} does not happen after the previous one",
+                    transfer
+                ));
+            }
+            prev_trigger = Some(trigger);
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for TransferSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "none");
+        }
+        let transfers: Vec<_> = self.transfers.iter().map(|t| t.to_string()).collect();
+        write!(f, "{}", transfers.join(";"))
+    }
+}
+
+/// What the client observed for a single leader transfer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransferRecord {
+    pub from: u64,
+    pub to: u64,
+    pub requested_at: Duration, // since the start of the run
+    /// From the request until the client learned that `to` leads. None if it never did.
+    pub elected_after: Option<Duration>,
+    /// From the request until the first proposal was decided under the new leader.
+    pub resumed_after: Option<Duration>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_transfer_schedule_test() {
+        let schedule = TransferSchedule::parse("2@0.3; next@5000").unwrap();
+        assert_eq!(
+            schedule.transfers,
+            vec![
+                LeaderTransfer {
+                    target: TransferTarget::Node(2),
+                    at: ReconfigOffset::Fraction(0.3),
+                },
+                LeaderTransfer {
+                    target: TransferTarget::Next,
+                    at: ReconfigOffset::Count(5000),
+                },
+            ]
+        );
+        assert_eq!(schedule.to_string(), "2@0.3;next@5000");
+        assert_eq!(TransferSchedule::parse(&schedule.to_string()), Ok(schedule));
+        assert!(TransferSchedule::parse("none").unwrap().is_empty());
+        assert!(TransferSchedule::parse("2").is_err());
+        assert!(TransferSchedule::parse("0@0.5").is_err());
+        assert!(TransferSchedule::parse("leader@0.5").is_err());
+        assert!(TransferSchedule::parse("2@1.5").is_err());
+    }
+
+    #[test]
+    fn resolve_transfer_target_test() {
+        assert_eq!(TransferTarget::Node(2).resolve(3, &[1, 2, 3]), 2);
+        assert_eq!(TransferTarget::Next.resolve(1, &[3, 1, 2]), 2);
+        assert_eq!(TransferTarget::Next.resolve(3, &[1, 2, 3]), 1);
+        assert_eq!(TransferTarget::Next.resolve(0, &[2, 3]), 2);
+    }
+
+    #[test]
+    fn validate_transfer_schedule_test() {
+        let schedule = TransferSchedule::parse("2@0.3;next@500;1@0.8").unwrap();
+        assert_eq!(schedule.validate(3, 1000), Ok(()));
+        // 0.3 of 2000 proposals is after the 500th
+        assert!(schedule.validate(3, 2000).is_err());
+        assert!(schedule.validate(3, 400).is_err());
+        assert!(TransferSchedule::parse("4@0.5")
+            .unwrap()
+            .validate(3, 1000)
+            .is_err());
+    }
+}
@@ -0,0 +1,663 @@
+use super::messages::PROPOSAL_ID_LEN;
+use benchmark_suite_shared::test_utils::{KVOperation, KVTimestamp};
+use chrono::Utc;
+use hashbrown::HashMap;
+use hdrhistogram::Histogram;
+use kompact::prelude::{Buf, BufMut};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+use std::time::SystemTime;
+
+const GET: u8 = 1;
+const PUT: u8 = 2;
+const CAS: u8 = 3;
+
+/// Skew of the key popularity, the YCSB default.
+const ZIPFIAN_THETA: f64 = 0.99;
+
+/// Mix of operations, named after the YCSB core workloads.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Workload {
+    /// 50% reads, 50% updates.
+    A,
+    /// 95% reads, 5% updates.
+    B,
+    /// Only reads.
+    C,
+    /// 50% reads, 50% read-modify-writes, issued as compare-and-swap.
+    F,
+}
+
+impl Workload {
+    pub fn parse(s: &str) -> Result<Workload, String> {
+        match s.to_lowercase().as_ref() {
+            "a" => Ok(Workload::A),
+            "b" => Ok(Workload::B),
+            "c" => Ok(Workload::C),
+            "f" => Ok(Workload::F),
+            unknown => Err(format!("Unknown workload: {}", unknown)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Workload::A => "a",
+            Workload::B => "b",
+            Workload::C => "c",
+            Workload::F => "f",
+        }
+    }
+
+    fn read_proportion(&self) -> f64 {
+        match self {
+            Workload::A | Workload::F => 0.5,
+            Workload::B => 0.95,
+            Workload::C => 1.0,
+        }
+    }
+}
+
+/// How a GET is served by the replicas.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReadMode {
+    /// Decided in the log like any other command.
+    Log,
+    /// Served by the leader once it has applied the commit index it confirmed with a quorum.
+    ReadIndex,
+    /// Served by the leader at its commit index while its lease has not expired.
+    Lease,
+}
+
+impl ReadMode {
+    pub fn parse(s: &str) -> Result<ReadMode, String> {
+        match s.to_lowercase().as_ref() {
+            "" | "log" => Ok(ReadMode::Log),
+            "read_index" => Ok(ReadMode::ReadIndex),
+            "lease" => Ok(ReadMode::Lease),
+            unknown => Err(format!("Unknown read mode: {}", unknown)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReadMode::Log => "log",
+            ReadMode::ReadIndex => "read_index",
+            ReadMode::Lease => "lease",
+        }
+    }
+}
+
+/// What the clients of a replicated key-value store run.
+#[derive(Clone, Copy, Debug)]
+pub struct KvConfig {
+    pub workload: Workload,
+    pub num_keys: u64,
+    pub read_mode: ReadMode,
+    pub check_linearizability: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KvOp {
+    Get,
+    Put,
+    Cas,
+}
+
+impl KvOp {
+    pub const ALL: [KvOp; 3] = [KvOp::Get, KvOp::Put, KvOp::Cas];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KvOp::Get => "get",
+            KvOp::Put => "put",
+            KvOp::Cas => "cas",
+        }
+    }
+}
+
+/// A command of the state machine. It is proposed after the 8 byte proposal id.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KvCommand {
+    Get(u64),
+    Put(u64, u32),
+    Cas { key: u64, expected: u32, new: u32 },
+}
+
+impl KvCommand {
+    pub fn key(&self) -> u64 {
+        match *self {
+            KvCommand::Get(key) | KvCommand::Put(key, _) | KvCommand::Cas { key, .. } => key,
+        }
+    }
+
+    pub fn op(&self) -> KvOp {
+        match self {
+            KvCommand::Get(_) => KvOp::Get,
+            KvCommand::Put(..) => KvOp::Put,
+            KvCommand::Cas { .. } => KvOp::Cas,
+        }
+    }
+
+    pub fn serialise(&self, id: u64) -> Vec<u8> {
+        let mut data = Vec::with_capacity(PROPOSAL_ID_LEN + 1 + 8 + 8);
+        data.put_u64(id);
+        match *self {
+            KvCommand::Get(key) => {
+                data.put_u8(GET);
+                data.put_u64(key);
+            }
+            KvCommand::Put(key, value) => {
+                data.put_u8(PUT);
+                data.put_u64(key);
+                data.put_u32(value);
+            }
+            KvCommand::Cas { key, expected, new } => {
+                data.put_u8(CAS);
+                data.put_u64(key);
+                data.put_u32(expected);
+                data.put_u32(new);
+            }
+        }
+        data
+    }
+
+    /// Reads the command following the proposal id, `None` if the proposal is not a command.
+    pub fn deserialise(buf: &mut dyn Buf) -> Option<KvCommand> {
+        if buf.remaining() < 1 + 8 {
+            return None;
+        }
+        let tag = buf.get_u8();
+        let key = buf.get_u64();
+        match tag {
+            GET => Some(KvCommand::Get(key)),
+            PUT if buf.remaining() >= 4 => Some(KvCommand::Put(key, buf.get_u32())),
+            CAS if buf.remaining() >= 8 => Some(KvCommand::Cas {
+                key,
+                expected: buf.get_u32(),
+                new: buf.get_u32(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Whether the proposal `data` is a GET, which may be served without going through the log.
+pub fn is_read(data: &[u8]) -> bool {
+    data.len() > PROPOSAL_ID_LEN && data[PROPOSAL_ID_LEN] == GET
+}
+
+/// The result of a command, as it is sent back after the proposal id.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KvResult {
+    Value(u32),
+    Written,
+    Cas { swapped: bool, value: u32 },
+}
+
+impl KvResult {
+    fn serialise(&self, data: &mut Vec<u8>) {
+        match *self {
+            KvResult::Value(value) => data.put_u32(value),
+            KvResult::Written => {}
+            KvResult::Cas { swapped, value } => {
+                data.put_u8(swapped as u8);
+                data.put_u32(value);
+            }
+        }
+    }
+
+    pub fn deserialise(op: KvOp, buf: &mut dyn Buf) -> Option<KvResult> {
+        match op {
+            KvOp::Get if buf.remaining() >= 4 => Some(KvResult::Value(buf.get_u32())),
+            KvOp::Put => Some(KvResult::Written),
+            KvOp::Cas if buf.remaining() >= 5 => Some(KvResult::Cas {
+                swapped: buf.get_u8() != 0,
+                value: buf.get_u32(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// The replicated state machine. Keys that were never written have the value 0.
+#[derive(Debug, Default)]
+pub struct KvStore {
+    map: HashMap<u64, u32>,
+}
+
+impl KvStore {
+    fn execute(&mut self, command: KvCommand) -> KvResult {
+        match command {
+            KvCommand::Get(key) => KvResult::Value(self.map.get(&key).copied().unwrap_or(0)),
+            KvCommand::Put(key, value) => {
+                self.map.insert(key, value);
+                KvResult::Written
+            }
+            KvCommand::Cas { key, expected, new } => {
+                let value = self.map.entry(key).or_insert(0);
+                let swapped = *value == expected;
+                if swapped {
+                    *value = new;
+                }
+                KvResult::Cas {
+                    swapped,
+                    value: *value,
+                }
+            }
+        }
+    }
+
+    /// Applies a decided proposal and returns the response to the client: the proposal id followed by the result.
+    /// Proposals that are not commands are only acked with their id.
+    pub fn apply(&mut self, data: &[u8]) -> Vec<u8> {
+        let mut buf = data;
+        if buf.remaining() < PROPOSAL_ID_LEN {
+            return data.to_vec();
+        }
+        let id = buf.get_u64();
+        let mut response = Vec::with_capacity(PROPOSAL_ID_LEN + 5);
+        response.put_u64(id);
+        if let Some(command) = KvCommand::deserialise(&mut buf) {
+            self.execute(command).serialise(&mut response);
+        }
+        response
+    }
+}
+
+/// Draws keys in `[0, items)` where key 0 is the most popular, as the zipfian generator of YCSB.
+#[derive(Clone, Debug)]
+pub struct Zipfian {
+    items: u64,
+    alpha: f64,
+    zetan: f64,
+    eta: f64,
+    theta: f64,
+}
+
+impl Zipfian {
+    pub fn new(items: u64) -> Zipfian {
+        Self::with_theta(items, ZIPFIAN_THETA)
+    }
+
+    pub fn with_theta(items: u64, theta: f64) -> Zipfian {
+        assert!(items > 0, "Zipfian requires at least one item");
+        let zeta = |n: u64| (1..=n).map(|i| 1.0 / (i as f64).powf(theta)).sum::<f64>();
+        let zetan = zeta(items);
+        let eta = if items > 2 {
+            (1.0 - (2.0 / items as f64).powf(1.0 - theta)) / (1.0 - zeta(2) / zetan)
+        } else {
+            0.0
+        };
+        Zipfian {
+            items,
+            alpha: 1.0 / (1.0 - theta),
+            zetan,
+            eta,
+            theta,
+        }
+    }
+
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> u64 {
+        let u: f64 = rng.gen();
+        let uz = u * self.zetan;
+        if uz < 1.0 || self.items == 1 {
+            return 0;
+        }
+        if uz < 1.0 + 0.5f64.powf(self.theta) {
+            return 1;
+        }
+        let key = (self.items as f64 * (self.eta * u - self.eta + 1.0).powf(self.alpha)) as u64;
+        key.min(self.items - 1)
+    }
+}
+
+/// Latencies and, if enabled, the history of the operations of a run.
+#[derive(Debug)]
+pub struct KvResults {
+    pub latency_hists: HashMap<KvOp, Histogram<u64>>, // in micros
+    pub failed_cas: u64,
+    pub timestamps: Option<Vec<KVTimestamp>>, // only kept if linearizability should be checked
+}
+
+impl KvResults {
+    /// Adds the results of another client of the same run.
+    pub fn merge(&mut self, other: KvResults) {
+        for (op, hist) in other.latency_hists {
+            match self.latency_hists.get_mut(&op) {
+                Some(h) => h.add(&hist).expect("Failed to merge latency histogram"),
+                None => {
+                    self.latency_hists.insert(op, hist);
+                }
+            }
+        }
+        self.failed_cas += other.failed_cas;
+        if let (Some(ts), Some(other_ts)) = (self.timestamps.as_mut(), other.timestamps) {
+            ts.extend(other_ts);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Invocation {
+    command: KvCommand,
+    start: SystemTime,
+}
+
+/// Identifies operation `id` of a client among the operations of all clients, as writer and as written value.
+/// Unique as long as each client issues fewer than 2^24 operations.
+fn tag(client_id: u64, id: u64) -> u32 {
+    ((client_id as u32) << 24) | (id as u32 & 0x00FF_FFFF)
+}
+
+/// Generates the commands of a client and measures their responses.
+pub struct KvClient {
+    config: KvConfig,
+    client_id: u64,
+    keys: Zipfian,
+    rng: SmallRng,
+    known_values: HashMap<u64, u32>, // latest value this client saw per key, what a CAS expects
+    invocations: HashMap<u64, Invocation>,
+    results: KvResults,
+}
+
+impl KvClient {
+    pub fn with(config: KvConfig, client_id: u64) -> KvClient {
+        let timestamps = if config.check_linearizability {
+            Some(vec![])
+        } else {
+            None
+        };
+        KvClient {
+            config,
+            client_id,
+            keys: Zipfian::new(config.num_keys),
+            rng: SmallRng::from_entropy(),
+            known_values: HashMap::new(),
+            invocations: HashMap::new(),
+            results: KvResults {
+                latency_hists: HashMap::new(),
+                failed_cas: 0,
+                timestamps,
+            },
+        }
+    }
+
+    /// The command of operation `id`. It is drawn on the first call, a retried operation sends the same command.
+    pub fn command(&mut self, id: u64) -> KvCommand {
+        if let Some(invocation) = self.invocations.get(&id) {
+            return invocation.command;
+        }
+        let key = self.keys.sample(&mut self.rng);
+        let command = if self.rng.gen::<f64>() < self.config.workload.read_proportion() {
+            KvCommand::Get(key)
+        } else if self.config.workload == Workload::F {
+            KvCommand::Cas {
+                key,
+                expected: self.known_values.get(&key).copied().unwrap_or(0),
+                new: tag(self.client_id, id),
+            }
+        } else {
+            KvCommand::Put(key, tag(self.client_id, id))
+        };
+        if let Some(ts) = self.results.timestamps.as_mut() {
+            let (operation, value) = match command {
+                KvCommand::Get(_) => (KVOperation::ReadInvokation, None),
+                KvCommand::Put(_, value) => (KVOperation::WriteInvokation, Some(value)),
+                // a CAS is only known to be a write once it has swapped, see `complete`
+                KvCommand::Cas { .. } => (KVOperation::ReadInvokation, None),
+            };
+            ts.push(KVTimestamp {
+                key,
+                operation,
+                value,
+                time: Utc::now().timestamp_nanos(),
+                sender: tag(self.client_id, id),
+            });
+        }
+        self.invocations.insert(
+            id,
+            Invocation {
+                command,
+                start: SystemTime::now(),
+            },
+        );
+        command
+    }
+
+    /// Handles the result of operation `id`, which follows the proposal id in the response.
+    pub fn complete(&mut self, id: u64, buf: &mut dyn Buf) {
+        let invocation = match self.invocations.remove(&id) {
+            Some(invocation) => invocation,
+            None => return,
+        };
+        let op = invocation.command.op();
+        let key = invocation.command.key();
+        let result = match KvResult::deserialise(op, buf) {
+            Some(result) => result,
+            None => return,
+        };
+        if let Ok(latency) = invocation.start.elapsed() {
+            self.results
+                .latency_hists
+                .entry(op)
+                .or_insert_with(|| Histogram::new(4).expect("Failed to create latency histogram"))
+                .record(latency.as_micros() as u64)
+                .expect("Failed to record latency");
+        }
+        let (operation, value) = match (invocation.command, result) {
+            (_, KvResult::Value(value)) => {
+                self.known_values.insert(key, value);
+                (KVOperation::ReadResponse, value)
+            }
+            (KvCommand::Put(_, value), _) => {
+                self.known_values.insert(key, value);
+                (KVOperation::WriteResponse, value)
+            }
+            (_, KvResult::Cas { swapped, value }) => {
+                self.known_values.insert(key, value);
+                if swapped {
+                    (KVOperation::WriteResponse, value)
+                } else {
+                    self.results.failed_cas += 1;
+                    (KVOperation::ReadResponse, value)
+                }
+            }
+            (_, KvResult::Written) => unreachable!("Only a PUT is acked without a value"),
+        };
+        let sender = tag(self.client_id, id);
+        if let Some(ts) = self.results.timestamps.as_mut() {
+            if operation == KVOperation::WriteResponse {
+                if let KvCommand::Cas { new, .. } = invocation.command {
+                    // the swap happened, so the invocation was a write after all
+                    if let Some(inv) = ts.iter_mut().rev().find(|t| t.sender == sender) {
+                        inv.operation = KVOperation::WriteInvokation;
+                        inv.value = Some(new);
+                    }
+                }
+            }
+            ts.push(KVTimestamp {
+                key,
+                operation,
+                value: Some(value),
+                time: Utc::now().timestamp_nanos(),
+                sender,
+            });
+        }
+    }
+
+    /// The results of the operations. Pending operations, e.g. timed out ones, may or may not have
+    /// been applied, so their writes stay in the history without a response and may be linearized
+    /// at any point after their invocation. Pending reads have no effect and are left out.
+    pub fn into_results(self) -> KvResults {
+        let KvClient {
+            client_id,
+            invocations,
+            mut results,
+            ..
+        } = self;
+        if let Some(ts) = results.timestamps.as_mut() {
+            let pending: HashMap<u32, KvCommand> = invocations
+                .iter()
+                .map(|(id, invocation)| (tag(client_id, *id), invocation.command))
+                .collect();
+            ts.retain(|t| match pending.get(&t.sender) {
+                Some(KvCommand::Get(_)) => false,
+                _ => true,
+            });
+            for t in ts.iter_mut() {
+                if let Some(KvCommand::Cas { new, .. }) = pending.get(&t.sender) {
+                    // the swap may have happened
+                    t.operation = KVOperation::WriteInvokation;
+                    t.value = Some(*new);
+                }
+            }
+        }
+        results
+    }
+}
+
+impl fmt::Display for KvConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{},{},{}",
+            self.workload.as_str(),
+            self.num_keys,
+            self.read_mode.as_str()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use benchmark_suite_shared::test_utils::all_linearizable;
+
+    #[test]
+    fn kv_store_apply_test() {
+        let mut store = KvStore::default();
+        let result = |response: Vec<u8>, op: KvOp| {
+            let mut buf = response.as_slice();
+            let id = buf.get_u64();
+            (id, KvResult::deserialise(op, &mut buf).unwrap())
+        };
+        let get = KvCommand::Get(7).serialise(1);
+        assert_eq!(
+            (1, KvResult::Value(0)),
+            result(store.apply(&get), KvOp::Get)
+        );
+        let put = KvCommand::Put(7, 42).serialise(2);
+        assert_eq!((2, KvResult::Written), result(store.apply(&put), KvOp::Put));
+        let get = KvCommand::Get(7).serialise(3);
+        assert_eq!(
+            (3, KvResult::Value(42)),
+            result(store.apply(&get), KvOp::Get)
+        );
+        let failed_cas = KvCommand::Cas {
+            key: 7,
+            expected: 1,
+            new: 2,
+        }
+        .serialise(4);
+        assert_eq!(
+            (
+                4,
+                KvResult::Cas {
+                    swapped: false,
+                    value: 42
+                }
+            ),
+            result(store.apply(&failed_cas), KvOp::Cas)
+        );
+        let cas = KvCommand::Cas {
+            key: 7,
+            expected: 42,
+            new: 43,
+        }
+        .serialise(5);
+        assert_eq!(
+            (
+                5,
+                KvResult::Cas {
+                    swapped: true,
+                    value: 43
+                }
+            ),
+            result(store.apply(&cas), KvOp::Cas)
+        );
+        // plain proposals are acked with their id
+        let mut plain = vec![];
+        plain.put_u64(6);
+        assert_eq!(plain, store.apply(&plain));
+    }
+
+    #[test]
+    fn is_read_test() {
+        assert!(is_read(&KvCommand::Get(1).serialise(1)));
+        assert!(!is_read(&KvCommand::Put(1, 1).serialise(1)));
+        assert!(!is_read(&1u64.to_be_bytes()));
+    }
+
+    #[test]
+    fn zipfian_test() {
+        let items = 1000;
+        let zipfian = Zipfian::new(items);
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut counts = vec![0u64; items as usize];
+        for _ in 0..100_000 {
+            let key = zipfian.sample(&mut rng);
+            assert!(key < items);
+            counts[key as usize] += 1;
+        }
+        assert!(counts[0] > counts[1]);
+        assert!(counts[1] > counts[10]);
+        assert!(counts[10] > counts[items as usize - 1]);
+        let single = Zipfian::new(1);
+        assert_eq!(0, single.sample(&mut rng));
+    }
+
+    #[test]
+    fn pending_operations_test() {
+        let config = KvConfig {
+            workload: Workload::F,
+            num_keys: 1,
+            read_mode: ReadMode::Log,
+            check_linearizability: true,
+        };
+        let mut client = KvClient::with(config, 0);
+        client.rng = SmallRng::seed_from_u64(0);
+        let commands: Vec<KvCommand> = (1..=20).map(|id| client.command(id)).collect();
+        let timestamps = client
+            .into_results()
+            .timestamps
+            .expect("No timestamps kept");
+        let writes: Vec<u32> = commands
+            .iter()
+            .filter_map(|c| match *c {
+                KvCommand::Get(_) => None,
+                KvCommand::Put(_, value) => Some(value),
+                KvCommand::Cas { new, .. } => Some(new),
+            })
+            .collect();
+        assert!(!writes.is_empty() && writes.len() < commands.len());
+        let pending: Vec<u32> = timestamps
+            .iter()
+            .map(|t| {
+                assert_eq!(KVOperation::WriteInvokation, t.operation);
+                t.value.unwrap()
+            })
+            .collect();
+        assert_eq!(writes, pending);
+        assert!(all_linearizable(&timestamps));
+    }
+
+    #[test]
+    fn parse_workload_test() {
+        assert_eq!(Ok(Workload::B), Workload::parse("B"));
+        assert!(Workload::parse("d").is_err());
+        assert_eq!(Ok(ReadMode::Log), ReadMode::parse(""));
+        assert_eq!(Ok(ReadMode::Lease), ReadMode::parse("lease"));
+        assert!(ReadMode::parse("stale").is_err());
+    }
+}
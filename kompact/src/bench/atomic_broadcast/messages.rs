@@ -1,10 +1,22 @@
 extern crate raft as tikv_raft;
 
+use super::client::{
+    Arrivals, ClientStats, MetaResults, OpenLoopResults, PayloadSize, ThroughputSeries,
+};
+use super::fault::{LinkEffect, LinkFault};
+use super::storage::IoStats;
+use crate::ser_utils::{
+    get_actor_path, get_bool, get_bytes, get_f64, get_len, get_u32, get_u64, get_u8,
+};
 use crate::serialiser_ids;
+use benchmark_suite_shared::test_utils::{DecidedEntry, DecidedSequence, ProposalLog};
+use hdrhistogram::Histogram;
 use kompact::prelude::*;
 use protobuf::{parse_from_bytes, Message};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-pub const DATA_SIZE_HINT: usize = 8; // TODO
+/// Length of the proposal id at the start of the data of every proposal, the rest is payload.
+pub const PROPOSAL_ID_LEN: usize = 8;
 
 pub mod raft {
     extern crate raft as tikv_raft;
@@ -14,7 +26,7 @@ pub mod raft {
 
     pub struct RawRaftSer;
 
-    #[derive(Debug)]
+    #[derive(Clone, Debug)]
     pub struct RaftMsg(pub TikvRaftMsg); // wrapper to implement eager serialisation
 
     impl Serialisable for RaftMsg {
@@ -23,16 +35,15 @@ pub mod raft {
         }
 
         fn size_hint(&self) -> Option<usize> {
-            let num_entries = self.0.entries.len();
-            Some(60 + num_entries * DATA_SIZE_HINT) // TODO
+            let entries_size: usize = self.0.entries.iter().map(|e| 16 + e.data.len()).sum();
+            Some(60 + entries_size)
         }
 
         fn serialise(&self, buf: &mut dyn BufMut) -> Result<(), SerError> {
-            let bytes = self
-                .0
-                .write_to_bytes()
+            // write straight into the buffer, the entries may carry large payloads
+            self.0
+                .write_to_writer(&mut buf.writer())
                 .expect("Protobuf failed to serialise TikvRaftMsg");
-            buf.put_slice(&bytes);
             Ok(())
         }
 
@@ -48,14 +59,15 @@ pub mod raft {
             let bytes = buf.bytes();
             let remaining = buf.remaining();
             let rm: TikvRaftMsg = if bytes.len() < remaining {
-                let mut dst = Vec::with_capacity(remaining);
+                let mut dst = vec![0; remaining];
                 buf.copy_to_slice(dst.as_mut_slice());
                 parse_from_bytes::<TikvRaftMsg>(dst.as_slice())
-                    .expect("Protobuf failed to deserialise TikvRaftMsg")
             } else {
                 parse_from_bytes::<TikvRaftMsg>(bytes)
-                    .expect("Protobuf failed to deserialise TikvRaftMsg")
-            };
+            }
+            .map_err(|e| {
+                SerError::InvalidData(format!("Protobuf failed to deserialise TikvRaftMsg: {}", e))
+            })?;
             Ok(rm)
         }
     }
@@ -63,8 +75,8 @@ pub mod raft {
 
 pub mod paxos {
     use super::super::paxos::raw_paxos::Entry;
-    use crate::bench::atomic_broadcast::messages::DATA_SIZE_HINT;
     use crate::bench::atomic_broadcast::paxos::raw_paxos::StopSign;
+    use crate::ser_utils::{get_bool, get_bytes, get_len, get_u32, get_u64, get_u8};
     use crate::serialiser_ids;
     use ballot_leader_election::Ballot;
     use kompact::prelude::{Any, Buf, BufMut, Deserialiser, SerError, Serialisable};
@@ -150,11 +162,12 @@ pub mod paxos {
     pub struct Accepted {
         pub n: Ballot,
         pub la: u64,
+        pub ld: u64, // decided length of the acceptor, bounds how far the leader may compact
     }
 
     impl Accepted {
-        pub fn with(n: Ballot, la: u64) -> Accepted {
-            Accepted { n, la }
+        pub fn with(n: Ballot, la: u64, ld: u64) -> Accepted {
+            Accepted { n, la, ld }
         }
     }
 
@@ -181,6 +194,8 @@ pub mod paxos {
         Accepted(Accepted),
         Decide(Decide),
         ProposalForward(Vec<Entry>),
+        Compaction(u64),
+        PrepareReq,
     }
 
     #[derive(Clone, Debug)]
@@ -205,6 +220,8 @@ pub mod paxos {
     const PROPOSALFORWARD_ID: u8 = 7;
     const FIRSTACCEPTREQ_ID: u8 = 8;
     const FIRSTACCEPT_ID: u8 = 9;
+    const COMPACTION_ID: u8 = 10;
+    const PREPAREREQ_ID: u8 = 11;
 
     const NORMAL_ENTRY_ID: u8 = 1;
     const SS_ENTRY_ID: u8 = 2;
@@ -222,7 +239,7 @@ pub mod paxos {
             buf.put_u64(ballot.pid);
         }
 
-        fn serialise_entry(e: &Entry, buf: &mut dyn BufMut) {
+        pub(crate) fn serialise_entry(e: &Entry, buf: &mut dyn BufMut) {
             match e {
                 Entry::Normal(d) => {
                     buf.put_u8(NORMAL_ENTRY_ID);
@@ -246,6 +263,19 @@ pub mod paxos {
             }
         }
 
+        fn entry_size_hint(e: &Entry) -> usize {
+            match e {
+                Entry::Normal(data) => 5 + data.len(),
+                Entry::StopSign(ss) => {
+                    10 + 8 * ss.nodes.len() + ss.skip_prepare_n.map_or(0, |_| 16)
+                }
+            }
+        }
+
+        fn entries_size_hint(ents: &[Entry]) -> usize {
+            4 + ents.iter().map(Self::entry_size_hint).sum::<usize>()
+        }
+
         pub(crate) fn serialise_entries(ents: &[Entry], buf: &mut dyn BufMut) {
             buf.put_u32(ents.len() as u32);
             for e in ents {
@@ -253,48 +283,49 @@ pub mod paxos {
             }
         }
 
-        fn deserialise_ballot(buf: &mut dyn Buf) -> Ballot {
-            let n = buf.get_u64();
-            let pid = buf.get_u64();
-            Ballot::with(n, pid)
+        fn deserialise_ballot(buf: &mut dyn Buf) -> Result<Ballot, SerError> {
+            let n = get_u64(buf)?;
+            let pid = get_u64(buf)?;
+            Ok(Ballot::with(n, pid))
+        }
+
+        fn deserialise_nodes(buf: &mut dyn Buf) -> Result<Vec<u64>, SerError> {
+            let len = get_len(buf, 8)?;
+            (0..len).map(|_| get_u64(buf)).collect()
         }
 
-        fn deserialise_entry(buf: &mut dyn Buf) -> Entry {
-            match buf.get_u8() {
+        pub(crate) fn deserialise_entry(buf: &mut dyn Buf) -> Result<Entry, SerError> {
+            match get_u8(buf)? {
                 NORMAL_ENTRY_ID => {
-                    let data_len = buf.get_u32() as usize;
-                    let mut data = vec![0; data_len];
-                    buf.copy_to_slice(&mut data);
-                    Entry::Normal(data)
+                    let data_len = get_u32(buf)? as usize;
+                    let data = get_bytes(buf, data_len)?;
+                    Ok(Entry::Normal(data))
                 }
                 SS_ENTRY_ID => {
-                    let config_id = buf.get_u32();
-                    let nodes_len = buf.get_u32() as usize;
-                    let mut nodes = Vec::with_capacity(nodes_len);
-                    for _ in 0..nodes_len {
-                        nodes.push(buf.get_u64());
-                    }
-                    let skip_prepare_n = match buf.get_u8() {
-                        1 => Some(Self::deserialise_ballot(buf)),
+                    let config_id = get_u32(buf)?;
+                    let nodes = Self::deserialise_nodes(buf)?;
+                    let skip_prepare_n = match get_u8(buf)? {
+                        1 => Some(Self::deserialise_ballot(buf)?),
                         _ => None,
                     };
                     let ss = StopSign::with(config_id, nodes, skip_prepare_n);
-                    Entry::StopSign(ss)
+                    Ok(Entry::StopSign(ss))
                 }
-                error_id => panic!(format!(
+                error_id => Err(SerError::InvalidType(format!(
                     "Got unexpected id in deserialise_entry: {}",
                     error_id
-                )),
+                ))),
             }
         }
 
-        pub fn deserialise_entries(buf: &mut dyn Buf) -> Vec<Entry> {
-            let len = buf.get_u32();
-            let mut ents = Vec::with_capacity(len as usize);
+        pub fn deserialise_entries(buf: &mut dyn Buf) -> Result<Vec<Entry>, SerError> {
+            // every entry takes at least its id and a u32 length
+            let len = get_len(buf, 5)?;
+            let mut ents = Vec::with_capacity(len);
             for _ in 0..len {
-                ents.push(Self::deserialise_entry(buf));
+                ents.push(Self::deserialise_entry(buf)?);
             }
-            ents
+            Ok(ents)
         }
     }
 
@@ -307,12 +338,15 @@ pub mod paxos {
             let overhead = 16;
             let msg_size = match &self.msg {
                 PaxosMsg::Prepare(_) => 41,
-                PaxosMsg::Promise(p) => 41 + p.sfx.len() * DATA_SIZE_HINT,
+                PaxosMsg::Promise(p) => 41 + PaxosSer::entries_size_hint(&p.sfx),
                 PaxosMsg::FirstAcceptReq => 1,
-                PaxosMsg::AcceptSync(a) => 26 + a.entries.len() * DATA_SIZE_HINT,
-                PaxosMsg::FirstAccept(_) => 17 + DATA_SIZE_HINT,
-                PaxosMsg::AcceptDecide(a) => 25 + a.entries.len() * DATA_SIZE_HINT,
-                PaxosMsg::ProposalForward(pf) => 1 + pf.len() * DATA_SIZE_HINT,
+                PaxosMsg::PrepareReq => 1,
+                PaxosMsg::AcceptSync(a) => 26 + PaxosSer::entries_size_hint(&a.entries),
+                PaxosMsg::FirstAccept(f) => 17 + PaxosSer::entries_size_hint(&f.entries),
+                PaxosMsg::AcceptDecide(a) => 25 + PaxosSer::entries_size_hint(&a.entries),
+                PaxosMsg::ProposalForward(pf) => 1 + PaxosSer::entries_size_hint(pf),
+                PaxosMsg::Accepted(_) => 33,
+                PaxosMsg::Compaction(_) => 9,
                 _ => 25,
             };
             Some(overhead + msg_size)
@@ -361,6 +395,7 @@ pub mod paxos {
                     buf.put_u8(ACCEPTED_ID);
                     PaxosSer::serialise_ballot(&acc.n, buf);
                     buf.put_u64(acc.la);
+                    buf.put_u64(acc.ld);
                 }
                 PaxosMsg::Decide(d) => {
                     buf.put_u8(DECIDE_ID);
@@ -371,6 +406,13 @@ pub mod paxos {
                     buf.put_u8(PROPOSALFORWARD_ID);
                     PaxosSer::serialise_entries(entries, buf);
                 }
+                PaxosMsg::Compaction(idx) => {
+                    buf.put_u8(COMPACTION_ID);
+                    buf.put_u64(*idx);
+                }
+                PaxosMsg::PrepareReq => {
+                    buf.put_u8(PREPAREREQ_ID);
+                }
             }
             Ok(())
         }
@@ -383,63 +425,60 @@ pub mod paxos {
         const SER_ID: u64 = serialiser_ids::PAXOS_ID;
 
         fn deserialise(buf: &mut dyn Buf) -> Result<Message, SerError> {
-            let from = buf.get_u64();
-            let to = buf.get_u64();
-            match buf.get_u8() {
+            let from = get_u64(buf)?;
+            let to = get_u64(buf)?;
+            match get_u8(buf)? {
                 PREPARE_ID => {
-                    let n = Self::deserialise_ballot(buf);
-                    let n_accepted = Self::deserialise_ballot(buf);
-                    let ld = buf.get_u64();
+                    let n = Self::deserialise_ballot(buf)?;
+                    let n_accepted = Self::deserialise_ballot(buf)?;
+                    let ld = get_u64(buf)?;
                     let p = Prepare::with(n, ld, n_accepted);
                     let msg = Message::with(from, to, PaxosMsg::Prepare(p));
                     Ok(msg)
                 }
                 PROMISE_ID => {
-                    let n = Self::deserialise_ballot(buf);
-                    let n_accepted = Self::deserialise_ballot(buf);
-                    let ld = buf.get_u64();
-                    let sfx = Self::deserialise_entries(buf);
+                    let n = Self::deserialise_ballot(buf)?;
+                    let n_accepted = Self::deserialise_ballot(buf)?;
+                    let ld = get_u64(buf)?;
+                    let sfx = Self::deserialise_entries(buf)?;
                     let prom = Promise::with(n, n_accepted, sfx, ld);
                     let msg = Message::with(from, to, PaxosMsg::Promise(prom));
                     Ok(msg)
                 }
                 ACCEPTSYNC_ID => {
-                    let ld = buf.get_u64();
-                    let sync = match buf.get_u8() {
-                        1 => true,
-                        0 => false,
-                        _ => panic!("Unexpected sync flag when deserialising acceptsync"),
-                    };
-                    let n = Self::deserialise_ballot(buf);
-                    let sfx = Self::deserialise_entries(buf);
+                    let ld = get_u64(buf)?;
+                    let sync = get_bool(buf)?;
+                    let n = Self::deserialise_ballot(buf)?;
+                    let sfx = Self::deserialise_entries(buf)?;
                     let acc_sync = AcceptSync::with(n, sfx, ld, sync);
                     let msg = Message::with(from, to, PaxosMsg::AcceptSync(acc_sync));
                     Ok(msg)
                 }
                 ACCEPTDECIDE_ID => {
-                    let ld = buf.get_u64();
-                    let n = Self::deserialise_ballot(buf);
-                    let entries = Self::deserialise_entries(buf);
+                    let ld = get_u64(buf)?;
+                    let n = Self::deserialise_ballot(buf)?;
+                    let entries = Self::deserialise_entries(buf)?;
                     let a = AcceptDecide::with(n, ld, entries);
                     let msg = Message::with(from, to, PaxosMsg::AcceptDecide(a));
                     Ok(msg)
                 }
                 ACCEPTED_ID => {
-                    let n = Self::deserialise_ballot(buf);
-                    let ld = buf.get_u64();
-                    let acc = Accepted::with(n, ld);
+                    let n = Self::deserialise_ballot(buf)?;
+                    let la = get_u64(buf)?;
+                    let ld = get_u64(buf)?;
+                    let acc = Accepted::with(n, la, ld);
                     let msg = Message::with(from, to, PaxosMsg::Accepted(acc));
                     Ok(msg)
                 }
                 DECIDE_ID => {
-                    let n = Self::deserialise_ballot(buf);
-                    let ld = buf.get_u64();
+                    let n = Self::deserialise_ballot(buf)?;
+                    let ld = get_u64(buf)?;
                     let d = Decide::with(ld, n);
                     let msg = Message::with(from, to, PaxosMsg::Decide(d));
                     Ok(msg)
                 }
                 PROPOSALFORWARD_ID => {
-                    let entries = Self::deserialise_entries(buf);
+                    let entries = Self::deserialise_entries(buf)?;
                     let pf = PaxosMsg::ProposalForward(entries);
                     let msg = Message::with(from, to, pf);
                     Ok(msg)
@@ -450,12 +489,21 @@ pub mod paxos {
                     Ok(msg)
                 }
                 FIRSTACCEPT_ID => {
-                    let n = Self::deserialise_ballot(buf);
-                    let entries = Self::deserialise_entries(buf);
+                    let n = Self::deserialise_ballot(buf)?;
+                    let entries = Self::deserialise_entries(buf)?;
                     let f = FirstAccept::with(n, entries);
                     let msg = Message::with(from, to, PaxosMsg::FirstAccept(f));
                     Ok(msg)
                 }
+                COMPACTION_ID => {
+                    let idx = get_u64(buf)?;
+                    let msg = Message::with(from, to, PaxosMsg::Compaction(idx));
+                    Ok(msg)
+                }
+                PREPAREREQ_ID => {
+                    let msg = Message::with(from, to, PaxosMsg::PrepareReq);
+                    Ok(msg)
+                }
                 _ => Err(SerError::InvalidType(
                     "Found unkown id but expected PaxosMsg".into(),
                 )),
@@ -615,9 +663,17 @@ pub mod paxos {
 
         fn size_hint(&self) -> Option<usize> {
             match self {
-                ReconfigurationMsg::Init(_) => Some(64),
-                ReconfigurationMsg::SequenceRequest(_) => Some(25),
-                ReconfigurationMsg::SequenceTransfer(_) => Some(1000),
+                ReconfigurationMsg::Init(r) => {
+                    let segment_size = r.segment.as_ref().map_or(0, |s| {
+                        16 + PaxosSer::entries_size_hint(s.entries.as_slice())
+                    });
+                    let skip_prepare_size = r.skip_prepare_n.map_or(0, |_| 16);
+                    Some(35 + 8 * r.nodes.len() + segment_size + skip_prepare_size)
+                }
+                ReconfigurationMsg::SequenceRequest(_) => Some(33),
+                ReconfigurationMsg::SequenceTransfer(st) => {
+                    Some(38 + PaxosSer::entries_size_hint(st.segment.entries.as_slice()))
+                }
             }
         }
 
@@ -685,33 +741,25 @@ pub mod paxos {
         const SER_ID: u64 = serialiser_ids::RECONFIG_ID;
 
         fn deserialise(buf: &mut dyn Buf) -> Result<ReconfigurationMsg, SerError> {
-            match buf.get_u8() {
+            match get_u8(buf)? {
                 RECONFIG_INIT_ID => {
-                    let config_id = buf.get_u32();
-                    let from = buf.get_u64();
-                    let seq_metadata_config_id = buf.get_u32();
-                    let seq_metadata_len = buf.get_u64();
-                    let continued_nodes_len = buf.get_u32();
-                    let mut continued_nodes = Vec::with_capacity(continued_nodes_len as usize);
-                    for _ in 0..continued_nodes_len {
-                        continued_nodes.push(buf.get_u64());
-                    }
-                    let new_nodes_len = buf.get_u32();
-                    let mut new_nodes = Vec::with_capacity(new_nodes_len as usize);
-                    for _ in 0..new_nodes_len {
-                        new_nodes.push(buf.get_u64());
-                    }
-                    let segment = match buf.get_u8() {
+                    let config_id = get_u32(buf)?;
+                    let from = get_u64(buf)?;
+                    let seq_metadata_config_id = get_u32(buf)?;
+                    let seq_metadata_len = get_u64(buf)?;
+                    let continued_nodes = PaxosSer::deserialise_nodes(buf)?;
+                    let new_nodes = PaxosSer::deserialise_nodes(buf)?;
+                    let segment = match get_u8(buf)? {
                         1 => {
-                            let from_idx = buf.get_u64();
-                            let to_idx = buf.get_u64();
-                            let entries = PaxosSer::deserialise_entries(buf);
+                            let from_idx = get_u64(buf)?;
+                            let to_idx = get_u64(buf)?;
+                            let entries = PaxosSer::deserialise_entries(buf)?;
                             Some(SequenceSegment::with(from_idx, to_idx, entries))
                         }
                         _ => None,
                     };
-                    let skip_prepare_n = match buf.get_u8() {
-                        1 => Some(PaxosSer::deserialise_ballot(buf)),
+                    let skip_prepare_n = match get_u8(buf)? {
+                        1 => Some(PaxosSer::deserialise_ballot(buf)?),
                         _ => None,
                     };
                     let seq_metadata =
@@ -728,23 +776,23 @@ pub mod paxos {
                     Ok(ReconfigurationMsg::Init(r))
                 }
                 SEQ_REQ_ID => {
-                    let config_id = buf.get_u32();
-                    let tag = buf.get_u32();
-                    let from_idx = buf.get_u64();
-                    let to_idx = buf.get_u64();
-                    let requestor_pid = buf.get_u64();
+                    let config_id = get_u32(buf)?;
+                    let tag = get_u32(buf)?;
+                    let from_idx = get_u64(buf)?;
+                    let to_idx = get_u64(buf)?;
+                    let requestor_pid = get_u64(buf)?;
                     let sr = SequenceRequest::with(config_id, tag, from_idx, to_idx, requestor_pid);
                     Ok(ReconfigurationMsg::SequenceRequest(sr))
                 }
                 SEQ_TRANSFER_ID => {
-                    let config_id = buf.get_u32();
-                    let tag = buf.get_u32();
-                    let succeeded = buf.get_u8() == 1;
-                    let from_idx = buf.get_u64();
-                    let to_idx = buf.get_u64();
-                    let metadata_config_id = buf.get_u32();
-                    let metadata_seq_len = buf.get_u64();
-                    let entries = PaxosSer::deserialise_entries(buf);
+                    let config_id = get_u32(buf)?;
+                    let tag = get_u32(buf)?;
+                    let succeeded = get_u8(buf)? == 1;
+                    let from_idx = get_u64(buf)?;
+                    let to_idx = get_u64(buf)?;
+                    let metadata_config_id = get_u32(buf)?;
+                    let metadata_seq_len = get_u64(buf)?;
+                    let entries = PaxosSer::deserialise_entries(buf)?;
                     let metadata = SequenceMetaData::with(metadata_config_id, metadata_seq_len);
                     let segment = SequenceSegment::with(from_idx, to_idx, entries);
                     let st = SequenceTransfer::with(config_id, tag, succeeded, metadata, segment);
@@ -792,13 +840,18 @@ pub mod paxos {
 
         #[derive(Clone, Debug)]
         pub struct HeartbeatRequest {
+            pub sender_pid: u64,
             pub round: u64,
             pub max_ballot: Ballot,
         }
 
         impl HeartbeatRequest {
-            pub fn with(round: u64, max_ballot: Ballot) -> HeartbeatRequest {
-                HeartbeatRequest { round, max_ballot }
+            pub fn with(sender_pid: u64, round: u64, max_ballot: Ballot) -> HeartbeatRequest {
+                HeartbeatRequest {
+                    sender_pid,
+                    round,
+                    max_ballot,
+                }
             }
         }
 
@@ -830,13 +883,14 @@ pub mod paxos {
             }
 
             fn size_hint(&self) -> Option<usize> {
-                Some(55)
+                Some(33)
             }
 
             fn serialise(&self, buf: &mut dyn BufMut) -> Result<(), SerError> {
                 match self {
                     HeartbeatMsg::Request(req) => {
                         buf.put_u8(HB_REQ_ID);
+                        buf.put_u64(req.sender_pid);
                         buf.put_u64(req.round);
                         buf.put_u64(req.max_ballot.n);
                         buf.put_u64(req.max_ballot.pid);
@@ -861,20 +915,21 @@ pub mod paxos {
             const SER_ID: u64 = serialiser_ids::BLE_ID;
 
             fn deserialise(buf: &mut dyn Buf) -> Result<HeartbeatMsg, SerError> {
-                match buf.get_u8() {
+                match get_u8(buf)? {
                     HB_REQ_ID => {
-                        let round = buf.get_u64();
-                        let n = buf.get_u64();
-                        let pid = buf.get_u64();
+                        let sender_pid = get_u64(buf)?;
+                        let round = get_u64(buf)?;
+                        let n = get_u64(buf)?;
+                        let pid = get_u64(buf)?;
                         let max_ballot = Ballot::with(n, pid);
-                        let hb_req = HeartbeatRequest::with(round, max_ballot);
+                        let hb_req = HeartbeatRequest::with(sender_pid, round, max_ballot);
                         Ok(HeartbeatMsg::Request(hb_req))
                     }
                     HB_REP_ID => {
-                        let sender_pid = buf.get_u64();
-                        let round = buf.get_u64();
-                        let n = buf.get_u64();
-                        let pid = buf.get_u64();
+                        let sender_pid = get_u64(buf)?;
+                        let round = get_u64(buf)?;
+                        let n = get_u64(buf)?;
+                        let pid = get_u64(buf)?;
                         let max_ballot = Ballot::with(n, pid);
                         let hb_rep = HeartbeatReply::with(sender_pid, round, max_ballot);
                         Ok(HeartbeatMsg::Reply(hb_rep))
@@ -893,6 +948,52 @@ pub mod paxos {
 pub struct Run;
 
 pub const RECONFIG_ID: u64 = 0;
+/// A proposal id carries the index of its client in the upper bits, so that replicas can route the response back.
+pub const CLIENT_ID_SHIFT: u32 = 48;
+
+/// The client components of an experiment, as sent to the replicas in the init data of an iteration.
+/// The first one is the primary client that handles reconfigurations, faults and stopping.
+#[derive(Clone, Debug)]
+pub struct Clients(Vec<ActorPath>);
+
+impl Clients {
+    pub fn serialise(clients: &[ActorPath]) -> Result<Vec<u8>, SerError> {
+        let mut buf = Vec::<u8>::new();
+        for client in clients {
+            client.serialise(&mut buf)?;
+        }
+        Ok(buf)
+    }
+
+    pub fn deserialise(mut buf: &[u8]) -> Result<Clients, SerError> {
+        let mut clients = vec![];
+        while buf.has_remaining() {
+            clients.push(get_actor_path(&mut buf)?);
+        }
+        if clients.is_empty() {
+            return Err(SerError::InvalidData("No client actorpath".to_string()));
+        }
+        Ok(Clients(clients))
+    }
+
+    pub fn primary(&self) -> &ActorPath {
+        &self.0[0]
+    }
+
+    pub fn all(&self) -> &[ActorPath] {
+        &self.0
+    }
+
+    /// The client that proposed the entry with the given response data.
+    pub fn of_response(&self, data: &[u8]) -> &ActorPath {
+        if data.len() < 8 {
+            return self.primary();
+        }
+        let id = (&data[..8]).get_u64();
+        let idx = (id >> CLIENT_ID_SHIFT) as usize;
+        self.0.get(idx).unwrap_or_else(|| self.primary())
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct Proposal {
@@ -929,6 +1030,12 @@ impl ProposalResp {
             latest_leader,
         }
     }
+
+    /// Responds to the proposal with the given data. Only its id is sent back, not the payload.
+    pub fn to_proposal(data: &[u8], latest_leader: u64) -> ProposalResp {
+        let id_len = data.len().min(PROPOSAL_ID_LEN);
+        ProposalResp::with(data[..id_len].to_vec(), latest_leader)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -956,10 +1063,10 @@ impl Serialisable for AtomicBroadcastMsg {
                     Some((v, f)) => v.len() + f.len(),
                     _ => 0,
                 };
-                13 + DATA_SIZE_HINT + reconfig_len * 8
+                13 + p.data.len() + reconfig_len * 8
             }
-            AtomicBroadcastMsg::ProposalResp(_) => 13 + DATA_SIZE_HINT,
-            AtomicBroadcastMsg::PendingReconfiguration(_) => 5 + DATA_SIZE_HINT,
+            AtomicBroadcastMsg::ProposalResp(pr) => 13 + pr.data.len(),
+            AtomicBroadcastMsg::PendingReconfiguration(data) => 5 + data.len(),
             AtomicBroadcastMsg::FirstLeader(_) => 9,
         };
         Some(msg_size)
@@ -1025,21 +1132,18 @@ impl Deserialiser<AtomicBroadcastMsg> for AtomicBroadcastDeser {
     const SER_ID: u64 = serialiser_ids::ATOMICBCAST_ID;
 
     fn deserialise(buf: &mut dyn Buf) -> Result<AtomicBroadcastMsg, SerError> {
-        match buf.get_u8() {
+        match get_u8(buf)? {
             PROPOSAL_ID => {
-                let data_len = buf.get_u32() as usize;
-                let mut data = vec![0; data_len];
-                buf.copy_to_slice(&mut data);
-                let voters_len = buf.get_u32() as usize;
-                let mut voters = Vec::with_capacity(voters_len);
-                for _ in 0..voters_len {
-                    voters.push(buf.get_u64());
-                }
-                let followers_len = buf.get_u32() as usize;
-                let mut followers = Vec::with_capacity(followers_len);
-                for _ in 0..followers_len {
-                    followers.push(buf.get_u64());
-                }
+                let data_len = get_u32(buf)? as usize;
+                let data = get_bytes(buf, data_len)?;
+                let voters_len = get_len(buf, 8)?;
+                let voters = (0..voters_len)
+                    .map(|_| get_u64(buf))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let followers_len = get_len(buf, 8)?;
+                let followers = (0..followers_len)
+                    .map(|_| get_u64(buf))
+                    .collect::<Result<Vec<_>, _>>()?;
                 let reconfig = if voters_len == 0 && followers_len == 0 {
                     None
                 } else {
@@ -1049,11 +1153,10 @@ impl Deserialiser<AtomicBroadcastMsg> for AtomicBroadcastDeser {
                 Ok(AtomicBroadcastMsg::Proposal(proposal))
             }
             PROPOSALRESP_ID => {
-                let latest_leader = buf.get_u64();
-                let data_len = buf.get_u32() as usize;
+                let latest_leader = get_u64(buf)?;
+                let data_len = get_u32(buf)? as usize;
                 // println!("latest_leader: {}, data_len: {}, buf remaining: {}", latest_leader, data_len, buf.remaining());
-                let mut data = vec![0; data_len];
-                buf.copy_to_slice(&mut data);
+                let data = get_bytes(buf, data_len)?;
                 let pr = ProposalResp {
                     data,
                     latest_leader,
@@ -1062,14 +1165,13 @@ impl Deserialiser<AtomicBroadcastMsg> for AtomicBroadcastDeser {
                 Ok(AtomicBroadcastMsg::ProposalResp(pr))
             }
             FIRSTLEADER_ID => {
-                let pid = buf.get_u64();
+                let pid = get_u64(buf)?;
                 Ok(AtomicBroadcastMsg::FirstLeader(pid))
             }
             PENDINGRECONFIG_ID => {
-                let data_len = buf.get_u32() as usize;
+                let data_len = get_u32(buf)? as usize;
                 // println!("latest_leader: {}, data_len: {}, buf remaining: {}", latest_leader, data_len, buf.remaining());
-                let mut data = vec![0; data_len];
-                buf.copy_to_slice(&mut data);
+                let data = get_bytes(buf, data_len)?;
                 Ok(AtomicBroadcastMsg::PendingReconfiguration(data))
             }
             _ => Err(SerError::InvalidType(
@@ -1094,7 +1196,10 @@ impl Serialisable for StopMsg {
     }
 
     fn size_hint(&self) -> Option<usize> {
-        Some(9)
+        match self {
+            StopMsg::Peer(_) => Some(9),
+            StopMsg::Client => Some(1),
+        }
     }
 
     fn serialise(&self, buf: &mut dyn BufMut) -> Result<(), SerError> {
@@ -1119,9 +1224,9 @@ impl Deserialiser<StopMsg> for StopMsgDeser {
     const SER_ID: u64 = serialiser_ids::STOP_ID;
 
     fn deserialise(buf: &mut dyn Buf) -> Result<StopMsg, SerError> {
-        match buf.get_u8() {
+        match get_u8(buf)? {
             PEER_STOP_ID => {
-                let pid = buf.get_u64();
+                let pid = get_u64(buf)?;
                 Ok(StopMsg::Peer(pid))
             }
             CLIENT_STOP_ID => Ok(StopMsg::Client),
@@ -1132,14 +1237,145 @@ impl Deserialiser<StopMsg> for StopMsgDeser {
     }
 }
 
+/// Sent by the client to inject a fault or a leader handover into a node.
+#[derive(Clone, Debug)]
+pub enum FaultMsg {
+    /// Kill the replica. It restarts from its storage after the downtime, if any.
+    Crash(Option<Duration>),
+    /// Apply a network fault to the node's outgoing links.
+    Link(LinkFault),
+    /// Remove the network fault with the given id.
+    Heal(u32),
+    /// Take over the leadership from the current leader.
+    TransferLeadership,
+}
+
+const CRASH_ID: u8 = 1;
+const LINK_ID: u8 = 2;
+const HEAL_ID: u8 = 3;
+const TRANSFER_LEADERSHIP_ID: u8 = 4;
+
+const LOSS_ID: u8 = 1;
+const DELAY_ID: u8 = 2;
+const DUPLICATE_ID: u8 = 3;
+const REORDER_ID: u8 = 4;
+
+impl Serialisable for FaultMsg {
+    fn ser_id(&self) -> u64 {
+        serialiser_ids::FAULT_ID
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        match self {
+            FaultMsg::Crash(downtime) => Some(downtime.map_or(2, |_| 10)),
+            FaultMsg::Link(fault) => Some(18 + 8 * fault.to.len()),
+            FaultMsg::Heal(_) => Some(5),
+            FaultMsg::TransferLeadership => Some(1),
+        }
+    }
+
+    fn serialise(&self, buf: &mut dyn BufMut) -> Result<(), SerError> {
+        match self {
+            FaultMsg::Crash(downtime) => {
+                buf.put_u8(CRASH_ID);
+                match downtime {
+                    Some(d) => {
+                        buf.put_u8(1);
+                        buf.put_u64(d.as_millis() as u64);
+                    }
+                    None => buf.put_u8(0),
+                }
+            }
+            FaultMsg::Link(fault) => {
+                buf.put_u8(LINK_ID);
+                buf.put_u32(fault.id);
+                match fault.effect {
+                    LinkEffect::Loss(pct) => {
+                        buf.put_u8(LOSS_ID);
+                        buf.put_u64(pct as u64);
+                    }
+                    LinkEffect::Delay(d) => {
+                        buf.put_u8(DELAY_ID);
+                        buf.put_u64(d.as_millis() as u64);
+                    }
+                    LinkEffect::Duplicate(pct) => {
+                        buf.put_u8(DUPLICATE_ID);
+                        buf.put_u64(pct as u64);
+                    }
+                    LinkEffect::Reorder(pct) => {
+                        buf.put_u8(REORDER_ID);
+                        buf.put_u64(pct as u64);
+                    }
+                }
+                buf.put_u32(fault.to.len() as u32);
+                for pid in &fault.to {
+                    buf.put_u64(*pid);
+                }
+            }
+            FaultMsg::Heal(id) => {
+                buf.put_u8(HEAL_ID);
+                buf.put_u32(*id);
+            }
+            FaultMsg::TransferLeadership => buf.put_u8(TRANSFER_LEADERSHIP_ID),
+        }
+        Ok(())
+    }
+
+    fn local(self: Box<Self>) -> Result<Box<dyn Any + Send>, Box<dyn Serialisable>> {
+        Ok(self)
+    }
+}
+
+pub struct FaultMsgDeser;
+
+impl Deserialiser<FaultMsg> for FaultMsgDeser {
+    const SER_ID: u64 = serialiser_ids::FAULT_ID;
+
+    fn deserialise(buf: &mut dyn Buf) -> Result<FaultMsg, SerError> {
+        match get_u8(buf)? {
+            CRASH_ID => {
+                let downtime = match get_u8(buf)? {
+                    1 => Some(Duration::from_millis(get_u64(buf)?)),
+                    _ => None,
+                };
+                Ok(FaultMsg::Crash(downtime))
+            }
+            LINK_ID => {
+                let id = get_u32(buf)?;
+                let effect_id = get_u8(buf)?;
+                let value = get_u64(buf)?;
+                let effect = match effect_id {
+                    LOSS_ID => LinkEffect::Loss(value as u8),
+                    DELAY_ID => LinkEffect::Delay(Duration::from_millis(value)),
+                    DUPLICATE_ID => LinkEffect::Duplicate(value as u8),
+                    REORDER_ID => LinkEffect::Reorder(value as u8),
+                    _ => {
+                        return Err(SerError::InvalidType(
+                            "Found unkown id but expected LinkEffect".into(),
+                        ))
+                    }
+                };
+                let len = get_len(buf, 8)?;
+                let to = (0..len).map(|_| get_u64(buf)).collect::<Result<_, _>>()?;
+                Ok(FaultMsg::Link(LinkFault { id, to, effect }))
+            }
+            HEAL_ID => Ok(FaultMsg::Heal(get_u32(buf)?)),
+            TRANSFER_LEADERSHIP_ID => Ok(FaultMsg::TransferLeadership),
+            _ => Err(SerError::InvalidType(
+                "Found unkown id but expected Crash, Link, Heal or TransferLeadership".into(),
+            )),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct SequenceResp {
     pub node_id: u64,
-    pub sequence: Vec<u64>,
+    pub sequence: DecidedSequence, // without the compacted prefix
 }
 
 impl SequenceResp {
-    pub fn with(node_id: u64, sequence: Vec<u64>) -> SequenceResp {
+    pub fn with(node_id: u64, sequence: DecidedSequence) -> SequenceResp {
         SequenceResp { node_id, sequence }
     }
 }
@@ -1148,6 +1384,9 @@ impl SequenceResp {
 pub enum TestMessage {
     SequenceReq,
     SequenceResp(SequenceResp),
+    /// Asks a stopped node for the I/O its storage did in the iteration.
+    IoStatsReq,
+    IoStatsResp(u64, IoStats), // (node_id, I/O)
 }
 
 #[derive(Clone)]
@@ -1155,6 +1394,10 @@ pub struct TestMessageSer;
 
 const SEQREQ_ID: u8 = 0;
 const SEQRESP_ID: u8 = 1;
+const IOSTATSREQ_ID: u8 = 2;
+const IOSTATSRESP_ID: u8 = 3;
+const DECIDED_NORMAL_ID: u8 = 1;
+const DECIDED_SS_ID: u8 = 2;
 
 impl Serialiser<TestMessage> for TestMessageSer {
     fn ser_id(&self) -> u64 {
@@ -1174,13 +1417,34 @@ impl Serialiser<TestMessage> for TestMessageSer {
             TestMessage::SequenceResp(sr) => {
                 buf.put_u8(SEQRESP_ID);
                 buf.put_u64(sr.node_id);
-                let seq_len = sr.sequence.len() as u32;
+                buf.put_u64(sr.sequence.compacted_idx);
+                let seq_len = sr.sequence.entries.len() as u32;
                 buf.put_u32(seq_len);
-                for i in &sr.sequence {
-                    buf.put_u64(*i);
+                for entry in &sr.sequence.entries {
+                    match entry {
+                        DecidedEntry::Normal(id) => {
+                            buf.put_u8(DECIDED_NORMAL_ID);
+                            buf.put_u64(*id);
+                        }
+                        DecidedEntry::StopSign(config_id) => {
+                            buf.put_u8(DECIDED_SS_ID);
+                            buf.put_u32(*config_id);
+                        }
+                    }
                 }
                 Ok(())
             }
+            TestMessage::IoStatsReq => {
+                buf.put_u8(IOSTATSREQ_ID);
+                Ok(())
+            }
+            TestMessage::IoStatsResp(node_id, io_stats) => {
+                buf.put_u8(IOSTATSRESP_ID);
+                buf.put_u64(*node_id);
+                buf.put_u64(io_stats.bytes_written);
+                buf.put_u64(io_stats.fsyncs);
+                Ok(())
+            }
         }
     }
 }
@@ -1189,23 +1453,817 @@ impl Deserialiser<TestMessage> for TestMessageSer {
     const SER_ID: u64 = serialiser_ids::TEST_SEQ_ID;
 
     fn deserialise(buf: &mut dyn Buf) -> Result<TestMessage, SerError> {
-        match buf.get_u8() {
+        match get_u8(buf)? {
             SEQREQ_ID => Ok(TestMessage::SequenceReq),
             SEQRESP_ID => {
-                let node_id = buf.get_u64();
-                let sequence_len = buf.get_u32();
-                let mut sequence: Vec<u64> = Vec::new();
+                let node_id = get_u64(buf)?;
+                let compacted_idx = get_u64(buf)?;
+                // a stopsign takes 5 bytes, a normal entry 9
+                let sequence_len = get_len(buf, 5)?;
+                let mut entries: Vec<DecidedEntry> = Vec::with_capacity(sequence_len);
                 for _ in 0..sequence_len {
-                    sequence.push(buf.get_u64());
+                    let entry = match get_u8(buf)? {
+                        DECIDED_NORMAL_ID => DecidedEntry::Normal(get_u64(buf)?),
+                        DECIDED_SS_ID => DecidedEntry::StopSign(get_u32(buf)?),
+                        _ => return Err(SerError::InvalidType("Found unkown id when deserialising DecidedEntry".into())),
+                    };
+                    entries.push(entry);
                 }
+                let sequence = DecidedSequence { compacted_idx, entries };
                 let sr = SequenceResp{ node_id, sequence};
                 Ok(TestMessage::SequenceResp(sr))
             },
+            IOSTATSREQ_ID => Ok(TestMessage::IoStatsReq),
+            IOSTATSRESP_ID => {
+                let node_id = get_u64(buf)?;
+                let io_stats = IoStats {
+                    bytes_written: get_u64(buf)?,
+                    fsyncs: get_u64(buf)?,
+                };
+                Ok(TestMessage::IoStatsResp(node_id, io_stats))
+            },
             _ => {
                 Err(SerError::InvalidType(
-                    "Found unkown id when deserialising TestMessage. Expected SequenceReq or SequenceResp".into(),
+                    "Found unkown id when deserialising TestMessage. Expected SequenceReq, SequenceResp, IoStatsReq or IoStatsResp".into(),
+                ))
+            }
+        }
+    }
+}
+
+/// What the client components on a benchmark client process run in an iteration. The client ids are global, so
+/// the proposals of all clients of the master are told apart.
+#[derive(Clone, Debug)]
+pub struct RemoteWorkload {
+    pub iteration_id: u32,
+    pub clients: Vec<(u64, u64, u64)>, // (client id, number of proposals, open-loop rate or 0 for a closed loop)
+    pub concurrent_proposals: u64,
+    pub num_initial_nodes: u64,
+    pub nodes: Vec<(u64, ActorPath)>,
+    pub timeout: Duration,
+    pub check_total_order: bool,
+    pub arrivals: Arrivals,
+    pub payload_size: PayloadSize,
+    pub throughput_window: Option<Duration>,
+}
+
+/// Between the master and the host of the client components on a benchmark client process.
+#[derive(Debug)]
+pub enum ClientHostMsg {
+    /// Create and start the clients of the iteration.
+    Prepare(RemoteWorkload),
+    /// The (client id, path) of each created client.
+    Prepared(Vec<(u64, ActorPath)>),
+    /// Every client of the host got the first leader.
+    LeadersKnown,
+    /// Start proposing, at the start of the run shared by all clients of the master.
+    Run(SystemTime),
+    /// Every client of the host got all of its proposals decided.
+    Finished,
+    Stop,
+    /// The (client id, results) of the clients of the host. They are not the primary, so the results carry no faults,
+    /// reconfigurations, leader changes, key-value results, storage I/O or decided sequences.
+    Stopped(Vec<(u64, MetaResults)>),
+}
+
+#[derive(Clone)]
+pub struct ClientHostSer;
+
+const HOST_PREPARE_ID: u8 = 1;
+const HOST_PREPARED_ID: u8 = 2;
+const HOST_LEADERS_KNOWN_ID: u8 = 3;
+const HOST_RUN_ID: u8 = 4;
+const HOST_FINISHED_ID: u8 = 5;
+const HOST_STOP_ID: u8 = 6;
+const HOST_STOPPED_ID: u8 = 7;
+
+const FIXED_SIZE_ID: u8 = 1;
+const UNIFORM_SIZE_ID: u8 = 2;
+const EXP_SIZE_ID: u8 = 3;
+
+impl ClientHostSer {
+    fn serialise_workload(w: &RemoteWorkload, buf: &mut dyn BufMut) -> Result<(), SerError> {
+        buf.put_u32(w.iteration_id);
+        buf.put_u32(w.clients.len() as u32);
+        for (id, num_proposals, rate) in &w.clients {
+            buf.put_u64(*id);
+            buf.put_u64(*num_proposals);
+            buf.put_u64(*rate);
+        }
+        buf.put_u64(w.concurrent_proposals);
+        buf.put_u64(w.num_initial_nodes);
+        buf.put_u32(w.nodes.len() as u32);
+        for (pid, path) in &w.nodes {
+            buf.put_u64(*pid);
+            path.serialise(buf)?;
+        }
+        buf.put_u64(w.timeout.as_millis() as u64);
+        buf.put_u8(w.check_total_order as u8);
+        buf.put_u8(match w.arrivals {
+            Arrivals::Constant => 0,
+            Arrivals::Poisson => 1,
+        });
+        match w.payload_size {
+            PayloadSize::Fixed(size) => {
+                buf.put_u8(FIXED_SIZE_ID);
+                buf.put_u64(size as u64);
+            }
+            PayloadSize::Uniform(min, max) => {
+                buf.put_u8(UNIFORM_SIZE_ID);
+                buf.put_u64(min as u64);
+                buf.put_u64(max as u64);
+            }
+            PayloadSize::Exponential(mean) => {
+                buf.put_u8(EXP_SIZE_ID);
+                buf.put_u64(mean as u64);
+            }
+        }
+        match w.throughput_window {
+            Some(window) => {
+                buf.put_u8(1);
+                buf.put_u64(window.as_nanos() as u64);
+            }
+            None => buf.put_u8(0),
+        }
+        Ok(())
+    }
+
+    fn deserialise_workload(buf: &mut dyn Buf) -> Result<RemoteWorkload, SerError> {
+        let iteration_id = get_u32(buf)?;
+        let clients = (0..get_len(buf, 24)?)
+            .map(|_| Ok((get_u64(buf)?, get_u64(buf)?, get_u64(buf)?)))
+            .collect::<Result<_, SerError>>()?;
+        let concurrent_proposals = get_u64(buf)?;
+        let num_initial_nodes = get_u64(buf)?;
+        let nodes = (0..get_len(buf, 8)?)
+            .map(|_| Ok((get_u64(buf)?, get_actor_path(buf)?)))
+            .collect::<Result<_, SerError>>()?;
+        let timeout = Duration::from_millis(get_u64(buf)?);
+        let check_total_order = get_bool(buf)?;
+        let arrivals = match get_u8(buf)? {
+            0 => Arrivals::Constant,
+            1 => Arrivals::Poisson,
+            _ => {
+                return Err(SerError::InvalidType(
+                    "Found unkown id but expected Arrivals".into(),
+                ))
+            }
+        };
+        let payload_size = match get_u8(buf)? {
+            FIXED_SIZE_ID => PayloadSize::Fixed(get_u64(buf)? as usize),
+            UNIFORM_SIZE_ID => PayloadSize::Uniform(get_u64(buf)? as usize, get_u64(buf)? as usize),
+            EXP_SIZE_ID => PayloadSize::Exponential(get_u64(buf)? as usize),
+            _ => {
+                return Err(SerError::InvalidType(
+                    "Found unkown id but expected PayloadSize".into(),
+                ))
+            }
+        }
+        .validated()
+        .map_err(SerError::InvalidData)?;
+        let throughput_window = match get_bool(buf)? {
+            true => Some(Duration::from_nanos(get_u64(buf)?)),
+            false => None,
+        };
+        Ok(RemoteWorkload {
+            iteration_id,
+            clients,
+            concurrent_proposals,
+            num_initial_nodes,
+            nodes,
+            timeout,
+            check_total_order,
+            arrivals,
+            payload_size,
+            throughput_window,
+        })
+    }
+
+    fn serialise_results(res: &MetaResults, buf: &mut dyn BufMut) {
+        buf.put_u64(res.num_timed_out);
+        buf.put_u32(res.latencies.len() as u32);
+        for (id, latency) in &res.latencies {
+            buf.put_u64(*id);
+            buf.put_u64(latency.as_nanos() as u64);
+        }
+        match &res.throughput {
+            Some(throughput) => {
+                buf.put_u8(1);
+                buf.put_u64(throughput.window.as_nanos() as u64);
+                buf.put_u32(throughput.decided.len() as u32);
+                for count in &throughput.decided {
+                    buf.put_u64(*count);
+                }
+            }
+            None => buf.put_u8(0),
+        }
+        match &res.open_loop {
+            Some(ol) => {
+                buf.put_u8(1);
+                buf.put_u64(ol.target_rate);
+                buf.put_f64(ol.offered_rate);
+                buf.put_f64(ol.achieved_rate);
+                buf.put_u64(ol.decided);
+                // (value, count) of each recorded bucket
+                let recorded: Vec<_> = ol.latency_hist.iter_recorded().collect();
+                buf.put_u32(recorded.len() as u32);
+                for v in recorded {
+                    buf.put_u64(v.value_iterated_to());
+                    buf.put_u64(v.count_at_value());
+                }
+            }
+            None => buf.put_u8(0),
+        }
+        buf.put_u32(res.clients.len() as u32);
+        for stats in &res.clients {
+            buf.put_u64(stats.decided);
+            buf.put_f64(stats.throughput);
+            buf.put_u64(stats.decided_bytes);
+            buf.put_f64(stats.byte_throughput);
+        }
+        match &res.proposal_log {
+            Some(log) => {
+                buf.put_u8(1);
+                for ids in &[&log.proposed, &log.acked, &log.pending_at_crash] {
+                    buf.put_u32(ids.len() as u32);
+                    for id in ids.iter() {
+                        buf.put_u64(*id);
+                    }
+                }
+                buf.put_u32(log.reconfigs.len() as u32);
+                for (num_acked, latest) in &log.reconfigs {
+                    buf.put_u64(*num_acked as u64);
+                    buf.put_u64(*latest);
+                }
+            }
+            None => buf.put_u8(0),
+        }
+    }
+
+    fn deserialise_results(buf: &mut dyn Buf) -> Result<MetaResults, SerError> {
+        let num_timed_out = get_u64(buf)?;
+        let latencies = (0..get_len(buf, 16)?)
+            .map(|_| Ok((get_u64(buf)?, Duration::from_nanos(get_u64(buf)?))))
+            .collect::<Result<_, SerError>>()?;
+        let throughput = match get_bool(buf)? {
+            true => {
+                let window = Duration::from_nanos(get_u64(buf)?);
+                let decided = (0..get_len(buf, 8)?)
+                    .map(|_| get_u64(buf))
+                    .collect::<Result<_, _>>()?;
+                Some(ThroughputSeries {
+                    window,
+                    decided,
+                    events: vec![],
+                })
+            }
+            false => None,
+        };
+        let open_loop = match get_bool(buf)? {
+            true => {
+                let target_rate = get_u64(buf)?;
+                let offered_rate = get_f64(buf)?;
+                let achieved_rate = get_f64(buf)?;
+                let decided = get_u64(buf)?;
+                let mut latency_hist =
+                    Histogram::new(4).map_err(|e| SerError::InvalidData(format!("{:?}", e)))?;
+                for _ in 0..get_len(buf, 16)? {
+                    let (value, count) = (get_u64(buf)?, get_u64(buf)?);
+                    if latency_hist.len().checked_add(count).is_none() {
+                        return Err(SerError::InvalidData("Latency count overflow".into()));
+                    }
+                    latency_hist.record_n(value, count).map_err(|e| {
+                        SerError::InvalidData(format!("Invalid latency {}: {:?}", value, e))
+                    })?;
+                }
+                Some(OpenLoopResults {
+                    target_rate,
+                    offered_rate,
+                    achieved_rate,
+                    decided,
+                    latency_hist,
+                })
+            }
+            false => None,
+        };
+        let clients = (0..get_len(buf, 32)?)
+            .map(|_| {
+                Ok(ClientStats {
+                    decided: get_u64(buf)?,
+                    throughput: get_f64(buf)?,
+                    decided_bytes: get_u64(buf)?,
+                    byte_throughput: get_f64(buf)?,
+                })
+            })
+            .collect::<Result<_, SerError>>()?;
+        let proposal_log = match get_bool(buf)? {
+            true => {
+                let mut ids = || -> Result<Vec<u64>, SerError> {
+                    (0..get_len(buf, 8)?).map(|_| get_u64(buf)).collect()
+                };
+                let proposed = ids()?;
+                let acked = ids()?;
+                let pending_at_crash = ids()?;
+                let reconfigs = (0..get_len(buf, 16)?)
+                    .map(|_| Ok((get_u64(buf)? as usize, get_u64(buf)?)))
+                    .collect::<Result<_, SerError>>()?;
+                Some(ProposalLog {
+                    proposed,
+                    acked,
+                    reconfigs,
+                    pending_at_crash,
+                })
+            }
+            false => None,
+        };
+        Ok(MetaResults {
+            num_timed_out,
+            latencies,
+            leader_changes: vec![],
+            reconfigurations: vec![],
+            throughput,
+            total_order: None,
+            proposal_log,
+            decided_sequences: std::collections::HashMap::new(),
+            faults: vec![],
+            network_faults: vec![],
+            leader_transfers: vec![],
+            open_loop,
+            clients,
+            kv: None,
+            io_stats: vec![],
+        })
+    }
+}
+
+impl Serialiser<ClientHostMsg> for ClientHostSer {
+    fn ser_id(&self) -> u64 {
+        serialiser_ids::CLIENT_HOST_ID
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(50000)
+    }
+
+    fn serialise(&self, msg: &ClientHostMsg, buf: &mut dyn BufMut) -> Result<(), SerError> {
+        match msg {
+            ClientHostMsg::Prepare(workload) => {
+                buf.put_u8(HOST_PREPARE_ID);
+                Self::serialise_workload(workload, buf)?;
+            }
+            ClientHostMsg::Prepared(clients) => {
+                buf.put_u8(HOST_PREPARED_ID);
+                buf.put_u32(clients.len() as u32);
+                for (id, path) in clients {
+                    buf.put_u64(*id);
+                    path.serialise(buf)?;
+                }
+            }
+            ClientHostMsg::LeadersKnown => buf.put_u8(HOST_LEADERS_KNOWN_ID),
+            ClientHostMsg::Run(run_start) => {
+                buf.put_u8(HOST_RUN_ID);
+                let since_epoch = run_start.duration_since(UNIX_EPOCH).unwrap_or_default();
+                buf.put_u64(since_epoch.as_nanos() as u64);
+            }
+            ClientHostMsg::Finished => buf.put_u8(HOST_FINISHED_ID),
+            ClientHostMsg::Stop => buf.put_u8(HOST_STOP_ID),
+            ClientHostMsg::Stopped(results) => {
+                buf.put_u8(HOST_STOPPED_ID);
+                buf.put_u32(results.len() as u32);
+                for (id, res) in results {
+                    buf.put_u64(*id);
+                    Self::serialise_results(res, buf);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Deserialiser<ClientHostMsg> for ClientHostSer {
+    const SER_ID: u64 = serialiser_ids::CLIENT_HOST_ID;
+
+    fn deserialise(buf: &mut dyn Buf) -> Result<ClientHostMsg, SerError> {
+        match get_u8(buf)? {
+            HOST_PREPARE_ID => Ok(ClientHostMsg::Prepare(Self::deserialise_workload(buf)?)),
+            HOST_PREPARED_ID => {
+                let clients = (0..get_len(buf, 8)?)
+                    .map(|_| Ok((get_u64(buf)?, get_actor_path(buf)?)))
+                    .collect::<Result<_, SerError>>()?;
+                Ok(ClientHostMsg::Prepared(clients))
+            }
+            HOST_LEADERS_KNOWN_ID => Ok(ClientHostMsg::LeadersKnown),
+            HOST_RUN_ID => {
+                let since_epoch = Duration::from_nanos(get_u64(buf)?);
+                Ok(ClientHostMsg::Run(UNIX_EPOCH + since_epoch))
+            }
+            HOST_FINISHED_ID => Ok(ClientHostMsg::Finished),
+            HOST_STOP_ID => Ok(ClientHostMsg::Stop),
+            HOST_STOPPED_ID => {
+                // the smallest results are 27 bytes with the id, but without any latencies, series, clients or log
+                let results = (0..get_len(buf, 27)?)
+                    .map(|_| Ok((get_u64(buf)?, Self::deserialise_results(buf)?)))
+                    .collect::<Result<_, SerError>>()?;
+                Ok(ClientHostMsg::Stopped(results))
+            }
+            _ => Err(SerError::InvalidType(
+                "Found unkown id when deserialising ClientHostMsg".into(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::paxos::ballot_leader_election::*;
+    use super::paxos::*;
+    // the paxos message rather than the protobuf trait
+    use super::paxos::Message;
+    use super::raft::{RaftMsg, RawRaftSer};
+    use super::*;
+    use crate::bench::atomic_broadcast::paxos::raw_paxos::{Entry, StopSign};
+    use crate::ser_utils::testing::*;
+    use ::raft::prelude::{Entry as TikvEntry, Message as TikvRaftMsg, MessageType};
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+    use std::str::FromStr;
+
+    fn maybe<T>(rng: &mut SmallRng, f: impl FnOnce(&mut SmallRng) -> T) -> Option<T> {
+        if rng.gen() {
+            Some(f(rng))
+        } else {
+            None
+        }
+    }
+
+    fn pids(rng: &mut SmallRng) -> Vec<u64> {
+        (0..rng.gen_range(0, 6)).map(|_| rng.gen()).collect()
+    }
+
+    fn data(rng: &mut SmallRng) -> Vec<u8> {
+        (0..rng.gen_range(0, 32)).map(|_| rng.gen()).collect()
+    }
+
+    fn ballot(rng: &mut SmallRng) -> Ballot {
+        Ballot::with(rng.gen(), rng.gen())
+    }
+
+    fn entries(rng: &mut SmallRng) -> Vec<Entry> {
+        (0..rng.gen_range(0, 5))
+            .map(|_| {
+                if rng.gen_bool(0.8) {
+                    Entry::Normal(data(rng))
+                } else {
+                    let skip_prepare_n = maybe(rng, ballot);
+                    Entry::StopSign(StopSign::with(rng.gen(), pids(rng), skip_prepare_n))
+                }
+            })
+            .collect()
+    }
+
+    fn segment(rng: &mut SmallRng) -> SequenceSegment {
+        SequenceSegment::with(rng.gen(), rng.gen(), entries(rng))
+    }
+
+    fn paxos_msg(rng: &mut SmallRng) -> Message {
+        let msg = match rng.gen_range(0, 11) {
+            0 => PaxosMsg::Prepare(Prepare::with(ballot(rng), rng.gen(), ballot(rng))),
+            1 => PaxosMsg::Promise(Promise::with(
+                ballot(rng),
+                ballot(rng),
+                entries(rng),
+                rng.gen(),
+            )),
+            2 => PaxosMsg::AcceptSync(AcceptSync::with(
+                ballot(rng),
+                entries(rng),
+                rng.gen(),
+                rng.gen(),
+            )),
+            3 => PaxosMsg::FirstAcceptReq,
+            4 => PaxosMsg::FirstAccept(FirstAccept::with(ballot(rng), entries(rng))),
+            5 => PaxosMsg::AcceptDecide(AcceptDecide::with(ballot(rng), rng.gen(), entries(rng))),
+            6 => PaxosMsg::Accepted(Accepted::with(ballot(rng), rng.gen(), rng.gen())),
+            7 => PaxosMsg::Decide(Decide::with(rng.gen(), ballot(rng))),
+            8 => PaxosMsg::ProposalForward(entries(rng)),
+            9 => PaxosMsg::Compaction(rng.gen()),
+            _ => PaxosMsg::PrepareReq,
+        };
+        Message::with(rng.gen(), rng.gen(), msg)
+    }
+
+    fn reconfig_msg(rng: &mut SmallRng) -> ReconfigurationMsg {
+        match rng.gen_range(0, 3) {
+            0 => ReconfigurationMsg::Init(ReconfigInit::with(
+                rng.gen(),
+                Reconfig::with(pids(rng), pids(rng)),
+                SequenceMetaData::with(rng.gen(), rng.gen()),
+                rng.gen(),
+                maybe(rng, segment),
+                maybe(rng, ballot),
+            )),
+            1 => ReconfigurationMsg::SequenceRequest(SequenceRequest::with(
+                rng.gen(),
+                rng.gen(),
+                rng.gen(),
+                rng.gen(),
+                rng.gen(),
+            )),
+            _ => ReconfigurationMsg::SequenceTransfer(SequenceTransfer::with(
+                rng.gen(),
+                rng.gen(),
+                rng.gen(),
+                SequenceMetaData::with(rng.gen(), rng.gen()),
+                segment(rng),
+            )),
+        }
+    }
+
+    fn heartbeat_msg(rng: &mut SmallRng) -> HeartbeatMsg {
+        if rng.gen() {
+            HeartbeatMsg::Request(HeartbeatRequest::with(rng.gen(), rng.gen(), ballot(rng)))
+        } else {
+            HeartbeatMsg::Reply(HeartbeatReply::with(rng.gen(), rng.gen(), ballot(rng)))
+        }
+    }
+
+    fn atomic_broadcast_msg(rng: &mut SmallRng) -> AtomicBroadcastMsg {
+        match rng.gen_range(0, 4) {
+            0 if rng.gen() => AtomicBroadcastMsg::Proposal(Proposal::normal(data(rng))),
+            0 => {
+                // a reconfiguration without any voters is read back as a normal proposal
+                let mut voters = pids(rng);
+                voters.push(rng.gen());
+                let followers = pids(rng);
+                AtomicBroadcastMsg::Proposal(Proposal::reconfiguration(
+                    data(rng),
+                    (voters, followers),
                 ))
             }
+            1 => AtomicBroadcastMsg::ProposalResp(ProposalResp::with(data(rng), rng.gen())),
+            2 => AtomicBroadcastMsg::FirstLeader(rng.gen()),
+            _ => AtomicBroadcastMsg::PendingReconfiguration(data(rng)),
+        }
+    }
+
+    fn fault_msg(rng: &mut SmallRng) -> FaultMsg {
+        match rng.gen_range(0, 4) {
+            0 => FaultMsg::Crash(maybe(rng, |rng| {
+                Duration::from_millis(rng.gen_range(0, 1 << 40))
+            })),
+            1 => {
+                let effect = match rng.gen_range(0, 4) {
+                    0 => LinkEffect::Loss(rng.gen_range(0, 101)),
+                    1 => LinkEffect::Delay(Duration::from_millis(rng.gen_range(0, 10_000))),
+                    2 => LinkEffect::Duplicate(rng.gen_range(0, 101)),
+                    _ => LinkEffect::Reorder(rng.gen_range(0, 101)),
+                };
+                FaultMsg::Link(LinkFault {
+                    id: rng.gen(),
+                    to: pids(rng),
+                    effect,
+                })
+            }
+            2 => FaultMsg::Heal(rng.gen()),
+            _ => FaultMsg::TransferLeadership,
+        }
+    }
+
+    fn test_msg(rng: &mut SmallRng) -> TestMessage {
+        match rng.gen_range(0, 10) {
+            0 => return TestMessage::SequenceReq,
+            1 => return TestMessage::IoStatsReq,
+            2 => {
+                let io_stats = IoStats {
+                    bytes_written: rng.gen(),
+                    fsyncs: rng.gen(),
+                };
+                return TestMessage::IoStatsResp(rng.gen(), io_stats);
+            }
+            _ => {}
+        }
+        let entries = (0..rng.gen_range(0, 100))
+            .map(|_| {
+                if rng.gen_bool(0.9) {
+                    DecidedEntry::Normal(rng.gen())
+                } else {
+                    DecidedEntry::StopSign(rng.gen())
+                }
+            })
+            .collect();
+        let sequence = DecidedSequence {
+            compacted_idx: rng.gen(),
+            entries,
+        };
+        TestMessage::SequenceResp(SequenceResp::with(rng.gen(), sequence))
+    }
+
+    fn raft_msg(rng: &mut SmallRng) -> TikvRaftMsg {
+        let mut msg = TikvRaftMsg::new();
+        msg.set_msg_type(MessageType::MsgAppend);
+        msg.set_from(rng.gen());
+        msg.set_to(rng.gen());
+        msg.set_term(rng.gen());
+        msg.set_index(rng.gen());
+        msg.set_commit(rng.gen());
+        for _ in 0..rng.gen_range(0, 5) {
+            let mut e = TikvEntry::new();
+            e.set_term(rng.gen());
+            e.set_index(rng.gen());
+            e.set_data(data(rng));
+            msg.mut_entries().push(e);
+        }
+        msg
+    }
+
+    fn actor_path(rng: &mut SmallRng) -> ActorPath {
+        let path = format!(
+            "tcp://127.0.0.1:{}/client{}",
+            rng.gen::<u16>(),
+            rng.gen::<u32>()
+        );
+        ActorPath::from_str(&path).expect("Invalid path")
+    }
+
+    fn ids(rng: &mut SmallRng) -> Vec<u64> {
+        (0..rng.gen_range(0, 20)).map(|_| rng.gen()).collect()
+    }
+
+    fn remote_workload(rng: &mut SmallRng) -> RemoteWorkload {
+        let payload_size = match rng.gen_range(0, 3) {
+            0 => PayloadSize::Fixed(rng.gen_range(0, 1 << 20)),
+            1 => PayloadSize::Uniform(rng.gen_range(0, 1 << 10), rng.gen_range(1 << 10, 1 << 20)),
+            _ => PayloadSize::Exponential(rng.gen_range(0, 1 << 20)),
+        };
+        RemoteWorkload {
+            iteration_id: rng.gen(),
+            clients: (0..rng.gen_range(0, 5))
+                .map(|_| (rng.gen(), rng.gen(), rng.gen()))
+                .collect(),
+            concurrent_proposals: rng.gen(),
+            num_initial_nodes: rng.gen(),
+            nodes: (0..rng.gen_range(0, 5))
+                .map(|_| (rng.gen(), actor_path(rng)))
+                .collect(),
+            timeout: Duration::from_millis(rng.gen_range(0, 1 << 40)),
+            check_total_order: rng.gen(),
+            arrivals: if rng.gen() {
+                Arrivals::Constant
+            } else {
+                Arrivals::Poisson
+            },
+            payload_size,
+            throughput_window: maybe(rng, |rng| Duration::from_nanos(rng.gen())),
+        }
+    }
+
+    fn client_results(rng: &mut SmallRng) -> MetaResults {
+        let open_loop = maybe(rng, |rng| {
+            // latencies below 2^15 are recorded exactly, so the histogram reads back the same
+            let mut latency_hist = Histogram::new(4).unwrap();
+            for _ in 0..rng.gen_range(0, 20) {
+                latency_hist
+                    .record_n(rng.gen_range(0, 1 << 15), rng.gen_range(1, 100))
+                    .unwrap();
+            }
+            OpenLoopResults {
+                target_rate: rng.gen(),
+                offered_rate: rng.gen(),
+                achieved_rate: rng.gen(),
+                decided: rng.gen(),
+                latency_hist,
+            }
+        });
+        let clients = (0..rng.gen_range(0, 3))
+            .map(|_| ClientStats {
+                decided: rng.gen(),
+                throughput: rng.gen(),
+                decided_bytes: rng.gen(),
+                byte_throughput: rng.gen(),
+            })
+            .collect();
+        MetaResults {
+            num_timed_out: rng.gen(),
+            latencies: (0..rng.gen_range(0, 20))
+                .map(|_| (rng.gen(), Duration::from_nanos(rng.gen())))
+                .collect(),
+            leader_changes: vec![],
+            reconfigurations: vec![],
+            throughput: maybe(rng, |rng| ThroughputSeries {
+                window: Duration::from_nanos(rng.gen()),
+                decided: ids(rng),
+                events: vec![],
+            }),
+            total_order: None,
+            proposal_log: maybe(rng, |rng| ProposalLog {
+                proposed: ids(rng),
+                acked: ids(rng),
+                reconfigs: (0..rng.gen_range(0, 3))
+                    .map(|_| (rng.gen::<u32>() as usize, rng.gen()))
+                    .collect(),
+                pending_at_crash: ids(rng),
+            }),
+            decided_sequences: std::collections::HashMap::new(),
+            faults: vec![],
+            network_faults: vec![],
+            leader_transfers: vec![],
+            open_loop,
+            clients,
+            kv: None,
+            io_stats: vec![],
+        }
+    }
+
+    fn client_host_msg(rng: &mut SmallRng) -> ClientHostMsg {
+        match rng.gen_range(0, 7) {
+            0 => ClientHostMsg::Prepare(remote_workload(rng)),
+            1 => ClientHostMsg::Prepared(
+                (0..rng.gen_range(0, 5))
+                    .map(|_| (rng.gen(), actor_path(rng)))
+                    .collect(),
+            ),
+            2 => ClientHostMsg::LeadersKnown,
+            3 => ClientHostMsg::Run(UNIX_EPOCH + Duration::from_nanos(rng.gen())),
+            4 => ClientHostMsg::Finished,
+            5 => ClientHostMsg::Stop,
+            _ => ClientHostMsg::Stopped(
+                (0..rng.gen_range(0, 3))
+                    .map(|_| (rng.gen(), client_results(rng)))
+                    .collect(),
+            ),
+        }
+    }
+
+    #[test]
+    fn paxos_ser_test() {
+        check_serialisable::<Message, PaxosSer>(1, paxos_msg);
+    }
+
+    #[test]
+    fn reconfig_ser_test() {
+        check_serialisable::<ReconfigurationMsg, ReconfigSer>(2, reconfig_msg);
+    }
+
+    #[test]
+    fn ballot_leader_ser_test() {
+        check_serialisable::<HeartbeatMsg, BallotLeaderSer>(3, heartbeat_msg);
+    }
+
+    #[test]
+    fn atomic_broadcast_ser_test() {
+        check_serialisable::<AtomicBroadcastMsg, AtomicBroadcastDeser>(4, atomic_broadcast_msg);
+    }
+
+    #[test]
+    fn stop_msg_ser_test() {
+        check_serialisable::<StopMsg, StopMsgDeser>(5, |rng| {
+            if rng.gen() {
+                StopMsg::Peer(rng.gen())
+            } else {
+                StopMsg::Client
+            }
+        });
+    }
+
+    #[test]
+    fn fault_msg_ser_test() {
+        check_serialisable::<FaultMsg, FaultMsgDeser>(6, fault_msg);
+    }
+
+    #[test]
+    fn test_message_ser_test() {
+        check_serialiser(7, &TestMessageSer, test_msg);
+    }
+
+    #[test]
+    fn client_host_ser_test() {
+        check_serialiser(10, &ClientHostSer, client_host_msg);
+    }
+
+    #[test]
+    fn raft_ser_test() {
+        // the size hint is only an estimate of the protobuf encoding, and a prefix may be a valid message
+        let mut rng = SmallRng::seed_from_u64(8);
+        let valid: Vec<Vec<u8>> = (0..CASES)
+            .map(|_| {
+                let msg = RaftMsg(raft_msg(&mut rng));
+                let mut bytes = Vec::new();
+                msg.serialise(&mut bytes).expect("Should serialise");
+                let deser = RawRaftSer::deserialise(&mut bytes.as_slice())
+                    .expect("Failed to deserialise RaftMsg");
+                assert_eq!(msg.0, deser);
+                bytes
+            })
+            .collect();
+        fuzz::<TikvRaftMsg, RawRaftSer>(&mut rng, &valid);
+    }
+
+    #[test]
+    fn clients_deserialise_test() {
+        let mut rng = SmallRng::seed_from_u64(9);
+        for _ in 0..CASES {
+            let len = rng.gen_range(0, 64);
+            let input: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            let _ = Clients::deserialise(&input);
         }
     }
 }
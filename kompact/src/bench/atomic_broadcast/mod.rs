@@ -1,7 +1,16 @@
 pub mod atomic_broadcast;
 mod client;
 mod communicator;
+mod fault;
+mod handover;
+mod kv;
 mod messages;
 mod paxos;
 mod raft;
+mod reconfig;
+mod remote_client;
+pub mod replicated_kv;
+mod results;
+#[cfg(test)]
+mod simulation;
 mod storage;
@@ -2,6 +2,8 @@ use super::atomic_broadcast::ExperimentConfig;
 use super::communicator::{
     AtomicBroadcastCompMsg, CommunicationPort, Communicator, CommunicatorMsg,
 };
+use super::fault::{LinkFault, LinkFaultMsg};
+use super::kv::KvStore;
 use super::messages::paxos::ballot_leader_election::{Ballot, Leader};
 use super::messages::paxos::{
     Reconfig, ReconfigInit, ReconfigSer, ReconfigurationMsg, SequenceMetaData, SequenceRequest,
@@ -9,17 +11,26 @@ use super::messages::paxos::{
 };
 use super::messages::{StopMsg as NetStopMsg, *};
 use super::storage::paxos::*;
+use super::storage::{CompactionPolicy, DiskConfig, IoStats};
 use crate::bench::atomic_broadcast::atomic_broadcast::Done;
 use crate::bench::atomic_broadcast::paxos::raw_paxos::StopSign;
 use crate::partitioning_actor::{Init, PartitioningActorMsg, PartitioningActorSer};
 use crate::serialiser_ids::ATOMICBCAST_ID;
-use ballot_leader_election::{BallotLeaderComp, BallotLeaderElection, Stop as BLEStop};
+use ballot_leader_election::{BallotLeaderComp, BallotLeaderElection, BallotLeaderMsg};
+use benchmark_suite_shared::test_utils::{DecidedEntry, DecidedSequence};
 use hashbrown::{HashMap, HashSet};
 use kompact::prelude::*;
 use kompact::KompactLogger;
 use rand::Rng;
 use raw_paxos::{Entry, Paxos};
-use std::{borrow::Borrow, fmt::Debug, ops::DerefMut, sync::Arc, time::Duration};
+use std::{
+    borrow::Borrow,
+    fmt::{self, Debug},
+    fs::remove_dir_all,
+    ops::DerefMut,
+    sync::Arc,
+    time::Duration,
+};
 
 const BLE: &str = "ble";
 const COMMUNICATOR: &str = "communicator";
@@ -61,8 +72,8 @@ where
 pub enum PaxosReplicaMsg {
     Propose(Proposal),
     LocalSequenceReq(ActorPath, SequenceRequest, SequenceMetaData),
-    GetAllEntries(Ask<(), Vec<Entry>>),
-    Stop(Ask<(bool, bool), ()>), // (ack_client, late)
+    GetDecidedSequence(Ask<(), (u64, Vec<Entry>)>), // (compacted_idx, decided entries after it)
+    Stop(Ask<(bool, bool), ()>),                    // (ack_client, late)
 }
 
 #[derive(Clone, Debug)]
@@ -71,6 +82,207 @@ pub enum ReconfigurationPolicy {
     Pull,
 }
 
+/// Optimisations of the Paxos replicas that can be switched on and off per experiment.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PaxosOptions {
+    pub batch_accept: bool, // append new entries to a pending AcceptDecide instead of sending a new one
+    pub latest_decide: bool, // update the decided index of a pending Decide or AcceptDecide
+    pub latest_accepted: bool, // update the accepted index of a pending Accepted
+    pub max_accsync: bool, // only send the new entries to followers that promised with the max suffix
+    pub headstart_ble: bool, // elect the first leader as soon as all peers replied instead of at the timeout
+    pub quorums: Quorums,
+}
+
+impl PaxosOptions {
+    const NAMES: [&'static str; 5] = [
+        "batch_accept",
+        "latest_decide",
+        "latest_accepted",
+        "max_accsync",
+        "headstart_ble",
+    ];
+
+    pub fn none() -> PaxosOptions {
+        PaxosOptions {
+            batch_accept: false,
+            latest_decide: false,
+            latest_accepted: false,
+            max_accsync: false,
+            headstart_ble: false,
+            quorums: Quorums::Majority,
+        }
+    }
+
+    fn flag_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "batch_accept" => Some(&mut self.batch_accept),
+            "latest_decide" => Some(&mut self.latest_decide),
+            "latest_accepted" => Some(&mut self.latest_accepted),
+            "max_accsync" => Some(&mut self.max_accsync),
+            "headstart_ble" => Some(&mut self.headstart_ble),
+            _ => None,
+        }
+    }
+
+    /// Parses `none` or the enabled options separated by `+`, e.g. `batch_accept+max_accsync`. Flexible quorums are
+    /// given by both `phase1_quorum=<size>` and `phase2_quorum=<size>` and must intersect for `num_nodes` replicas.
+    pub fn parse(s: &str, num_nodes: u64) -> Result<PaxosOptions, String> {
+        let mut options = PaxosOptions::none();
+        let s = s.trim().to_lowercase();
+        if s == "none" {
+            return Ok(options);
+        }
+        let (mut phase1, mut phase2) = (None, None);
+        for name in s.split('+') {
+            let name = name.trim();
+            let mut kv = name.splitn(2, '=');
+            let key = kv.next().unwrap_or_default();
+            if let Some(value) = kv.next() {
+                let size = value
+                    .parse::<usize>()
+                    .map_err(|e| format!("Invalid quorum size in '{}': {}", name, e))?;
+                match key {
+                    "phase1_quorum" => phase1 = Some(size),
+                    "phase2_quorum" => phase2 = Some(size),
+                    _ => return Err(format!("Unknown Paxos option: {}", name)),
+                }
+                continue;
+            }
+            let flag = options
+                .flag_mut(name)
+                .ok_or_else(|| format!("Unknown Paxos option: {}", name))?;
+            *flag = true;
+        }
+        options.quorums = match (phase1, phase2) {
+            (Some(phase1), Some(phase2)) => {
+                let quorums = Quorums::Flexible { phase1, phase2 };
+                quorums.validate(num_nodes as usize)?;
+                quorums
+            }
+            (None, None) => Quorums::Majority,
+            _ => {
+                return Err(format!(
+                    "Flexible quorums need both phase1_quorum and phase2_quorum: {}",
+                    s
+                ))
+            }
+        };
+        Ok(options)
+    }
+
+    fn enabled(&self) -> Vec<&'static str> {
+        let flags = [
+            self.batch_accept,
+            self.latest_decide,
+            self.latest_accepted,
+            self.max_accsync,
+            self.headstart_ble,
+        ];
+        Self::NAMES
+            .iter()
+            .zip(flags.iter())
+            .filter(|(_, enabled)| **enabled)
+            .map(|(name, _)| *name)
+            .collect()
+    }
+}
+
+impl Default for PaxosOptions {
+    /// The optimisations that were enabled by the default cargo features.
+    fn default() -> PaxosOptions {
+        PaxosOptions {
+            batch_accept: true,
+            latest_decide: true,
+            latest_accepted: true,
+            max_accsync: true,
+            headstart_ble: false,
+            quorums: Quorums::Majority,
+        }
+    }
+}
+
+impl fmt::Display for PaxosOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut enabled: Vec<String> = self.enabled().iter().map(|s| s.to_string()).collect();
+        if let Quorums::Flexible { phase1, phase2 } = self.quorums {
+            enabled.push(format!("phase1_quorum={}", phase1));
+            enabled.push(format!("phase2_quorum={}", phase2));
+        }
+        if enabled.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", enabled.join("+"))
+        }
+    }
+}
+
+/// The number of replicas of a configuration that form a quorum in each phase.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Quorums {
+    Majority,
+    /// Flexible Paxos: a leader needs the promises of `phase1` replicas and an entry is chosen when `phase2` replicas
+    /// accepted it. This is safe as long as every phase 1 quorum intersects every phase 2 quorum, i.e.
+    /// `phase1 + phase2 > n`.
+    Flexible {
+        phase1: usize,
+        phase2: usize,
+    },
+}
+
+impl Quorums {
+    /// The quorums requested for `num_nodes` replicas. A size of 0 is the smallest one that intersects the other, so
+    /// if both are 0 the replicas use majorities.
+    pub fn with(phase1: u64, phase2: u64, num_nodes: u64) -> Result<Quorums, String> {
+        let smallest_intersecting = |q: u64| num_nodes.saturating_sub(q) + 1;
+        let (phase1, phase2) = match (phase1, phase2) {
+            (0, 0) => return Ok(Quorums::Majority),
+            (0, q2) => (smallest_intersecting(q2), q2),
+            (q1, 0) => (q1, smallest_intersecting(q1)),
+            (q1, q2) => (q1, q2),
+        };
+        let quorums = Quorums::Flexible {
+            phase1: phase1 as usize,
+            phase2: phase2 as usize,
+        };
+        quorums.validate(num_nodes as usize)?;
+        Ok(quorums)
+    }
+
+    pub fn validate(&self, num_nodes: usize) -> Result<(), String> {
+        if let Quorums::Flexible { phase1, phase2 } = *self {
+            if phase1 == 0 || phase1 > num_nodes || phase2 == 0 || phase2 > num_nodes {
+                return Err(format!(
+                    "Quorum sizes must be between 1 and the {} nodes: phase1_quorum={}, phase2_quorum={}",
+                    num_nodes, phase1, phase2
+                ));
+            }
+            if phase1 + phase2 <= num_nodes {
+                return Err(format!(
+                    "The quorums of {} nodes do not intersect: phase1_quorum={} + phase2_quorum={} <= {}",
+                    num_nodes, phase1, phase2, num_nodes
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The promises a leader needs to start accepting, which is also the number of heartbeats that elect it.
+    pub fn phase1(&self, num_nodes: usize) -> usize {
+        match self {
+            Quorums::Majority => num_nodes / 2 + 1,
+            Quorums::Flexible { phase1, .. } => *phase1,
+        }
+    }
+
+    /// The replicas that must have accepted an entry for it to be chosen.
+    pub fn phase2(&self, num_nodes: usize) -> usize {
+        match self {
+            Quorums::Majority => num_nodes / 2 + 1,
+            Quorums::Flexible { phase2, .. } => *phase2,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 struct ConfigMeta {
     id: u32,
@@ -108,13 +320,22 @@ where
     partitioning_actor: Option<ActorPath>,
     policy: ReconfigurationPolicy,
     next_config_id: Option<u32>,
-    pending_seq_transfers: Vec<(Vec<u32>, Vec<Entry>)>, // (remaining_segments, entries)
+    pending_seq_transfers: Vec<(Vec<u32>, Vec<Entry>, u64)>, // (remaining_segments, entries, compacted_idx)
     complete_sequences: Vec<u32>,
     active_peers: (Vec<u64>, Vec<u64>), // (ready, not_ready)
     retry_transfer_timers: HashMap<u32, ScheduledTimer>,
-    cached_client: Option<ActorPath>,
+    cached_clients: Option<Clients>,
     hb_proposals: Vec<NetMessage>,
     experiment_config: ExperimentConfig,
+    disk_config: Option<DiskConfig>,
+    compaction: CompactionPolicy,
+    kv: bool, // apply the decided commands to a key-value store
+    options: PaxosOptions,
+    initial_leader: u64, // the node that should win the first election, 0 if any
+    crashed: Option<(u32, Vec<u64>)>, // (config_id, nodes) of the replica killed by an injected fault
+    crashed_io_stats: IoStats, // I/O of the replicas killed by injected faults in this iteration
+    recovery_timer: Option<ScheduledTimer>,
+    link_faults: Vec<LinkFault>, // injected network faults, also applied to replicas created later
 }
 
 impl<S, P> PaxosComp<S, P>
@@ -126,6 +347,11 @@ where
         initial_config: Vec<u64>,
         policy: ReconfigurationPolicy,
         experiment_config: ExperimentConfig,
+        disk_config: Option<DiskConfig>,
+        compaction: CompactionPolicy,
+        kv: bool,
+        options: PaxosOptions,
+        initial_leader: u64,
     ) -> PaxosComp<S, P> {
         PaxosComp {
             ctx: ComponentContext::uninitialised(),
@@ -146,20 +372,42 @@ where
             complete_sequences: vec![],
             active_peers: (vec![], vec![]),
             retry_transfer_timers: HashMap::new(),
-            cached_client: None,
+            cached_clients: None,
             hb_proposals: vec![],
             experiment_config,
+            disk_config,
+            compaction,
+            kv,
+            options,
+            initial_leader,
+            crashed: None,
+            crashed_io_stats: IoStats::default(),
+            recovery_timer: None,
+            link_faults: vec![],
         }
     }
 
+    /// Where the replica of configuration `config_id` is persisted in this iteration, if disk storage is used.
+    fn replica_disk_config(&self, config_id: u32) -> Option<DiskConfig> {
+        self.node_disk_config()
+            .map(|c| c.sub_dir(&format!("{}-{}", self.iteration_id, config_id)))
+    }
+
+    /// Where this node persists its replicas, if it uses disk storage.
+    fn node_disk_config(&self) -> Option<DiskConfig> {
+        self.disk_config
+            .as_ref()
+            .map(|c| c.sub_dir(&format!("paxos{}", self.pid)))
+    }
+
     fn derive_actorpaths(
         &self,
         config_id: u32,
         peers: &Vec<u64>,
-    ) -> (Vec<ActorPath>, HashMap<u64, ActorPath>) {
+    ) -> (HashMap<u64, ActorPath>, HashMap<u64, ActorPath>) {
         let num_peers = peers.len();
         let mut communicator_peers = HashMap::with_capacity(num_peers);
-        let mut ble_peers = Vec::with_capacity(num_peers);
+        let mut ble_peers = HashMap::with_capacity(num_peers);
         for pid in peers {
             let idx = *pid as usize - 1;
             let actorpath = self.nodes.get(idx).expect("No actorpath found");
@@ -189,7 +437,7 @@ where
                         )],
                     );
                     communicator_peers.insert(*pid, ActorPath::Named(named_communicator));
-                    ble_peers.push(ActorPath::Named(named_ble));
+                    ble_peers.insert(*pid, ActorPath::Named(named_ble));
                 }
                 _ => error!(
                     self.ctx.log(),
@@ -209,6 +457,7 @@ where
         start: bool,
         ble_quick_start: bool,
         skip_prepare_n: Option<Ballot>,
+        recovered: Option<Storage<S, P>>, // of a crashed replica that restarts
     ) -> Handled {
         let mut peers = nodes;
         peers.retain(|pid| pid != &self.pid);
@@ -217,6 +466,23 @@ where
         /*** create and register Paxos ***/
         let log: KompactLogger = self.ctx.log().new(o!("raw_paxos" => self.pid));
         let max_inflight = self.experiment_config.max_inflight;
+        let recover = recovered.is_some();
+        let storage = match recovered {
+            Some(storage) => storage,
+            None => {
+                let (seq, paxos_state) = match self.replica_disk_config(config_id) {
+                    Some(c) => (S::open(&c), P::open(&c)),
+                    None => (
+                        S::new_with_sequence(Vec::with_capacity(max_inflight)),
+                        P::new(),
+                    ),
+                };
+                Storage::with(seq, paxos_state)
+            }
+        };
+        let compaction = self.compaction;
+        let kv = self.kv;
+        let options = self.options;
         let (paxos, paxos_f) = system.create_and_register(|| {
             PaxosReplica::with(
                 self.ctx.actor_ref(),
@@ -225,35 +491,50 @@ where
                 self.pid,
                 log,
                 skip_prepare_n,
+                storage,
                 max_inflight,
+                compaction,
+                recover,
+                kv,
+                options,
             )
         });
         /*** create and register Communicator ***/
         let (communicator, comm_f) = system.create_and_register(|| {
             Communicator::with(
                 communicator_peers,
-                self.cached_client
+                self.cached_clients
                     .as_ref()
-                    .expect("No cached client!")
+                    .expect("No cached clients!")
                     .clone(),
+                true,
             )
         });
         /*** create and register BLE ***/
         let election_timeout = self.experiment_config.election_timeout;
-        let config = self.ctx.config();
-        let ble_delta = config["paxos"]["ble_delta"]
-            .as_i64()
-            .expect("Failed to load get_decided_period");
+        let ble_delta = self.experiment_config.ble_delta;
         let initial_election_factor = self.experiment_config.initial_election_factor;
+        let ble_tick_period = self.experiment_config.ble_tick_period;
+        let ble_quorum = options.quorums.phase1(ble_peers.len() + 1);
+        let initial_max_ballot = match skip_prepare_n {
+            // the preferred leader starts the first configuration with a higher ballot than its peers
+            None if config_id == 1 && !recover && self.initial_leader != 0 => {
+                Some(Ballot::with(1, self.initial_leader))
+            }
+            n => n,
+        };
         let (ble_comp, ble_f) = system.create_and_register(|| {
             BallotLeaderComp::with(
                 ble_peers,
                 self.pid,
+                ble_quorum,
                 election_timeout as u64,
-                ble_delta as u64,
+                ble_delta,
                 ble_quick_start,
-                skip_prepare_n.clone(),
+                initial_max_ballot,
                 initial_election_factor,
+                options.headstart_ble,
+                ble_tick_period,
             )
         });
         let communicator_alias = format!(
@@ -261,8 +542,28 @@ where
             COMMUNICATOR, self.pid, config_id, self.iteration_id
         );
         let ble_alias = format!("{}{},{}-{}", BLE, self.pid, config_id, self.iteration_id);
-        let comm_alias_f = system.register_by_alias(&communicator, communicator_alias);
-        let ble_alias_f = system.register_by_alias(&ble_comp, ble_alias);
+        let (comm_alias_f, ble_alias_f) = if recover {
+            // the aliases are still registered to the crashed components
+            (
+                system.update_alias_registration(&communicator, communicator_alias),
+                system.update_alias_registration(&ble_comp, ble_alias),
+            )
+        } else {
+            (
+                system.register_by_alias(&communicator, communicator_alias),
+                system.register_by_alias(&ble_comp, ble_alias),
+            )
+        };
+        for fault in &self.link_faults {
+            communicator
+                .actor_ref()
+                .tell(LinkFaultMsg::Apply(fault.clone()));
+            ble_comp
+                .actor_ref()
+                .tell(BallotLeaderMsg::LinkFault(LinkFaultMsg::Apply(
+                    fault.clone(),
+                )));
+        }
         /*** connect components ***/
         biconnect_components::<CommunicationPort, _, _>(&communicator, &paxos)
             .expect("Could not connect Communicator and PaxosComp!");
@@ -348,8 +649,147 @@ where
         }
     }
 
+    /// Kills the active replica to inject a crash. If `downtime` is given, it is restarted from its storage after it.
+    fn crash(&mut self, downtime: Option<Duration>) -> Handled {
+        if self.stopped || self.crashed.is_some() || self.active_config.id == 0 {
+            return Handled::Ok;
+        }
+        let config_id = self.active_config.id;
+        let paxos = self
+            .paxos_replicas
+            .pop()
+            .expect("No paxos replica to crash");
+        let ble = self.ble_comps.pop().expect("No ble to crash");
+        let communicator = self
+            .communicator_comps
+            .pop()
+            .expect("No communicator to crash");
+        let (mut nodes, io_stats) = paxos.on_definition(|p| (p.peers.clone(), p.paxos.io_stats()));
+        nodes.push(self.pid);
+        self.crashed_io_stats += io_stats;
+        info!(
+            self.ctx.log(),
+            "Crashing replica pid: {}, config_id: {}, downtime: {:?}",
+            self.pid,
+            config_id,
+            downtime
+        );
+        self.crashed = Some((config_id, nodes));
+        self.active_config.leader = 0;
+        self.hb_proposals.clear();
+        let system = self.ctx.system();
+        let kill_futures = vec![
+            system.kill_notify(ble),
+            system.kill_notify(paxos),
+            system.kill_notify(communicator),
+        ];
+        Handled::block_on(self, move |mut async_self| async move {
+            for f in kill_futures {
+                f.await.expect("Failed to kill crashed components");
+            }
+            if let Some(downtime) = downtime {
+                let timer = async_self.schedule_once(downtime, move |c, _| c.recover());
+                async_self.recovery_timer = Some(timer);
+            }
+        })
+    }
+
+    fn recover(&mut self) -> Handled {
+        self.recovery_timer = None;
+        match self.crashed.take() {
+            Some((config_id, nodes)) if !self.stopped => {
+                info!(
+                    self.ctx.log(),
+                    "Recovering replica pid: {}, config_id: {}", self.pid, config_id
+                );
+                let recovered = self
+                    .replica_disk_config(config_id)
+                    .ok_or_else(|| "Replica has no disk storage".to_string())
+                    .and_then(|c| Ok(Storage::with(S::recover(&c)?, P::recover(&c)?)));
+                match recovered {
+                    Ok(storage) => self.create_replica(
+                        config_id,
+                        nodes,
+                        false,
+                        true,
+                        false,
+                        None,
+                        Some(storage),
+                    ),
+                    Err(e) => {
+                        error!(
+                            self.ctx.log(),
+                            "Replica pid: {} can not recover and stays crashed: {}", self.pid, e
+                        );
+                        self.crashed = Some((config_id, nodes));
+                        Handled::Ok
+                    }
+                }
+            }
+            _ => Handled::Ok,
+        }
+    }
+
+    /// Stops on behalf of a crashed replica, so that the peers and the client do not wait for it.
+    fn stop_crashed(&mut self, config_id: u32, nodes: Vec<u64>) -> Handled {
+        if let Some(timer) = self.recovery_timer.take() {
+            self.cancel_timer(timer);
+        }
+        let mut peers = nodes;
+        peers.retain(|pid| pid != &self.pid);
+        let (ble_peers, communicator_peers) = self.derive_actorpaths(config_id, &peers);
+        for (_, ble_peer) in ble_peers {
+            ble_peer
+                .tell_serialised(NetStopMsg::Peer(self.pid), self)
+                .expect("NetStopMsg should serialise!");
+        }
+        for (_, comm_peer) in communicator_peers {
+            comm_peer
+                .tell_serialised(NetStopMsg::Peer(self.pid), self)
+                .expect("NetStopMsg should serialise!");
+        }
+        self.cached_clients
+            .as_ref()
+            .expect("No cached clients!")
+            .primary()
+            .tell_serialised(NetStopMsg::Peer(self.pid), self)
+            .expect("NetStopMsg should serialise!");
+        Handled::Ok
+    }
+
+    /// Applies or heals an injected network fault on the links of all replicas.
+    fn handle_link_fault(&mut self, msg: LinkFaultMsg) {
+        match &msg {
+            LinkFaultMsg::Apply(fault) => self.link_faults.push(fault.clone()),
+            LinkFaultMsg::Heal(id) => self.link_faults.retain(|f| f.id != *id),
+        }
+        for communicator in &self.communicator_comps {
+            communicator.actor_ref().tell(msg.clone());
+        }
+        for ble in &self.ble_comps {
+            ble.actor_ref()
+                .tell(BallotLeaderMsg::LinkFault(msg.clone()));
+        }
+    }
+
+    /// Makes the ballot leader election of the active configuration elect this replica.
+    fn take_leadership(&mut self) {
+        if self.stopped || self.crashed.is_some() {
+            return;
+        }
+        info!(self.ctx.log(), "Taking over the leadership");
+        if let Some(ble) = self.ble_comps.last() {
+            ble.actor_ref().tell(BallotLeaderMsg::TakeLeadership);
+        }
+    }
+
     fn stop_components(&mut self) -> Handled {
         self.stopped = true;
+        if let Some((config_id, nodes)) = self.crashed.take() {
+            self.active_config.id = 0;
+            self.active_config.leader = 0;
+            return self.stop_crashed(config_id, nodes);
+        }
         let retry_timers = std::mem::take(&mut self.retry_transfer_timers);
         for (_, timer) in retry_timers {
             self.cancel_timer(timer);
@@ -373,13 +813,13 @@ where
         for ble in rest_ble {
             stop_futures.push(
                 ble.actor_ref()
-                    .ask(|p| BLEStop(Ask::new(p, (self.pid, false)))),
+                    .ask(|p| BallotLeaderMsg::Stop(Ask::new(p, (self.pid, false)))),
             );
         }
         stop_futures.push(
             ble_last
                 .actor_ref()
-                .ask(|p| BLEStop(Ask::new(p, (self.pid, late_stop)))),
+                .ask(|p| BallotLeaderMsg::Stop(Ask::new(p, (self.pid, late_stop)))),
         );
 
         let (paxos_last, rest) = self
@@ -425,29 +865,66 @@ where
             let comm_f = system.kill_notify(communicator);
             kill_futures.push(comm_f);
         }
+        let disk_config = self.node_disk_config();
         Handled::block_on(self, move |_| async move {
             for f in kill_futures {
                 f.await.expect("Failed to kill child components");
             }
+            if let Some(c) = disk_config {
+                if c.dir.exists() {
+                    remove_dir_all(&c.dir).expect("Failed to remove Paxos storage");
+                }
+            }
             ask.reply(Done).unwrap();
         })
     }
 
+    /// I/O done by the storage of all replicas of this node in the current iteration.
+    fn io_stats(&self) -> IoStats {
+        let mut io_stats = self.crashed_io_stats;
+        for paxos in &self.paxos_replicas {
+            io_stats += paxos.on_definition(|p| p.paxos.io_stats());
+        }
+        io_stats
+    }
+
+    fn to_decided_entry(entry: &Entry) -> DecidedEntry {
+        match entry {
+            Entry::Normal(data) => DecidedEntry::Normal(data.as_slice().get_u64()),
+            // a stopsign holds the id of the configuration it starts
+            Entry::StopSign(ss) => DecidedEntry::StopSign(ss.config_id - 1),
+        }
+    }
+
     fn new_iteration(&mut self, init: Init) -> Handled {
         self.stopped = false;
+        self.crashed = None;
+        self.crashed_io_stats = IoStats::default();
+        self.link_faults.clear();
+        if let Some(timer) = self.recovery_timer.take() {
+            self.cancel_timer(timer);
+        }
         self.nodes = init.nodes;
         self.pid = init.pid as u64;
         self.iteration_id = init.init_id;
         self.active_config = ConfigMeta::new(0);
-        let ser_client = init
+        let ser_clients = init
             .init_data
-            .expect("Init should include ClientComp's actorpath");
-        let client = ActorPath::deserialise(&mut ser_client.as_slice())
-            .expect("Failed to deserialise Client's actorpath");
-        self.cached_client = Some(client);
+            .expect("Init should include the ClientComps' actorpaths");
+        let clients = Clients::deserialise(ser_clients.as_slice())
+            .expect("Failed to deserialise Clients' actorpaths");
+        self.cached_clients = Some(clients);
         if self.initial_config.contains(&self.pid) {
             self.next_config_id = Some(1);
-            self.create_replica(1, self.initial_config.clone(), true, false, true, None)
+            self.create_replica(
+                1,
+                self.initial_config.clone(),
+                true,
+                false,
+                true,
+                None,
+                None,
+            )
         } else {
             let resp = PartitioningActorMsg::InitAck(self.iteration_id);
             let ap = self
@@ -478,8 +955,11 @@ where
         let num_continued_nodes = num_ready_peers + num_unready_peers;
         let idx = config_id as usize - 1;
         let rem_segments: Vec<_> = (1..=num_continued_nodes).map(|x| x as u32).collect();
-        self.pending_seq_transfers[idx] =
-            (rem_segments, vec![Entry::Normal(vec![]); seq_len as usize]);
+        self.pending_seq_transfers[idx] = (
+            rem_segments,
+            vec![Entry::Normal(vec![]); seq_len as usize],
+            0,
+        );
         let offset = seq_len / num_continued_nodes as u64;
         // get segment from unready nodes (probably have early segments of final sequence)
         let skip = skip_tag.unwrap_or(0);
@@ -551,7 +1031,7 @@ where
         seq_len: u64,
         total_segments: u64,
     ) -> Handled {
-        if let Some((rem_segments, _)) = self.pending_seq_transfers.get(config_id as usize) {
+        if let Some((rem_segments, _, _)) = self.pending_seq_transfers.get(config_id as usize) {
             let offset = seq_len / total_segments;
             let num_active = self.active_peers.0.len();
             if num_active > 0 {
@@ -594,13 +1074,18 @@ where
     }
 
     fn append_transferred_segment(&mut self, st: SequenceTransfer) {
-        let (rem_transfers, sequence) = self
+        let (rem_transfers, sequence, compacted_idx) = self
             .pending_seq_transfers
             .get_mut(st.config_id as usize - 1)
             .expect("Should have initialised pending sequence");
         let tag = st.tag;
         let segment = st.segment;
-        let offset = segment.from_idx as usize;
+        // a segment only holds the entries the sender has not compacted
+        let start_idx = segment.to_idx - segment.entries.len() as u64;
+        if start_idx > segment.from_idx && start_idx > *compacted_idx {
+            *compacted_idx = start_idx;
+        }
+        let offset = start_idx as usize;
         for (i, entry) in segment.entries.into_iter().enumerate() {
             sequence[offset + i] = entry;
         }
@@ -626,8 +1111,8 @@ where
         let offset = seq_len / n_continued as u64;
         let from_idx = index as u64 * offset;
         let to_idx = from_idx + offset;
-        let entries = final_seq.get_entries(from_idx, to_idx).to_vec();
-        SequenceSegment::with(from_idx, to_idx, entries)
+        let (_, entries) = final_seq.get_retained_entries(from_idx, to_idx);
+        SequenceSegment::with(from_idx, to_idx, entries.to_vec())
     }
 
     fn create_eager_sequence_transfer(
@@ -648,8 +1133,8 @@ where
         }
         let (succeeded, entries) = match self.prev_sequences.get(&sr.config_id) {
             Some(seq) => {
-                let ents = seq.get_entries(sr.from_idx, sr.to_idx).to_vec();
-                (true, ents)
+                let (_, ents) = seq.get_retained_entries(sr.from_idx, sr.to_idx);
+                (true, ents.to_vec())
             }
             None => {
                 // (false, vec![])
@@ -690,7 +1175,7 @@ where
         // pull previous sequence if exists and not already started
         if prev_config_id != 0 && !self.complete_sequences.contains(&prev_config_id) {
             let idx = prev_config_id as usize - 1;
-            if let Some((rem_segments, _)) = self.pending_seq_transfers.get(idx) {
+            if let Some((rem_segments, _, _)) = self.pending_seq_transfers.get(idx) {
                 if rem_segments.is_empty() {
                     self.pull_sequence(prev_config_id, prev_seq_len, None);
                 }
@@ -703,10 +1188,13 @@ where
             let got_all_segments = self.pending_seq_transfers[idx].0.is_empty();
             if got_all_segments {
                 self.complete_sequences.push(config_id);
-                let mut c = (vec![], vec![]);
-                std::mem::swap(&mut c, &mut self.pending_seq_transfers[idx]);
-                self.prev_sequences
-                    .insert(config_id, Arc::new(S::new_with_sequence(c.1)));
+                let (_, mut sequence, compacted_idx) =
+                    std::mem::replace(&mut self.pending_seq_transfers[idx], (vec![], vec![], 0));
+                let suffix = sequence.split_off(compacted_idx as usize);
+                self.prev_sequences.insert(
+                    config_id,
+                    Arc::new(S::new_from_snapshot(compacted_idx, suffix)),
+                );
                 if let Some(timer) = self.retry_transfer_timers.remove(&config_id) {
                     self.cancel_timer(timer);
                 }
@@ -765,16 +1253,20 @@ where
     fn receive_local(&mut self, msg: Self::Message) -> Handled {
         match msg {
             PaxosCompMsg::Leader(config_id, pid) => {
-                if self.active_config.id == config_id {
-                    if self.active_config.leader == 0 {
-                        let hb_proposals = std::mem::take(&mut self.hb_proposals);
-                        if pid == self.pid {
-                            // notify client if no leader before
-                            self.cached_client
-                                .as_ref()
-                                .expect("No cached client!")
+                if self.active_config.id == config_id && self.crashed.is_none() {
+                    let prev_leader = self.active_config.leader;
+                    if pid == self.pid && prev_leader != pid {
+                        // notify client if no leader before or the previous leader crashed
+                        let clients = self.cached_clients.as_ref().expect("No cached clients!");
+                        for client in clients.all() {
+                            client
                                 .tell_serialised(AtomicBroadcastMsg::FirstLeader(pid), self)
                                 .expect("Should serialise FirstLeader");
+                        }
+                    }
+                    if prev_leader == 0 {
+                        let hb_proposals = std::mem::take(&mut self.hb_proposals);
+                        if pid == self.pid {
                             for net_msg in hb_proposals {
                                 self.deserialise_and_propose(net_msg);
                             }
@@ -796,9 +1288,10 @@ where
             }
             PaxosCompMsg::PendingReconfig(data) => {
                 self.active_config.pending_reconfig = true;
-                self.cached_client
+                self.cached_clients
                     .as_ref()
-                    .expect("No cached client!")
+                    .expect("No cached clients!")
+                    .primary()
                     .tell_serialised(AtomicBroadcastMsg::PendingReconfiguration(data), self)
                     .expect("Should serialise FirstLeader");
             }
@@ -817,9 +1310,10 @@ where
                     data.put_u64(*pid);
                 }
                 let pr = ProposalResp::with(data, 0); // let new leader notify client itself when it's ready
-                self.cached_client
+                self.cached_clients
                     .as_ref()
-                    .expect("No cached client!")
+                    .expect("No cached clients!")
+                    .primary()
                     .tell_serialised(AtomicBroadcastMsg::ProposalResp(pr), self)
                     .expect("Should serialise ReconfigResponse");
                 /*** handle final sequence and notify new nodes ***/
@@ -885,6 +1379,7 @@ where
                         true,
                         ble_quick_start,
                         r.skip_prepare_n,
+                        None,
                     );
                     return handled;
                 }
@@ -900,7 +1395,7 @@ where
     fn receive_network(&mut self, m: NetMessage) -> Handled {
         match m.data.ser_id {
             ATOMICBCAST_ID => {
-                if !self.stopped && !self.active_config.pending_reconfig {
+                if !self.stopped && !self.active_config.pending_reconfig && self.crashed.is_none() {
                     match self.active_config.leader {
                         my_pid if my_pid == self.pid => self.deserialise_and_propose(m),
                         other_pid if other_pid > 0 => {
@@ -943,7 +1438,7 @@ where
                                     let mut new_nodes = r.nodes.new_nodes;
                                     peers.append(&mut new_nodes);
                                     let (ble_peers, communicator_peers) = self.derive_actorpaths(r.config_id, &peers);
-                                    for ble_peer in ble_peers {
+                                    for (_, ble_peer) in ble_peers {
                                         ble_peer.tell_serialised(NetStopMsg::Peer(self.pid), self)
                                                 .expect("NetStopMsg should serialise!");
                                     }
@@ -975,10 +1470,10 @@ where
                                             // only SS in final sequence and no other prev sequences -> start directly
                                             let final_sequence = S::new_with_sequence(vec![]);
                                             self.prev_sequences.insert(r.seq_metadata.config_id, Arc::new(final_sequence));
-                                            let handled = self.create_replica(r.config_id, nodes, false, true, ble_quick_start, r.skip_prepare_n);
+                                            let handled = self.create_replica(r.config_id, nodes, false, true, ble_quick_start, r.skip_prepare_n, None);
                                             return handled;
                                         } else {
-                                            self.pending_seq_transfers = vec![(vec![], vec![]); r.config_id as usize];
+                                            self.pending_seq_transfers = vec![(vec![], vec![], 0); r.config_id as usize];
                                             let skip_tag = if r.segment.is_some() {
                                                 // check which tag this segment corresponds to
                                                 let index = self.active_peers.0.iter().position(|x| x == &from).unwrap();
@@ -997,7 +1492,7 @@ where
                                                     let seq_len = r.seq_metadata.len;
                                                     let idx = config_id as usize - 1;
                                                     let rem_segments: Vec<_> = (1..=num_expected_transfers).map(|x| x as u32).collect();
-                                                    self.pending_seq_transfers[idx] = (rem_segments, vec![Entry::Normal(vec![]); seq_len as usize]);
+                                                    self.pending_seq_transfers[idx] = (rem_segments, vec![Entry::Normal(vec![]); seq_len as usize], 0);
                                                     let transfer_timeout = self.ctx.config()["paxos"]["transfer_timeout"].as_duration().expect("Failed to load get_decided_period");
                                                     let timer = self.schedule_once(transfer_timeout, move |c, _| c.retry_request_sequence(config_id, seq_len, num_expected_transfers as u64));
                                                     self.retry_transfer_timers.insert(config_id, timer);
@@ -1007,7 +1502,7 @@ where
                                                 let st = SequenceTransfer::with(r.seq_metadata.config_id, tag as u32, true, r.seq_metadata, r.segment.unwrap());
                                                 self.handle_sequence_transfer(st);
                                             }
-                                            let handled = self.create_replica(r.config_id, nodes, false, false, ble_quick_start, r.skip_prepare_n);
+                                            let handled = self.create_replica(r.config_id, nodes, false, false, ble_quick_start, r.skip_prepare_n, None);
                                             return handled;
                                         }
                                     },
@@ -1037,32 +1532,46 @@ where
                             return self.stop_components();
                         }
                     },
+                    fault: FaultMsg [FaultMsgDeser] => {
+                        match fault {
+                            FaultMsg::Crash(downtime) => return self.crash(downtime),
+                            FaultMsg::Link(link_fault) => self.handle_link_fault(LinkFaultMsg::Apply(link_fault)),
+                            FaultMsg::Heal(id) => self.handle_link_fault(LinkFaultMsg::Heal(id)),
+                            FaultMsg::TransferLeadership => self.take_leadership(),
+                        }
+                    },
                     tm: TestMessage [TestMessageSer] => {
                         match tm {
                             TestMessage::SequenceReq => {
                                 let mut all_entries = vec![];
+                                let mut compacted_idx = 0;
                                 let mut unique = HashSet::new();
                                 for i in 1..self.active_config.id {
                                     if let Some(seq) = self.prev_sequences.get(&i) {
                                         let sequence = seq.get_sequence();
-                                        for entry in sequence {
-                                            if let Entry::Normal(n) = entry {
-                                                let id = n.as_slice().get_u64();
-                                                all_entries.push(id);
+                                        for entry in &sequence {
+                                            let decided = Self::to_decided_entry(entry);
+                                            if let DecidedEntry::Normal(id) = decided {
                                                 unique.insert(id);
                                             }
+                                            all_entries.push(decided);
                                         }
                                     }
                                 }
-                                if self.active_config.id > 0 {
+                                if self.active_config.id > 0 && self.crashed.is_none() {
                                     let active_paxos = self.paxos_replicas.last().unwrap();
-                                    let sequence = active_paxos.actor_ref().ask(|promise| PaxosReplicaMsg::GetAllEntries(Ask::new(promise, ()))).wait();
-                                    for entry in sequence {
-                                        if let Entry::Normal(n) = entry {
-                                            let id = n.as_slice().get_u64();
-                                            all_entries.push(id);
+                                    let (active_compacted_idx, sequence) = active_paxos.actor_ref().ask(|promise| PaxosReplicaMsg::GetDecidedSequence(Ask::new(promise, ()))).wait();
+                                    if active_compacted_idx > 0 {
+                                        // the previous configurations and the compacted prefix are missing in front of the suffix
+                                        compacted_idx = all_entries.len() as u64 + active_compacted_idx;
+                                        all_entries.clear();
+                                    }
+                                    for entry in &sequence {
+                                        let decided = Self::to_decided_entry(entry);
+                                        if let DecidedEntry::Normal(id) = decided {
                                             unique.insert(id);
-                                         }
+                                        }
+                                        all_entries.push(decided);
                                     }
                                     let min = unique.iter().min();
                                     let max = unique.iter().max();
@@ -1070,9 +1579,14 @@ where
                                 } else {
                                     warn!(self.ctx.log(), "Got SequenceReq but no active paxos: {}", self.active_config.id);
                                 }
-                                let sr = SequenceResp::with(self.pid, all_entries);
+                                let sequence = DecidedSequence { compacted_idx, entries: all_entries };
+                                let sr = SequenceResp::with(self.pid, sequence);
                                 sender.tell((TestMessage::SequenceResp(sr), TestMessageSer), self);
                             },
+                            TestMessage::IoStatsReq => {
+                                let io_stats = TestMessage::IoStatsResp(self.pid, self.io_stats());
+                                sender.tell((io_stats, TestMessageSer), self);
+                            },
                             _ => error!(self.ctx.log(), "Got unexpected TestMessage: {:?}", tm),
                         }
                     },
@@ -1106,6 +1620,7 @@ where
     stopped: bool,
     stopped_peers: HashSet<u64>,
     stop_ask: Option<Ask<(bool, bool), ()>>,
+    kv: Option<KvStore>,
 }
 
 impl<S, P> PaxosReplica<S, P>
@@ -1120,11 +1635,13 @@ where
         pid: u64,
         raw_paxos_log: KompactLogger,
         skipped_prepare_ballot: Option<Ballot>,
+        storage: Storage<S, P>,
         max_inflight: usize,
+        compaction: CompactionPolicy,
+        recover: bool,
+        kv: bool,
+        options: PaxosOptions,
     ) -> PaxosReplica<S, P> {
-        let seq = S::new_with_sequence(Vec::with_capacity(max_inflight));
-        let paxos_state = P::new();
-        let storage = Storage::with(seq, paxos_state);
         let skipped_prepare = match skipped_prepare_ballot {
             Some(b) if b.pid != pid => true,
             _ => false,
@@ -1137,6 +1654,9 @@ where
             raw_paxos_log,
             skipped_prepare_ballot,
             Some(max_inflight),
+            compaction,
+            recover,
+            options,
         );
         PaxosReplica {
             ctx: ComponentContext::uninitialised(),
@@ -1154,6 +1674,7 @@ where
             pending_reconfig: false,
             stopped: false,
             stop_ask: None,
+            kv: if kv { Some(KvStore::default()) } else { None },
         }
     }
 
@@ -1246,9 +1767,24 @@ where
         }
         if self.current_leader == self.pid {
             // leader: check reconfiguration and send responses to client
-            let decided_entries = self.paxos.get_decided_entries().to_vec();
-            let last = decided_entries.last();
-            if let Some(Entry::StopSign(ss)) = last {
+            let decided_entries = self.paxos.get_decided_entries();
+            let stopsign = match decided_entries.last() {
+                Some(Entry::StopSign(ss)) => Some(ss.clone()),
+                _ => None,
+            };
+            // only the ids, or the results of the commands, are sent back, so the payloads are not copied
+            let kv = &mut self.kv;
+            let responses: Vec<Vec<u8>> = decided_entries
+                .iter()
+                .filter_map(|e| match e {
+                    Entry::Normal(data) => Some(match kv {
+                        Some(store) => store.apply(data),
+                        None => data[..data.len().min(PROPOSAL_ID_LEN)].to_vec(),
+                    }),
+                    _ => None,
+                })
+                .collect();
+            if let Some(ss) = stopsign {
                 self.handle_stopsign(&ss);
             }
             let latest_leader = if self.paxos.stopped() {
@@ -1256,16 +1792,25 @@ where
             } else {
                 self.pid
             };
-            for decided in decided_entries {
-                if let Entry::Normal(data) = decided {
-                    let pr = ProposalResp::with(data, latest_leader);
-                    self.communication_port
-                        .trigger(CommunicatorMsg::ProposalResponse(pr));
-                }
+            for data in responses {
+                let pr = ProposalResp::with(data, latest_leader);
+                self.communication_port
+                    .trigger(CommunicatorMsg::ProposalResponse(pr));
+            }
+            if let Some(idx) = self.paxos.compact_if_needed() {
+                debug!(self.ctx.log(), "Compacted sequence up to {}", idx);
             }
         } else {
-            // follower: just handle a possible reconfiguration
-            if let Some(Entry::StopSign(ss)) = self.paxos.get_decided_entries().last().cloned() {
+            // follower: apply the commands and handle a possible reconfiguration
+            let decided_entries = self.paxos.get_decided_entries();
+            if let Some(store) = self.kv.as_mut() {
+                for e in decided_entries {
+                    if let Entry::Normal(data) = e {
+                        store.apply(data);
+                    }
+                }
+            }
+            if let Some(Entry::StopSign(ss)) = decided_entries.last().cloned() {
                 self.handle_stopsign(&ss);
             }
         }
@@ -1319,10 +1864,10 @@ where
                     .tell_serialised(ReconfigurationMsg::SequenceTransfer(st), self)
                     .expect("Should serialise!");
             }
-            PaxosReplicaMsg::GetAllEntries(a) => {
-                // for testing only
-                let seq = self.paxos.get_sequence();
-                a.reply(seq).expect("Failed to reply to GetAllEntries");
+            PaxosReplicaMsg::GetDecidedSequence(a) => {
+                // for validation only
+                let seq = self.paxos.get_decided_sequence();
+                a.reply(seq).expect("Failed to reply to GetDecidedSequence");
             }
             PaxosReplicaMsg::Stop(ask) => {
                 let (ack_client, late_stop) = *ask.request();
@@ -1377,6 +1922,12 @@ where
             AtomicBroadcastCompMsg::RawPaxosMsg(pm) if !self.stopped => {
                 self.paxos.handle(pm);
             }
+            AtomicBroadcastCompMsg::LinkDown(pid) if !self.stopped => {
+                self.paxos.connection_lost(pid);
+            }
+            AtomicBroadcastCompMsg::LinkUp(pid) if !self.stopped => {
+                self.paxos.connection_restored(pid);
+            }
             AtomicBroadcastCompMsg::StopMsg(pid) => {
                 assert!(
                     self.stopped_peers.insert(pid),
@@ -1417,8 +1968,8 @@ where
 pub mod raw_paxos {
     use super::super::messages::paxos::ballot_leader_election::{Ballot, Leader};
     use super::super::messages::paxos::*;
-    use super::super::storage::paxos::Storage;
-    use super::{PaxosStateTraits, SequenceTraits};
+    use super::super::storage::{paxos::Storage, CompactionPolicy, IoStats};
+    use super::{PaxosOptions, PaxosStateTraits, SequenceTraits};
     use crate::serialiser_ids::RECONFIG_ID;
     use kompact::prelude::BufMut;
     use kompact::KompactLogger;
@@ -1434,7 +1985,8 @@ pub mod raw_paxos {
         storage: Storage<S, P>,
         config_id: u32,
         pid: u64,
-        majority: usize,
+        phase1_quorum: usize,
+        phase2_quorum: usize,
         peers: Vec<u64>, // excluding self pid
         state: (Role, Phase),
         leader: u64,
@@ -1456,6 +2008,9 @@ pub mod raw_paxos {
         log: KompactLogger,
         max_inflight: usize,
         requested_firstaccept: bool,
+        compaction: CompactionPolicy,
+        reported_lds: Vec<u64>, // decided lengths reported in Accepted
+        options: PaxosOptions,
     }
 
     impl<S, P> Paxos<S, P>
@@ -1472,9 +2027,13 @@ pub mod raw_paxos {
             log: KompactLogger,
             skipped_prepare: Option<Ballot>,
             max_inflight: Option<usize>,
+            compaction: CompactionPolicy,
+            recovered: bool,
+            options: PaxosOptions,
         ) -> Paxos<S, P> {
             let num_nodes = &peers.len() + 1;
-            let majority = num_nodes / 2 + 1;
+            let phase1_quorum = options.quorums.phase1(num_nodes);
+            let phase2_quorum = options.quorums.phase2(num_nodes);
             let max_peer_pid = peers.iter().max().unwrap();
             let max_pid = std::cmp::max(max_peer_pid, &pid);
             let num_nodes = *max_pid as usize;
@@ -1494,7 +2053,12 @@ pub mod raw_paxos {
                     (state, lds)
                 }
                 _ => {
-                    let state = (Role::Follower, Phase::None);
+                    let phase = if recovered {
+                        Phase::Recover
+                    } else {
+                        Phase::None
+                    };
+                    let state = (Role::Follower, phase);
                     let lds = vec![None; num_nodes];
                     (state, lds)
                 }
@@ -1506,7 +2070,8 @@ pub mod raw_paxos {
                 storage,
                 pid,
                 config_id,
-                majority,
+                phase1_quorum,
+                phase2_quorum,
                 peers,
                 state,
                 leader: n_leader.pid,
@@ -1528,8 +2093,16 @@ pub mod raw_paxos {
                 log,
                 max_inflight,
                 requested_firstaccept: false,
+                compaction,
+                reported_lds: vec![0; num_nodes],
+                options,
             };
-            paxos.storage.set_promise(n_leader);
+            if recovered {
+                // everything decided before the crash has already been delivered
+                paxos.prev_ld = paxos.storage.get_decided_len();
+            } else {
+                paxos.storage.set_promise(n_leader);
+            }
             paxos
         }
 
@@ -1540,16 +2113,13 @@ pub mod raw_paxos {
         pub fn get_outgoing_msgs(&mut self) -> Vec<Message> {
             let mut outgoing = Vec::with_capacity(self.max_inflight);
             std::mem::swap(&mut self.outgoing, &mut outgoing);
-            #[cfg(feature = "batch_accept")]
-            {
+            if self.options.batch_accept {
                 self.batch_accept_meta = vec![None; self.num_nodes];
             }
-            #[cfg(feature = "latest_decide")]
-            {
+            if self.options.latest_decide {
                 self.latest_decide_meta = vec![None; self.num_nodes];
             }
-            #[cfg(feature = "latest_accepted")]
-            {
+            if self.options.latest_accepted {
                 self.latest_accepted_meta = None;
             }
             outgoing
@@ -1581,6 +2151,8 @@ pub mod raw_paxos {
                 PaxosMsg::Accepted(accepted) => self.handle_accepted(accepted, m.from),
                 PaxosMsg::Decide(d) => self.handle_decide(d),
                 PaxosMsg::ProposalForward(proposals) => self.handle_forwarded_proposal(proposals),
+                PaxosMsg::Compaction(idx) => self.handle_compaction(idx, m.from),
+                PaxosMsg::PrepareReq => self.handle_preparereq(m.from),
             }
         }
 
@@ -1588,6 +2160,10 @@ pub mod raw_paxos {
             self.storage.stopped()
         }
 
+        pub fn io_stats(&self) -> IoStats {
+            self.storage.io_stats()
+        }
+
         pub fn propose_normal(&mut self, data: Vec<u8>) -> Result<(), Vec<u8>> {
             if self.stopped() {
                 Err(data)
@@ -1647,16 +2223,84 @@ pub mod raw_paxos {
             }
         }
 
+        /// Returns the chosen entries in `from_idx..to_idx` that have not been compacted.
+        /// If the range is partly compacted, only its retained suffix is returned.
         pub fn get_chosen_entries(&self, from_idx: u64, to_idx: u64) -> (bool, Vec<Entry>) {
             let ld = self.storage.get_decided_len();
             let max_idx = std::cmp::max(ld, self.lc);
             if to_idx > max_idx {
                 (false, vec![])
             } else {
-                (true, self.storage.get_entries(from_idx, to_idx).to_vec())
+                let (_, entries) = self.storage.get_retained_entries(from_idx, to_idx);
+                (true, entries.to_vec())
             }
         }
 
+        /// Compacts the decided prefix if the compaction policy says so and tells the followers to do the same.
+        /// There is no snapshot transfer, so only the prefix that every node of the configuration has reported as
+        /// decided is compacted and no node should ever need to be synced with a compacted entry.
+        /// A node that is down or lagging behind therefore holds back the compaction of all others.
+        /// Returns the new compacted index if anything was compacted.
+        pub fn compact_if_needed(&mut self) -> Option<u64> {
+            if self.state != (Role::Leader, Phase::Accept)
+                || self.stopped()
+                || !self.compaction.is_enabled()
+            {
+                return None;
+            }
+            let my_idx = self.pid as usize - 1;
+            let min_reported_ld = self
+                .peers
+                .iter()
+                .map(|pid| self.reported_lds[*pid as usize - 1])
+                .min()
+                .unwrap_or(self.prev_ld);
+            let idx = std::cmp::min(min_reported_ld, self.prev_ld);
+            let compacted_idx = self.storage.get_compacted_idx();
+            if idx <= compacted_idx {
+                return None;
+            }
+            let bytes: usize = self
+                .storage
+                .get_entries(compacted_idx, idx)
+                .iter()
+                .map(|e| e.size())
+                .sum();
+            if !self
+                .compaction
+                .should_compact(idx - compacted_idx, bytes as u64)
+            {
+                return None;
+            }
+            self.storage.compact(idx);
+            self.reported_lds[my_idx] = idx;
+            for pid in &self.peers {
+                self.outgoing
+                    .push(Message::with(self.pid, *pid, PaxosMsg::Compaction(idx)));
+            }
+            Some(idx)
+        }
+
+        /// The suffix from `from` on, or `None` if a part of it was compacted. This only happens if a node lost
+        /// decided entries, as compaction only covers what every node has decided.
+        fn get_retained_suffix(&self, from: u64) -> Option<Vec<Entry>> {
+            if from < self.storage.get_compacted_idx() {
+                None
+            } else {
+                Some(self.storage.get_suffix(from))
+            }
+        }
+
+        fn log_compacted_sync(&self, pid: u64, ld: u64) {
+            error!(
+                self.log,
+                "Can not sync {} from its decided length {}, it was compacted up to {}",
+                pid,
+                ld,
+                self.storage.get_compacted_idx()
+            );
+        }
+
         pub(crate) fn stop_and_get_sequence(&mut self) -> Arc<S> {
             self.storage.stop_and_get_sequence()
         }
@@ -1675,15 +2319,45 @@ pub mod raw_paxos {
             } // else: we have already started in new config, don't care about this call
         }
 
+        /// A network fault dropped a message to `pid`. A leader stops replicating to it, as the follower can not accept
+        /// anything after the lost messages until it has been prepared again.
+        pub fn connection_lost(&mut self, pid: u64) {
+            if self.state.0 == Role::Leader {
+                self.lds[pid as usize - 1] = None;
+            }
+        }
+
+        /// The network fault towards `pid` healed. The follower is prepared again, as it might have missed messages
+        /// from the leader or the leader might have missed its promise.
+        pub fn connection_restored(&mut self, pid: u64) {
+            if self.state.0 == Role::Leader && self.state.1 != Phase::FirstAccept {
+                self.handle_preparereq(pid);
+            } else if self.state.0 == Role::Follower && pid == self.leader {
+                self.outgoing
+                    .push(Message::with(self.pid, pid, PaxosMsg::PrepareReq));
+            }
+        }
+
         fn clear_peers_state(&mut self) {
             self.las = vec![0; self.num_nodes];
             self.promises_meta = vec![None; self.num_nodes];
             self.lds = vec![None; self.num_nodes];
+            self.reported_lds = vec![0; self.num_nodes];
         }
 
         /*** Leader ***/
         pub fn handle_leader(&mut self, l: Leader) {
             let n = l.ballot;
+            if self.state.1 == Phase::Recover
+                && self.pid != l.pid
+                && n >= self.storage.get_promise()
+            {
+                // the leader might have prepared us before we crashed, ask it to prepare us again
+                self.leader = l.pid;
+                self.outgoing
+                    .push(Message::with(self.pid, l.pid, PaxosMsg::PrepareReq));
+                return;
+            }
             if n <= self.n_leader || n <= self.storage.get_promise() {
                 return;
             }
@@ -1771,7 +2445,7 @@ pub mod raw_paxos {
                 .filter(|(_, x)| x.is_some())
                 .map(|(idx, _)| idx);
             for idx in promised_idx {
-                if cfg!(feature = "batch_accept") {
+                if self.options.batch_accept {
                     match self.batch_accept_meta.get_mut(idx).unwrap() {
                         Some((ballot, outgoing_idx)) if ballot == &self.n_leader => {
                             let Message { msg, .. } = self.outgoing.get_mut(*outgoing_idx).unwrap();
@@ -1793,8 +2467,7 @@ pub mod raw_paxos {
                                 PaxosMsg::AcceptDecide(acc),
                             ));
                             self.batch_accept_meta[idx] = Some((self.n_leader, cache_idx));
-                            #[cfg(feature = "latest_decide")]
-                            {
+                            if self.options.latest_decide {
                                 self.latest_decide_meta[idx] = Some((self.n_leader, cache_idx));
                             }
                         }
@@ -1818,7 +2491,7 @@ pub mod raw_paxos {
                 .filter(|(_, x)| x.is_some())
                 .map(|(idx, _)| idx);
             for idx in promised_idx {
-                if cfg!(feature = "batch_accept") {
+                if self.options.batch_accept {
                     match self.batch_accept_meta.get_mut(idx).unwrap() {
                         Some((ballot, outgoing_idx)) if ballot == &self.n_leader => {
                             let Message { msg, .. } = self.outgoing.get_mut(*outgoing_idx).unwrap();
@@ -1845,8 +2518,7 @@ pub mod raw_paxos {
                                 PaxosMsg::AcceptDecide(acc),
                             ));
                             self.batch_accept_meta[idx] = Some((self.n_leader, cache_idx));
-                            #[cfg(feature = "latest_decide")]
-                            {
+                            if self.options.latest_decide {
                                 self.latest_decide_meta[idx] = Some((self.n_leader, cache_idx));
                             }
                         }
@@ -1876,7 +2548,7 @@ pub mod raw_paxos {
                 self.promises_meta[idx] = Some((prom.n_accepted, sfx_len));
                 self.lds[idx] = Some(prom.ld);
                 let num_promised = self.promises_meta.iter().filter(|x| x.is_some()).count();
-                if num_promised >= self.majority {
+                if num_promised >= self.phase1_quorum {
                     let (max_promise_n, max_sfx_len, max_pid) = self.max_promise_meta;
                     let last_is_stop = match self.max_promise_sfx.last() {
                         Some(e) => e.is_stopsign(),
@@ -1918,7 +2590,7 @@ pub mod raw_paxos {
                         let promise_meta = &self.promises_meta[idx].unwrap_or_else(|| {
                             panic!("No promise from {}. Max pid: {}", pid, max_pid)
                         });
-                        if cfg!(feature = "max_accsync") {
+                        if self.options.max_accsync {
                             if promise_meta == &(max_promise_n, max_sfx_len) {
                                 if !max_sfx_is_empty || ld >= self.acc_sync_ld {
                                     let msg = Message::with(
@@ -1929,20 +2601,41 @@ pub mod raw_paxos {
                                     self.outgoing.push(msg);
                                 }
                             } else {
-                                let sfx = self.storage.get_suffix(ld);
-                                let acc_sync = AcceptSync::with(self.n_leader, sfx, ld, true);
-                                let msg =
-                                    Message::with(self.pid, pid, PaxosMsg::AcceptSync(acc_sync));
-                                self.outgoing.push(msg);
+                                match self.get_retained_suffix(ld) {
+                                    Some(sfx) => {
+                                        let acc_sync =
+                                            AcceptSync::with(self.n_leader, sfx, ld, true);
+                                        let msg = Message::with(
+                                            self.pid,
+                                            pid,
+                                            PaxosMsg::AcceptSync(acc_sync),
+                                        );
+                                        self.outgoing.push(msg);
+                                    }
+                                    None => {
+                                        self.log_compacted_sync(pid, ld);
+                                        continue;
+                                    }
+                                }
                             }
                         } else {
-                            let sfx = self.storage.get_suffix(ld);
-                            let acc_sync = AcceptSync::with(self.n_leader, sfx, ld, true);
-                            let msg = Message::with(self.pid, pid, PaxosMsg::AcceptSync(acc_sync));
-                            self.outgoing.push(msg);
+                            match self.get_retained_suffix(ld) {
+                                Some(sfx) => {
+                                    let acc_sync = AcceptSync::with(self.n_leader, sfx, ld, true);
+                                    let msg = Message::with(
+                                        self.pid,
+                                        pid,
+                                        PaxosMsg::AcceptSync(acc_sync),
+                                    );
+                                    self.outgoing.push(msg);
+                                }
+                                None => {
+                                    self.log_compacted_sync(pid, ld);
+                                    continue;
+                                }
+                            }
                         }
-                        #[cfg(feature = "batch_accept")]
-                        {
+                        if self.options.batch_accept {
                             self.batch_accept_meta[idx] =
                                 Some((self.n_leader, self.outgoing.len() - 1));
                         }
@@ -1958,20 +2651,27 @@ pub mod raw_paxos {
                 let sfx_len = prom.sfx.len();
                 let promise_meta = &(prom.n_accepted, sfx_len);
                 let (max_ballot, max_sfx_len, _) = self.max_promise_meta;
-                let (sync, sfx_start) = if promise_meta == &(max_ballot, max_sfx_len)
-                    && cfg!(feature = "max_accsync")
-                {
-                    match max_sfx_len == 0 {
-                        false => (false, self.acc_sync_ld + sfx_len as u64),
-                        true if prom.ld >= self.acc_sync_ld => {
-                            (false, self.acc_sync_ld + sfx_len as u64)
+                let (sync, sfx_start) =
+                    if promise_meta == &(max_ballot, max_sfx_len) && self.options.max_accsync {
+                        match max_sfx_len == 0 {
+                            false => (false, self.acc_sync_ld + sfx_len as u64),
+                            true if prom.ld >= self.acc_sync_ld => {
+                                (false, self.acc_sync_ld + sfx_len as u64)
+                            }
+                            _ => (true, prom.ld),
                         }
-                        _ => (true, prom.ld),
-                    }
-                } else {
+                    } else {
+                        (true, prom.ld)
+                    };
+                let (sync, sfx_start) = if sfx_start < self.storage.get_compacted_idx() {
                     (true, prom.ld)
+                } else {
+                    (sync, sfx_start)
+                };
+                let sfx = match self.get_retained_suffix(sfx_start) {
+                    Some(sfx) => sfx,
+                    None => return self.log_compacted_sync(from, prom.ld),
                 };
-                let sfx = self.storage.get_suffix(sfx_start);
                 // println!("Handle promise from {} in Accept phase: {:?}, sfx len: {}", from, (sync, sfx_start), sfx.len());
                 let acc_sync = AcceptSync::with(self.n_leader, sfx, prom.ld, sync);
                 self.outgoing.push(Message::with(
@@ -1989,8 +2689,7 @@ pub mod raw_paxos {
                     let d = Decide::with(ld, self.n_leader);
                     self.outgoing
                         .push(Message::with(self.pid, from, PaxosMsg::Decide(d)));
-                    #[cfg(feature = "latest_decide")]
-                    {
+                    if self.options.latest_decide {
                         let idx = from as usize - 1;
                         let cached_idx = self.outgoing.len() - 1;
                         self.latest_decide_meta[idx] = Some((self.n_leader, cached_idx));
@@ -1999,6 +2698,18 @@ pub mod raw_paxos {
             }
         }
 
+        fn handle_preparereq(&mut self, from: u64) {
+            if self.state.0 == Role::Leader && self.state.1 != Phase::FirstAccept {
+                let prep = Prepare::with(
+                    self.n_leader,
+                    self.storage.get_decided_len(),
+                    self.storage.get_accepted_ballot(),
+                );
+                self.outgoing
+                    .push(Message::with(self.pid, from, PaxosMsg::Prepare(prep)));
+            }
+        }
+
         fn handle_firstacceptreq(&mut self, from: u64) {
             if self.state.0 != Role::Leader || self.state.1 == Phase::FirstAccept {
                 return;
@@ -2006,7 +2717,7 @@ pub mod raw_paxos {
             let entries = self.storage.get_sequence();
             let f = FirstAccept::with(self.n_leader, entries);
             let pm = PaxosMsg::FirstAccept(f);
-            if cfg!(feature = "batch_accept") {
+            if self.options.batch_accept {
                 let idx = from as usize - 1;
                 /*** replace any cached msg with the FirstAccept (as receiver will discard the original msg anyway) ***/
                 let cache_idx = if let Some((_, cached_accept_idx)) =
@@ -2026,8 +2737,7 @@ pub mod raw_paxos {
                     self.outgoing.len() - 1
                 };
                 self.batch_accept_meta[idx] = Some((self.n_leader, cache_idx));
-                #[cfg(feature = "latest_decide")]
-                {
+                if self.options.latest_decide {
                     self.latest_decide_meta[idx] = None;
                 }
             } else {
@@ -2038,13 +2748,14 @@ pub mod raw_paxos {
         fn handle_accepted(&mut self, accepted: Accepted, from: u64) {
             if accepted.n == self.n_leader && self.state == (Role::Leader, Phase::Accept) {
                 self.las[from as usize - 1] = accepted.la;
+                self.reported_lds[from as usize - 1] = accepted.ld;
                 if accepted.la > self.lc {
-                    let chosen =
-                        self.las.iter().filter(|la| *la >= &accepted.la).count() >= self.majority;
+                    let chosen = self.las.iter().filter(|la| *la >= &accepted.la).count()
+                        >= self.phase2_quorum;
                     if chosen {
                         self.lc = accepted.la;
                         let d = Decide::with(self.lc, self.n_leader);
-                        if cfg!(feature = "latest_decide") {
+                        if self.options.latest_decide {
                             let promised_idx =
                                 self.lds.iter().enumerate().filter(|(_, ld)| ld.is_some());
                             for (idx, _) in promised_idx {
@@ -2098,13 +2809,28 @@ pub mod raw_paxos {
 
         /*** Follower ***/
         fn handle_prepare(&mut self, prep: Prepare, from: u64) {
-            if self.storage.get_promise() < prep.n {
+            let promise = self.storage.get_promise();
+            // an equal ballot is our leader preparing us again after we crashed or it lost messages to us
+            if promise <= prep.n {
                 self.leader = from;
                 self.storage.set_promise(prep.n);
                 self.state = (Role::Follower, Phase::Prepare);
                 let na = self.storage.get_accepted_ballot();
                 let sfx = if na >= prep.n_accepted {
-                    self.storage.get_suffix(prep.ld)
+                    match self.get_retained_suffix(prep.ld) {
+                        Some(sfx) => sfx,
+                        None => {
+                            // the leader lost decided entries, promising without them could lose them for good
+                            error!(
+                                self.log,
+                                "Not promising {}: its decided length {} was compacted up to {}",
+                                from,
+                                prep.ld,
+                                self.storage.get_compacted_idx()
+                            );
+                            return;
+                        }
+                    }
                 } else {
                     vec![]
                 };
@@ -2126,9 +2852,8 @@ pub mod raw_paxos {
                     self.storage.append_sequence(&mut entries)
                 };
                 self.state = (Role::Follower, Phase::Accept);
-                let accepted = Accepted::with(acc_sync.n, la);
-                #[cfg(feature = "latest_accepted")]
-                {
+                let accepted = Accepted::with(acc_sync.n, la, self.storage.get_decided_len());
+                if self.options.latest_accepted {
                     let cached_idx = self.outgoing.len();
                     self.latest_accepted_meta = Some((acc_sync.n, cached_idx));
                 }
@@ -2191,6 +2916,7 @@ pub mod raw_paxos {
         fn handle_decide(&mut self, dec: Decide) {
             if self.storage.get_promise() == dec.n {
                 match self.state.1 {
+                    Phase::Recover => {} // wait until the leader has prepared us again
                     Phase::FirstAccept => {
                         if !self.requested_firstaccept {
                             self.requested_firstaccept = true;
@@ -2211,6 +2937,16 @@ pub mod raw_paxos {
             }
         }
 
+        fn handle_compaction(&mut self, idx: u64, from: u64) {
+            if from == self.leader && self.state.0 == Role::Follower {
+                // never compact what has not been delivered yet
+                let idx = std::cmp::min(idx, self.prev_ld);
+                if idx > self.storage.get_compacted_idx() {
+                    self.storage.compact(idx);
+                }
+            }
+        }
+
         /*** algorithm specific functions ***/
         fn drop_after_stopsign(entries: &mut Vec<Entry>) {
             // drop all entries ordered after stopsign (if any)
@@ -2224,19 +2960,33 @@ pub mod raw_paxos {
             self.storage.get_sequence()
         }
 
+        /// Returns the index the sequence was compacted up to and the decided entries after it.
+        pub fn get_decided_sequence(&self) -> (u64, Vec<Entry>) {
+            let ld = self.storage.get_decided_len();
+            let compacted_idx = self.storage.get_compacted_idx();
+            (
+                compacted_idx,
+                self.storage.get_entries(compacted_idx, ld).to_vec(),
+            )
+        }
+
         fn accept_entries(&mut self, n: Ballot, entries: &mut Vec<Entry>) {
             let la = self.storage.append_sequence(entries);
-            if cfg!(feature = "latest_accepted") {
+            let ld = self.storage.get_decided_len();
+            if self.options.latest_accepted {
                 match self.latest_accepted_meta {
                     Some((ballot, outgoing_idx)) if ballot == n => {
                         let Message { msg, .. } = self.outgoing.get_mut(outgoing_idx).unwrap();
                         match msg {
-                            PaxosMsg::Accepted(a) => a.la = la,
+                            PaxosMsg::Accepted(a) => {
+                                a.la = la;
+                                a.ld = ld;
+                            }
                             _ => panic!("Cached idx is not an Accepted message!"),
                         }
                     }
                     _ => {
-                        let accepted = Accepted::with(n, la);
+                        let accepted = Accepted::with(n, la, ld);
                         let cached_idx = self.outgoing.len();
                         self.latest_accepted_meta = Some((n, cached_idx));
                         self.outgoing.push(Message::with(
@@ -2247,7 +2997,7 @@ pub mod raw_paxos {
                     }
                 }
             } else {
-                let accepted = Accepted::with(n, la);
+                let accepted = Accepted::with(n, la, ld);
                 self.outgoing.push(Message::with(
                     self.pid,
                     self.leader,
@@ -2262,6 +3012,7 @@ pub mod raw_paxos {
         Prepare,
         FirstAccept,
         Accept,
+        Recover, // restarted after a crash and waiting to be prepared by the leader
         None,
     }
 
@@ -2307,10 +3058,19 @@ pub mod raw_paxos {
                 _ => false,
             }
         }
+
+        /// Approximate number of bytes the entry occupies in the log.
+        pub(crate) fn size(&self) -> usize {
+            match self {
+                Entry::Normal(data) => data.len(),
+                Entry::StopSign(ss) => 4 + 8 * ss.nodes.len(),
+            }
+        }
     }
 }
 
-mod ballot_leader_election {
+pub mod ballot_leader_election {
+    use super::super::fault::{Delivery, LinkFilter};
     use super::super::messages::{
         paxos::ballot_leader_election::*, StopMsg as NetStopMsg, StopMsgDeser,
     };
@@ -2318,7 +3078,11 @@ mod ballot_leader_election {
     use std::time::Duration;
 
     #[derive(Debug)]
-    pub struct Stop(pub Ask<(u64, bool), ()>);
+    pub enum BallotLeaderMsg {
+        Stop(Ask<(u64, bool), ()>),
+        LinkFault(LinkFaultMsg),
+        TakeLeadership,
+    }
 
     pub struct BallotLeaderElection;
 
@@ -2327,110 +3091,106 @@ mod ballot_leader_election {
         type Request = ();
     }
 
+    /// Drives a [`raw_ble::BallotLeaderElection`] with a periodic timer and sends its heartbeats to the peers.
     #[derive(ComponentDefinition)]
     pub struct BallotLeaderComp {
-        // TODO decouple from kompact, similar style to tikv_raft with tick() replacing timers
         ctx: ComponentContext<Self>,
         ble_port: ProvidedPort<BallotLeaderElection>,
-        pid: u64,
-        peers: Vec<ActorPath>,
-        round: u64,
-        ballots: Vec<(Ballot, u64)>,
-        current_ballot: Ballot, // (round, pid)
-        leader: Option<(Ballot, u64)>,
-        max_ballot: Ballot,
-        hb_delay: u64,
-        delta: u64,
-        majority: usize,
+        ble: raw_ble::BallotLeaderElection,
+        peers: HashMap<u64, ActorPath>,
+        tick_period: u64,
         timer: Option<ScheduledTimer>,
-        stopped: bool,
         stopped_peers: HashSet<u64>,
         stop_ask: Option<Ask<(u64, bool), ()>>,
-        quick_timeout: bool,
-        initial_election_factor: u64,
+        link_filter: LinkFilter,
     }
 
     impl BallotLeaderComp {
+        /// `hb_delay`, `delta` and `tick_period` are in ms. The election works in ticks of `tick_period`. A leader is
+        /// elected by `quorum` nodes, which is the phase 1 quorum of the replicas.
         pub fn with(
-            peers: Vec<ActorPath>,
+            peers: HashMap<u64, ActorPath>,
             pid: u64,
+            quorum: usize,
             hb_delay: u64,
             delta: u64,
             quick_timeout: bool,
             initial_max_ballot: Option<Ballot>,
             initial_election_factor: u64,
+            headstart: bool,
+            tick_period: u64,
         ) -> BallotLeaderComp {
-            let n = &peers.len() + 1;
-            let initial_round = match initial_max_ballot {
-                Some(ballot) if ballot.pid == pid => ballot.n,
-                _ => 0,
-            };
-            let initial_ballot = Ballot::with(initial_round, pid);
+            let n = peers.len() + 1;
+            let ble = raw_ble::BallotLeaderElection::with(
+                pid,
+                peers.keys().copied().collect(),
+                quorum,
+                std::cmp::max(hb_delay / tick_period, 1),
+                delta / tick_period,
+                quick_timeout,
+                initial_max_ballot,
+                initial_election_factor,
+                headstart,
+            );
             BallotLeaderComp {
                 ctx: ComponentContext::uninitialised(),
                 ble_port: ProvidedPort::uninitialised(),
-                pid,
-                majority: n / 2 + 1, // +1 because peers is exclusive ourselves
+                ble,
                 peers,
-                round: initial_round,
-                ballots: Vec::with_capacity(n),
-                current_ballot: initial_ballot,
-                leader: None,
-                max_ballot: initial_max_ballot.unwrap_or(initial_ballot),
-                hb_delay,
-                delta,
+                tick_period,
                 timer: None,
-                stopped: false,
                 stopped_peers: HashSet::with_capacity(n),
                 stop_ask: None,
-                quick_timeout,
-                initial_election_factor,
+                link_filter: LinkFilter::default(),
             }
         }
 
-        fn check_leader(&mut self) {
-            let mut ballots = Vec::with_capacity(self.peers.len());
-            std::mem::swap(&mut self.ballots, &mut ballots);
-            let (top_ballot, top_pid) = ballots.into_iter().max().unwrap();
-            if top_ballot < self.max_ballot {
-                // did not get HB from leader
-                self.current_ballot.n = self.max_ballot.n + 1;
-                self.leader = None;
-            } else if self.leader != Some((top_ballot, top_pid)) {
-                // got a new leader with greater ballot
-                self.quick_timeout = false;
-                self.max_ballot = top_ballot;
-                self.leader = Some((top_ballot, top_pid));
-                self.ble_port.trigger(Leader::with(top_pid, top_ballot));
+        fn tick(&mut self) -> Handled {
+            if let Some(leader) = self.ble.tick() {
+                self.ble_port.trigger(leader);
             }
+            self.send_outgoing();
+            Handled::Ok
         }
 
-        fn hb_timeout(&mut self) -> Handled {
-            if self.ballots.len() + 1 >= self.majority {
-                self.ballots.push((self.current_ballot, self.pid));
-                self.check_leader();
-            } else {
-                self.ballots.clear();
-            }
-            let delay = if self.quick_timeout {
-                // use short timeout if still no first leader
-                self.hb_delay / self.initial_election_factor
-            } else {
-                self.hb_delay
-            };
-            self.round += 1;
-            for peer in &self.peers {
-                let hb_request = HeartbeatRequest::with(self.round, self.max_ballot);
-                peer.tell_serialised(HeartbeatMsg::Request(hb_request), self)
-                    .expect("HBRequest should serialise!");
+        fn send_outgoing(&mut self) {
+            for (to, hb) in self.ble.outgoing() {
+                match self.peers.get(&to) {
+                    Some(receiver) if self.link_filter.is_empty() => receiver
+                        .tell_serialised(hb, self)
+                        .expect("HeartbeatMsg should serialise!"),
+                    Some(receiver) => {
+                        let receiver = receiver.clone();
+                        self.send_hb(to, receiver, hb);
+                    }
+                    None => {
+                        debug!(self.ctx.log(), "Dropping heartbeat to unknown peer {}", to);
+                    }
+                }
             }
-            self.start_timer(delay);
-            Handled::Ok
         }
 
-        fn start_timer(&mut self, t: u64) {
-            let timer = self.schedule_once(Duration::from_millis(t), move |c, _| c.hb_timeout());
-            self.timer = Some(timer);
+        /// Sends a heartbeat message through the injected network faults, if any.
+        fn send_hb(&mut self, to: u64, receiver: ActorPath, hb: HeartbeatMsg) {
+            if let Delivery::Send { copies, delay, .. } = self.link_filter.delivery(to) {
+                for _ in 0..copies {
+                    match delay {
+                        Some(delay) => {
+                            let receiver = receiver.clone();
+                            let hb = hb.clone();
+                            self.schedule_once(delay, move |c, _| {
+                                receiver
+                                    .tell_serialised(hb, &*c)
+                                    .expect("HeartbeatMsg should serialise!");
+                                Handled::Ok
+                            });
+                        }
+                        None => receiver
+                            .tell_serialised(hb.clone(), self)
+                            .expect("HeartbeatMsg should serialise!"),
+                    }
+                }
+            }
         }
 
         fn stop_timer(&mut self) {
@@ -2453,21 +3213,12 @@ mod ballot_leader_election {
 
     impl ComponentLifecycle for BallotLeaderComp {
         fn on_start(&mut self) -> Handled {
-            // info!(self.ctx.log(), "Started BLE with params: current_ballot: {:?}, quick timeout: {}, round: {}, max_ballot: {:?}", self.current_ballot, self.quick_timeout, self.round, self.max_ballot);
             let bc = BufferConfig::default();
             self.ctx.borrow().init_buffers(Some(bc), None);
-            for peer in &self.peers {
-                let hb_request = HeartbeatRequest::with(self.round, self.max_ballot);
-                peer.tell_serialised(HeartbeatMsg::Request(hb_request), self)
-                    .expect("HBRequest should serialise!");
-            }
-            let delay = if self.quick_timeout {
-                // use short timeout if still no first leader
-                self.hb_delay / self.initial_election_factor
-            } else {
-                self.hb_delay
-            };
-            self.start_timer(delay);
+            self.send_outgoing();
+            let period = Duration::from_millis(self.tick_period);
+            let timer = self.schedule_periodic(period, period, move |c, _| c.tick());
+            self.timer = Some(timer);
             Handled::Ok
         }
 
@@ -2484,54 +3235,48 @@ mod ballot_leader_election {
     }
 
     impl Actor for BallotLeaderComp {
-        type Message = Stop;
-
-        fn receive_local(&mut self, stop: Stop) -> Handled {
-            let (pid, late_stop) = *stop.0.request();
-            self.stop_timer();
-            for peer in &self.peers {
-                peer.tell_serialised(NetStopMsg::Peer(pid), self)
-                    .expect("NetStopMsg should serialise!");
-            }
-            self.stopped = true;
-            if self.stopped_peers.len() == self.peers.len() {
-                stop.0.reply(()).expect("Failed to reply to stop ask!");
-            } else {
-                self.stop_ask = Some(stop.0);
-                if late_stop {
-                    self.schedule_once(Duration::from_secs(3), move |c, _| c.stop_if_pending());
+        type Message = BallotLeaderMsg;
+
+        fn receive_local(&mut self, msg: BallotLeaderMsg) -> Handled {
+            match msg {
+                BallotLeaderMsg::Stop(stop) => {
+                    let (pid, late_stop) = *stop.request();
+                    self.stop_timer();
+                    self.ble.stop();
+                    for peer in self.peers.values() {
+                        peer.tell_serialised(NetStopMsg::Peer(pid), self)
+                            .expect("NetStopMsg should serialise!");
+                    }
+                    if self.stopped_peers.len() == self.peers.len() {
+                        stop.reply(()).expect("Failed to reply to stop ask!");
+                    } else {
+                        self.stop_ask = Some(stop);
+                        if late_stop {
+                            self.schedule_once(Duration::from_secs(3), move |c, _| {
+                                c.stop_if_pending()
+                            });
+                        }
+                    }
                 }
+                BallotLeaderMsg::LinkFault(lf) => self.link_filter.handle(lf),
+                BallotLeaderMsg::TakeLeadership => self.ble.take_leadership(),
             }
             Handled::Ok
         }
 
         fn receive_network(&mut self, m: NetMessage) -> Handled {
-            let NetMessage { sender, data, .. } = m;
+            let NetMessage { data, .. } = m;
             match_deser! {data; {
                 hb: HeartbeatMsg [BallotLeaderSer] => {
-                    match hb {
-                        HeartbeatMsg::Request(req) if !self.stopped => {
-                            if req.max_ballot > self.max_ballot {
-                                self.max_ballot = req.max_ballot;
-                            }
-                            let hb_reply = HeartbeatReply::with(self.pid, req.round, self.current_ballot);
-                            sender.tell_serialised(HeartbeatMsg::Reply(hb_reply), self).expect("HBReply should serialise!");
-                        },
-                        HeartbeatMsg::Reply(rep) if !self.stopped => {
-                            if rep.round == self.round {
-                                self.ballots.push((rep.max_ballot, rep.sender_pid));
-                            } else {
-                                debug!(self.ctx.log(), "Got late hb reply. HB delay: {}", self.hb_delay);
-                                self.hb_delay += self.delta;
-                            }
-                        },
-                        _ => {},
+                    if let Some(leader) = self.ble.handle(hb) {
+                        self.ble_port.trigger(leader);
                     }
+                    self.send_outgoing();
                 },
                 stop: NetStopMsg [StopMsgDeser] => {
                     if let NetStopMsg::Peer(pid) = stop {
                         assert!(self.stopped_peers.insert(pid), "Got duplicate stop from peer {}", pid);
-                        if self.stopped && self.stopped_peers.len() == self.peers.len() {
+                        if self.ble.stopped() && self.stopped_peers.len() == self.peers.len() {
                             self.stop_ask.take().expect("No stop ask!").reply(()).expect("Failed to reply ask");
                         }
                     }
@@ -2543,6 +3288,387 @@ mod ballot_leader_election {
             Handled::Ok
         }
     }
+
+    /// The ballot leader election as a state machine without timers or networking, similar to the `RawNode` of
+    /// tikv raft: the owner calls `tick()` periodically, passes it the heartbeats of the peers and sends the
+    /// heartbeats returned by `outgoing()`. All delays are in ticks.
+    pub mod raw_ble {
+        use super::super::super::messages::paxos::ballot_leader_election::*;
+
+        pub struct BallotLeaderElection {
+            pid: u64,
+            peers: Vec<u64>,
+            round: u64,
+            ballots: Vec<(Ballot, u64)>,
+            current_ballot: Ballot, // (round, pid)
+            leader: Option<(Ballot, u64)>,
+            max_ballot: Ballot,
+            hb_delay: u64,
+            delta: u64,
+            quorum: usize, // heartbeat replies, including its own, that a round needs to elect a leader
+            ticks_elapsed: u64,
+            timeout: u64, // ticks until the current round ends
+            quick_timeout: bool,
+            initial_election_factor: u64,
+            headstart: bool, // elect the first leader as soon as all peers replied
+            stopped: bool,
+            outgoing: Vec<(u64, HeartbeatMsg)>,
+        }
+
+        impl BallotLeaderElection {
+            pub fn with(
+                pid: u64,
+                peers: Vec<u64>,
+                quorum: usize,
+                hb_delay: u64,
+                delta: u64,
+                quick_timeout: bool,
+                initial_max_ballot: Option<Ballot>,
+                initial_election_factor: u64,
+                headstart: bool,
+            ) -> BallotLeaderElection {
+                let n = peers.len() + 1;
+                let initial_round = match initial_max_ballot {
+                    Some(ballot) if ballot.pid == pid => ballot.n,
+                    _ => 0,
+                };
+                let initial_ballot = Ballot::with(initial_round, pid);
+                let mut ble = BallotLeaderElection {
+                    pid,
+                    peers,
+                    round: initial_round,
+                    ballots: Vec::with_capacity(n),
+                    current_ballot: initial_ballot,
+                    leader: None,
+                    max_ballot: initial_max_ballot.unwrap_or(initial_ballot),
+                    hb_delay,
+                    delta,
+                    quorum,
+                    ticks_elapsed: 0,
+                    timeout: 0,
+                    quick_timeout,
+                    initial_election_factor,
+                    headstart,
+                    stopped: false,
+                    outgoing: Vec::with_capacity(n),
+                };
+                ble.new_round();
+                ble
+            }
+
+            /// Advances the logical clock by one tick. Returns the new leader if the round that ended elected one.
+            pub fn tick(&mut self) -> Option<Leader> {
+                if self.stopped {
+                    return None;
+                }
+                self.ticks_elapsed += 1;
+                if self.ticks_elapsed < self.timeout {
+                    return None;
+                }
+                let leader = if self.ballots.len() + 1 >= self.quorum {
+                    self.ballots.push((self.current_ballot, self.pid));
+                    self.check_leader()
+                } else {
+                    self.ballots.clear();
+                    None
+                };
+                self.round += 1;
+                self.new_round();
+                leader
+            }
+
+            /// Handles a heartbeat from a peer. Returns the new leader if the heartbeat completed an election.
+            pub fn handle(&mut self, hb: HeartbeatMsg) -> Option<Leader> {
+                if self.stopped {
+                    return None;
+                }
+                match hb {
+                    HeartbeatMsg::Request(req) => {
+                        if req.max_ballot > self.max_ballot {
+                            self.max_ballot = req.max_ballot;
+                        }
+                        let hb_reply =
+                            HeartbeatReply::with(self.pid, req.round, self.current_ballot);
+                        self.outgoing
+                            .push((req.sender_pid, HeartbeatMsg::Reply(hb_reply)));
+                        None
+                    }
+                    HeartbeatMsg::Reply(rep) => {
+                        if rep.round == self.round {
+                            // replies might be duplicated by an injected network fault
+                            if self.ballots.iter().all(|(_, pid)| *pid != rep.sender_pid) {
+                                self.ballots.push((rep.max_ballot, rep.sender_pid));
+                            }
+                            if self.headstart
+                                && self.quick_timeout
+                                && self.ballots.len() == self.peers.len()
+                            {
+                                // no need to wait for the timeout of the first election
+                                self.ballots.push((self.current_ballot, self.pid));
+                                return self.check_leader();
+                            }
+                        } else {
+                            // the peer is slower than expected, give it more time in the next rounds
+                            self.hb_delay += self.delta;
+                        }
+                        None
+                    }
+                }
+            }
+
+            /// The heartbeats to send, as (receiver pid, heartbeat).
+            pub fn outgoing(&mut self) -> Vec<(u64, HeartbeatMsg)> {
+                std::mem::take(&mut self.outgoing)
+            }
+
+            pub fn stop(&mut self) {
+                self.stopped = true;
+                self.outgoing.clear();
+            }
+
+            /// Raises the ballot above the highest one seen, so that the peers elect this node when the heartbeats of
+            /// the next round reach them.
+            pub fn take_leadership(&mut self) {
+                if !self.stopped {
+                    self.current_ballot.n = self.max_ballot.n + 1;
+                }
+            }
+
+            pub fn stopped(&self) -> bool {
+                self.stopped
+            }
+
+            fn new_round(&mut self) {
+                let hb_request = HeartbeatRequest::with(self.pid, self.round, self.max_ballot);
+                for peer in &self.peers {
+                    self.outgoing
+                        .push((*peer, HeartbeatMsg::Request(hb_request.clone())));
+                }
+                self.ticks_elapsed = 0;
+                self.timeout = if self.quick_timeout {
+                    // use short timeout if still no first leader
+                    std::cmp::max(self.hb_delay / self.initial_election_factor, 1)
+                } else {
+                    self.hb_delay
+                };
+            }
+
+            fn check_leader(&mut self) -> Option<Leader> {
+                let ballots = std::mem::take(&mut self.ballots);
+                let (top_ballot, top_pid) = ballots.into_iter().max().unwrap();
+                if top_ballot < self.max_ballot {
+                    // did not get HB from leader
+                    self.current_ballot.n = self.max_ballot.n + 1;
+                    self.leader = None;
+                    None
+                } else if self.leader != Some((top_ballot, top_pid)) {
+                    // got a new leader with greater ballot
+                    self.quick_timeout = false;
+                    self.max_ballot = top_ballot;
+                    self.leader = Some((top_ballot, top_pid));
+                    Some(Leader::with(top_pid, top_ballot))
+                } else {
+                    None
+                }
+            }
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+            use std::collections::BTreeMap;
+
+            /// Ticks all nodes and delivers their heartbeats immediately, except to and from the nodes in `down`.
+            /// Returns the leaders that were elected in this tick.
+            fn tick_all(
+                nodes: &mut BTreeMap<u64, BallotLeaderElection>,
+                down: &[u64],
+            ) -> Vec<(u64, Leader)> {
+                let mut elected = vec![];
+                for (pid, ble) in nodes.iter_mut() {
+                    if let Some(l) = ble.tick() {
+                        elected.push((*pid, l));
+                    }
+                }
+                loop {
+                    let mut msgs = vec![];
+                    for (pid, ble) in nodes.iter_mut() {
+                        for (to, hb) in ble.outgoing() {
+                            if !down.contains(pid) && !down.contains(&to) {
+                                msgs.push((to, hb));
+                            }
+                        }
+                    }
+                    if msgs.is_empty() {
+                        return elected;
+                    }
+                    for (to, hb) in msgs {
+                        if let Some(l) = nodes.get_mut(&to).unwrap().handle(hb) {
+                            elected.push((to, l));
+                        }
+                    }
+                }
+            }
+
+            fn create_nodes(
+                n: u64,
+                quorum: usize,
+                hb_delay: u64,
+                quick_timeout: bool,
+                headstart: bool,
+            ) -> BTreeMap<u64, BallotLeaderElection> {
+                (1..=n)
+                    .map(|pid| {
+                        let peers = (1..=n).filter(|p| *p != pid).collect();
+                        let ble = BallotLeaderElection::with(
+                            pid,
+                            peers,
+                            quorum,
+                            hb_delay,
+                            1,
+                            quick_timeout,
+                            None,
+                            10,
+                            headstart,
+                        );
+                        (pid, ble)
+                    })
+                    .collect()
+            }
+
+            /// Ticks until some node elects a leader and returns the number of ticks and the elected leaders.
+            fn ticks_until_elected(
+                nodes: &mut BTreeMap<u64, BallotLeaderElection>,
+                down: &[u64],
+            ) -> (u64, Vec<(u64, Leader)>) {
+                for ticks in 1..=1000 {
+                    let elected = tick_all(nodes, down);
+                    if !elected.is_empty() {
+                        return (ticks, elected);
+                    }
+                }
+                panic!("No leader was elected");
+            }
+
+            #[test]
+            fn ble_election_test() {
+                let mut nodes = create_nodes(3, 2, 10, false, false);
+                let (ticks, elected) = ticks_until_elected(&mut nodes, &[]);
+                assert_eq!(ticks, 10);
+                assert_eq!(elected.len(), 3);
+                for (_, l) in &elected {
+                    assert_eq!(l.pid, 3);
+                    assert_eq!(l.ballot, Ballot::with(0, 3));
+                }
+                // the leader keeps its position as long as it is connected
+                for _ in 0..100 {
+                    assert!(tick_all(&mut nodes, &[]).is_empty());
+                }
+                // the remaining majority elects a new leader with a higher ballot
+                let (_, elected) = ticks_until_elected(&mut nodes, &[3]);
+                assert_eq!(elected.len(), 2);
+                for (_, l) in &elected {
+                    assert_eq!(l.pid, 2);
+                    assert!(l.ballot > Ballot::with(0, 3));
+                }
+                // a minority can not elect anyone
+                for _ in 0..100 {
+                    assert!(tick_all(&mut nodes, &[2, 3]).is_empty());
+                }
+            }
+
+            #[test]
+            fn ble_quick_timeout_test() {
+                let mut nodes = create_nodes(3, 2, 100, true, false);
+                // the first leader is elected after hb_delay / initial_election_factor
+                let (ticks, elected) = ticks_until_elected(&mut nodes, &[]);
+                assert_eq!(ticks, 10);
+                assert_eq!(elected.len(), 3);
+                // and then the full delay is used
+                let (ticks, elected) = ticks_until_elected(&mut nodes, &[3]);
+                assert!(ticks > 100, "Elected a new leader after {} ticks", ticks);
+                assert_eq!(elected.len(), 2);
+            }
+
+            #[test]
+            fn ble_headstart_test() {
+                let mut nodes = create_nodes(3, 2, 100, true, true);
+                // elected as soon as all replies arrived, without any timeout
+                let (ticks, elected) = ticks_until_elected(&mut nodes, &[]);
+                assert_eq!(ticks, 1);
+                assert_eq!(elected.len(), 3);
+                assert!(elected.iter().all(|(_, l)| l.pid == 3));
+                // headstart only applies before the first leader
+                let mut nodes = create_nodes(3, 2, 100, false, true);
+                let (ticks, _) = ticks_until_elected(&mut nodes, &[]);
+                assert_eq!(ticks, 100);
+            }
+
+            #[test]
+            fn ble_phase1_quorum_test() {
+                // with a phase 1 quorum of 4 out of 5, the leader needs the heartbeats of all but one node
+                let mut nodes = create_nodes(5, 4, 10, false, false);
+                let (_, elected) = ticks_until_elected(&mut nodes, &[5]);
+                assert_eq!(elected.len(), 4);
+                assert!(elected.iter().all(|(_, l)| l.pid == 4));
+                // a majority is not enough
+                for _ in 0..100 {
+                    assert!(tick_all(&mut nodes, &[4, 5]).is_empty());
+                }
+            }
+
+            #[test]
+            fn ble_take_leadership_test() {
+                let mut nodes = create_nodes(3, 2, 10, false, false);
+                let (_, elected) = ticks_until_elected(&mut nodes, &[]);
+                assert!(elected.iter().all(|(_, l)| l.pid == 3));
+                nodes.get_mut(&1).unwrap().take_leadership();
+                // node 1 elects itself at the end of the round, the peers when they got its raised ballot
+                let mut elected = BTreeMap::new();
+                for _ in 0..30 {
+                    elected.extend(tick_all(&mut nodes, &[]));
+                }
+                assert_eq!(elected.len(), 3);
+                for l in elected.values() {
+                    assert_eq!(l.pid, 1);
+                    assert!(l.ballot > Ballot::with(0, 3));
+                }
+            }
+
+            #[test]
+            fn ble_late_reply_test() {
+                let mut ble =
+                    BallotLeaderElection::with(1, vec![2, 3], 2, 10, 5, false, None, 10, false);
+                ble.outgoing();
+                for _ in 0..10 {
+                    assert!(ble.tick().is_none());
+                }
+                assert_eq!(ble.outgoing().len(), 2);
+                // a reply to the previous round increases the delay of the next rounds
+                let late = HeartbeatReply::with(2, 0, Ballot::with(0, 2));
+                assert!(ble.handle(HeartbeatMsg::Reply(late)).is_none());
+                for _ in 0..10 {
+                    ble.tick();
+                }
+                // the round that was already started keeps its timeout
+                assert_eq!(ble.outgoing().len(), 2);
+                for _ in 0..14 {
+                    ble.tick();
+                }
+                assert!(ble.outgoing().is_empty());
+                ble.tick();
+                assert_eq!(ble.outgoing().len(), 2);
+                // a stopped election neither times out nor replies
+                ble.stop();
+                let req = HeartbeatRequest::with(2, 3, Ballot::with(0, 2));
+                assert!(ble.handle(HeartbeatMsg::Request(req)).is_none());
+                for _ in 0..100 {
+                    ble.tick();
+                }
+                assert!(ble.outgoing().is_empty());
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -2592,6 +3718,60 @@ mod tests {
         (systems, nodes, actorpaths)
     }
 
+    #[test]
+    fn paxos_options_test() {
+        let default = PaxosOptions::default();
+        assert_eq!(
+            default.to_string(),
+            "batch_accept+latest_decide+latest_accepted+max_accsync"
+        );
+        assert_eq!(PaxosOptions::parse(&default.to_string(), 5), Ok(default));
+        assert_eq!(PaxosOptions::parse("none", 5), Ok(PaxosOptions::none()));
+        let headstart = PaxosOptions::parse("headstart_ble", 5).unwrap();
+        assert!(headstart.headstart_ble && !headstart.batch_accept);
+        assert!(PaxosOptions::parse("batch_accept+fast", 5).is_err());
+        let flexible = PaxosOptions {
+            quorums: Quorums::Flexible {
+                phase1: 4,
+                phase2: 2,
+            },
+            ..PaxosOptions::none()
+        };
+        assert_eq!(flexible.to_string(), "phase1_quorum=4+phase2_quorum=2");
+        assert_eq!(PaxosOptions::parse(&flexible.to_string(), 5), Ok(flexible));
+        assert!(PaxosOptions::parse("max_accsync+phase2_quorum=2", 5).is_err());
+        assert!(PaxosOptions::parse("phase1_quorum=x+phase2_quorum=2", 5).is_err());
+        // the quorums must intersect for the given number of nodes
+        assert!(PaxosOptions::parse(&flexible.to_string(), 6).is_err());
+        assert!(PaxosOptions::parse("phase1_quorum=1+phase2_quorum=1", 3).is_err());
+        assert!(PaxosOptions::parse("phase1_quorum=0+phase2_quorum=3", 3).is_err());
+    }
+
+    #[test]
+    fn quorums_test() {
+        assert_eq!(Quorums::with(0, 0, 5), Ok(Quorums::Majority));
+        assert_eq!(Quorums::Majority.phase1(5), 3);
+        assert_eq!(Quorums::Majority.phase2(4), 3);
+        let flexible = Quorums::Flexible {
+            phase1: 4,
+            phase2: 2,
+        };
+        assert_eq!(Quorums::with(4, 2, 5), Ok(flexible));
+        // an unset size is the smallest that intersects the other one
+        assert_eq!(Quorums::with(0, 2, 5), Ok(flexible));
+        assert_eq!(
+            Quorums::with(5, 0, 5),
+            Ok(Quorums::Flexible {
+                phase1: 5,
+                phase2: 1
+            })
+        );
+        assert_eq!((flexible.phase1(5), flexible.phase2(5)), (4, 2));
+        assert!(Quorums::with(3, 2, 5).is_err());
+        assert!(Quorums::with(6, 1, 5).is_err());
+        assert!(Quorums::with(0, 6, 5).is_err());
+    }
+
     #[test]
     fn paxos_test() {
         let num_proposals = 4000;
@@ -1,17 +1,22 @@
 extern crate raft as tikv_raft;
 
+use super::fault::{LinkFault, LinkFaultMsg};
+use super::kv::{is_read, KvStore, ReadMode};
 use super::messages::{StopMsg as NetStopMsg, StopMsgDeser, *};
 use super::storage::raft::*;
+use super::storage::CompactionPolicy;
 use crate::bench::atomic_broadcast::atomic_broadcast::Done;
 use crate::bench::atomic_broadcast::communicator::{
     AtomicBroadcastCompMsg, CommunicationPort, Communicator, CommunicatorMsg,
 };
 use crate::partitioning_actor::{PartitioningActorMsg, PartitioningActorSer};
 use crate::serialiser_ids::ATOMICBCAST_ID;
+use benchmark_suite_shared::test_utils::{DecidedEntry, DecidedSequence};
 use hashbrown::{HashMap, HashSet};
 use kompact::prelude::*;
+use kompact::KompactLogger;
 use protobuf::Message as PbMessage;
-use rand::Rng;
+use rand::{rngs::SmallRng, Rng, SeedableRng};
 use std::{borrow::Borrow, clone::Clone, marker::Send, ops::DerefMut, sync::Arc, time::Duration};
 use tikv_raft::{prelude::Entry, prelude::Message as TikvRaftMsg, prelude::*, StateRole};
 
@@ -39,16 +44,32 @@ where
     iteration_id: u32,
     stopped: bool,
     partitioning_actor: Option<ActorPath>,
-    cached_client: Option<ActorPath>,
+    cached_clients: Option<Clients>,
     current_leader: u64,
     reconfig_policy: ReconfigurationPolicy,
+    storage_dir: Option<String>,
+    storage: Option<S>,
+    compaction: CompactionPolicy,
+    kv: Option<ReadMode>, // apply the decided commands to a key-value store and serve its reads this way
+    initial_leader: u64,  // the node that should win the first election, 0 if any
+    crashed: bool,        // the replica was killed by an injected fault
+    recovery_timer: Option<ScheduledTimer>,
+    link_faults: Vec<LinkFault>, // injected network faults, also applied to a recovered communicator
 }
 
 impl<S> RaftComp<S>
 where
     S: RaftStorage + Send + Clone + 'static,
 {
-    pub fn with(initial_config: Vec<u64>, reconfig_policy: ReconfigurationPolicy) -> Self {
+    /// `storage_dir` is where disk-backed storage keeps its files.
+    pub fn with(
+        initial_config: Vec<u64>,
+        reconfig_policy: ReconfigurationPolicy,
+        storage_dir: Option<String>,
+        compaction: CompactionPolicy,
+        kv: Option<ReadMode>,
+        initial_leader: u64,
+    ) -> Self {
         RaftComp {
             ctx: ComponentContext::uninitialised(),
             pid: 0,
@@ -59,13 +80,22 @@ where
             iteration_id: 0,
             stopped: false,
             partitioning_actor: None,
-            cached_client: None,
+            cached_clients: None,
             current_leader: 0,
             reconfig_policy,
+            storage_dir,
+            storage: None,
+            compaction,
+            kv,
+            initial_leader,
+            crashed: false,
+            recovery_timer: None,
+            link_faults: vec![],
         }
     }
 
-    fn create_rawraft_config(&self) -> Config {
+    /// `applied` is the last index the replica applied, i.e. delivered, before it was restarted.
+    fn create_rawraft_config(&self, applied: u64) -> Config {
         let config = self.ctx.config();
         let max_inflight_msgs = config["experiment"]["max_inflight"]
             .as_i64()
@@ -87,6 +117,10 @@ where
         let heartbeat_tick = leader_hb_period / tick_period;
         // info!(self.ctx.log(), "RawRaft config: election_tick={}, heartbeat_tick={}", election_tick, heartbeat_tick);
         let max_size_per_msg = max_batch_size;
+        let read_only_option = match self.kv {
+            Some(ReadMode::Lease) => ReadOnlyOption::LeaseBased,
+            _ => ReadOnlyOption::Safe,
+        };
         let c = Config {
             id: self.pid,
             election_tick,  // number of ticks without HB before starting election
@@ -94,13 +128,33 @@ where
             max_inflight_msgs,
             max_size_per_msg,
             batch_append: true,
+            applied,
+            read_only_option,
+            // a lease is only safe if the leader steps down when it stops hearing from a quorum
+            check_quorum: read_only_option == ReadOnlyOption::LeaseBased,
             ..Default::default()
         };
         assert!(c.validate().is_ok(), "Invalid RawRaft config");
         c
     }
 
-    fn create_components(&mut self) -> Handled {
+    /// The shortened timeout of the first election in ticks.
+    fn initial_timeout_ticks(&self) -> usize {
+        let config = self.ctx.config();
+        let tick_period = config["raft"]["tick_period"]
+            .as_i64()
+            .expect("Failed to load tick_period") as usize;
+        let election_timeout = config["experiment"]["election_timeout"]
+            .as_i64()
+            .expect("Failed to load election_timeout") as usize;
+        let initial_election_factor = config["experiment"]["initial_election_factor"]
+            .as_i64()
+            .expect("Failed to load initial_election_factor")
+            as usize;
+        (election_timeout / initial_election_factor) / tick_period
+    }
+
+    fn communicator_peers(&self) -> HashMap<u64, ActorPath> {
         let mut communicator_peers: HashMap<u64, ActorPath> =
             HashMap::with_capacity(self.peers.len());
         for (pid, ap) in &self.peers {
@@ -121,36 +175,74 @@ where
                 _ => unimplemented!(),
             }
         }
+        communicator_peers
+    }
 
+    /// Creates the replica and its communicator. If `recover` is set, the replica restarts
+    /// from the storage of a crashed one and is started directly.
+    fn create_components(&mut self, recover: bool) -> Handled {
+        let communicator_peers = self.communicator_peers();
         let system = self.ctx.system();
-        let dir = &format!("./diskstorage_node{}", self.pid);
-        let conf_state: (Vec<u64>, Vec<u64>) = (self.initial_config.clone(), vec![]);
-        let store = S::new_with_conf_state(Some(dir), conf_state);
-        let raw_raft =
-            RawNode::new(&self.create_rawraft_config(), store).expect("Failed to create tikv Raft");
+        let (store, applied) = if recover {
+            let store = self.storage.clone().expect("No storage to recover from");
+            let commit = store
+                .initial_state()
+                .expect("Failed to read recovered state")
+                .hard_state
+                .commit;
+            (store, commit)
+        } else {
+            let dir = self
+                .storage_dir
+                .as_ref()
+                .map(|d| format!("{}/raft{}", d, self.pid));
+            let conf_state: (Vec<u64>, Vec<u64>) = (self.initial_config.clone(), vec![]);
+            let store = S::new_with_conf_state(dir.as_deref(), conf_state);
+            self.storage = Some(store.clone()); // shares its state with the replica's, to read the stats
+            (store, 0)
+        };
+        let raw_raft = RawNode::new(&self.create_rawraft_config(applied), store)
+            .expect("Failed to create tikv Raft");
         let max_inflight = self.ctx.config()["experiment"]["max_inflight"]
             .as_i64()
             .expect("Failed to load max_inflight") as usize;
-        let (raft_replica, raft_f) = system.create_and_register(|| {
-            RaftReplica::with(
-                raw_raft,
-                self.actor_ref(),
-                self.reconfig_policy.clone(),
-                self.peers.len(),
-                max_inflight,
-            )
-        });
+        let mut core = RaftCore::with(
+            raw_raft,
+            self.ctx.log().new(o!("raft_core" => self.pid)),
+            self.reconfig_policy.clone(),
+            max_inflight,
+            self.compaction,
+            self.kv,
+            self.initial_timeout_ticks(),
+            SmallRng::from_entropy(),
+        );
+        if !recover && self.initial_leader == self.pid {
+            core.campaign_first();
+        }
+        let (raft_replica, raft_f) = system
+            .create_and_register(|| RaftReplica::with(core, self.actor_ref(), self.peers.len()));
         let (communicator, comm_f) = system.create_and_register(|| {
             Communicator::with(
                 communicator_peers,
-                self.cached_client
+                self.cached_clients
                     .as_ref()
-                    .expect("No cached client")
+                    .expect("No cached clients")
                     .clone(),
+                false,
             )
         });
+        for fault in &self.link_faults {
+            communicator
+                .actor_ref()
+                .tell(LinkFaultMsg::Apply(fault.clone()));
+        }
         let communicator_alias = format!("{}{}-{}", COMMUNICATOR, self.pid, self.iteration_id);
-        let comm_alias_f = system.register_by_alias(&communicator, communicator_alias);
+        let comm_alias_f = if recover {
+            // the alias is still registered to the crashed communicator
+            system.update_alias_registration(&communicator, communicator_alias)
+        } else {
+            system.register_by_alias(&communicator, communicator_alias)
+        };
         biconnect_components::<CommunicationPort, _, _>(&communicator, &raft_replica)
             .expect("Could not connect components!");
         self.raft_replica = Some(raft_replica);
@@ -168,6 +260,10 @@ where
                 .await
                 .unwrap()
                 .expect("Timed out registering communicator alias");
+            if recover {
+                async_self.start_components();
+                return;
+            }
             async_self
                 .partitioning_actor
                 .take()
@@ -190,8 +286,82 @@ where
         self.ctx.system().start(communicator);
     }
 
+    /// Kills the replica to inject a crash. If `downtime` is given, it is restarted from its storage after it.
+    fn crash(&mut self, downtime: Option<Duration>) -> Handled {
+        if self.stopped || self.crashed || self.raft_replica.is_none() {
+            return Handled::Ok;
+        }
+        info!(
+            self.ctx.log(),
+            "Crashing replica pid: {}, downtime: {:?}", self.pid, downtime
+        );
+        self.crashed = true;
+        self.current_leader = 0;
+        let system = self.ctx.system();
+        let mut kill_futures = Vec::with_capacity(2);
+        if let Some(raft) = self.raft_replica.take() {
+            kill_futures.push(system.kill_notify(raft));
+        }
+        if let Some(communicator) = self.communicator.take() {
+            kill_futures.push(system.kill_notify(communicator));
+        }
+        Handled::block_on(self, move |mut async_self| async move {
+            for f in kill_futures {
+                f.await.expect("Failed to kill crashed components");
+            }
+            if let Some(downtime) = downtime {
+                let timer = async_self.schedule_once(downtime, move |c, _| c.recover());
+                async_self.recovery_timer = Some(timer);
+            }
+        })
+    }
+
+    fn recover(&mut self) -> Handled {
+        self.recovery_timer = None;
+        if !self.crashed || self.stopped {
+            return Handled::Ok;
+        }
+        info!(self.ctx.log(), "Recovering replica pid: {}", self.pid);
+        self.crashed = false;
+        self.create_components(true)
+    }
+
+    /// Applies or heals an injected network fault on the links of the replica.
+    fn handle_link_fault(&mut self, msg: LinkFaultMsg) {
+        match &msg {
+            LinkFaultMsg::Apply(fault) => self.link_faults.push(fault.clone()),
+            LinkFaultMsg::Heal(id) => self.link_faults.retain(|f| f.id != *id),
+        }
+        if let Some(communicator) = self.communicator.as_ref() {
+            communicator.actor_ref().tell(msg);
+        }
+    }
+
+    /// Stops on behalf of a crashed replica, so that the peers and the client do not wait for it.
+    fn stop_crashed(&mut self) -> Handled {
+        self.crashed = false;
+        if let Some(timer) = self.recovery_timer.take() {
+            self.cancel_timer(timer);
+        }
+        for (_, comm_peer) in self.communicator_peers() {
+            comm_peer
+                .tell_serialised(NetStopMsg::Peer(self.pid), self)
+                .expect("NetStopMsg should serialise!");
+        }
+        self.cached_clients
+            .as_ref()
+            .expect("No cached clients!")
+            .primary()
+            .tell_serialised(NetStopMsg::Peer(self.pid), self)
+            .expect("NetStopMsg should serialise!");
+        Handled::Ok
+    }
+
     fn stop_components(&mut self) -> Handled {
         self.stopped = true;
+        if self.crashed {
+            return self.stop_crashed();
+        }
         // info!(self.ctx.log(), "Stopping components");
         let raft = self
             .raft_replica
@@ -218,10 +388,14 @@ where
             kill_futures.push(kill_comm);
         }
 
+        let storage = self.storage.take();
         Handled::block_on(self, move |_| async move {
             for f in kill_futures {
                 f.await.expect("Failed to kill");
             }
+            if let Some(mut s) = storage {
+                s.clear().expect("Failed to clear storage!");
+            }
             ask.reply(Done).expect("Failed to reply done");
         })
     }
@@ -238,13 +412,18 @@ where
     fn receive_local(&mut self, msg: Self::Message) -> Handled {
         match msg {
             RaftCompMsg::Leader(notify_client, pid) => {
+                if self.crashed {
+                    return Handled::Ok; // from the killed replica
+                }
                 debug!(self.ctx.log(), "Node {} became leader", pid);
-                if notify_client {
-                    self.cached_client
-                        .as_ref()
-                        .expect("No cached client!")
-                        .tell_serialised(AtomicBroadcastMsg::FirstLeader(pid), self)
-                        .expect("Should serialise FirstLeader");
+                // also notify client if we took over from a crashed leader
+                if notify_client || (pid == self.pid && self.current_leader != pid) {
+                    let clients = self.cached_clients.as_ref().expect("No cached clients!");
+                    for client in clients.all() {
+                        client
+                            .tell_serialised(AtomicBroadcastMsg::FirstLeader(pid), self)
+                            .expect("Should serialise FirstLeader");
+                    }
                 }
                 self.current_leader = pid
             }
@@ -269,7 +448,7 @@ where
     fn receive_network(&mut self, m: NetMessage) -> Handled {
         match m.data.ser_id {
             ATOMICBCAST_ID => {
-                if !self.stopped {
+                if !self.stopped && !self.crashed {
                     if self.current_leader == self.pid || self.current_leader == 0 {
                         // if no leader, let raftcomp hold back
                         if let AtomicBroadcastMsg::Proposal(p) = m
@@ -303,14 +482,19 @@ where
                                 self.current_leader = 0;
                                 self.iteration_id = init.init_id;
                                 let my_pid = init.pid as u64;
-                                let ser_client = init.init_data.expect("Init should include ClientComp's actorpath");
-                                let client = ActorPath::deserialise(&mut ser_client.as_slice()).expect("Failed to deserialise Client's actorpath");
-                                self.cached_client = Some(client);
+                                let ser_clients = init.init_data.expect("Init should include the ClientComps' actorpaths");
+                                let clients = Clients::deserialise(ser_clients.as_slice()).expect("Failed to deserialise Clients' actorpaths");
+                                self.cached_clients = Some(clients);
                                 self.peers = init.nodes.into_iter().enumerate().map(|(idx, ap)| (idx as u64 + 1, ap)).filter(|(pid, _)| pid != &my_pid).collect();
                                 self.pid = my_pid;
                                 self.partitioning_actor = Some(sender);
                                 self.stopped = false;
-                                let handled = self.create_components();
+                                self.crashed = false;
+                                self.link_faults.clear();
+                                if let Some(timer) = self.recovery_timer.take() {
+                                    self.cancel_timer(timer);
+                                }
+                                let handled = self.create_components(false);
                                 return handled;
                             },
                             PartitioningActorMsg::Run => {
@@ -326,14 +510,34 @@ where
                             return self.stop_components();
                         }
                     },
+                    fault: FaultMsg [FaultMsgDeser] => {
+                        match fault {
+                            FaultMsg::Crash(downtime) => return self.crash(downtime),
+                            FaultMsg::Link(link_fault) => self.handle_link_fault(LinkFaultMsg::Apply(link_fault)),
+                            FaultMsg::Heal(id) => self.handle_link_fault(LinkFaultMsg::Heal(id)),
+                            FaultMsg::TransferLeadership => {
+                                if let Some(raft_replica) = self.raft_replica.as_ref() {
+                                    info!(self.ctx.log(), "Taking over the leadership");
+                                    raft_replica.actor_ref().tell(RaftReplicaMsg::TransferLeadership);
+                                }
+                            }
+                        }
+                    },
                     tm: TestMessage [TestMessageSer] => {
                         match tm {
                             TestMessage::SequenceReq => {
-                                if let Some(raft_replica) = self.raft_replica.as_ref() {
-                                    let seq = raft_replica.actor_ref().ask(|promise| RaftReplicaMsg::SequenceReq(Ask::new(promise, ()))).wait();
-                                    let sr = SequenceResp::with(self.pid, seq);
-                                    sender.tell((TestMessage::SequenceResp(sr), TestMessageSer), self);
-                                }
+                                // a crashed replica answers with an empty sequence, so that the client does not wait for it
+                                let seq = match self.raft_replica.as_ref() {
+                                    Some(raft_replica) => raft_replica.actor_ref().ask(|promise| RaftReplicaMsg::SequenceReq(Ask::new(promise, ()))).wait(),
+                                    None => DecidedSequence::default(),
+                                };
+                                let sr = SequenceResp::with(self.pid, seq);
+                                sender.tell((TestMessage::SequenceResp(sr), TestMessageSer), self);
+                            },
+                            TestMessage::IoStatsReq => {
+                                // the storage is shared with the replica and kept if it crashed
+                                let io_stats = self.storage.as_ref().map(|s| s.io_stats()).unwrap_or_default();
+                                sender.tell((TestMessage::IoStatsResp(self.pid, io_stats), TestMessageSer), self);
                             },
                             _ => error!(self.ctx.log(), "Got unexpected TestMessage: {:?}", tm),
                         }
@@ -351,13 +555,16 @@ where
 pub enum RaftReplicaMsg {
     Propose(Proposal),
     Stop(Ask<(), ()>),
-    SequenceReq(Ask<(), Vec<u64>>),
+    SequenceReq(Ask<(), DecidedSequence>),
+    TransferLeadership,
 }
 
 #[derive(Clone, Debug)]
 pub enum ReconfigurationPolicy {
     ReplaceLeader,
     ReplaceFollower,
+    /// Reconfigures to the requested voters and learners as they are.
+    Exact,
 }
 
 #[derive(Debug, PartialEq)]
@@ -381,18 +588,12 @@ where
 {
     ctx: ComponentContext<Self>,
     supervisor: ActorRef<RaftCompMsg>,
-    state: State,
-    raw_raft: RawNode<S>,
+    core: RaftCore<S>,
     communication_port: RequiredPort<CommunicationPort>,
     timers: Option<(ScheduledTimer, ScheduledTimer)>,
-    reconfig_state: ReconfigurationState,
-    current_leader: u64,
-    reconfig_policy: ReconfigurationPolicy,
     num_peers: usize,
     stopped: bool,
     stopped_peers: HashSet<u64>,
-    hb_proposals: Vec<Proposal>,
-    max_inflight: usize,
     stop_ask: Option<Ask<(), ()>>,
 }
 
@@ -408,12 +609,8 @@ where
     }
 
     fn on_kill(&mut self) -> Handled {
-        // info!(self.ctx.log(), "Got kill! RaftLog commited: {}", self.raw_raft.raft.raft_log.committed);
+        // the storage is kept, a crashed replica recovers from it. RaftComp clears it after the iteration.
         self.stop_timers();
-        self.raw_raft
-            .mut_store()
-            .clear()
-            .expect("Failed to clear storage!");
         Handled::Ok
     }
 }
@@ -427,35 +624,26 @@ where
     fn receive_local(&mut self, msg: Self::Message) -> Handled {
         match msg {
             RaftReplicaMsg::Propose(p) => {
-                if self.reconfig_state != ReconfigurationState::Removed {
-                    self.propose(p);
-                }
+                self.core.propose(p);
+                self.handle_effects();
+            }
+            RaftReplicaMsg::TransferLeadership => {
+                self.core.transfer_leadership();
+                self.handle_effects();
             }
             RaftReplicaMsg::SequenceReq(sr) => {
-                let raft_entries: Vec<Entry> = self.raw_raft.raft.raft_log.all_entries();
-                let mut sequence: Vec<u64> = Vec::with_capacity(raft_entries.len());
-                let mut unique = HashSet::new();
-                for entry in raft_entries {
-                    if entry.get_entry_type() == EntryType::EntryNormal && !&entry.data.is_empty() {
-                        let id = entry.data.as_slice().get_u64();
-                        if id != 0 {
-                            sequence.push(id);
-                        }
-                        unique.insert(id);
-                    }
-                }
+                let sequence = self.core.decided_sequence();
                 info!(
                     self.ctx.log(),
-                    "Got SequenceReq: my seq_len={}. Unique={}",
-                    sequence.len(),
-                    unique.len()
+                    "Got SequenceReq: my seq_len={}",
+                    sequence.entries.len()
                 );
                 sr.reply(sequence)
                     .expect("Failed to respond SequenceReq ask");
             }
             RaftReplicaMsg::Stop(ask) => {
                 self.communication_port
-                    .trigger(CommunicatorMsg::SendStop(self.raw_raft.raft.id, true));
+                    .trigger(CommunicatorMsg::SendStop(self.core.pid(), true));
                 self.stop_timers();
                 self.stopped = true;
                 if self.stopped_peers.len() == self.num_peers {
@@ -480,10 +668,8 @@ where
 {
     fn handle(&mut self, msg: AtomicBroadcastCompMsg) -> Handled {
         match msg {
-            AtomicBroadcastCompMsg::RawRaftMsg(rm)
-                if !self.stopped && self.reconfig_state != ReconfigurationState::Removed =>
-            {
-                self.step(rm);
+            AtomicBroadcastCompMsg::RawRaftMsg(rm) if !self.stopped => {
+                self.core.step(rm);
             }
             AtomicBroadcastCompMsg::StopMsg(from_pid) => {
                 assert!(
@@ -491,7 +677,6 @@ where
                     "Got duplicate stop from {}",
                     from_pid
                 );
-                // info!(self.ctx.log(), "Got stop from {}. received: {}, num_peers: {}", from_pid, self.stopped_peers.len(), self.num_peers);
                 if self.stopped_peers.len() == self.num_peers && self.stopped {
                     self.stop_ask
                         .take()
@@ -511,27 +696,19 @@ where
     S: RaftStorage + Send + Clone + 'static,
 {
     pub fn with(
-        raw_raft: RawNode<S>,
+        core: RaftCore<S>,
         replica: ActorRef<RaftCompMsg>,
-        reconfig_policy: ReconfigurationPolicy,
         num_peers: usize,
-        max_inflight: usize,
     ) -> RaftReplica<S> {
         RaftReplica {
             ctx: ComponentContext::uninitialised(),
             supervisor: replica,
-            state: State::Election,
-            raw_raft,
+            core,
             communication_port: RequiredPort::uninitialised(),
             timers: None,
-            reconfig_state: ReconfigurationState::None,
-            current_leader: 0,
-            reconfig_policy,
+            num_peers,
             stopped: false,
             stopped_peers: HashSet::new(),
-            num_peers,
-            hb_proposals: vec![],
-            max_inflight,
             stop_ask: None,
         }
     }
@@ -559,17 +736,212 @@ where
         }
     }
 
-    fn try_campaign_leader(&mut self) -> Handled {
+    fn tick(&mut self) -> Handled {
+        self.core.tick();
+        self.handle_effects();
+        Handled::Ok
+    }
+
+    fn on_ready(&mut self) -> Handled {
+        self.core.on_ready();
+        if self.core.removed() {
+            self.stop_timers();
+        }
+        self.handle_effects();
+        Handled::Ok
+    }
+
+    fn handle_effects(&mut self) {
+        for effect in self.core.take_effects() {
+            match effect {
+                RaftEffect::Send(msg) => self
+                    .communication_port
+                    .trigger(CommunicatorMsg::RawRaftMsg(msg)),
+                RaftEffect::Respond(pr) => self
+                    .communication_port
+                    .trigger(CommunicatorMsg::ProposalResponse(pr)),
+                RaftEffect::Leader(notify_client, pid) => self
+                    .supervisor
+                    .tell(RaftCompMsg::Leader(notify_client, pid)),
+                RaftEffect::ForwardReconfig(leader_pid, reconfig) => self
+                    .supervisor
+                    .tell(RaftCompMsg::ForwardReconfig(leader_pid, reconfig)),
+            }
+        }
+    }
+}
+
+/// What a `RaftCore` asks the component that drives it to do.
+#[derive(Debug)]
+pub enum RaftEffect {
+    Send(TikvRaftMsg),
+    Respond(ProposalResp),
+    /// A new leader was observed, and if it is the first one since the replica started or its leader was removed.
+    Leader(bool, u64),
+    /// A reconfiguration that has to be proposed to the leader.
+    ForwardReconfig(u64, (Vec<u64>, Vec<u64>)),
+}
+
+/// The logic of a `RaftReplica` without Kompact: it proposes, handles the `Ready`s of the `RawNode` and reconfigures
+/// with joint consensus according to the `ReconfigurationPolicy`. The owner calls `tick()` and `on_ready()`
+/// periodically, passes it the messages of the peers and carries out the effects returned by `take_effects()`.
+pub struct RaftCore<S>
+where
+    S: RaftStorage + Send + Clone + 'static,
+{
+    raw_raft: RawNode<S>,
+    log: KompactLogger,
+    state: State,
+    reconfig_state: ReconfigurationState,
+    current_leader: u64,
+    reconfig_policy: ReconfigurationPolicy,
+    hb_proposals: Vec<Proposal>,
+    max_inflight: usize,
+    compaction: CompactionPolicy,
+    uncompacted: (u64, u64), // (entries, bytes) applied since the last compaction
+    kv: Option<KvStore>,
+    read_mode: ReadMode,
+    applied: u64,
+    pending_reads: Vec<ReadState>, // confirmed reads waiting for their index to be applied
+    initial_timeout_ticks: usize,
+    campaign_in: Option<usize>, // ticks until campaigning after the leader was removed, or as the first leader
+    rng: SmallRng,
+    effects: Vec<RaftEffect>,
+}
+
+impl<S> RaftCore<S>
+where
+    S: RaftStorage + Send + Clone + 'static,
+{
+    /// `initial_timeout_ticks` is the shortened election timeout of the first election in ticks. A replica campaigns
+    /// after a random timeout of one to two times that when the leader was removed by a reconfiguration.
+    pub fn with(
+        raw_raft: RawNode<S>,
+        log: KompactLogger,
+        reconfig_policy: ReconfigurationPolicy,
+        max_inflight: usize,
+        compaction: CompactionPolicy,
+        kv: Option<ReadMode>,
+        initial_timeout_ticks: usize,
+        rng: SmallRng,
+    ) -> RaftCore<S> {
+        let applied = raw_raft.raft.raft_log.applied;
+        RaftCore {
+            raw_raft,
+            log,
+            state: State::Election,
+            reconfig_state: ReconfigurationState::None,
+            current_leader: 0,
+            reconfig_policy,
+            hb_proposals: vec![],
+            max_inflight,
+            compaction,
+            uncompacted: (0, 0),
+            kv: kv.map(|_| KvStore::default()),
+            read_mode: kv.unwrap_or(ReadMode::Log),
+            applied,
+            pending_reads: vec![],
+            initial_timeout_ticks,
+            campaign_in: None,
+            rng,
+            effects: vec![],
+        }
+    }
+
+    pub fn pid(&self) -> u64 {
+        self.raw_raft.raft.id
+    }
+
+    /// If the replica was removed by a reconfiguration and takes no further part in the replication.
+    pub fn removed(&self) -> bool {
+        self.reconfig_state == ReconfigurationState::Removed
+    }
+
+    pub fn take_effects(&mut self) -> Vec<RaftEffect> {
+        std::mem::take(&mut self.effects)
+    }
+
+    /// The decided entries that are still in the log, after the index it was compacted up to. A finalized membership
+    /// change ends a configuration like a stopsign, where the initial configuration has id 1.
+    pub fn decided_sequence(&self) -> DecidedSequence {
+        let raft_log = &self.raw_raft.raft.raft_log;
+        let committed = raft_log.committed;
+        let mut sequence = vec![];
+        let mut config_id = 1;
+        let mut in_membership_change = false;
+        for e in raft_log.all_entries().iter() {
+            if e.index > committed || e.data.is_empty() {
+                continue;
+            }
+            match e.get_entry_type() {
+                EntryType::EntryNormal => {
+                    let id = e.data.as_slice().get_u64();
+                    if id != 0 {
+                        sequence.push(DecidedEntry::Normal(id));
+                    }
+                }
+                EntryType::EntryConfChange => {
+                    // skips the duplicates like when the entries are applied
+                    let mut cc = ConfChange::default();
+                    cc.merge_from_bytes(&e.data).unwrap();
+                    match cc.get_change_type() {
+                        ConfChangeType::BeginMembershipChange if !in_membership_change => {
+                            in_membership_change = true;
+                        }
+                        ConfChangeType::FinalizeMembershipChange if in_membership_change => {
+                            in_membership_change = false;
+                            sequence.push(DecidedEntry::StopSign(config_id));
+                            config_id += 1;
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+        DecidedSequence {
+            compacted_idx: raft_log.first_index() - 1,
+            entries: sequence,
+        }
+    }
+
+    /// Campaigns after the shortened timeout of the first election, so that this replica is elected before the
+    /// election timeout of any peer runs out.
+    pub fn campaign_first(&mut self) {
+        self.campaign_in = Some(std::cmp::max(self.initial_timeout_ticks, 1));
+    }
+
+    /// Asks the leader to hand its leadership over to this replica. A follower forwards the request to the leader,
+    /// which lets this replica campaign once its log is up to date.
+    pub fn transfer_leadership(&mut self) {
+        if self.removed() {
+            return;
+        }
+        let pid = self.pid();
+        self.raw_raft.transfer_leader(pid);
+    }
+
+    fn try_campaign_leader(&mut self) {
         // start campaign to become leader if none has been elected yet
         let leader = self.raw_raft.raft.leader_id;
         if leader == 0 && self.state == State::Election {
             let _ = self.raw_raft.campaign();
         }
-        Handled::Ok
     }
 
-    fn tick(&mut self) -> Handled {
+    pub fn tick(&mut self) {
+        if self.removed() {
+            return;
+        }
         self.raw_raft.tick();
+        match self.campaign_in {
+            Some(ticks) if ticks <= 1 => {
+                self.campaign_in = None;
+                self.try_campaign_leader();
+            }
+            Some(ticks) => self.campaign_in = Some(ticks - 1),
+            None => {}
+        }
         let leader = self.raw_raft.raft.leader_id;
         if leader != 0 {
             if !self.hb_proposals.is_empty() {
@@ -579,7 +951,6 @@ where
                 }
             }
             if leader != self.current_leader {
-                // info!(self.ctx.log(), "New leader: {}, old: {}", leader, self.current_leader);
                 self.current_leader = leader;
                 let notify_client = if self.state == State::Election {
                     self.state = State::Running;
@@ -587,34 +958,41 @@ where
                 } else {
                     false
                 };
-                self.supervisor
-                    .tell(RaftCompMsg::Leader(notify_client, leader));
+                self.effects.push(RaftEffect::Leader(notify_client, leader));
             }
         }
-        Handled::Ok
     }
 
-    fn step(&mut self, msg: TikvRaftMsg) {
-        let _ = self.raw_raft.step(msg);
+    pub fn step(&mut self, msg: TikvRaftMsg) {
+        if !self.removed() {
+            let _ = self.raw_raft.step(msg);
+        }
     }
 
-    fn propose(&mut self, proposal: Proposal) {
-        if self.raw_raft.raft.leader_id == 0 {
+    pub fn propose(&mut self, proposal: Proposal) {
+        if self.removed() {
+            return;
+        }
+        if self.raw_raft.raft.leader_id == 0 || self.raw_raft.raft.lead_transferee.is_some() {
+            // a leader drops proposals while it hands over the leadership
             self.hb_proposals.push(proposal);
             return;
         }
         match proposal.reconfig {
             Some(mut reconfig) => {
-                if let ReconfigurationState::None = self.reconfig_state {
+                // a replica takes part in successive reconfigurations, but only one at a time
+                if self.reconfig_state != ReconfigurationState::Pending
+                    && !self.raw_raft.raft.is_in_membership_change()
+                {
                     let leader_pid = self.raw_raft.raft.leader_id;
                     if leader_pid != self.raw_raft.raft.id {
-                        self.supervisor
-                            .tell(RaftCompMsg::ForwardReconfig(leader_pid, reconfig));
+                        self.effects
+                            .push(RaftEffect::ForwardReconfig(leader_pid, reconfig));
                         return;
                     }
                     let mut current_config =
                         self.raw_raft.raft.prs().configuration().voters().clone();
-                    match self.reconfig_policy {
+                    let new_config = match self.reconfig_policy {
                         ReconfigurationPolicy::ReplaceLeader => {
                             let mut add_nodes: Vec<u64> = reconfig
                                 .0
@@ -624,14 +1002,7 @@ where
                             current_config.remove(&leader_pid);
                             let mut new_voters = current_config.into_iter().collect::<Vec<u64>>();
                             new_voters.append(&mut add_nodes);
-                            let new_config = (new_voters, vec![]);
-                            // info!(self.ctx.log(), "Joint consensus remove leader: my pid: {}, reconfig: {:?}", leader_pid, new_config);
-                            self.raw_raft
-                                .raft
-                                .propose_membership_change(new_config)
-                                .expect(
-                                "Failed to propose joint consensus reconfiguration (remove leader)",
-                            );
+                            (new_voters, vec![])
                         }
                         ReconfigurationPolicy::ReplaceFollower => {
                             if !reconfig.0.contains(&leader_pid) {
@@ -651,23 +1022,30 @@ where
                                 let mut new_voters =
                                     current_config.into_iter().collect::<Vec<u64>>();
                                 new_voters.append(&mut add_nodes);
-                                let new_config = (new_voters, vec![]);
-                                // info!(self.ctx.log(), "Joint consensus remove follower: my pid: {}, reconfig: {:?}", leader_pid, new_config);
-                                self.raw_raft
-                                    .raft
-                                    .propose_membership_change(new_config)
-                                    .expect("Failed to propose joint consensus reconfiguration");
+                                (new_voters, vec![])
                             } else {
-                                self.raw_raft.raft.propose_membership_change(reconfig).expect("Failed to propose joint consensus reconfiguration (remove follower)");
+                                reconfig
                             }
                         }
+                        ReconfigurationPolicy::Exact => reconfig,
+                    };
+                    match self.raw_raft.raft.propose_membership_change(new_config) {
+                        Ok(_) => self.reconfig_state = ReconfigurationState::Pending,
+                        // e.g. a membership change of a previous leader is not applied yet, the client retries
+                        Err(e) => warn!(
+                            self.log,
+                            "Failed to propose joint consensus reconfiguration: {:?}", e
+                        ),
                     }
-                    self.reconfig_state = ReconfigurationState::Pending;
                 }
             }
             None => {
                 // i.e normal operation
                 let data = proposal.data;
+                if self.read_mode != ReadMode::Log && is_read(&data) {
+                    self.read_index(data);
+                    return;
+                }
                 self.raw_raft.propose(vec![], data).unwrap_or_else(|_| {
                     panic!(
                         "Failed to propose. leader: {}, lead_transferee: {:?}",
@@ -678,62 +1056,114 @@ where
         }
     }
 
-    fn on_ready(&mut self) -> Handled {
+    /// Serves a GET from the key-value store at the commit index confirmed by the leader, instead of deciding it.
+    fn read_index(&mut self, data: Vec<u8>) {
+        let raft = &self.raw_raft.raft;
+        let committed_in_term = raft
+            .raft_log
+            .term(raft.raft_log.committed)
+            .map_or(false, |term| term == raft.term);
+        if raft.state == StateRole::Leader && !committed_in_term {
+            // the commit index of a new leader is not known to be up to date, it would drop the read
+            self.hb_proposals.push(Proposal::normal(data));
+            return;
+        }
+        self.raw_raft.read_index(data);
+    }
+
+    /// Responds to the confirmed reads whose index has been applied.
+    fn serve_reads(&mut self) {
+        let applied = self.applied;
+        let (ready, pending): (Vec<ReadState>, Vec<ReadState>) =
+            std::mem::take(&mut self.pending_reads)
+                .into_iter()
+                .partition(|rs| rs.index <= applied);
+        self.pending_reads = pending;
+        let store = self
+            .kv
+            .as_mut()
+            .expect("Serving reads without a key-value store");
+        let leader = self.raw_raft.raft.leader_id;
+        for rs in ready {
+            let pr = ProposalResp::with(store.apply(&rs.request_ctx), leader);
+            self.effects.push(RaftEffect::Respond(pr));
+        }
+    }
+
+    pub fn on_ready(&mut self) {
         if !self.raw_raft.has_ready() {
-            return Handled::Ok;
+            return;
         }
         let mut store = self.raw_raft.raft.raft_log.store.clone();
 
         // Get the `Ready` with `RawNode::ready` interface.
         let mut ready = self.raw_raft.ready();
 
+        // Apply the snapshot. It's necessary because in `RawNode::advance` we stabilize the snapshot.
+        // The snapshot replaces the log, so it must be applied before appending the new entries.
+        if *ready.snapshot() != Snapshot::default() {
+            let snapshot = ready.snapshot().clone();
+            debug!(
+                self.log,
+                "Applying snapshot at index {}",
+                snapshot.get_metadata().index
+            );
+            if let Err(e) = store.apply_snapshot(snapshot) {
+                error!(
+                    self.log,
+                    "{}",
+                    format!("apply snapshot fail: {:?}, need to retry or panic", e)
+                );
+                return;
+            }
+            self.uncompacted = (0, 0);
+        }
+
         // Persistent raft logs. It's necessary because in `RawNode::advance` we stabilize
         // raft logs to the latest position.
         if let Err(e) = store.append_log(ready.entries()) {
             error!(
-                self.ctx.log(),
+                self.log,
                 "{}",
                 format!("persist raft log fail: {:?}, need to retry or panic", e)
             );
-            return Handled::Ok;
-        }
-
-        // Apply the snapshot. It's necessary because in `RawNode::advance` we stabilize the snapshot.
-        if *ready.snapshot() != Snapshot::default() {
-            unimplemented!("Should not be any snapshots to handle!");
+            return;
         }
 
         // Send out the messages come from the node.
         let mut ready_msgs = Vec::with_capacity(self.max_inflight);
         std::mem::swap(&mut ready.messages, &mut ready_msgs);
-        for msg in ready_msgs {
-            self.communication_port
-                .trigger(CommunicatorMsg::RawRaftMsg(msg));
-        }
-        // let mut next_conf_change: Option<ConfChangeType> = None;
+        self.effects
+            .extend(ready_msgs.into_iter().map(RaftEffect::Send));
         // Apply all committed proposals.
         if let Some(committed_entries) = ready.committed_entries.take() {
             for entry in &committed_entries {
+                self.uncompacted.0 += 1;
+                self.uncompacted.1 += entry.data.len() as u64;
                 if entry.data.is_empty() {
                     // From new elected leaders.
                     continue;
                 }
                 if let EntryType::EntryConfChange = entry.get_entry_type() {
-                    if self.reconfig_state == ReconfigurationState::Removed
-                        || self.reconfig_state == ReconfigurationState::Finished
-                    {
+                    if self.reconfig_state == ReconfigurationState::Removed {
                         continue;
                     }
                     // For conf change messages, make them effective.
                     let mut cc = ConfChange::default();
                     cc.merge_from_bytes(&entry.data).unwrap();
                     let change_type = cc.get_change_type();
+                    let in_membership_change = self.raw_raft.raft.is_in_membership_change();
                     match &change_type {
+                        // a duplicate proposal of a membership change that was already begun or finalized
+                        ConfChangeType::BeginMembershipChange if in_membership_change => continue,
+                        ConfChangeType::FinalizeMembershipChange if !in_membership_change => {
+                            continue
+                        }
                         ConfChangeType::BeginMembershipChange => {
                             let reconfig = cc.get_configuration();
                             let start_index = cc.get_start_index();
                             debug!(
-                                self.ctx.log(),
+                                self.log,
                                 "{}",
                                 format!(
                                     "Beginning reconfiguration to: {:?}, start_index: {}",
@@ -756,7 +1186,6 @@ where
                             let current_conf = self.raw_raft.raft.prs().configuration().clone();
                             let current_voters = current_conf.voters();
                             if !current_voters.contains(&self.raw_raft.raft.id) {
-                                self.stop_timers();
                                 self.reconfig_state = ReconfigurationState::Removed;
                             } else {
                                 self.reconfig_state = ReconfigurationState::Finished;
@@ -766,32 +1195,12 @@ where
                                 // leader was removed
                                 self.state = State::Election; // reset leader so it can notify client when new leader emerges
                                 if self.reconfig_state != ReconfigurationState::Removed {
-                                    // campaign later if we are not removed
-                                    let mut rng = rand::thread_rng();
-                                    let config = self.ctx.config();
-                                    let tick_period = config["raft"]["tick_period"]
-                                        .as_i64()
-                                        .expect("Failed to load tick_period")
-                                        as usize;
-                                    let election_timeout = config["experiment"]["election_timeout"]
-                                        .as_i64()
-                                        .expect("Failed to load election_timeout")
-                                        as usize;
-                                    let initial_election_factor = config["experiment"]
-                                        ["initial_election_factor"]
-                                        .as_i64()
-                                        .expect("Failed to load initial_election_factor")
-                                        as usize;
-                                    // randomize with ticks to ensure at least one tick difference in timeout
-                                    let intial_timeout_ticks =
-                                        (election_timeout / initial_election_factor) / tick_period;
-                                    let rnd = rng
-                                        .gen_range(intial_timeout_ticks, 2 * intial_timeout_ticks);
-                                    let timeout = rnd * tick_period;
-                                    self.schedule_once(
-                                        Duration::from_millis(timeout as u64),
-                                        move |c, _| c.try_campaign_leader(),
+                                    // campaign later if we are not removed, randomized with ticks to ensure at least one tick difference in timeout
+                                    let timeout = self.rng.gen_range(
+                                        self.initial_timeout_ticks,
+                                        2 * self.initial_timeout_ticks,
                                     );
+                                    self.campaign_in = Some(timeout);
                                 }
                             }
                             let conf_len = current_voters.len();
@@ -805,30 +1214,45 @@ where
                             store.set_conf_state(cs, None);
 
                             let pr = ProposalResp::with(data, leader);
-                            self.communication_port
-                                .trigger(CommunicatorMsg::ProposalResponse(pr));
+                            self.effects.push(RaftEffect::Respond(pr));
                         }
                         _ => unimplemented!(),
                     }
                 } else {
                     // normal proposals
+                    let response = self.kv.as_mut().map(|store| store.apply(entry.get_data()));
                     if self.raw_raft.raft.state == StateRole::Leader {
-                        let pr =
-                            ProposalResp::with(entry.get_data().to_vec(), self.raw_raft.raft.id);
-                        self.communication_port
-                            .trigger(CommunicatorMsg::ProposalResponse(pr));
+                        let leader = self.raw_raft.raft.id;
+                        let pr = match response {
+                            Some(data) => ProposalResp::with(data, leader),
+                            None => ProposalResp::to_proposal(entry.get_data(), leader),
+                        };
+                        self.effects.push(RaftEffect::Respond(pr));
                     }
                 }
             }
             if let Some(last_committed) = committed_entries.last() {
+                self.applied = last_committed.index;
                 store
                     .set_hard_state(last_committed.index, last_committed.term)
                     .expect("Failed to set hardstate");
+                let (entries, bytes) = self.uncompacted;
+                if self.compaction.should_compact(entries, bytes) {
+                    // keep the last applied entry so its term can still be looked up
+                    store
+                        .compact(last_committed.index)
+                        .expect("Failed to compact log");
+                    debug!(self.log, "Compacted log up to {}", last_committed.index);
+                    self.uncompacted = (0, 0);
+                }
             }
         }
+        if self.kv.is_some() && !(ready.read_states().is_empty() && self.pending_reads.is_empty()) {
+            self.pending_reads.extend_from_slice(ready.read_states());
+            self.serve_reads();
+        }
         // Call `RawNode::advance` interface to update position flags in the raft.
         self.raw_raft.advance(ready);
-        Handled::Ok
     }
 }
 
@@ -0,0 +1,507 @@
+use super::fault::FaultTarget;
+use std::fmt;
+
+/// When a reconfiguration is proposed or the leadership is transferred, in decided proposals of the primary client.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReconfigOffset {
+    /// `@0.5`: once this fraction of the proposals has been decided.
+    Fraction(f64),
+    /// `@5000`: once this many proposals have been decided.
+    Count(u64),
+}
+
+impl ReconfigOffset {
+    pub(super) fn parse(s: &str) -> Result<ReconfigOffset, String> {
+        let s = s.trim();
+        if s.contains('.') {
+            match s.parse::<f64>() {
+                Ok(at) if at > 0.0 && at < 1.0 => Ok(ReconfigOffset::Fraction(at)),
+                _ => Err(format!(
+                    "Invalid offset fraction, should be in (0, 1): {}",
+                    s
+                )),
+            }
+        } else {
+            match s.parse::<u64>() {
+                Ok(count) if count > 0 => Ok(ReconfigOffset::Count(count)),
+                _ => Err(format!("Invalid offset: {}", s)),
+            }
+        }
+    }
+
+    /// Number of decided proposals after which the reconfiguration is proposed.
+    pub fn trigger_count(&self, num_proposals: u64) -> u64 {
+        match self {
+            ReconfigOffset::Fraction(at) => (at * num_proposals as f64).ceil() as u64,
+            ReconfigOffset::Count(count) => *count,
+        }
+    }
+}
+
+impl fmt::Display for ReconfigOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReconfigOffset::Fraction(at) => write!(f, "{}", at),
+            ReconfigOffset::Count(count) => write!(f, "{}", count),
+        }
+    }
+}
+
+/// A change of the members of the configuration.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigChange {
+    /// `add(4,5)`: new voters, or learners that are promoted to voters.
+    Add(Vec<u64>),
+    /// `learner(6)`: new members that get the decided entries but do not vote.
+    Learner(Vec<u64>),
+    /// `remove(leader,2)`: voters or learners that leave the configuration.
+    Remove(Vec<FaultTarget>),
+    /// `replace(leader>4,2>5)`: a voter that leaves and the new voter that takes its place.
+    Replace(Vec<(FaultTarget, u64)>),
+}
+
+fn parse_pid(s: &str) -> Result<u64, String> {
+    match s.trim().parse::<u64>() {
+        Ok(pid) if pid > 0 => Ok(pid),
+        _ => Err(format!("Invalid node id: {}", s)),
+    }
+}
+
+/// Parses `<leader|node id>><new node id>`.
+fn parse_replacement(s: &str) -> Result<(FaultTarget, u64), String> {
+    match s.find('>') {
+        Some(i) => Ok((FaultTarget::parse(&s[..i])?, parse_pid(&s[i + 1..])?)),
+        None => Err(format!("Replacement '{}' should look like <old>><new>", s)),
+    }
+}
+
+fn join<T: fmt::Display>(items: &[T]) -> String {
+    let items: Vec<_> = items.iter().map(|i| i.to_string()).collect();
+    items.join(",")
+}
+
+impl ConfigChange {
+    fn parse(s: &str) -> Result<ConfigChange, String> {
+        let s = s.trim();
+        let open = match s.find('(') {
+            Some(i) if s.ends_with(')') => i,
+            _ => {
+                return Err(format!(
+                    "Configuration change '{}' should look like <change>(<nodes>)",
+                    s
+                ))
+            }
+        };
+        let args: Vec<&str> = s[open + 1..s.len() - 1].split(',').collect();
+        let pids = || {
+            args.iter()
+                .map(|a| parse_pid(a))
+                .collect::<Result<Vec<u64>, String>>()
+        };
+        match s[..open].trim().to_lowercase().as_ref() {
+            "add" => Ok(ConfigChange::Add(pids()?)),
+            "learner" => Ok(ConfigChange::Learner(pids()?)),
+            "remove" => args
+                .iter()
+                .map(|a| FaultTarget::parse(a))
+                .collect::<Result<_, _>>()
+                .map(ConfigChange::Remove),
+            "replace" => args
+                .iter()
+                .map(|a| parse_replacement(a))
+                .collect::<Result<_, _>>()
+                .map(ConfigChange::Replace),
+            unknown => Err(format!("Unknown configuration change: {}", unknown)),
+        }
+    }
+
+    /// The nodes that leave the configuration.
+    fn leaving(&self) -> Vec<FaultTarget> {
+        match self {
+            ConfigChange::Add(_) | ConfigChange::Learner(_) => vec![],
+            ConfigChange::Remove(targets) => targets.clone(),
+            ConfigChange::Replace(replacements) => replacements.iter().map(|(t, _)| *t).collect(),
+        }
+    }
+
+    /// The nodes that join the configuration.
+    fn joining(&self) -> Vec<u64> {
+        match self {
+            ConfigChange::Add(pids) | ConfigChange::Learner(pids) => pids.clone(),
+            ConfigChange::Remove(_) => vec![],
+            ConfigChange::Replace(replacements) => replacements.iter().map(|(_, p)| *p).collect(),
+        }
+    }
+
+    fn apply(&self, voters: &mut Vec<u64>, learners: &mut Vec<u64>, leader: u64) {
+        let add = |voters: &mut Vec<u64>, learners: &mut Vec<u64>, pid: u64| {
+            learners.retain(|l| *l != pid);
+            if !voters.contains(&pid) {
+                voters.push(pid);
+            }
+        };
+        let remove = |voters: &mut Vec<u64>, learners: &mut Vec<u64>, target: &FaultTarget| {
+            let pid = target.resolve(leader);
+            voters.retain(|v| *v != pid);
+            learners.retain(|l| *l != pid);
+        };
+        match self {
+            ConfigChange::Add(pids) => {
+                for pid in pids {
+                    add(voters, learners, *pid);
+                }
+            }
+            ConfigChange::Learner(pids) => {
+                for pid in pids {
+                    if !voters.contains(pid) && !learners.contains(pid) {
+                        learners.push(*pid);
+                    }
+                }
+            }
+            ConfigChange::Remove(targets) => {
+                for target in targets {
+                    remove(voters, learners, target);
+                }
+            }
+            ConfigChange::Replace(replacements) => {
+                for (target, pid) in replacements {
+                    remove(voters, learners, target);
+                    add(voters, learners, *pid);
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for ConfigChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigChange::Add(pids) => write!(f, "add({})", join(pids)),
+            ConfigChange::Learner(pids) => write!(f, "learner({})", join(pids)),
+            ConfigChange::Remove(targets) => write!(f, "remove({})", join(targets)),
+            ConfigChange::Replace(replacements) => {
+                let replacements: Vec<_> = replacements
+                    .iter()
+                    .map(|(target, pid)| format!("{}>{}", target, pid))
+                    .collect();
+                write!(f, "replace({})", replacements.join(","))
+            }
+        }
+    }
+}
+
+/// One reconfiguration of a run: the changes that are proposed together once `at` proposals are decided.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReconfigStep {
+    pub changes: Vec<ConfigChange>,
+    pub at: ReconfigOffset,
+}
+
+impl ReconfigStep {
+    /// Parses `<change>[+<change>...]@<offset>`.
+    fn parse(s: &str) -> Result<ReconfigStep, String> {
+        let (changes, at) = match s.rfind('@') {
+            Some(i) => (&s[..i], &s[i + 1..]),
+            None => return Err(format!("Reconfiguration '{}' has no '@'", s)),
+        };
+        let changes = changes
+            .split('+')
+            .map(ConfigChange::parse)
+            .collect::<Result<_, _>>()?;
+        let at = ReconfigOffset::parse(at)?;
+        Ok(ReconfigStep { changes, at })
+    }
+
+    pub fn trigger_count(&self, num_proposals: u64) -> u64 {
+        self.at.trigger_count(num_proposals)
+    }
+
+    /// The (voters, learners) that the current configuration is reconfigured to, with `leader` as the current leader.
+    pub fn apply(&self, voters: &[u64], learners: &[u64], leader: u64) -> (Vec<u64>, Vec<u64>) {
+        let mut voters = voters.to_vec();
+        let mut learners = learners.to_vec();
+        for change in &self.changes {
+            change.apply(&mut voters, &mut learners, leader);
+        }
+        (voters, learners)
+    }
+}
+
+impl fmt::Display for ReconfigStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let changes: Vec<_> = self.changes.iter().map(|c| c.to_string()).collect();
+        write!(f, "{}@{}", changes.join("+"), self.at)
+    }
+}
+
+/// The reconfigurations of a run, proposed one after another by the primary client.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReconfigSchedule {
+    pub steps: Vec<ReconfigStep>,
+}
+
+impl ReconfigSchedule {
+    /// If `s` turns reconfiguration off.
+    pub fn is_off(s: &str) -> bool {
+        let s = s.trim();
+        s.is_empty() || s.eq_ignore_ascii_case("off") || s.eq_ignore_ascii_case("none")
+    }
+
+    /// If `s` is one of the fixed reconfigurations that the Raft reconfiguration policies were made for.
+    pub fn is_alias(s: &str) -> bool {
+        let s = s.trim();
+        s.eq_ignore_ascii_case("single") || s.eq_ignore_ascii_case("majority")
+    }
+
+    /// Parses `;`-separated reconfigurations, in the order they are proposed. `off` is a run without reconfiguration,
+    /// `single` replaces node 1 and `majority` replaces a majority of the `num_nodes` nodes halfway through the run.
+    pub fn parse(s: &str, num_nodes: u64) -> Result<ReconfigSchedule, String> {
+        if Self::is_off(s) {
+            return Ok(ReconfigSchedule::default());
+        }
+        let halfway = ReconfigOffset::Fraction(0.5);
+        let steps = match s.trim().to_lowercase().as_ref() {
+            "single" => vec![ReconfigStep {
+                changes: vec![
+                    ConfigChange::Remove(vec![FaultTarget::Node(1)]),
+                    ConfigChange::Add(vec![num_nodes + 1]),
+                ],
+                at: halfway,
+            }],
+            "majority" => {
+                let majority = num_nodes / 2 + 1;
+                vec![ReconfigStep {
+                    changes: vec![
+                        ConfigChange::Remove(
+                            (majority..=num_nodes).map(FaultTarget::Node).collect(),
+                        ),
+                        ConfigChange::Add((num_nodes + 1..=num_nodes + majority).collect()),
+                    ],
+                    at: halfway,
+                }]
+            }
+            _ => s
+                .split(';')
+                .map(|step| ReconfigStep::parse(step.trim()))
+                .collect::<Result<_, _>>()?,
+        };
+        Ok(ReconfigSchedule { steps })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// If any of the reconfigurations adds learners.
+    pub fn has_learners(&self) -> bool {
+        self.steps.iter().any(|step| {
+            step.changes.iter().any(|c| match c {
+                ConfigChange::Learner(_) => true,
+                _ => false,
+            })
+        })
+    }
+
+    /// Checks that the reconfigurations can be carried out, starting from the nodes `1..=num_nodes`. Returns how many
+    /// nodes the run needs in total. Which node leads is only known in the run, so a removed leader is just counted.
+    pub fn validate(&self, num_nodes: u64, num_proposals: u64) -> Result<u64, String> {
+        let mut voters: Vec<u64> = (1..=num_nodes).collect();
+        let mut learners: Vec<u64> = vec![];
+        let mut left: Vec<u64> = vec![];
+        let mut removed_leaders = 0;
+        let mut prev_count = 0;
+        for step in &self.steps {
+            let count = step.trigger_count(num_proposals);
+            if count <= prev_count || count >= num_proposals {
+                return Err(format!(
+                    "Reconfiguration {} is proposed after {} proposals, but should come after the previous one and before all {} proposals are decided",
+                    step, count, num_proposals
+                ));
+            }
+            prev_count = count;
+            for change in &step.changes {
+                for target in change.leaving() {
+                    match target {
+                        FaultTarget::Leader => removed_leaders += 1,
+                        FaultTarget::Node(pid) => {
+                            if !voters.contains(&pid) && !learners.contains(&pid) {
+                                return Err(format!(
+                                    "Reconfiguration {} removes node {}, which is not in the configuration",
+                                    step, pid
+                                ));
+                            }
+                            voters.retain(|v| *v != pid);
+                            learners.retain(|l| *l != pid);
+                            left.push(pid);
+                        }
+                    }
+                }
+                let adds_learners = match change {
+                    ConfigChange::Learner(_) => true,
+                    _ => false,
+                };
+                for pid in change.joining() {
+                    if left.contains(&pid) {
+                        return Err(format!(
+                            "Reconfiguration {} adds node {}, which already left the configuration",
+                            step, pid
+                        ));
+                    }
+                    let promoted = !adds_learners && learners.contains(&pid);
+                    if !promoted && (voters.contains(&pid) || learners.contains(&pid)) {
+                        return Err(format!(
+                            "Reconfiguration {} adds node {}, which is already in the configuration",
+                            step, pid
+                        ));
+                    }
+                    if adds_learners {
+                        learners.push(pid);
+                    } else {
+                        learners.retain(|l| *l != pid);
+                        voters.push(pid);
+                    }
+                }
+            }
+            if voters.len() <= removed_leaders {
+                return Err(format!("Reconfiguration {} leaves no voters", step));
+            }
+        }
+        let max_pid = left
+            .iter()
+            .chain(voters.iter())
+            .chain(learners.iter())
+            .copied()
+            .max()
+            .unwrap_or(0);
+        Ok(max_pid.max(num_nodes))
+    }
+}
+
+impl fmt::Display for ReconfigSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "off");
+        }
+        let steps: Vec<_> = self.steps.iter().map(|step| step.to_string()).collect();
+        write!(f, "{}", steps.join(";"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reconfig_schedule_test() {
+        let schedule = ReconfigSchedule::parse(
+            "add(4,5)@0.25; replace(leader>6,2>7)+learner(8)@500; remove(4, 5)@0.75",
+            3,
+        )
+        .expect("Should parse");
+        assert_eq!(
+            vec![
+                ReconfigStep {
+                    changes: vec![ConfigChange::Add(vec![4, 5])],
+                    at: ReconfigOffset::Fraction(0.25),
+                },
+                ReconfigStep {
+                    changes: vec![
+                        ConfigChange::Replace(vec![
+                            (FaultTarget::Leader, 6),
+                            (FaultTarget::Node(2), 7)
+                        ]),
+                        ConfigChange::Learner(vec![8]),
+                    ],
+                    at: ReconfigOffset::Count(500),
+                },
+                ReconfigStep {
+                    changes: vec![ConfigChange::Remove(vec![
+                        FaultTarget::Node(4),
+                        FaultTarget::Node(5)
+                    ])],
+                    at: ReconfigOffset::Fraction(0.75),
+                },
+            ],
+            schedule.steps
+        );
+        assert!(schedule.has_learners());
+        assert_eq!(
+            "add(4,5)@0.25;replace(leader>6,2>7)+learner(8)@500;remove(4,5)@0.75",
+            schedule.to_string()
+        );
+        assert_eq!(250, schedule.steps[0].trigger_count(1000));
+        assert_eq!(500, schedule.steps[1].trigger_count(1000));
+        assert_eq!(Ok(8), schedule.validate(3, 1000));
+        for off in &["off", "none", ""] {
+            assert!(ReconfigSchedule::parse(off, 3).unwrap().is_empty());
+        }
+        assert!(ReconfigSchedule::parse("add(4)", 3).is_err());
+        assert!(ReconfigSchedule::parse("add(0)@0.5", 3).is_err());
+        assert!(ReconfigSchedule::parse("add(4)@1.5", 3).is_err());
+        assert!(ReconfigSchedule::parse("grow(4)@0.5", 3).is_err());
+        assert!(ReconfigSchedule::parse("replace(1)@0.5", 3).is_err());
+    }
+
+    #[test]
+    fn reconfig_aliases_test() {
+        let single = ReconfigSchedule::parse("single", 3).unwrap();
+        assert_eq!(
+            (vec![2, 3, 4], vec![]),
+            single.steps[0].apply(&[1, 2, 3], &[], 2)
+        );
+        assert_eq!(50, single.steps[0].trigger_count(100));
+        let majority = ReconfigSchedule::parse("Majority", 5).unwrap();
+        assert_eq!(
+            (vec![1, 2, 6, 7, 8], vec![]),
+            majority.steps[0].apply(&[1, 2, 3, 4, 5], &[], 1)
+        );
+        assert_eq!(Ok(8), majority.validate(5, 100));
+        assert!(ReconfigSchedule::is_alias("single"));
+        assert!(!ReconfigSchedule::is_alias("add(4)@0.5"));
+    }
+
+    #[test]
+    fn apply_reconfig_step_test() {
+        let grow = ReconfigStep::parse("add(4,5)@0.5").unwrap();
+        assert_eq!(
+            (vec![1, 2, 3, 4, 5], vec![]),
+            grow.apply(&[1, 2, 3], &[], 1)
+        );
+        let shrink = ReconfigStep::parse("remove(leader,4)@0.5").unwrap();
+        assert_eq!(
+            (vec![1, 2, 5], vec![]),
+            shrink.apply(&[1, 2, 3, 4, 5], &[], 3)
+        );
+        let learn = ReconfigStep::parse("replace(leader>4)+learner(5)@0.5").unwrap();
+        assert_eq!((vec![1, 3, 4], vec![5]), learn.apply(&[1, 2, 3], &[], 2));
+        let promote = ReconfigStep::parse("add(5)@0.5").unwrap();
+        assert_eq!(
+            (vec![1, 3, 4, 5], vec![]),
+            promote.apply(&[1, 3, 4], &[5], 1)
+        );
+    }
+
+    #[test]
+    fn validate_reconfig_schedule_test() {
+        let validate = |s: &str, num_nodes: u64| {
+            ReconfigSchedule::parse(s, num_nodes)
+                .unwrap()
+                .validate(num_nodes, 100)
+        };
+        assert_eq!(Ok(5), validate("add(4,5)@0.5", 3));
+        assert_eq!(Ok(5), validate("remove(4,5)@0.5", 5));
+        assert_eq!(Ok(5), validate("learner(5)@0.3;add(5)@0.6", 4));
+        // not in order, or never proposed
+        assert!(validate("add(4)@0.6;add(5)@0.3", 3).is_err());
+        assert!(validate("add(4)@100", 3).is_err());
+        // not a member, already a member or rejoining
+        assert!(validate("remove(4)@0.5", 3).is_err());
+        assert!(validate("add(3)@0.5", 3).is_err());
+        assert!(validate("learner(4)@0.3;learner(4)@0.6", 3).is_err());
+        assert!(validate("remove(3)@0.3;add(3)@0.6", 3).is_err());
+        // no voters left, counting the leader
+        assert!(validate("remove(1,2,3)@0.5", 3).is_err());
+        assert!(validate("remove(1,2)+remove(leader)@0.5", 3).is_err());
+        assert_eq!(Ok(3), validate("remove(1)+remove(leader)@0.5", 3));
+    }
+}
@@ -0,0 +1,330 @@
+//! Client components on the benchmark client processes, next to the replicas. The master hands each process a
+//! share of the clients through its `ClientHost`, and merges their results with the ones of its in-process clients.
+use super::client::{Client, LocalClientMessage, MetaResults, OpenLoop};
+use super::fault::FaultSchedule;
+use super::handover::TransferSchedule;
+use super::messages::{ClientHostMsg, ClientHostSer, RemoteWorkload};
+use super::reconfig::ReconfigSchedule;
+use hashbrown::HashMap;
+use kompact::prelude::*;
+use std::sync::Arc;
+use std::thread;
+use std::time::SystemTime;
+use synchronoise::CountdownEvent;
+
+#[derive(Debug)]
+pub enum ClientHostLocal {
+    LeadersKnown,
+    Finished,
+    /// Kills the clients of the iteration.
+    Kill(Ask<(), ()>),
+}
+
+/// Creates, runs and stops the clients that the master assigns to this process.
+#[derive(ComponentDefinition)]
+pub struct ClientHost {
+    ctx: ComponentContext<Self>,
+    master: Option<ActorPath>,
+    clients: Vec<(u64, Arc<Component<Client>>)>,
+}
+
+impl ClientHost {
+    pub fn new() -> ClientHost {
+        ClientHost {
+            ctx: ComponentContext::uninitialised(),
+            master: None,
+            clients: vec![],
+        }
+    }
+
+    fn reply(&self, msg: ClientHostMsg) {
+        self.master
+            .as_ref()
+            .expect("No master")
+            .tell((msg, ClientHostSer), self);
+    }
+
+    fn prepare(&mut self, w: RemoteWorkload) -> Handled {
+        let system = self.ctx.system();
+        let initial_config: Vec<u64> = (1..=w.num_initial_nodes).collect();
+        let nodes: HashMap<u64, ActorPath> = w.nodes.into_iter().collect();
+        let finished_latch = Arc::new(CountdownEvent::new(w.clients.len()));
+        let mut leader_election_latches = Vec::with_capacity(w.clients.len());
+        let mut registrations = Vec::with_capacity(w.clients.len());
+        for (id, num_proposals, rate) in w.clients {
+            let leader_election_latch = Arc::new(CountdownEvent::new(1));
+            let open_loop = match rate {
+                0 => None,
+                rate => Some(OpenLoop {
+                    rate,
+                    arrivals: w.arrivals,
+                }),
+            };
+            let (client, unique_reg_f) = system.create_and_register(|| {
+                Client::with(
+                    id,
+                    initial_config.clone(),
+                    num_proposals,
+                    w.concurrent_proposals,
+                    nodes.clone(),
+                    ReconfigSchedule::default(),
+                    w.timeout,
+                    leader_election_latch.clone(),
+                    finished_latch.clone(),
+                    w.check_total_order,
+                    FaultSchedule::default(),
+                    TransferSchedule::default(),
+                    open_loop,
+                    w.payload_size,
+                    None,
+                    w.throughput_window,
+                )
+            });
+            let alias_f =
+                system.register_by_alias(&client, format!("client{}_{}", w.iteration_id, id));
+            leader_election_latches.push(leader_election_latch);
+            registrations.push((id, client, unique_reg_f, alias_f));
+        }
+        // the latches are awaited outside of the component, which has to keep handling messages meanwhile
+        let host = self.actor_ref();
+        thread::spawn(move || {
+            for leader_election_latch in leader_election_latches {
+                leader_election_latch.wait(); // wait until every client knows the leader
+            }
+            host.tell(ClientHostLocal::LeadersKnown);
+        });
+        let host = self.actor_ref();
+        thread::spawn(move || {
+            finished_latch.wait();
+            host.tell(ClientHostLocal::Finished);
+        });
+        Handled::block_on(self, move |mut async_self| async move {
+            let mut paths = Vec::with_capacity(registrations.len());
+            for (id, client, unique_reg_f, alias_f) in registrations {
+                unique_reg_f
+                    .await
+                    .unwrap()
+                    .expect("Client failed to register!");
+                let path = alias_f
+                    .await
+                    .unwrap()
+                    .expect("Failed to register alias for ClientComp");
+                system.start(&client);
+                async_self.clients.push((id, client));
+                paths.push((id, path));
+            }
+            async_self.reply(ClientHostMsg::Prepared(paths));
+        })
+    }
+
+    fn run(&self, run_start: SystemTime) {
+        for (_, client) in &self.clients {
+            client.actor_ref().tell(LocalClientMessage::Run(run_start));
+        }
+    }
+
+    fn stop(&mut self) -> Handled {
+        let stop_futures: Vec<_> = self
+            .clients
+            .iter()
+            .map(|(id, client)| {
+                let stop_f = client
+                    .actor_ref()
+                    .ask(|promise| LocalClientMessage::Stop(Ask::new(promise, ())));
+                (*id, stop_f)
+            })
+            .collect();
+        Handled::block_on(self, move |async_self| async move {
+            let mut results = Vec::with_capacity(stop_futures.len());
+            for (id, stop_f) in stop_futures {
+                results.push((id, stop_f.await.expect("Client never stopped")));
+            }
+            async_self.reply(ClientHostMsg::Stopped(results));
+        })
+    }
+
+    fn kill_clients(&mut self, ask: Ask<(), ()>) -> Handled {
+        let system = self.ctx.system();
+        let kill_futures: Vec<_> = self
+            .clients
+            .drain(..)
+            .map(|(_, client)| system.kill_notify(client))
+            .collect();
+        Handled::block_on(self, move |_| async move {
+            for kill_f in kill_futures {
+                kill_f.await.expect("Client never died");
+            }
+            ask.reply(()).expect("Failed to reply kill");
+        })
+    }
+}
+
+ignore_lifecycle!(ClientHost);
+
+impl Actor for ClientHost {
+    type Message = ClientHostLocal;
+
+    fn receive_local(&mut self, msg: Self::Message) -> Handled {
+        match msg {
+            ClientHostLocal::LeadersKnown => self.reply(ClientHostMsg::LeadersKnown),
+            ClientHostLocal::Finished => self.reply(ClientHostMsg::Finished),
+            ClientHostLocal::Kill(ask) => return self.kill_clients(ask),
+        }
+        Handled::Ok
+    }
+
+    fn receive_network(&mut self, m: NetMessage) -> Handled {
+        let NetMessage { sender, data, .. } = m;
+        match_deser! {data; {
+            msg: ClientHostMsg [ClientHostSer] => {
+                match msg {
+                    ClientHostMsg::Prepare(workload) => {
+                        self.master = Some(sender);
+                        return self.prepare(workload);
+                    }
+                    ClientHostMsg::Run(run_start) => self.run(run_start),
+                    ClientHostMsg::Stop => return self.stop(),
+                    _ => error!(self.ctx.log(), "ClientHost received unexpected msg: {:?}", msg),
+                }
+            },
+            !Err(e) => error!(self.ctx.log(), "ClientHost failed to deserialise msg: {:?}", e),
+        }
+        }
+        Handled::Ok
+    }
+}
+
+#[derive(Debug)]
+pub enum RemoteClientsMsg {
+    /// Sends the workloads to the given hosts.
+    Prepare(Vec<(ActorPath, RemoteWorkload)>),
+    /// The paths of the prepared clients, by client id.
+    AwaitPrepared(Ask<(), Vec<ActorPath>>),
+    AwaitLeaders(Ask<(), Vec<()>>),
+    Run(SystemTime),
+    AwaitFinished(Ask<(), Vec<()>>),
+    /// Stops the clients and returns their results, by client id.
+    Stop(Ask<(), Vec<MetaResults>>),
+}
+
+/// The replies of all hosts to one request of the master, answered once every host replied.
+struct Replies<T: Send + 'static> {
+    expected: usize,
+    received: usize,
+    values: Vec<(u64, T)>, // (client id, value)
+    ask: Option<Ask<(), Vec<T>>>,
+}
+
+impl<T: Send + 'static> Replies<T> {
+    fn with(expected: usize) -> Replies<T> {
+        Replies {
+            expected,
+            received: 0,
+            values: vec![],
+            ask: None,
+        }
+    }
+
+    fn add(&mut self, values: Vec<(u64, T)>) {
+        self.received += 1;
+        self.values.extend(values);
+        self.try_reply();
+    }
+
+    fn wait(&mut self, ask: Ask<(), Vec<T>>) {
+        self.ask = Some(ask);
+        self.try_reply();
+    }
+
+    fn try_reply(&mut self) {
+        if self.received < self.expected {
+            return;
+        }
+        if let Some(ask) = self.ask.take() {
+            let mut values = std::mem::take(&mut self.values);
+            values.sort_by_key(|(id, _)| *id);
+            ask.reply(values.into_iter().map(|(_, v)| v).collect())
+                .expect("Failed to reply to master");
+        }
+    }
+}
+
+/// Runs in the master's system and talks to the client hosts on behalf of the master.
+#[derive(ComponentDefinition)]
+pub struct RemoteClients {
+    ctx: ComponentContext<Self>,
+    hosts: Vec<ActorPath>, // that got a workload in the current iteration
+    prepared: Replies<ActorPath>,
+    leaders_known: Replies<()>,
+    finished: Replies<()>,
+    stopped: Replies<MetaResults>,
+}
+
+impl RemoteClients {
+    pub fn new() -> RemoteClients {
+        RemoteClients {
+            ctx: ComponentContext::uninitialised(),
+            hosts: vec![],
+            prepared: Replies::with(0),
+            leaders_known: Replies::with(0),
+            finished: Replies::with(0),
+            stopped: Replies::with(0),
+        }
+    }
+
+    fn tell_hosts(&self, msg: impl Fn() -> ClientHostMsg) {
+        for host in &self.hosts {
+            host.tell((msg(), ClientHostSer), self);
+        }
+    }
+}
+
+ignore_lifecycle!(RemoteClients);
+
+impl Actor for RemoteClients {
+    type Message = RemoteClientsMsg;
+
+    fn receive_local(&mut self, msg: Self::Message) -> Handled {
+        match msg {
+            RemoteClientsMsg::Prepare(workloads) => {
+                let n = workloads.len();
+                self.prepared = Replies::with(n);
+                self.leaders_known = Replies::with(n);
+                self.finished = Replies::with(n);
+                self.stopped = Replies::with(n);
+                self.hosts.clear();
+                for (host, workload) in workloads {
+                    host.tell((ClientHostMsg::Prepare(workload), ClientHostSer), self);
+                    self.hosts.push(host);
+                }
+            }
+            RemoteClientsMsg::AwaitPrepared(ask) => self.prepared.wait(ask),
+            RemoteClientsMsg::AwaitLeaders(ask) => self.leaders_known.wait(ask),
+            RemoteClientsMsg::Run(run_start) => self.tell_hosts(|| ClientHostMsg::Run(run_start)),
+            RemoteClientsMsg::AwaitFinished(ask) => self.finished.wait(ask),
+            RemoteClientsMsg::Stop(ask) => {
+                self.tell_hosts(|| ClientHostMsg::Stop);
+                self.stopped.wait(ask);
+            }
+        }
+        Handled::Ok
+    }
+
+    fn receive_network(&mut self, m: NetMessage) -> Handled {
+        let NetMessage { data, .. } = m;
+        match_deser! {data; {
+            msg: ClientHostMsg [ClientHostSer] => {
+                match msg {
+                    ClientHostMsg::Prepared(paths) => self.prepared.add(paths),
+                    ClientHostMsg::LeadersKnown => self.leaders_known.add(vec![]),
+                    ClientHostMsg::Finished => self.finished.add(vec![]),
+                    ClientHostMsg::Stopped(results) => self.stopped.add(results),
+                    _ => error!(self.ctx.log(), "RemoteClients received unexpected msg: {:?}", msg),
+                }
+            },
+            !Err(e) => error!(self.ctx.log(), "RemoteClients failed to deserialise msg: {:?}", e),
+        }
+        }
+        Handled::Ok
+    }
+}
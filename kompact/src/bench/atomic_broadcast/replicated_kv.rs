@@ -0,0 +1,162 @@
+use super::super::*;
+use super::atomic_broadcast::{
+    load_hocon, AtomicBroadcast, AtomicBroadcastClient, AtomicBroadcastMaster, ClientParams,
+    ClientProcess, CONFIG_PATH,
+};
+use super::kv::{KvConfig, ReadMode, Workload};
+use crate::kompact_system_provider::{is_missing, WithOverrides};
+use benchmark_suite_shared::kompics_benchmarks::benchmarks::{
+    AtomicBroadcastRequest, ReplicatedKVRequest,
+};
+use kompact::prelude::*;
+
+/// A key-value store replicated with atomic broadcast: the replicas apply the decided GET, PUT and CAS commands.
+#[derive(Default)]
+pub struct ReplicatedKV;
+
+impl DistributedBenchmark for ReplicatedKV {
+    type MasterConf = ReplicatedKVRequest;
+    type ClientConf = WithOverrides<ClientParams>;
+    type ClientData = ClientProcess;
+    type Master = ReplicatedKVMaster;
+    type Client = AtomicBroadcastClient;
+    const LABEL: &'static str = "ReplicatedKV";
+
+    fn new_master() -> Self::Master {
+        ReplicatedKVMaster::new()
+    }
+
+    fn msg_to_master_conf(
+        msg: Box<dyn (::protobuf::Message)>,
+    ) -> Result<Self::MasterConf, BenchmarkError> {
+        downcast_kompact_msg!(msg; ReplicatedKVRequest)
+    }
+
+    fn new_client() -> Self::Client {
+        AtomicBroadcastClient::new()
+    }
+
+    fn str_to_client_conf(s: String) -> Result<Self::ClientConf, BenchmarkError> {
+        AtomicBroadcast::str_to_client_conf(s)
+    }
+
+    fn str_to_client_data(str: String) -> Result<Self::ClientData, BenchmarkError> {
+        AtomicBroadcast::str_to_client_data(str)
+    }
+
+    fn client_conf_to_str(c: Self::ClientConf) -> String {
+        AtomicBroadcast::client_conf_to_str(c)
+    }
+
+    fn client_data_to_str(d: Self::ClientData) -> String {
+        AtomicBroadcast::client_data_to_str(d)
+    }
+}
+
+/// Runs the key-value workload with the master of the atomic broadcast benchmark.
+pub struct ReplicatedKVMaster {
+    atomic_broadcast: AtomicBroadcastMaster,
+}
+
+impl ReplicatedKVMaster {
+    fn new() -> ReplicatedKVMaster {
+        ReplicatedKVMaster {
+            atomic_broadcast: AtomicBroadcastMaster::new(),
+        }
+    }
+
+    fn validate_kv(c: &ReplicatedKVRequest) -> Result<KvConfig, BenchmarkError> {
+        let workload = Workload::parse(&c.workload).map_err(BenchmarkError::InvalidTest)?;
+        let read_mode = ReadMode::parse(&c.read_mode).map_err(BenchmarkError::InvalidTest)?;
+        if c.number_of_keys == 0 {
+            return Err(BenchmarkError::InvalidTest(
+                "number_of_keys should be at least 1".to_string(),
+            ));
+        }
+        let num_clients = std::cmp::max(c.in_process_clients, 1);
+        let ops_per_client = (c.number_of_operations + num_clients - 1) / num_clients;
+        if ops_per_client > 1 << 24 {
+            return Err(BenchmarkError::InvalidTest(format!(
+                "At most 2^24 operations per client are supported, but found: {}",
+                ops_per_client
+            )));
+        }
+        if read_mode != ReadMode::Log && c.algorithm.to_lowercase() != "raft" {
+            return Err(BenchmarkError::InvalidTest(format!(
+                "{} reads are only implemented for Raft, but found: {}",
+                read_mode.as_str(),
+                c.algorithm
+            )));
+        }
+        Ok(KvConfig {
+            workload,
+            num_keys: c.number_of_keys,
+            read_mode,
+            check_linearizability: Self::load_check_linearizability(&c.experiment_config).map_err(
+                |e| BenchmarkError::InvalidTest(format!("Invalid experiment config: {}", e)),
+            )?,
+        })
+    }
+
+    /// Whether the history of each iteration should be checked, which is only feasible for small runs.
+    fn load_check_linearizability(overrides: &str) -> Result<bool, String> {
+        let config = load_hocon(Some(CONFIG_PATH), overrides)?;
+        match &config["experiment"]["check_linearizability"] {
+            v if is_missing(v) => Ok(false),
+            v => v.as_bool().ok_or_else(|| {
+                format!(
+                    "experiment.check_linearizability should be a boolean, but was {:?}",
+                    v
+                )
+            }),
+        }
+    }
+}
+
+impl DistributedBenchmarkMaster for ReplicatedKVMaster {
+    type MasterConf = ReplicatedKVRequest;
+    type ClientConf = WithOverrides<ClientParams>;
+    type ClientData = ClientProcess;
+
+    fn setup(
+        &mut self,
+        c: Self::MasterConf,
+        m: &DeploymentMetaData,
+    ) -> Result<Self::ClientConf, BenchmarkError> {
+        println!("Setting up Replicated KV (Master)");
+        let kv = Self::validate_kv(&c)?;
+        let mut ab = AtomicBroadcastRequest::new();
+        ab.set_algorithm(c.algorithm);
+        ab.set_number_of_nodes(c.number_of_nodes);
+        ab.set_number_of_proposals(c.number_of_operations);
+        ab.set_concurrent_proposals(c.concurrent_operations);
+        ab.set_in_process_clients(c.in_process_clients);
+        ab.set_reconfiguration("off".to_string());
+        ab.set_reconfig_policy("none".to_string());
+        ab.set_storage(c.storage);
+        ab.set_system_overrides(c.system_overrides);
+        ab.set_experiment_config(c.experiment_config);
+        self.atomic_broadcast.setup_kv(ab, kv, m)
+    }
+
+    fn system_config(&self) -> Option<String> {
+        self.atomic_broadcast.system_config()
+    }
+
+    fn prepare_iteration(&mut self, d: Vec<Self::ClientData>) -> () {
+        self.atomic_broadcast.prepare_iteration(d)
+    }
+
+    fn run_iteration(&mut self) -> () {
+        self.atomic_broadcast.run_iteration()
+    }
+
+    fn validate_iteration(&mut self) -> Result<(), BenchmarkError> {
+        self.atomic_broadcast.validate_iteration()
+    }
+
+    fn cleanup_iteration(&mut self, last_iteration: bool, exec_time_millis: f64) -> () {
+        self.atomic_broadcast
+            .cleanup_iteration(last_iteration, exec_time_millis)
+    }
+}
@@ -0,0 +1,578 @@
+use super::client::{
+    ClientStats, OpenLoopResults, ReconfigTiming, ThroughputSeries, TimelineEvent,
+};
+use super::fault::{FaultRecord, NetworkFaultRecord};
+use super::handover::TransferRecord;
+use super::kv::{KvOp, KvResults};
+use super::storage::IoStats;
+use hdrhistogram::Histogram;
+use std::fmt::Write as FmtWrite;
+use std::fs::{create_dir_all, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// The latency quantiles that are recorded for each iteration.
+const QUANTILES: [f64; 15] = [
+    0.001, 0.005, 0.01, 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9, 0.95, 0.99, 0.999,
+];
+
+/// The latency in micros of the decided proposals of an iteration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencySummary {
+    pub count: u64,
+    pub min: u64,
+    pub max: u64,
+    pub mean: f64,
+    pub quantiles: Vec<(f64, u64)>,
+}
+
+impl LatencySummary {
+    pub fn from_histogram(hist: &Histogram<u64>) -> LatencySummary {
+        LatencySummary {
+            count: hist.len(),
+            min: hist.min(),
+            max: hist.max(),
+            mean: hist.mean(),
+            quantiles: QUANTILES
+                .iter()
+                .map(|q| (*q, hist.value_at_quantile(*q)))
+                .collect(),
+        }
+    }
+}
+
+/// What the clients observed in one iteration of an experiment, written as a single JSON object.
+#[derive(Debug, Clone)]
+pub struct IterationRecord<'a> {
+    pub experiment: &'a str,
+    pub params: &'a [(&'static str, String)],
+    pub iteration: u32,
+    pub exec_time_millis: f64,
+    pub num_timed_out: u64,
+    pub leader_changes: &'a [u64],
+    pub latency: Option<LatencySummary>,
+    pub reconfigurations: &'a [ReconfigTiming],
+    pub leader_transfers: &'a [TransferRecord],
+    pub faults: &'a [FaultRecord],
+    pub network_faults: &'a [NetworkFaultRecord],
+    pub open_loop: Option<&'a OpenLoopResults>,
+    pub clients: &'a [ClientStats],
+    pub kv: Option<&'a KvResults>,
+    pub storage_io: &'a [(u64, IoStats)], // (pid, I/O of its storage)
+}
+
+impl<'a> IterationRecord<'a> {
+    pub fn to_json(&self) -> String {
+        let mut s = String::new();
+        write!(s, "{{\"experiment\":{}", json_string(self.experiment)).unwrap();
+        s.push_str(",\"params\":{");
+        for (i, (key, value)) in self.params.iter().enumerate() {
+            if i > 0 {
+                s.push(',');
+            }
+            write!(s, "{}:{}", json_string(key), json_string(value)).unwrap();
+        }
+        s.push('}');
+        write!(
+            s,
+            ",\"iteration\":{},\"exec_time_millis\":{},\"num_timed_out\":{}",
+            self.iteration,
+            json_number(self.exec_time_millis),
+            self.num_timed_out
+        )
+        .unwrap();
+        let leader_changes: Vec<String> =
+            self.leader_changes.iter().map(|l| l.to_string()).collect();
+        write!(s, ",\"leader_changes\":[{}]", leader_changes.join(",")).unwrap();
+        s.push_str(",\"latency_micros\":");
+        match &self.latency {
+            Some(l) => {
+                write!(
+                    s,
+                    "{{\"count\":{},\"min\":{},\"max\":{},\"mean\":{},\"quantiles\":{{",
+                    l.count,
+                    l.min,
+                    l.max,
+                    json_number(l.mean)
+                )
+                .unwrap();
+                for (i, (q, value)) in l.quantiles.iter().enumerate() {
+                    if i > 0 {
+                        s.push(',');
+                    }
+                    write!(s, "\"{}\":{}", q, value).unwrap();
+                }
+                s.push_str("}}");
+            }
+            None => s.push_str("null"),
+        }
+        let reconfigurations: Vec<String> = self
+            .reconfigurations
+            .iter()
+            .map(|r| {
+                format!(
+                    "{{\"proposed_at_millis\":{},\"decided_after_millis\":{}}}",
+                    r.proposed_at.as_millis(),
+                    r.decided_after
+                        .map_or("null".to_string(), |d| d.as_millis().to_string())
+                )
+            })
+            .collect();
+        write!(s, ",\"reconfigurations\":[{}]", reconfigurations.join(",")).unwrap();
+        let to_millis =
+            |d: Option<Duration>| d.map_or("null".to_string(), |d| d.as_millis().to_string());
+        let leader_transfers: Vec<String> = self
+            .leader_transfers
+            .iter()
+            .map(|t| {
+                format!(
+                    "{{\"from\":{},\"to\":{},\"requested_at_millis\":{},\"elected_after_millis\":{},\"resumed_after_millis\":{}}}",
+                    t.from,
+                    t.to,
+                    t.requested_at.as_millis(),
+                    to_millis(t.elected_after),
+                    to_millis(t.resumed_after)
+                )
+            })
+            .collect();
+        write!(s, ",\"leader_transfers\":[{}]", leader_transfers.join(",")).unwrap();
+        let faults: Vec<String> = self
+            .faults
+            .iter()
+            .map(|f| {
+                format!(
+                    "{{\"pid\":{},\"was_leader\":{},\"downtime_millis\":{},\"election_time_millis\":{},\"unavailability_millis\":{}}}",
+                    f.pid,
+                    f.was_leader,
+                    to_millis(f.downtime),
+                    to_millis(f.election_time),
+                    to_millis(f.unavailability)
+                )
+            })
+            .collect();
+        write!(s, ",\"faults\":[{}]", faults.join(",")).unwrap();
+        let network_faults: Vec<String> = self
+            .network_faults
+            .iter()
+            .map(|f| {
+                format!(
+                    "{{\"fault\":{},\"window_millis\":{},\"leader_changes\":{},\"decided\":{},\"timed_out\":{},\"max_stall_millis\":{}}}",
+                    json_string(&f.fault),
+                    f.window.as_millis(),
+                    f.leader_changes,
+                    f.decided,
+                    f.timed_out,
+                    f.max_stall.as_millis()
+                )
+            })
+            .collect();
+        write!(s, ",\"network_faults\":[{}]", network_faults.join(",")).unwrap();
+        s.push_str(",\"open_loop\":");
+        match self.open_loop {
+            Some(ol) => write!(
+                s,
+                "{{\"target_rate\":{},\"offered_rate\":{},\"achieved_rate\":{},\"decided\":{},\"p99_latency_micros\":{}}}",
+                ol.target_rate,
+                json_number(ol.offered_rate),
+                json_number(ol.achieved_rate),
+                ol.decided,
+                ol.latency_hist.value_at_quantile(0.99)
+            )
+            .unwrap(),
+            None => s.push_str("null"),
+        }
+        s.push_str(&self.clients_json());
+        s.push_str(",\"kv\":");
+        match self.kv {
+            Some(kv) => {
+                write!(s, "{{\"failed_cas\":{},\"ops\":{{", kv.failed_cas).unwrap();
+                let ops: Vec<String> = KvOp::ALL
+                    .iter()
+                    .filter_map(|op| kv.latency_hists.get(op).map(|hist| (op, hist)))
+                    .map(|(op, hist)| {
+                        format!(
+                            "\"{}\":{{\"count\":{},\"mean_micros\":{},\"p50_micros\":{},\"p99_micros\":{},\"p999_micros\":{}}}",
+                            op.as_str(),
+                            hist.len(),
+                            json_number(hist.mean()),
+                            hist.value_at_quantile(0.5),
+                            hist.value_at_quantile(0.99),
+                            hist.value_at_quantile(0.999)
+                        )
+                    })
+                    .collect();
+                s.push_str(&ops.join(","));
+                s.push_str("}}");
+            }
+            None => s.push_str("null"),
+        }
+        let storage_io: Vec<String> = self
+            .storage_io
+            .iter()
+            .map(|(pid, io)| {
+                format!(
+                    "{{\"pid\":{},\"bytes_written\":{},\"fsyncs\":{}}}",
+                    pid, io.bytes_written, io.fsyncs
+                )
+            })
+            .collect();
+        write!(s, ",\"storage_io\":[{}]", storage_io.join(",")).unwrap();
+        s.push('}');
+        s
+    }
+
+    /// The total throughput in proposals and bytes per second, the fairness between the clients and the
+    /// throughput of each client.
+    fn clients_json(&self) -> String {
+        let secs = self.exec_time_millis / 1000.0;
+        let decided: u64 = self.clients.iter().map(|c| c.decided).sum();
+        let decided_bytes: u64 = self.clients.iter().map(|c| c.decided_bytes).sum();
+        let throughputs: Vec<f64> = self.clients.iter().map(|c| c.throughput).collect();
+        let per_client: Vec<String> = throughputs.iter().map(|t| json_number(*t)).collect();
+        format!(
+            ",\"throughput\":{},\"byte_throughput\":{},\"fairness\":{},\"client_throughputs\":[{}]",
+            json_number(decided as f64 / secs),
+            json_number(decided_bytes as f64 / secs),
+            json_number(jain_fairness(&throughputs)),
+            per_client.join(",")
+        )
+    }
+}
+
+/// Jain's fairness index of the given throughputs: 1 if they are all equal, down to 1/n if one gets everything.
+fn jain_fairness(throughputs: &[f64]) -> f64 {
+    let sum: f64 = throughputs.iter().sum();
+    let sum_of_squares: f64 = throughputs.iter().map(|t| t * t).sum();
+    if sum_of_squares == 0.0 {
+        return 1.0;
+    }
+    sum * sum / (throughputs.len() as f64 * sum_of_squares)
+}
+
+/// The throughput series of an iteration as a single JSON object. The events are placed at their offset in micros
+/// from the start of the run.
+pub fn throughput_json(iteration: u32, series: &ThroughputSeries) -> String {
+    let decided: Vec<String> = series.decided.iter().map(|d| d.to_string()).collect();
+    let events: Vec<String> = series
+        .events
+        .iter()
+        .map(|(at, event)| {
+            let detail = match event {
+                TimelineEvent::LeaderChange(pid)
+                | TimelineEvent::Crash(pid)
+                | TimelineEvent::LeaderTransfer(pid) => {
+                    format!(",\"pid\":{}", pid)
+                }
+                TimelineEvent::NetworkFault(fault) => format!(",\"fault\":{}", json_string(fault)),
+                TimelineEvent::ReconfigProposed | TimelineEvent::ReconfigDecided => String::new(),
+            };
+            format!(
+                "{{\"at_micros\":{},\"event\":\"{}\"{}}}",
+                at.as_micros(),
+                event.as_str(),
+                detail
+            )
+        })
+        .collect();
+    format!(
+        "{{\"iteration\":{},\"window_micros\":{},\"decided\":[{}],\"events\":[{}]}}",
+        iteration,
+        series.window.as_micros(),
+        decided.join(","),
+        events.join(",")
+    )
+}
+
+/// The latency in micros of each decided proposal of an iteration as a single JSON object, ordered by proposal id.
+pub fn latencies_json(iteration: u32, latencies: &[(u64, Duration)]) -> String {
+    let mut sorted: Vec<&(u64, Duration)> = latencies.iter().collect();
+    sorted.sort_by_key(|(id, _)| *id);
+    let ids: Vec<String> = sorted.iter().map(|(id, _)| id.to_string()).collect();
+    let micros: Vec<String> = sorted
+        .iter()
+        .map(|(_, l)| l.as_micros().to_string())
+        .collect();
+    format!(
+        "{{\"iteration\":{},\"proposal_ids\":[{}],\"latency_micros\":[{}]}}",
+        iteration,
+        ids.join(","),
+        micros.join(",")
+    )
+}
+
+/// The name of the files of `experiment`. The experiment string contains the fault and reconfiguration specs, so every
+/// byte that is not safe in a file name, including `%` itself, is percent-encoded, which keeps the names distinct.
+pub fn file_name(experiment: &str) -> String {
+    let mut name = String::with_capacity(experiment.len());
+    for b in experiment.bytes() {
+        match b {
+            b'a'..=b'z'
+            | b'A'..=b'Z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'_'
+            | b'.'
+            | b','
+            | b'='
+            | b'+'
+            | b'@' => name.push(b as char),
+            b => write!(name, "%{:02X}", b).unwrap(),
+        }
+    }
+    name
+}
+
+fn json_string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(escaped, "\\u{:04x}", c as u32).unwrap(),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// JSON has no NaN or infinity, e.g. the mean of an empty histogram.
+fn json_number(x: f64) -> String {
+    if x.is_finite() {
+        x.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+/// Appends `line` to the file at `path` with a single write to a file opened in append mode, so the records of an
+/// experiment never interleave and each iteration only writes its own record.
+pub fn append_line(path: &Path, line: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut record = String::with_capacity(line.len() + 1);
+    record.push_str(line);
+    record.push('\n');
+    file.write_all(record.as_bytes())?;
+    file.sync_data()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iteration_record_json_test() {
+        let params = vec![
+            ("algorithm", "paxos".to_string()),
+            ("fault_schedule", "crash \"leader\"".to_string()),
+        ];
+        let mut hist = Histogram::<u64>::new(4).unwrap();
+        hist.record(100).unwrap();
+        let record = IterationRecord {
+            experiment: "paxos,3",
+            params: &params,
+            iteration: 2,
+            exec_time_millis: 10000.0,
+            num_timed_out: 1,
+            leader_changes: &[1, 3],
+            latency: Some(LatencySummary::from_histogram(&hist)),
+            reconfigurations: &[
+                ReconfigTiming {
+                    proposed_at: Duration::from_millis(40),
+                    decided_after: Some(Duration::from_millis(15)),
+                },
+                ReconfigTiming {
+                    proposed_at: Duration::from_millis(90),
+                    decided_after: None,
+                },
+            ],
+            leader_transfers: &[TransferRecord {
+                from: 1,
+                to: 3,
+                requested_at: Duration::from_millis(60),
+                elected_after: Some(Duration::from_millis(8)),
+                resumed_after: None,
+            }],
+            faults: &[FaultRecord {
+                pid: 1,
+                was_leader: true,
+                downtime: Some(Duration::from_millis(500)),
+                election_time: Some(Duration::from_millis(120)),
+                unavailability: None,
+            }],
+            network_faults: &[NetworkFaultRecord {
+                fault: "partition(1|*)".to_string(),
+                window: Duration::from_millis(1000),
+                leader_changes: 1,
+                decided: 42,
+                timed_out: 2,
+                max_stall: Duration::from_millis(300),
+            }],
+            open_loop: None,
+            clients: &[
+                ClientStats {
+                    decided: 30,
+                    throughput: 3.0,
+                    decided_bytes: 300,
+                    byte_throughput: 30.0,
+                },
+                ClientStats {
+                    decided: 10,
+                    throughput: 1.0,
+                    decided_bytes: 100,
+                    byte_throughput: 10.0,
+                },
+            ],
+            kv: None,
+            storage_io: &[(
+                2,
+                IoStats {
+                    bytes_written: 4096,
+                    fsyncs: 3,
+                },
+            )],
+        };
+        let json = record.to_json();
+        assert!(json.starts_with(
+            "{\"experiment\":\"paxos,3\",\"params\":{\"algorithm\":\"paxos\",\"fault_schedule\":\"crash \\\"leader\\\"\"}"
+        ));
+        assert!(json.contains(",\"iteration\":2,\"exec_time_millis\":10000,\"num_timed_out\":1,"));
+        assert!(json.contains("\"leader_changes\":[1,3]"));
+        assert!(json.contains("\"count\":1,\"min\":100,\"max\":100,\"mean\":100,"));
+        assert!(json.contains("\"0.5\":100"));
+        assert!(json.contains(
+            "\"reconfigurations\":[{\"proposed_at_millis\":40,\"decided_after_millis\":15},\
+             {\"proposed_at_millis\":90,\"decided_after_millis\":null}]"
+        ));
+        assert!(json.contains(
+            "\"leader_transfers\":[{\"from\":1,\"to\":3,\"requested_at_millis\":60,\
+             \"elected_after_millis\":8,\"resumed_after_millis\":null}]"
+        ));
+        assert!(json.contains(
+            "\"faults\":[{\"pid\":1,\"was_leader\":true,\"downtime_millis\":500,\
+             \"election_time_millis\":120,\"unavailability_millis\":null}]"
+        ));
+        assert!(json.contains(
+            "\"network_faults\":[{\"fault\":\"partition(1|*)\",\"window_millis\":1000,\
+             \"leader_changes\":1,\"decided\":42,\"timed_out\":2,\"max_stall_millis\":300}]"
+        ));
+        assert!(json.contains("\"open_loop\":null"));
+        assert!(json.contains(
+            "\"throughput\":4,\"byte_throughput\":40,\"fairness\":0.8,\"client_throughputs\":[3,1]"
+        ));
+        assert!(json.contains("\"kv\":null"));
+        assert!(json.ends_with("\"storage_io\":[{\"pid\":2,\"bytes_written\":4096,\"fsyncs\":3}]}"));
+        assert!(!json.contains('\n'));
+    }
+
+    #[test]
+    fn throughput_json_test() {
+        let mut series = ThroughputSeries::with(Duration::from_millis(10));
+        series.record_decided(Duration::from_millis(3));
+        series.record_decided(Duration::from_millis(9));
+        series.record_decided(Duration::from_millis(25));
+        series.record_event(Duration::from_millis(12), TimelineEvent::Crash(2));
+        series.record_event(Duration::from_millis(14), TimelineEvent::LeaderChange(3));
+        series.record_event(Duration::from_millis(20), TimelineEvent::ReconfigProposed);
+        series.record_event(Duration::from_millis(22), TimelineEvent::LeaderTransfer(1));
+        assert_eq!(
+            "{\"iteration\":1,\"window_micros\":10000,\"decided\":[2,0,1],\"events\":[\
+             {\"at_micros\":12000,\"event\":\"crash\",\"pid\":2},\
+             {\"at_micros\":14000,\"event\":\"leader_change\",\"pid\":3},\
+             {\"at_micros\":20000,\"event\":\"reconfig_proposed\"},\
+             {\"at_micros\":22000,\"event\":\"leader_transfer\",\"pid\":1}]}",
+            throughput_json(1, &series)
+        );
+    }
+
+    #[test]
+    fn kv_json_test() {
+        let mut hist = Histogram::<u64>::new(4).unwrap();
+        hist.record(200).unwrap();
+        let mut latency_hists = hashbrown::HashMap::new();
+        latency_hists.insert(KvOp::Cas, hist);
+        let kv = KvResults {
+            latency_hists,
+            failed_cas: 3,
+            timestamps: None,
+        };
+        let record = IterationRecord {
+            experiment: "raft,3",
+            params: &[],
+            iteration: 1,
+            exec_time_millis: 0.0,
+            num_timed_out: 0,
+            leader_changes: &[],
+            latency: None,
+            reconfigurations: &[],
+            leader_transfers: &[],
+            faults: &[],
+            network_faults: &[],
+            open_loop: None,
+            clients: &[],
+            kv: Some(&kv),
+            storage_io: &[],
+        };
+        let json = record.to_json();
+        assert!(json.contains(
+            "\"kv\":{\"failed_cas\":3,\"ops\":{\"cas\":{\"count\":1,\"mean_micros\":200,\
+             \"p50_micros\":200,\"p99_micros\":200,\"p999_micros\":200}}}"
+        ));
+        assert!(json.contains("\"throughput\":null,\"byte_throughput\":null,\"fairness\":1,"));
+    }
+
+    #[test]
+    fn jain_fairness_test() {
+        assert_eq!(1.0, jain_fairness(&[5.0, 5.0, 5.0]));
+        assert_eq!(0.5, jain_fairness(&[4.0, 0.0]));
+        assert_eq!(1.0, jain_fairness(&[]));
+    }
+
+    #[test]
+    fn latencies_json_test() {
+        let latencies = vec![
+            (3, Duration::from_micros(250)),
+            (1, Duration::from_millis(1)),
+            (2, Duration::from_micros(90)),
+        ];
+        assert_eq!(
+            "{\"iteration\":4,\"proposal_ids\":[1,2,3],\"latency_micros\":[1000,90,250]}",
+            latencies_json(4, &latencies)
+        );
+        assert_eq!(
+            "{\"iteration\":1,\"proposal_ids\":[],\"latency_micros\":[]}",
+            latencies_json(1, &[])
+        );
+    }
+
+    #[test]
+    fn file_name_test() {
+        assert_eq!("paxos,3,1000,none", file_name("paxos,3,1000,none"));
+        assert_eq!(
+            "leader@0.3%3Bpartition%28leader%7C%2A%29@0.6%3A1000",
+            file_name("leader@0.3;partition(leader|*)@0.6:1000")
+        );
+        assert_eq!("a%3Eb%2Fc", file_name("a>b/c"));
+        assert_ne!(file_name("a%7C"), file_name("a|"));
+        assert!(!file_name("../x").contains('/'));
+    }
+
+    #[test]
+    fn append_line_test() {
+        let dir = std::env::temp_dir().join(format!("ab_results_test_{}", std::process::id()));
+        let path = dir.join("records.jsonl");
+        append_line(&path, "{\"a\":1}").unwrap();
+        append_line(&path, "{\"a\":2}").unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!("{\"a\":1}\n{\"a\":2}\n", content);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,512 @@
+//! Deterministic simulation of the replication algorithms: the sans-IO cores are driven in virtual steps over a
+//! seeded network that delays, reorders, drops and duplicates messages, and the decided sequences are checked after
+//! every step.
+
+mod paxos;
+mod raft;
+
+use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Number of seeds a simulation test runs, overridden by `SIM_SEEDS` to run longer e.g. in CI.
+pub fn num_seeds(default: u64) -> u64 {
+    std::env::var("SIM_SEEDS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(default)
+}
+
+/// A single seed to replay instead, given by `SIM_SEED`.
+pub fn replay_seed() -> Option<u64> {
+    std::env::var("SIM_SEED").ok().and_then(|s| s.parse().ok())
+}
+
+/// How badly the simulated network treats the messages.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Chaos {
+    pub drop: f64,      // probability that a message is lost
+    pub duplicate: f64, // probability that a message is delivered twice
+    pub reorder: f64,   // probability that a message is held back for up to max_delay steps
+    pub max_delay: u64,
+    pub max_down: u64, // longest number of steps a link stays down after a drop
+}
+
+impl Chaos {
+    pub fn none() -> Chaos {
+        Chaos {
+            drop: 0.0,
+            duplicate: 0.0,
+            reorder: 0.0,
+            max_delay: 0,
+            max_down: 1,
+        }
+    }
+
+    pub fn random<R: Rng>(rng: &mut R) -> Chaos {
+        Chaos {
+            drop: rng.gen_range(0.0, 0.05),
+            duplicate: rng.gen_range(0.0, 0.1),
+            reorder: rng.gen_range(0.0, 0.3),
+            max_delay: rng.gen_range(1, 10),
+            max_down: rng.gen_range(1, 30),
+        }
+    }
+}
+
+/// What the network hands to the nodes in a step.
+#[derive(Clone, Debug)]
+pub enum Delivery<M> {
+    Msg {
+        from: u64,
+        to: u64,
+        msg: M,
+    },
+    /// A message from `from` to `to` was dropped, `from` sends nothing more to `to` until the link is up again.
+    LinkDown {
+        from: u64,
+        to: u64,
+    },
+    LinkUp {
+        from: u64,
+        to: u64,
+    },
+}
+
+struct Packet<M> {
+    deliver_at: u64,
+    from: u64,
+    to: u64,
+    session: u64,
+    seq: u64,
+    msg: M,
+}
+
+/// The state of the sessions of a directed link, like the connections the communicator keeps to a peer.
+struct Link<M> {
+    session: u64,
+    next_seq: u64,
+    expected_seq: u64,
+    buffered: BTreeMap<u64, M>,
+    down_until: Option<u64>,
+}
+
+impl<M> Link<M> {
+    fn new() -> Link<M> {
+        Link {
+            session: 0,
+            next_seq: 0,
+            expected_seq: 0,
+            buffered: BTreeMap::new(),
+            down_until: None,
+        }
+    }
+}
+
+/// A seeded network between the nodes of a configuration.
+///
+/// With `sessions`, messages are numbered per link and delivered in order and exactly once, and a drop brings the link
+/// down until it is healed, which is the contract of the communicator that the Paxos replicas rely on. Without, every
+/// message is subject to the chaos on its own.
+pub struct SimNetwork<M> {
+    rng: SmallRng,
+    chaos: Chaos,
+    sessions: bool,
+    in_flight: Vec<Packet<M>>,
+    links: BTreeMap<(u64, u64), Link<M>>,
+    notifications: Vec<Delivery<M>>,
+}
+
+impl<M: Clone> SimNetwork<M> {
+    pub fn with(seed: u64, chaos: Chaos, sessions: bool) -> SimNetwork<M> {
+        SimNetwork {
+            rng: SmallRng::seed_from_u64(seed),
+            chaos,
+            sessions,
+            in_flight: vec![],
+            links: BTreeMap::new(),
+            notifications: vec![],
+        }
+    }
+
+    pub fn send(&mut self, now: u64, from: u64, to: u64, msg: M) {
+        let link = self.links.entry((from, to)).or_insert_with(Link::new);
+        if link.down_until.is_some() {
+            return;
+        }
+        if self.rng.gen_bool(self.chaos.drop) {
+            if self.sessions {
+                link.down_until = Some(now + 1 + self.rng.gen_range(1, self.chaos.max_down + 1));
+                self.notifications.push(Delivery::LinkDown { from, to });
+            }
+            return;
+        }
+        let seq = link.next_seq;
+        link.next_seq += 1;
+        let session = link.session;
+        let copies = if self.rng.gen_bool(self.chaos.duplicate) {
+            2
+        } else {
+            1
+        };
+        for _ in 0..copies {
+            let delay = if self.chaos.max_delay > 0 && self.rng.gen_bool(self.chaos.reorder) {
+                self.rng.gen_range(1, self.chaos.max_delay + 1)
+            } else {
+                0
+            };
+            self.in_flight.push(Packet {
+                deliver_at: now + 1 + delay,
+                from,
+                to,
+                session,
+                seq,
+                msg: msg.clone(),
+            });
+        }
+    }
+
+    /// Everything that reaches the nodes in step `now`, in a random order.
+    pub fn deliver(&mut self, now: u64) -> Vec<Delivery<M>> {
+        let mut deliveries = std::mem::take(&mut self.notifications);
+        for (&(from, to), link) in self.links.iter_mut() {
+            if link.down_until.map_or(false, |t| t <= now) {
+                // a new session starts from scratch, anything still in flight from the old one is discarded
+                link.down_until = None;
+                link.session += 1;
+                link.next_seq = 0;
+                link.expected_seq = 0;
+                link.buffered.clear();
+                deliveries.push(Delivery::LinkUp { from, to });
+            }
+        }
+        let (mut due, in_flight): (Vec<_>, Vec<_>) = std::mem::take(&mut self.in_flight)
+            .into_iter()
+            .partition(|p| p.deliver_at <= now);
+        self.in_flight = in_flight;
+        due.shuffle(&mut self.rng);
+        for p in due {
+            if !self.sessions {
+                deliveries.push(Delivery::Msg {
+                    from: p.from,
+                    to: p.to,
+                    msg: p.msg,
+                });
+                continue;
+            }
+            let link = self.links.get_mut(&(p.from, p.to)).expect("Unknown link");
+            if p.session != link.session || p.seq < link.expected_seq {
+                continue; // old session or duplicate
+            }
+            link.buffered.insert(p.seq, p.msg);
+            while let Some(msg) = link.buffered.remove(&link.expected_seq) {
+                link.expected_seq += 1;
+                deliveries.push(Delivery::Msg {
+                    from: p.from,
+                    to: p.to,
+                    msg,
+                });
+            }
+        }
+        deliveries
+    }
+}
+
+/// A decided entry as seen by the invariant checks, independent of the algorithm.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SimEntry {
+    Normal(u64),
+    /// The id of the next configuration and its nodes.
+    StopSign(u32, Vec<u64>),
+}
+
+/// The total-order invariants, checked every time a node delivers newly decided entries.
+#[derive(Default)]
+pub struct Invariants {
+    proposed: BTreeSet<u64>,
+    decided: BTreeMap<(u32, u64), Vec<SimEntry>>, // (config_id, pid) -> decided sequence
+}
+
+impl Invariants {
+    pub fn proposed(&mut self, value: u64) {
+        self.proposed.insert(value);
+    }
+
+    /// Appends what `pid` decided in `config_id` to its sequence and checks it against the sequences of the others.
+    pub fn decided(
+        &mut self,
+        config_id: u32,
+        pid: u64,
+        entries: Vec<SimEntry>,
+    ) -> Result<(), String> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let seq = self
+            .decided
+            .entry((config_id, pid))
+            .or_insert_with(Vec::new);
+        for e in entries {
+            if let Some(SimEntry::StopSign(..)) = seq.last() {
+                return Err(format!(
+                    "Node {} decided {:?} after the stopsign of config {}",
+                    pid, e, config_id
+                ));
+            }
+            match &e {
+                SimEntry::Normal(value) if !self.proposed.contains(value) => {
+                    return Err(format!(
+                        "Node {} decided {} in config {}, which was never proposed",
+                        pid, value, config_id
+                    ));
+                }
+                SimEntry::Normal(value) if seq.contains(&e) => {
+                    return Err(format!(
+                        "Node {} decided {} twice in config {}",
+                        pid, value, config_id
+                    ));
+                }
+                SimEntry::StopSign(next_id, _) if *next_id != config_id + 1 => {
+                    return Err(format!(
+                        "Node {} decided a stopsign for config {} in config {}",
+                        pid, next_id, config_id
+                    ));
+                }
+                _ => {}
+            }
+            seq.push(e);
+        }
+        let seq = &self.decided[&(config_id, pid)];
+        for ((other_config, other_pid), other_seq) in &self.decided {
+            if *other_config != config_id || *other_pid == pid {
+                continue;
+            }
+            let len = seq.len().min(other_seq.len());
+            if let Some(idx) = (0..len).find(|i| seq[*i] != other_seq[*i]) {
+                return Err(format!(
+                    "Nodes {} and {} disagree at index {} of config {}: {:?} vs {:?}",
+                    pid, other_pid, idx, config_id, seq[idx], other_seq[idx]
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The sequence `pid` decided in `config_id`.
+    pub fn sequence(&self, config_id: u32, pid: u64) -> &[SimEntry] {
+        self.decided
+            .get(&(config_id, pid))
+            .map_or(&[], |seq| seq.as_slice())
+    }
+
+    /// All values decided by any node.
+    pub fn decided_values(&self) -> BTreeSet<u64> {
+        self.decided
+            .values()
+            .flatten()
+            .filter_map(|e| match e {
+                SimEntry::Normal(value) => Some(*value),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Something the simulator does at a given step. The events are independent of each other, so that any subset of a
+/// schedule is a valid schedule as well.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SimEvent {
+    /// A client proposes the value to the node it considers leader.
+    Propose(u64),
+    /// Paxos: the leader election elects the node at this index of the active configuration. Raft elects its leaders
+    /// itself, so the node is cut off from its peers for a while instead, which forces an election if it leads.
+    ElectLeader(usize),
+    /// The leader is asked to reconfigure to these nodes, unless a reconfiguration is already ongoing.
+    Reconfigure(Vec<u64>),
+}
+
+/// The events of a run and when they happen.
+pub type Schedule = Vec<(u64, SimEvent)>;
+
+/// Generates a schedule of `steps` steps with proposals, leader changes and reconfigurations to nodes up to `max_pid`.
+pub fn random_schedule<R: Rng>(rng: &mut R, steps: u64, max_pid: u64) -> Schedule {
+    let mut schedule = vec![(0, SimEvent::ElectLeader(rng.gen_range(0, 5)))];
+    let num_proposals = rng.gen_range(10, 100);
+    for value in 1..=num_proposals {
+        schedule.push((rng.gen_range(1, steps), SimEvent::Propose(value)));
+    }
+    for _ in 0..rng.gen_range(0, 4) {
+        schedule.push((
+            rng.gen_range(1, steps),
+            SimEvent::ElectLeader(rng.gen_range(0, 5)),
+        ));
+    }
+    for _ in 0..rng.gen_range(0, 3) {
+        let mut pids: Vec<u64> = (1..=max_pid).collect();
+        pids.shuffle(rng);
+        pids.truncate(rng.gen_range(3, max_pid as usize + 1));
+        pids.sort();
+        schedule.push((rng.gen_range(1, steps), SimEvent::Reconfigure(pids)));
+    }
+    schedule.sort_by_key(|(step, _)| *step);
+    schedule
+}
+
+/// Removes as many events as possible from a failing schedule while `fails` keeps failing.
+pub fn shrink<E, F>(mut events: Vec<E>, mut fails: F) -> Vec<E>
+where
+    E: Clone,
+    F: FnMut(&[E]) -> bool,
+{
+    let mut chunk = events.len() / 2;
+    while chunk > 0 {
+        let mut start = 0;
+        let mut removed = false;
+        while start < events.len() {
+            let end = (start + chunk).min(events.len());
+            let candidate: Vec<E> = events[..start]
+                .iter()
+                .chain(events[end..].iter())
+                .cloned()
+                .collect();
+            if fails(&candidate) {
+                events = candidate;
+                removed = true;
+            } else {
+                start = end;
+            }
+        }
+        if !removed {
+            chunk /= 2;
+        }
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_network_test() {
+        let chaos = Chaos {
+            drop: 0.0,
+            duplicate: 0.5,
+            reorder: 0.5,
+            max_delay: 5,
+            max_down: 1,
+        };
+        let mut network = SimNetwork::with(7, chaos, true);
+        for i in 0..100 {
+            network.send(0, 1, 2, i);
+        }
+        let mut received = vec![];
+        for now in 1..=10 {
+            for d in network.deliver(now) {
+                if let Delivery::Msg { msg, .. } = d {
+                    received.push(msg);
+                }
+            }
+        }
+        assert_eq!(received, (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn session_contract_test() {
+        // whatever the chaos, a session delivers a gapless run of what was sent on the link, in order and once, and a
+        // later session only delivers what was sent after it
+        let chaos = Chaos {
+            drop: 0.05,
+            duplicate: 0.2,
+            reorder: 0.5,
+            max_delay: 5,
+            max_down: 5,
+        };
+        for seed in 0..num_seeds(20) {
+            let mut network = SimNetwork::with(seed, chaos, true);
+            let mut last: Option<u64> = None;
+            let mut new_session = true;
+            let mut sessions = 0;
+            for now in 0..500 {
+                network.send(now, 1, 2, now);
+                for d in network.deliver(now) {
+                    match d {
+                        Delivery::Msg { msg, .. } => {
+                            match last {
+                                Some(prev) if new_session => assert!(msg > prev, "seed {}", seed),
+                                Some(prev) => assert_eq!(msg, prev + 1, "seed {}", seed),
+                                None => {}
+                            }
+                            last = Some(msg);
+                            new_session = false;
+                        }
+                        Delivery::LinkUp { .. } => {
+                            new_session = true;
+                            sessions += 1;
+                        }
+                        Delivery::LinkDown { .. } => {}
+                    }
+                }
+            }
+            assert!(sessions > 0 && last.is_some(), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn link_down_test() {
+        let mut chaos = Chaos::none();
+        chaos.drop = 1.0;
+        chaos.max_down = 3;
+        let mut network = SimNetwork::with(7, chaos, true);
+        network.send(0, 1, 2, 0);
+        match network.deliver(1).as_slice() {
+            [Delivery::LinkDown { from: 1, to: 2 }] => {}
+            d => panic!("Expected the link to go down, but got {:?}", d),
+        }
+        network.send(1, 1, 2, 1); // dropped while the link is down
+        let mut up = false;
+        for now in 2..=4 {
+            for d in network.deliver(now) {
+                match d {
+                    Delivery::LinkUp { from: 1, to: 2 } => up = true,
+                    d => panic!("Unexpected delivery {:?}", d),
+                }
+            }
+        }
+        assert!(up);
+    }
+
+    #[test]
+    fn invariants_test() {
+        let mut inv = Invariants::default();
+        inv.proposed(1);
+        inv.proposed(2);
+        assert!(inv.decided(1, 1, vec![SimEntry::Normal(1)]).is_ok());
+        assert!(inv
+            .decided(1, 2, vec![SimEntry::Normal(1), SimEntry::Normal(2)])
+            .is_ok());
+        // decided twice
+        assert!(inv.decided(1, 2, vec![SimEntry::Normal(1)]).is_err());
+        // never proposed
+        assert!(inv.decided(1, 4, vec![SimEntry::Normal(3)]).is_err());
+        let ss = SimEntry::StopSign(2, vec![1, 2, 3]);
+        assert!(inv.decided(1, 1, vec![SimEntry::Normal(2), ss]).is_ok());
+        // after the stopsign
+        assert!(inv.decided(1, 1, vec![SimEntry::Normal(2)]).is_err());
+        // for the wrong configuration
+        assert!(inv
+            .decided(1, 5, vec![SimEntry::StopSign(3, vec![1, 2, 3])])
+            .is_err());
+        // disagrees with the others
+        assert!(inv.decided(1, 3, vec![SimEntry::Normal(2)]).is_err());
+        assert_eq!(inv.decided_values(), vec![1, 2].into_iter().collect());
+    }
+
+    #[test]
+    fn shrink_test() {
+        let events: Vec<u64> = (0..100).collect();
+        let fails = |e: &[u64]| e.contains(&17) && e.contains(&64);
+        assert_eq!(shrink(events, fails), vec![17, 64]);
+    }
+}
@@ -0,0 +1,433 @@
+use super::super::messages::paxos::ballot_leader_election::{Ballot, Leader};
+use super::super::messages::paxos::Message;
+use super::super::paxos::raw_paxos::{Entry, Paxos};
+use super::super::paxos::{PaxosOptions, Quorums};
+use super::super::storage::paxos::{MemorySequence, MemoryState, PaxosState, Sequence, Storage};
+use super::super::storage::CompactionPolicy;
+use super::*;
+use kompact::runtime::default_logger;
+
+const STEPS: u64 = 300; // in which the events of a schedule happen
+const MAX_STEPS: u64 = 600; // the rest of the run lets the replicas catch up
+const MAX_PID: u64 = 6;
+const MAX_INFLIGHT: usize = 10000;
+const REQUEST_ACCEPTSYNC_PERIOD: u64 = 5; // steps between the retries of the request_acceptsync_timer
+const PRIO_START_ROUND: u64 = 10;
+
+type SimPaxos = Paxos<MemorySequence, MemoryState>;
+
+fn to_sim_entry(e: &Entry) -> SimEntry {
+    match e {
+        Entry::Normal(data) => {
+            let mut value = [0; 8];
+            value.copy_from_slice(&data[..8]);
+            SimEntry::Normal(u64::from_be_bytes(value))
+        }
+        Entry::StopSign(ss) => SimEntry::StopSign(ss.config_id, ss.nodes.clone()),
+    }
+}
+
+/// The replicas of one configuration and the network between them.
+struct SimConfig {
+    id: u32,
+    nodes: Vec<u64>,
+    replicas: BTreeMap<u64, SimPaxos>,
+    network: SimNetwork<Message>,
+    round: u64,  // of the latest ballot handed out by the leader election
+    leader: u64, // as the clients see it, 0 if there is none yet
+    reconfiguring: bool,
+    stopped: BTreeSet<u64>, // the replicas that decided the stopsign
+}
+
+/// Runs the `Paxos` instances of a benchmark like the `PaxosReplica`s do, with the simulator as leader election and
+/// client.
+pub struct PaxosSimulation {
+    seed: u64,
+    rng: SmallRng,
+    chaos: Chaos,
+    options: PaxosOptions,
+    flexible_quorums: bool, // every configuration gets random asymmetric quorums for its size
+    configs: Vec<SimConfig>, // the last one is the active configuration
+    pending: Vec<u64>,      // proposals waiting for a leader
+    leader_events: Vec<(u64, u32, u64, Leader)>, // (step, config_id, pid, leader)
+    invariants: Invariants,
+    now: u64,
+}
+
+impl PaxosSimulation {
+    pub fn with(seed: u64, chaos: Chaos, options: PaxosOptions, num_nodes: u64) -> PaxosSimulation {
+        PaxosSimulation::with_quorums(seed, chaos, options, num_nodes, false)
+    }
+
+    /// With `flexible_quorums`, the quorums of `options` are replaced by random ones in every configuration.
+    pub fn with_quorums(
+        seed: u64,
+        chaos: Chaos,
+        options: PaxosOptions,
+        num_nodes: u64,
+        flexible_quorums: bool,
+    ) -> PaxosSimulation {
+        let mut sim = PaxosSimulation {
+            seed,
+            rng: SmallRng::seed_from_u64(seed),
+            chaos,
+            options,
+            flexible_quorums,
+            configs: vec![],
+            pending: vec![],
+            leader_events: vec![],
+            invariants: Invariants::default(),
+            now: 0,
+        };
+        sim.start_config(1, (1..=num_nodes).collect(), None);
+        sim
+    }
+
+    /// A simulation with the chaos, Paxos options and number of nodes drawn from `seed`, and also the use of flexible
+    /// quorums unless `flexible_quorums` is given.
+    pub fn random(seed: u64, flexible_quorums: Option<bool>) -> PaxosSimulation {
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let chaos = Chaos::random(&mut rng);
+        let options = PaxosOptions {
+            batch_accept: rng.gen(),
+            latest_decide: rng.gen(),
+            latest_accepted: rng.gen(),
+            max_accsync: rng.gen(),
+            headstart_ble: false,
+            quorums: Quorums::Majority,
+        };
+        let num_nodes = if rng.gen() { 3 } else { 5 };
+        let flexible_quorums = flexible_quorums.unwrap_or_else(|| rng.gen());
+        PaxosSimulation::with_quorums(seed, chaos, options, num_nodes, flexible_quorums)
+    }
+
+    fn start_config(&mut self, id: u32, nodes: Vec<u64>, skip_prepare_n: Option<Ballot>) {
+        let mut options = self.options;
+        if self.flexible_quorums {
+            // the smallest phase 1 quorum that intersects a random phase 2 quorum
+            let phase2 = self.rng.gen_range(1, nodes.len() + 1);
+            options.quorums = Quorums::Flexible {
+                phase1: nodes.len() - phase2 + 1,
+                phase2,
+            };
+        }
+        let mut replicas = BTreeMap::new();
+        for pid in &nodes {
+            let peers: Vec<u64> = nodes.iter().filter(|p| *p != pid).copied().collect();
+            let storage = Storage::with(
+                MemorySequence::new_with_sequence(Vec::with_capacity(MAX_INFLIGHT)),
+                MemoryState::new(),
+            );
+            let paxos = Paxos::with(
+                id,
+                *pid,
+                peers,
+                storage,
+                default_logger().clone(),
+                skip_prepare_n,
+                Some(MAX_INFLIGHT),
+                CompactionPolicy::with(0, 0),
+                false,
+                options,
+            );
+            replicas.insert(*pid, paxos);
+        }
+        self.configs.push(SimConfig {
+            id,
+            nodes,
+            replicas,
+            // Paxos relies on the FIFO, exactly-once links of the communicator, which the sessions enforce (see
+            // `session_contract_test`), so the chaos only reaches it as delays and links going down
+            network: SimNetwork::with(self.seed ^ ((id as u64) << 32), self.chaos, true),
+            round: skip_prepare_n.map_or(0, |n| n.n),
+            leader: skip_prepare_n.map_or(0, |n| n.pid),
+            reconfiguring: false,
+            stopped: BTreeSet::new(),
+        });
+        if skip_prepare_n.is_none() {
+            let idx = self.rng.gen_range(0, MAX_PID as usize);
+            self.elect_leader(idx);
+        }
+    }
+
+    fn active(&mut self) -> &mut SimConfig {
+        self.configs.last_mut().expect("No configuration")
+    }
+
+    /// Hands out a higher ballot to the node at `idx`, which the nodes learn after a random delay like with the
+    /// heartbeats of the ballot leader election.
+    fn elect_leader(&mut self, idx: usize) {
+        let now = self.now;
+        let max_delay = self.chaos.max_delay;
+        let config = self.configs.last_mut().expect("No configuration");
+        let pid = config.nodes[idx % config.nodes.len()];
+        config.round += 1;
+        config.leader = pid;
+        let leader = Leader::with(pid, Ballot::with(config.round, pid));
+        for node in &config.nodes {
+            let at = now + self.rng.gen_range(0, max_delay + 1);
+            self.leader_events.push((at, config.id, *node, leader));
+        }
+    }
+
+    fn propose(&mut self, value: u64) {
+        self.invariants.proposed(value);
+        self.pending.push(value);
+    }
+
+    /// Proposes the pending values to the leader. They stay pending if the configuration was stopped.
+    fn flush_pending(&mut self) {
+        let pending = std::mem::take(&mut self.pending);
+        let config = self.active();
+        if config.leader == 0 {
+            self.pending = pending;
+            return;
+        }
+        let leader = config.leader;
+        let replica = config.replicas.get_mut(&leader).expect("Unknown leader");
+        let mut rest = vec![];
+        for value in pending {
+            if !rest.is_empty()
+                || replica
+                    .propose_normal(value.to_be_bytes().to_vec())
+                    .is_err()
+            {
+                rest.push(value);
+            }
+        }
+        self.pending = rest;
+    }
+
+    fn reconfigure(&mut self, nodes: Vec<u64>) {
+        let config = self.active();
+        if config.reconfiguring || config.leader == 0 {
+            return;
+        }
+        let leader = config.leader;
+        let replica = config.replicas.get_mut(&leader).expect("Unknown leader");
+        if replica
+            .propose_reconfiguration(nodes, Some(PRIO_START_ROUND))
+            .is_ok()
+        {
+            config.reconfiguring = true;
+        }
+    }
+
+    /// Runs a single step and checks the invariants on what was decided in it.
+    fn step(&mut self, events: &[SimEvent]) -> Result<(), String> {
+        for event in events {
+            match event {
+                SimEvent::Propose(value) => self.propose(*value),
+                SimEvent::ElectLeader(idx) => self.elect_leader(*idx),
+                SimEvent::Reconfigure(nodes) => self.reconfigure(nodes.clone()),
+            }
+        }
+        let now = self.now;
+        let (due, later): (Vec<_>, Vec<_>) = std::mem::take(&mut self.leader_events)
+            .into_iter()
+            .partition(|(at, ..)| *at <= now);
+        self.leader_events = later;
+        for (_, config_id, pid, leader) in due {
+            let config = self
+                .configs
+                .iter_mut()
+                .find(|c| c.id == config_id)
+                .expect("Unknown configuration");
+            if !config.stopped.contains(&pid) {
+                config.replicas.get_mut(&pid).unwrap().handle_leader(leader);
+            }
+        }
+        let mut next_config = None;
+        for config in self.configs.iter_mut() {
+            for replica in config.replicas.values_mut() {
+                for msg in replica.get_outgoing_msgs() {
+                    config.network.send(now, msg.from, msg.to, msg);
+                }
+            }
+            for delivery in config.network.deliver(now) {
+                match delivery {
+                    Delivery::Msg { to, msg, .. } => {
+                        config.replicas.get_mut(&to).unwrap().handle(msg)
+                    }
+                    Delivery::LinkDown { from, to } => {
+                        config.replicas.get_mut(&from).unwrap().connection_lost(to)
+                    }
+                    Delivery::LinkUp { from, to } => config
+                        .replicas
+                        .get_mut(&from)
+                        .unwrap()
+                        .connection_restored(to),
+                }
+            }
+            for (pid, replica) in config.replicas.iter_mut() {
+                if now % REQUEST_ACCEPTSYNC_PERIOD == 0 {
+                    replica.request_firstaccept_if_not_started();
+                }
+                let decided = replica.get_decided_entries();
+                let stopsign = match decided.last() {
+                    Some(Entry::StopSign(ss)) => Some(ss.clone()),
+                    _ => None,
+                };
+                let entries = decided.iter().map(to_sim_entry).collect();
+                self.invariants.decided(config.id, *pid, entries)?;
+                if let Some(ss) = stopsign {
+                    // like the replica, hand over the final sequence when the stopsign is decided
+                    let final_seq: Vec<SimEntry> = replica
+                        .stop_and_get_sequence()
+                        .get_sequence()
+                        .iter()
+                        .map(to_sim_entry)
+                        .collect();
+                    if final_seq.as_slice() != self.invariants.sequence(config.id, *pid) {
+                        return Err(format!(
+                            "Final sequence of node {} in config {} differs from what it decided: {:?}",
+                            pid, config.id, final_seq
+                        ));
+                    }
+                    if config.stopped.is_empty() {
+                        next_config = Some(ss);
+                    }
+                    config.stopped.insert(*pid);
+                }
+            }
+        }
+        if let Some(ss) = next_config {
+            if ss.config_id > self.active().id {
+                self.start_config(ss.config_id, ss.nodes, ss.skip_prepare_n);
+            }
+        }
+        self.flush_pending();
+        self.now += 1;
+        Ok(())
+    }
+
+    /// Runs `schedule` to the end and returns the first violated invariant, if any.
+    pub fn run(&mut self, schedule: &[(u64, SimEvent)]) -> Result<(), String> {
+        let mut events = schedule.iter().peekable();
+        while self.now < MAX_STEPS {
+            let mut step_events = vec![];
+            while let Some((step, event)) = events.peek() {
+                if *step > self.now {
+                    break;
+                }
+                step_events.push(event.clone());
+                events.next();
+            }
+            self.step(&step_events)
+                .map_err(|e| format!("Step {}: {}", self.now, e))?;
+        }
+        Ok(())
+    }
+}
+
+/// The schedule that is run from `seed`.
+fn schedule_of(seed: u64) -> Schedule {
+    let mut rng = SmallRng::seed_from_u64(!seed);
+    random_schedule(&mut rng, STEPS, MAX_PID)
+}
+
+fn run_seed(
+    seed: u64,
+    schedule: &[(u64, SimEvent)],
+    flexible_quorums: Option<bool>,
+) -> Result<PaxosSimulation, String> {
+    let mut sim = PaxosSimulation::random(seed, flexible_quorums);
+    sim.run(schedule).map(|_| sim)
+}
+
+/// Runs the seeds of a simulation test and panics with the shrunk schedule of the first one that fails.
+fn run_seeds(default_seeds: u64, flexible_quorums: Option<bool>) {
+    let seeds: Vec<u64> = match replay_seed() {
+        Some(seed) => vec![seed],
+        None => (0..num_seeds(default_seeds)).collect(),
+    };
+    let mut decided = 0;
+    for seed in seeds {
+        let schedule = schedule_of(seed);
+        match run_seed(seed, &schedule, flexible_quorums) {
+            Ok(sim) => decided += sim.invariants.decided_values().len(),
+            Err(violation) => {
+                let minimal = shrink(schedule, |s| run_seed(seed, s, flexible_quorums).is_err());
+                panic!(
+                    "Seed {} failed: {}\nMinimal schedule: {:?}\nwhich fails with: {}",
+                    seed,
+                    violation,
+                    minimal,
+                    run_seed(seed, &minimal, flexible_quorums)
+                        .err()
+                        .unwrap_or_default()
+                );
+            }
+        }
+    }
+    assert!(decided > 0, "Nothing was decided in any run");
+}
+
+#[test]
+fn paxos_simulation_test() {
+    run_seeds(200, None);
+}
+
+#[test]
+fn paxos_simulation_flexible_quorums_test() {
+    // every configuration runs with asymmetric phase 1 and phase 2 quorums
+    run_seeds(100, Some(true));
+}
+
+#[test]
+fn paxos_simulation_liveness_test() {
+    // without chaos, every proposal is decided
+    for seed in 0..10 {
+        let mut schedule: Schedule = vec![(0, SimEvent::ElectLeader(0))];
+        schedule.extend((1..=50).map(|v| (v, SimEvent::Propose(v))));
+        let mut sim = PaxosSimulation::with(seed, Chaos::none(), PaxosOptions::default(), 3);
+        sim.run(&schedule).expect("Invariant violated");
+        assert_eq!(sim.invariants.decided_values(), (1..=50).collect());
+        for pid in 1..=3 {
+            assert_eq!(sim.invariants.sequence(1, pid).len(), 50);
+        }
+    }
+}
+
+#[test]
+fn paxos_simulation_reconfiguration_test() {
+    let mut schedule: Schedule = vec![(0, SimEvent::ElectLeader(0))];
+    schedule.extend((1..=20).map(|v| (v, SimEvent::Propose(v))));
+    schedule.push((50, SimEvent::Reconfigure(vec![2, 3, 4])));
+    schedule.extend((21..=40).map(|v| (v + 80, SimEvent::Propose(v))));
+    let mut sim = PaxosSimulation::with(1, Chaos::none(), PaxosOptions::default(), 3);
+    sim.run(&schedule).expect("Invariant violated");
+    assert_eq!(sim.configs.len(), 2);
+    assert_eq!(sim.configs[1].nodes, vec![2, 3, 4]);
+    let old = sim.invariants.sequence(1, 1);
+    assert_eq!(old.len(), 21);
+    assert_eq!(old[20], SimEntry::StopSign(2, vec![2, 3, 4]));
+    assert_eq!(sim.invariants.sequence(2, 4).len(), 20);
+    assert_eq!(sim.invariants.decided_values(), (1..=40).collect());
+}
+
+#[test]
+fn paxos_simulation_asymmetric_quorums_test() {
+    // a large phase 1 quorum lets a single follower complete phase 2, also across leader changes
+    let options = PaxosOptions {
+        quorums: Quorums::Flexible {
+            phase1: 4,
+            phase2: 2,
+        },
+        ..PaxosOptions::default()
+    };
+    let mut schedule: Schedule = vec![(0, SimEvent::ElectLeader(0))];
+    schedule.extend((1..=30).map(|v| (v, SimEvent::Propose(v))));
+    schedule.push((40, SimEvent::ElectLeader(3)));
+    schedule.extend((31..=60).map(|v| (v + 30, SimEvent::Propose(v))));
+    schedule.push((100, SimEvent::ElectLeader(1)));
+    schedule.extend((61..=90).map(|v| (v + 50, SimEvent::Propose(v))));
+    for seed in 0..10 {
+        let mut sim = PaxosSimulation::with(seed, Chaos::none(), options, 5);
+        sim.run(&schedule).expect("Invariant violated");
+        assert_eq!(sim.invariants.decided_values(), (1..=90).collect());
+        for pid in 1..=5 {
+            assert_eq!(sim.invariants.sequence(1, pid).len(), 90);
+        }
+    }
+}
@@ -0,0 +1,399 @@
+extern crate raft as tikv_raft;
+
+use super::super::messages::Proposal;
+use super::super::raft::{RaftCore, RaftEffect, ReconfigurationPolicy};
+use super::super::storage::CompactionPolicy;
+use super::*;
+use crate::serialiser_ids::RECONFIG_ID;
+use benchmark_suite_shared::test_utils::DecidedEntry;
+use kompact::runtime::default_logger;
+use tikv_raft::{prelude::Message as TikvRaftMsg, storage::MemStorage, Config, RawNode};
+
+const STEPS: u64 = 300; // in which the events of a schedule happen
+const MAX_STEPS: u64 = 800; // the rest of the run lets the replicas catch up
+const MAX_PID: u64 = 6;
+const ELECTION_TICK: usize = 10;
+const HEARTBEAT_TICK: usize = 2;
+const INITIAL_TIMEOUT_TICKS: usize = 5;
+const ISOLATION_STEPS: u64 = 4 * ELECTION_TICK as u64;
+const MAX_INFLIGHT: usize = 1000;
+const MAX_BATCH_SIZE: u64 = 50000000;
+
+fn reconfig_data() -> Vec<u8> {
+    RECONFIG_ID.to_be_bytes().to_vec()
+}
+
+/// The nodes of a finished reconfiguration, which the replicas respond with like to the clients.
+fn parse_reconfig_response(data: &[u8]) -> Option<Vec<u64>> {
+    if data.len() < 12 || data[..8] != RECONFIG_ID.to_be_bytes() {
+        return None;
+    }
+    let mut len = [0; 4];
+    len.copy_from_slice(&data[8..12]);
+    let nodes = data[12..]
+        .chunks_exact(8)
+        .take(u32::from_be_bytes(len) as usize)
+        .map(|pid| {
+            let mut b = [0; 8];
+            b.copy_from_slice(pid);
+            u64::from_be_bytes(b)
+        })
+        .collect();
+    Some(nodes)
+}
+
+/// Runs the `RaftCore`s of a benchmark like the `RaftComp`s do, with the simulator as client. The leader election is
+/// Raft's own, so leader changes are injected by cutting a node off from its peers for a while.
+pub struct RaftSimulation {
+    replicas: BTreeMap<u64, RaftCore<MemStorage>>,
+    leaders: BTreeMap<u64, u64>, // the leader each replica last reported, like the current_leader of a RaftComp
+    network: SimNetwork<TikvRaftMsg>,
+    voters: Vec<u64>,
+    leader: u64, // as the clients see it, 0 if there is none yet
+    pending: Vec<u64>,
+    isolated: Option<(u64, u64)>, // (pid, until step)
+    invariants: Invariants,
+    now: u64,
+}
+
+impl RaftSimulation {
+    pub fn with(
+        seed: u64,
+        chaos: Chaos,
+        policy: ReconfigurationPolicy,
+        num_nodes: u64,
+    ) -> RaftSimulation {
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let voters: Vec<u64> = (1..=num_nodes).collect();
+        // distinct election timeouts instead of Raft's randomized ones, which are not seeded
+        let mut offsets: Vec<usize> = (0..MAX_PID as usize).collect();
+        offsets.shuffle(&mut rng);
+        let mut replicas = BTreeMap::new();
+        for pid in 1..=MAX_PID {
+            let election_tick = ELECTION_TICK + 2 * offsets[pid as usize - 1];
+            let config = Config {
+                id: pid,
+                election_tick,
+                heartbeat_tick: HEARTBEAT_TICK,
+                min_election_tick: election_tick,
+                max_election_tick: election_tick + 1,
+                max_inflight_msgs: MAX_INFLIGHT,
+                max_size_per_msg: MAX_BATCH_SIZE,
+                batch_append: true,
+                ..Default::default()
+            };
+            let store = MemStorage::new_with_conf_state((voters.clone(), vec![]));
+            let raw_raft = RawNode::new(&config, store).expect("Failed to create tikv Raft");
+            let core = RaftCore::with(
+                raw_raft,
+                default_logger().clone(),
+                policy.clone(),
+                MAX_INFLIGHT,
+                CompactionPolicy::with(0, 0),
+                None,
+                INITIAL_TIMEOUT_TICKS,
+                SmallRng::seed_from_u64(seed ^ pid),
+            );
+            replicas.insert(pid, core);
+        }
+        RaftSimulation {
+            replicas,
+            leaders: BTreeMap::new(),
+            network: SimNetwork::with(seed, chaos, false),
+            voters,
+            leader: 0,
+            pending: vec![],
+            isolated: None,
+            invariants: Invariants::default(),
+            now: 0,
+        }
+    }
+
+    /// A simulation with the chaos, reconfiguration policy and number of nodes drawn from `seed`.
+    pub fn random(seed: u64) -> RaftSimulation {
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let chaos = Chaos::random(&mut rng);
+        let policy = if rng.gen() {
+            ReconfigurationPolicy::ReplaceLeader
+        } else {
+            ReconfigurationPolicy::ReplaceFollower
+        };
+        let num_nodes = if rng.gen() { 3 } else { 5 };
+        RaftSimulation::with(seed, chaos, policy, num_nodes)
+    }
+
+    fn is_isolated(&self, pid: u64) -> bool {
+        self.isolated.map_or(false, |(isolated, _)| isolated == pid)
+    }
+
+    /// Hands a proposal to `pid`, which forwards it to the leader it knows of like a `RaftComp`. Returns false if the
+    /// replica was removed and the proposal has to go elsewhere.
+    fn propose_at(&mut self, pid: u64, proposal: Proposal) -> bool {
+        let leader = self.leaders.get(&pid).copied().unwrap_or(0);
+        let target = if leader == 0 || leader == pid {
+            pid
+        } else {
+            leader
+        };
+        let replica = self.replicas.get_mut(&target).unwrap();
+        if replica.removed() {
+            return false;
+        }
+        replica.propose(proposal);
+        true
+    }
+
+    fn flush_pending(&mut self) {
+        if self.leader == 0 {
+            return;
+        }
+        let pending = std::mem::take(&mut self.pending);
+        for value in pending {
+            let proposal = Proposal::normal(value.to_be_bytes().to_vec());
+            if !self.propose_at(self.leader, proposal) {
+                self.pending.push(value);
+            }
+        }
+    }
+
+    fn handle_effects(&mut self) {
+        let mut forwards = vec![];
+        for (pid, replica) in self.replicas.iter_mut() {
+            for effect in replica.take_effects() {
+                match effect {
+                    RaftEffect::Send(msg) => {
+                        let isolated = self.isolated.map_or(false, |(isolated, _)| {
+                            isolated == msg.from || isolated == msg.to
+                        });
+                        if !isolated {
+                            self.network.send(self.now, msg.from, msg.to, msg);
+                        }
+                    }
+                    RaftEffect::Respond(pr) => {
+                        if let Some(voters) = parse_reconfig_response(&pr.data) {
+                            self.voters = voters;
+                        }
+                    }
+                    RaftEffect::Leader(_, leader) => {
+                        self.leaders.insert(*pid, leader);
+                        self.leader = leader;
+                    }
+                    RaftEffect::ForwardReconfig(leader, reconfig) => {
+                        forwards.push((leader, reconfig));
+                    }
+                }
+            }
+        }
+        for (leader, reconfig) in forwards {
+            self.replicas
+                .get_mut(&leader)
+                .unwrap()
+                .propose(Proposal::reconfiguration(reconfig_data(), reconfig));
+        }
+    }
+
+    /// Runs a single step and checks the invariants on what was decided in it.
+    fn step(&mut self, events: &[SimEvent]) -> Result<(), String> {
+        let now = self.now;
+        if self.isolated.map_or(false, |(_, until)| until <= now) {
+            self.isolated = None;
+        }
+        for event in events {
+            match event {
+                SimEvent::Propose(value) => {
+                    self.invariants.proposed(*value);
+                    self.pending.push(*value);
+                }
+                SimEvent::ElectLeader(idx) => {
+                    let pid = self.voters[idx % self.voters.len()];
+                    self.isolated = Some((pid, now + ISOLATION_STEPS));
+                }
+                SimEvent::Reconfigure(nodes) => {
+                    if self.leader != 0 {
+                        let proposal =
+                            Proposal::reconfiguration(reconfig_data(), (nodes.clone(), vec![]));
+                        self.propose_at(self.leader, proposal);
+                    }
+                }
+            }
+        }
+        for replica in self.replicas.values_mut() {
+            replica.tick();
+        }
+        self.handle_effects();
+        self.flush_pending();
+        for delivery in self.network.deliver(now) {
+            if let Delivery::Msg { to, msg, .. } = delivery {
+                if !self.is_isolated(to) && !self.is_isolated(msg.from) {
+                    self.replicas.get_mut(&to).unwrap().step(msg);
+                }
+            }
+        }
+        for replica in self.replicas.values_mut() {
+            replica.on_ready();
+        }
+        self.handle_effects();
+        for (pid, replica) in self.replicas.iter() {
+            // the configurations are followed through the responses instead of the membership changes
+            let sequence: Vec<SimEntry> = replica
+                .decided_sequence()
+                .entries
+                .into_iter()
+                .filter_map(|e| match e {
+                    DecidedEntry::Normal(value) => Some(SimEntry::Normal(value)),
+                    DecidedEntry::StopSign(_) => None,
+                })
+                .collect();
+            let known = self.invariants.sequence(1, *pid);
+            if sequence.len() < known.len() || sequence[..known.len()] != *known {
+                return Err(format!(
+                    "Node {} changed what it decided from {:?} to {:?}",
+                    pid, known, sequence
+                ));
+            }
+            let new_entries = sequence[known.len()..].to_vec();
+            self.invariants.decided(1, *pid, new_entries)?;
+        }
+        self.now += 1;
+        Ok(())
+    }
+
+    /// Runs the events of `schedule` up to step `until` and returns the first violated invariant, if any.
+    pub fn run_until(&mut self, schedule: &[(u64, SimEvent)], until: u64) -> Result<(), String> {
+        while self.now < until {
+            let events: Vec<SimEvent> = schedule
+                .iter()
+                .filter(|(step, _)| *step == self.now)
+                .map(|(_, event)| event.clone())
+                .collect();
+            self.step(&events)
+                .map_err(|e| format!("Step {}: {}", self.now, e))?;
+        }
+        Ok(())
+    }
+
+    /// Runs `schedule` to the end and returns the first violated invariant, if any.
+    pub fn run(&mut self, schedule: &[(u64, SimEvent)]) -> Result<(), String> {
+        self.run_until(schedule, MAX_STEPS)
+    }
+}
+
+/// The schedule that is run from `seed`.
+fn schedule_of(seed: u64) -> Schedule {
+    let mut rng = SmallRng::seed_from_u64(!seed);
+    random_schedule(&mut rng, STEPS, MAX_PID)
+}
+
+fn run_seed(seed: u64, schedule: &[(u64, SimEvent)]) -> Result<RaftSimulation, String> {
+    let mut sim = RaftSimulation::random(seed);
+    sim.run(schedule).map(|_| sim)
+}
+
+#[test]
+fn raft_simulation_test() {
+    let seeds: Vec<u64> = match replay_seed() {
+        Some(seed) => vec![seed],
+        None => (0..num_seeds(100)).collect(),
+    };
+    let mut decided = 0;
+    for seed in seeds {
+        let schedule = schedule_of(seed);
+        match run_seed(seed, &schedule) {
+            Ok(sim) => decided += sim.invariants.decided_values().len(),
+            Err(violation) => {
+                let minimal = shrink(schedule, |s| run_seed(seed, s).is_err());
+                panic!(
+                    "Seed {} failed: {}\nMinimal schedule: {:?}\nwhich fails with: {}",
+                    seed,
+                    violation,
+                    minimal,
+                    run_seed(seed, &minimal).err().unwrap_or_default()
+                );
+            }
+        }
+    }
+    assert!(decided > 0, "Nothing was decided in any run");
+}
+
+#[test]
+fn raft_simulation_liveness_test() {
+    // without chaos, every proposal is decided
+    for seed in 0..10 {
+        let schedule: Schedule = (1..=50).map(|v| (v, SimEvent::Propose(v))).collect();
+        let mut sim = RaftSimulation::with(
+            seed,
+            Chaos::none(),
+            ReconfigurationPolicy::ReplaceFollower,
+            3,
+        );
+        sim.run(&schedule).expect("Invariant violated");
+        assert_eq!(sim.invariants.decided_values(), (1..=50).collect());
+        for pid in 1..=3 {
+            assert_eq!(sim.invariants.sequence(1, pid).len(), 50);
+        }
+    }
+}
+
+#[test]
+fn raft_simulation_reconfiguration_test() {
+    for policy in vec![
+        ReconfigurationPolicy::ReplaceLeader,
+        ReconfigurationPolicy::ReplaceFollower,
+    ] {
+        let mut schedule: Schedule = (1..=20).map(|v| (v, SimEvent::Propose(v))).collect();
+        schedule.push((100, SimEvent::Reconfigure(vec![4, 5, 6])));
+        schedule.extend((21..=40).map(|v| (v + 300, SimEvent::Propose(v))));
+        let mut sim = RaftSimulation::with(1, Chaos::none(), policy.clone(), 3);
+        sim.run_until(&schedule, 100).expect("Invariant violated");
+        let old_leader = sim.leader;
+        assert_ne!(old_leader, 0);
+        sim.run(&schedule).expect("Invariant violated");
+        assert_eq!(sim.voters.len(), 5, "{:?}: {:?}", policy, sim.voters);
+        assert!(sim.voters.contains(&4) && sim.voters.contains(&6));
+        if let ReconfigurationPolicy::ReplaceLeader = policy {
+            assert!(!sim.voters.contains(&old_leader));
+            assert_ne!(sim.leader, old_leader);
+        }
+        assert_eq!(sim.invariants.decided_values(), (1..=40).collect());
+        assert_eq!(sim.invariants.sequence(1, 4).len(), 40);
+    }
+}
+
+#[test]
+fn raft_simulation_successive_reconfigurations_test() {
+    // grow from 3 to 5 nodes, then shrink to 3 without the original nodes
+    let mut schedule: Schedule = (1..=20).map(|v| (v, SimEvent::Propose(v))).collect();
+    schedule.push((100, SimEvent::Reconfigure(vec![1, 2, 3, 4, 5])));
+    schedule.extend((21..=40).map(|v| (v + 200, SimEvent::Propose(v))));
+    schedule.push((350, SimEvent::Reconfigure(vec![3, 4, 5])));
+    schedule.extend((41..=60).map(|v| (v + 450, SimEvent::Propose(v))));
+    let mut sim = RaftSimulation::with(1, Chaos::none(), ReconfigurationPolicy::Exact, 3);
+    sim.run_until(&schedule, 300).expect("Invariant violated");
+    let mut voters = sim.voters.clone();
+    voters.sort();
+    assert_eq!(voters, vec![1, 2, 3, 4, 5]);
+    sim.run(&schedule).expect("Invariant violated");
+    voters = sim.voters.clone();
+    voters.sort();
+    assert_eq!(voters, vec![3, 4, 5]);
+    assert_ne!(sim.leader, 0);
+    assert_eq!(sim.invariants.decided_values(), (1..=60).collect());
+    assert_eq!(sim.invariants.sequence(1, 5).len(), 60);
+}
+
+#[test]
+fn raft_simulation_leader_transfer_test() {
+    // node 2 is elected first and hands over to node 3 while proposals are in flight
+    let schedule: Schedule = (1..=40).map(|v| (5 * v, SimEvent::Propose(v))).collect();
+    let mut sim = RaftSimulation::with(1, Chaos::none(), ReconfigurationPolicy::ReplaceFollower, 3);
+    sim.replicas.get_mut(&2).unwrap().campaign_first();
+    sim.run_until(&schedule, 100).expect("Invariant violated");
+    assert_eq!(sim.leader, 2);
+    sim.replicas.get_mut(&3).unwrap().transfer_leadership();
+    sim.run(&schedule).expect("Invariant violated");
+    assert_eq!(sim.leader, 3);
+    assert_eq!(sim.invariants.decided_values(), (1..=40).collect());
+    for pid in 1..=3 {
+        assert_eq!(sim.invariants.sequence(1, pid).len(), 40);
+    }
+}
@@ -1,6 +1,171 @@
+use std::fmt;
+use std::ops::AddAssign;
+use std::path::PathBuf;
+
+/// The storage backend replicas use, for either algorithm.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StorageKind {
+    Memory,
+    Disk,
+}
+
+impl StorageKind {
+    pub fn parse(s: &str) -> Result<StorageKind, String> {
+        match s.to_lowercase().as_ref() {
+            "" | "memory" => Ok(StorageKind::Memory), // requests without storage predate disk storage
+            "disk" => Ok(StorageKind::Disk),
+            unknown => Err(format!("Unknown storage kind: {}", unknown)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StorageKind::Memory => "memory",
+            StorageKind::Disk => "disk",
+        }
+    }
+}
+
+/// When disk-backed storage forces its writes to the device.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FsyncPolicy {
+    /// After every write.
+    Always,
+    /// After every `n` writes.
+    Batched(usize),
+    /// Never, it is left to the OS.
+    Never,
+}
+
+impl FsyncPolicy {
+    pub fn parse(s: &str, batch_size: usize) -> Result<FsyncPolicy, String> {
+        match s.to_lowercase().as_ref() {
+            "always" => Ok(FsyncPolicy::Always),
+            "batched" if batch_size > 0 => Ok(FsyncPolicy::Batched(batch_size)),
+            "batched" => Err(String::from("Batched fsync needs a positive batch size")),
+            "never" => Ok(FsyncPolicy::Never),
+            unknown => Err(format!("Unknown fsync policy: {}", unknown)),
+        }
+    }
+}
+
+/// When replicas compact their log, based on what was decided since the last compaction.
+///
+/// Compaction happens as soon as any of the thresholds is reached.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CompactionPolicy {
+    pub entries: Option<u64>,
+    pub bytes: Option<u64>,
+}
+
+impl CompactionPolicy {
+    /// A threshold of 0 is disabled.
+    pub fn with(entries: u64, bytes: u64) -> CompactionPolicy {
+        let threshold = |t| if t > 0 { Some(t) } else { None };
+        CompactionPolicy {
+            entries: threshold(entries),
+            bytes: threshold(bytes),
+        }
+    }
+
+    /// Parses the `<entries>:<bytes>` format it is displayed in.
+    pub fn parse(s: &str) -> Result<CompactionPolicy, String> {
+        let split: Vec<_> = s.split(':').collect();
+        if split.len() != 2 {
+            return Err(format!("Invalid compaction policy: {}", s));
+        }
+        let parse = |t: &str| {
+            t.parse::<u64>()
+                .map_err(|e| format!("Invalid compaction threshold '{}': {}", t, e))
+        };
+        Ok(CompactionPolicy::with(parse(split[0])?, parse(split[1])?))
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.entries.is_some() || self.bytes.is_some()
+    }
+
+    pub fn should_compact(&self, entries: u64, bytes: u64) -> bool {
+        self.entries.map_or(false, |t| entries >= t) || self.bytes.map_or(false, |t| bytes >= t)
+    }
+}
+
+impl fmt::Display for CompactionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}",
+            self.entries.unwrap_or(0),
+            self.bytes.unwrap_or(0)
+        )
+    }
+}
+
+/// I/O done by a storage backend, since it was created.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct IoStats {
+    pub bytes_written: u64,
+    pub fsyncs: u64,
+}
+
+impl IoStats {
+    fn written(&mut self, bytes: usize) {
+        self.bytes_written += bytes as u64;
+    }
+
+    fn synced(&mut self) {
+        self.fsyncs += 1;
+    }
+}
+
+impl AddAssign for IoStats {
+    fn add_assign(&mut self, other: IoStats) {
+        self.bytes_written += other.bytes_written;
+        self.fsyncs += other.fsyncs;
+    }
+}
+
+/// Where and how disk-backed storage persists its data.
+#[derive(Clone, Debug)]
+pub struct DiskConfig {
+    pub dir: PathBuf,
+    pub fsync: FsyncPolicy,
+}
+
+impl DiskConfig {
+    pub fn with(dir: PathBuf, fsync: FsyncPolicy) -> DiskConfig {
+        DiskConfig { dir, fsync }
+    }
+
+    /// A config for a subdirectory of this one.
+    pub fn sub_dir(&self, name: &str) -> DiskConfig {
+        DiskConfig::with(self.dir.join(name), self.fsync)
+    }
+
+    /// The config as the `storage` section it is loaded from.
+    pub fn to_hocon(&self) -> String {
+        let (fsync, batch_size) = match self.fsync {
+            FsyncPolicy::Always => ("always", 0),
+            FsyncPolicy::Batched(n) => ("batched", n),
+            FsyncPolicy::Never => ("never", 0),
+        };
+        let batch_size = match batch_size {
+            0 => String::new(),
+            n => format!(", fsync_batch_size = {}", n),
+        };
+        format!(
+            "storage {{ directory = {:?}, fsync = {}{} }}",
+            self.dir.to_string_lossy(),
+            fsync,
+            batch_size
+        )
+    }
+}
+
 pub mod raft {
     extern crate raft as tikv_raft;
 
+    use super::IoStats;
     use memmap::MmapMut;
     use protobuf::{parse_from_bytes, Message as PbMessage};
     use std::fs::remove_dir_all;
@@ -23,6 +188,11 @@ pub mod raft {
         fn set_hard_state(&mut self, commit: u64, term: u64) -> Result<(), Error>;
         fn new_with_conf_state(dir: Option<&str>, conf_state: (Vec<u64>, Vec<u64>)) -> Self;
         fn clear(&mut self) -> Result<(), IOError>;
+        fn io_stats(&self) -> IoStats;
+        /// Discards all entries before `compact_index`, which must have been applied.
+        fn compact(&mut self, compact_index: u64) -> Result<(), Error>;
+        /// Replaces the log with a snapshot received from the leader.
+        fn apply_snapshot(&mut self, snapshot: Snapshot) -> Result<(), Error>;
     }
 
     impl RaftStorage for MemStorage {
@@ -51,6 +221,18 @@ pub mod raft {
         fn clear(&mut self) -> Result<(), IOError> {
             Ok(())
         }
+
+        fn io_stats(&self) -> IoStats {
+            IoStats::default()
+        }
+
+        fn compact(&mut self, compact_index: u64) -> Result<(), Error> {
+            self.wl().compact(compact_index)
+        }
+
+        fn apply_snapshot(&mut self, snapshot: Snapshot) -> Result<(), Error> {
+            self.wl().apply_snapshot(snapshot)
+        }
     }
 
     struct FileMmap {
@@ -120,6 +302,18 @@ pub mod raft {
         fn clear(&mut self) -> Result<(), IOError> {
             self.wl().clear_dir()
         }
+
+        fn io_stats(&self) -> IoStats {
+            self.rl().io_stats
+        }
+
+        fn compact(&mut self, compact_index: u64) -> Result<(), Error> {
+            self.wl().compact(compact_index)
+        }
+
+        fn apply_snapshot(&mut self, snapshot: Snapshot) -> Result<(), Error> {
+            self.wl().apply_snapshot(snapshot)
+        }
     }
 
     impl Storage for DiskStorage {
@@ -148,8 +342,8 @@ pub mod raft {
             self.rl().last_index()
         }
 
-        fn snapshot(&self, _request_index: u64) -> Result<Snapshot, Error> {
-            unimplemented!()
+        fn snapshot(&self, request_index: u64) -> Result<Snapshot, Error> {
+            self.rl().snapshot(request_index)
         }
     }
 
@@ -165,6 +359,7 @@ pub mod raft {
         // TODO: Persist these as well?
         num_entries: u64,
         snapshot_metadata: SnapshotMetadata,
+        io_stats: IoStats,
     }
 
     impl DiskStorageCore {
@@ -258,6 +453,7 @@ pub mod raft {
                 raft_metadata,
                 num_entries: 0,
                 snapshot_metadata,
+                io_stats: IoStats::default(),
                 pending_conf_state: None,
                 pending_conf_state_start_index: None,
             }
@@ -321,6 +517,8 @@ pub mod raft {
                     .expect("Protobuf failed to serialise Entry");
                 let ser_entry_len = ser_entry.len() as u64;
                 let ser_len = ser_entry_len.to_be_bytes();
+                self.io_stats
+                    .written(ser_len.len() + ser_entry.len() + size_of::<u64>());
                 self.log.file.write(&ser_len)?; // write len of serialised entry
                 match self.log.file.write(&ser_entry) {
                     // write entry
@@ -394,6 +592,8 @@ pub mod raft {
                 .write(&value.to_be_bytes())
                 .expect("Failed to write raft metadata");
             self.raft_metadata.flush()?;
+            self.io_stats.written(1 + size_of::<u64>());
+            self.io_stats.synced();
             Ok(())
         }
 
@@ -471,6 +671,78 @@ pub mod raft {
         fn set_hard_state(&mut self, commit: u64, term: u64) -> Result<(), Error> {
             (&mut self.hard_state[DiskStorageCore::TERM_INDEX]).write(&term.to_be_bytes())?;
             (&mut self.hard_state[DiskStorageCore::COMMIT_INDEX]).write(&commit.to_be_bytes())?;
+            self.io_stats.written(2 * size_of::<u64>());
+            Ok(())
+        }
+
+        /// Moves the entries from `compact_index` on to the start of the log and offset files.
+        fn compact(&mut self, compact_index: u64) -> Result<(), Error> {
+            let first_index = self.first_index()?;
+            if compact_index <= first_index {
+                return Ok(());
+            }
+            let last_index = self.last_index()?;
+            if compact_index > last_index + 1 {
+                panic!(
+                    "compact not received raft logs: {}, last index: {}",
+                    compact_index, last_index
+                );
+            }
+            let term = self.term(compact_index - 1)?;
+            let num_compacted = compact_index - first_index;
+            let end = self.log.file.seek(SeekFrom::Current(0))?;
+            let start = if num_compacted < self.num_entries {
+                self.get_log_offset(num_compacted) as u64
+            } else {
+                end
+            };
+            self.log
+                .mem_map
+                .copy_within(start as usize..end as usize, 0);
+            for log_index in num_compacted..self.num_entries {
+                let offset = self.get_log_offset(log_index) as u64 - start;
+                let slot = (log_index - num_compacted) as usize * size_of::<u64>();
+                (&mut self.offset.mem_map[slot..slot + size_of::<u64>()])
+                    .write(&offset.to_be_bytes())
+                    .expect("Failed to write to offset");
+            }
+            self.log.mem_map.flush()?;
+            self.offset.mem_map.flush()?;
+            self.log.file.seek(SeekFrom::Start(end - start))?;
+            self.num_entries -= num_compacted;
+            self.io_stats
+                .written((end - start) as usize + self.num_entries as usize * size_of::<u64>());
+            self.io_stats.synced();
+            self.io_stats.synced();
+            self.snapshot_metadata.index = compact_index - 1;
+            self.snapshot_metadata.term = term;
+            self.set_raft_metadata(DiskStorageCore::FIRST_INDEX, compact_index)
+        }
+
+        fn apply_snapshot(&mut self, mut snapshot: Snapshot) -> Result<(), Error> {
+            let meta = snapshot.take_metadata();
+            let index = meta.index;
+            if self.first_index()? > index {
+                return Err(Error::Store(StorageError::SnapshotOutOfDate));
+            }
+            self.log.file.seek(SeekFrom::Start(0))?;
+            self.num_entries = 0;
+            self.set_raft_metadata(DiskStorageCore::FIRST_INDEX, index + 1)?;
+            self.set_raft_metadata(DiskStorageCore::LAST_INDEX, index)?;
+            let term = std::cmp::max(
+                self.read_hard_state_field(DiskStorageCore::TERM_INDEX),
+                meta.term,
+            );
+            self.set_hard_state(index, term)?;
+            self.conf_state = meta.get_conf_state().clone();
+            if meta.has_pending_membership_change() {
+                self.pending_conf_state = Some(meta.get_pending_membership_change().clone());
+                self.pending_conf_state_start_index = Some(meta.pending_membership_change_index);
+            } else {
+                self.pending_conf_state = None;
+                self.pending_conf_state_start_index = None;
+            }
+            self.snapshot_metadata = meta;
             Ok(())
         }
 
@@ -561,8 +833,24 @@ pub mod raft {
             }
         }
 
-        fn snapshot(&self, _request_index: u64) -> Result<Snapshot, Error> {
-            unimplemented!();
+        /// The log holds no application state, so snapshots only consist of metadata.
+        fn snapshot(&self, request_index: u64) -> Result<Snapshot, Error> {
+            let commit = self.read_hard_state_field(DiskStorageCore::COMMIT_INDEX);
+            let mut snapshot = Snapshot::default();
+            let meta = snapshot.mut_metadata();
+            meta.index = commit;
+            meta.term = self.term(commit)?;
+            meta.set_conf_state(self.conf_state.clone());
+            if let Some(cs) = &self.pending_conf_state {
+                meta.set_pending_membership_change(cs.clone());
+                meta.pending_membership_change_index = self
+                    .pending_conf_state_start_index
+                    .expect("Pending conf state without start index");
+            }
+            if meta.index < request_index {
+                meta.index = request_index;
+            }
+            Ok(snapshot)
         }
     }
 
@@ -717,22 +1005,113 @@ pub mod raft {
             assert_eq!(storage.last_index(), Ok(5));
             remove_dir_all("metadata_test").expect("Failed to remove test storage files");
         }
+
+        #[test]
+        fn diskstorage_io_stats_test() {
+            let mut storage =
+                DiskStorage::new_with_conf_state("io_stats_test", (vec![1, 2, 3], vec![]));
+            assert_eq!(IoStats::default(), storage.io_stats());
+            let ents = vec![new_entry(2, 2), new_entry(3, 3)];
+            storage.append_log(&ents).expect("Failed to append logs");
+            let stats = storage.io_stats();
+            let entry_bytes: u64 = ents.iter().map(|e| 16 + size_of(e) as u64).sum();
+            assert_eq!(entry_bytes + 2 * 9, stats.bytes_written); // first and last index metadata
+            assert_eq!(2, stats.fsyncs);
+            storage.clear().expect("Failed to clear storage");
+            assert!(!std::path::Path::new("io_stats_test").exists());
+            assert_eq!(stats, storage.io_stats(), "Stats should survive clearing");
+        }
+
+        #[test]
+        fn diskstorage_compaction_test() {
+            let ents = vec![
+                new_entry(2, 2),
+                new_entry(3, 3),
+                new_entry(4, 4),
+                new_entry(5, 5),
+            ];
+            let mut store =
+                DiskStorage::new_with_conf_state("compaction_test", (vec![1, 2, 3], vec![]));
+            store.append_log(&ents).expect("Failed to append logs");
+            store
+                .set_hard_state(5, 5)
+                .expect("Failed to set hard state");
+            store.compact(4).expect("Failed to compact");
+            assert_eq!(Ok(4), store.first_index());
+            assert_eq!(Ok(5), store.last_index());
+            assert_eq!(Ok(3), store.term(3));
+            assert_eq!(Err(Error::Store(StorageError::Compacted)), store.term(2));
+            assert_eq!(
+                Err(Error::Store(StorageError::Compacted)),
+                store.entries(3, 6, None)
+            );
+            assert_eq!(Ok(ents[2..].to_vec()), store.entries(4, 6, None));
+            // appending after compaction, overwriting the last entry
+            let new_ents = vec![new_entry(5, 6), new_entry(6, 6)];
+            store.append_log(&new_ents).expect("Failed to append logs");
+            assert_eq!(
+                Ok(vec![
+                    ents[2].clone(),
+                    new_ents[0].clone(),
+                    new_ents[1].clone()
+                ]),
+                store.entries(4, 7, None)
+            );
+
+            let snapshot = store.snapshot(0).expect("Failed to create snapshot");
+            assert_eq!(5, snapshot.get_metadata().index);
+            assert_eq!(6, snapshot.get_metadata().term);
+            let mut follower =
+                DiskStorage::new_with_conf_state("snapshot_test", (vec![1, 2, 3], vec![]));
+            follower
+                .apply_snapshot(snapshot)
+                .expect("Failed to apply snapshot");
+            assert_eq!(Ok(6), follower.first_index());
+            assert_eq!(Ok(5), follower.last_index());
+            assert_eq!(Ok(6), follower.term(5));
+            follower
+                .append_log(&[new_entry(6, 6)])
+                .expect("Failed to append logs");
+            assert_eq!(Ok(vec![new_entry(6, 6)]), follower.entries(6, 7, None));
+            store.clear().expect("Failed to clear storage");
+            follower.clear().expect("Failed to clear storage");
+        }
     }
 }
 
 pub mod paxos {
     use super::super::messages::paxos::ballot_leader_election::Ballot;
     use super::super::paxos::{raw_paxos::Entry, PaxosStateTraits, SequenceTraits};
+    use super::{DiskConfig, FsyncPolicy, IoStats};
     use crate::bench::atomic_broadcast::messages::paxos::PaxosSer;
+    use kompact::prelude::Buf;
     use std::fmt::Debug;
+    use std::fs::{create_dir_all, rename, File, OpenOptions};
+    use std::io::{Read, Seek, SeekFrom, Write};
     use std::mem;
+    use std::path::{Path, PathBuf};
     use std::sync::Arc;
 
+    const SEQUENCE_FILE: &str = "sequence";
+    const STATE_FILE: &str = "state";
+
     pub trait Sequence {
         fn new() -> Self;
 
         fn new_with_sequence(seq: Vec<Entry>) -> Self;
 
+        /// Creates a sequence installed from a snapshot, i.e. the first `compacted_idx` entries were compacted and `suffix` follows them.
+        fn new_from_snapshot(compacted_idx: u64, suffix: Vec<Entry>) -> Self;
+
+        /// Creates an empty sequence that is persisted according to `config`.
+        fn open(config: &DiskConfig) -> Self;
+
+        /// Reads back the sequence persisted in `config.dir`, e.g. when a crashed replica restarts.
+        /// Fails if nothing was persisted there or the sequence is not persisted at all.
+        fn recover(config: &DiskConfig) -> Result<Self, String>
+        where
+            Self: Sized;
+
         fn append_entry(&mut self, entry: Entry);
 
         fn append_sequence(&mut self, seq: &mut Vec<Entry>);
@@ -752,11 +1131,34 @@ pub mod paxos {
         fn get_sequence_len(&self) -> u64;
 
         fn stopped(&self) -> bool;
+
+        /// I/O done by the persisted log, if any.
+        fn io_stats(&self) -> IoStats;
+
+        /// Discards all entries before `idx`. Indices of the remaining entries do not change.
+        fn compact(&mut self, idx: u64);
+
+        fn get_compacted_idx(&self) -> u64;
+
+        /// The entries in `from..to` that were not compacted, and the index the first of them has.
+        fn get_retained_entries(&self, from: u64, to: u64) -> (u64, &[Entry]) {
+            let from = from.max(self.get_compacted_idx()).min(to);
+            (from, self.get_entries(from, to))
+        }
     }
 
     pub trait PaxosState {
         fn new() -> Self;
 
+        /// Creates an initial state that is persisted according to `config`.
+        fn open(config: &DiskConfig) -> Self;
+
+        /// Reads back the state persisted in `config.dir`, e.g. when a crashed replica restarts.
+        /// Fails if nothing was persisted there or the state is not persisted at all.
+        fn recover(config: &DiskConfig) -> Result<Self, String>
+        where
+            Self: Sized;
+
         fn set_promise(&mut self, nprom: Ballot);
 
         fn set_decided_len(&mut self, ld: u64);
@@ -768,6 +1170,9 @@ pub mod paxos {
         fn get_decided_len(&self) -> u64;
 
         fn get_promise(&self) -> Ballot;
+
+        /// I/O done by the persisted state, if any.
+        fn io_stats(&self) -> IoStats;
     }
 
     enum PaxosSequence<S>
@@ -935,22 +1340,87 @@ pub mod paxos {
                 _ => panic!("Got unexpected intermediate PaxosSequence::None in get_sequence"),
             }
         }
+
+        pub fn get_retained_entries(&self, from: u64, to: u64) -> (u64, &[Entry]) {
+            match &self.sequence {
+                PaxosSequence::Active(s) => s.get_retained_entries(from, to),
+                PaxosSequence::Stopped(s) => s.get_retained_entries(from, to),
+                _ => panic!(
+                    "Got unexpected intermediate PaxosSequence::None in get_retained_entries"
+                ),
+            }
+        }
+
+        /// Compacts the sequence up to `idx`. The final sequence of a stopped configuration is kept as it is.
+        pub fn compact(&mut self, idx: u64) {
+            if let PaxosSequence::Active(s) = &mut self.sequence {
+                s.compact(idx);
+            }
+        }
+
+        pub fn get_compacted_idx(&self) -> u64 {
+            match &self.sequence {
+                PaxosSequence::Active(s) => s.get_compacted_idx(),
+                PaxosSequence::Stopped(s) => s.get_compacted_idx(),
+                _ => panic!("Got unexpected intermediate PaxosSequence::None in get_compacted_idx"),
+            }
+        }
+
+        /// I/O done by the sequence and the state together.
+        pub fn io_stats(&self) -> IoStats {
+            let mut io_stats = match &self.sequence {
+                PaxosSequence::Active(s) => s.io_stats(),
+                PaxosSequence::Stopped(s) => s.io_stats(),
+                _ => panic!("Got unexpected intermediate PaxosSequence::None in io_stats"),
+            };
+            io_stats += self.paxos_state.io_stats();
+            io_stats
+        }
     }
 
     #[derive(Debug)]
     pub struct MemorySequence {
-        sequence: Vec<Entry>,
+        sequence: Vec<Entry>, // entries from compacted_idx on
+        compacted_idx: u64,
+    }
+
+    impl MemorySequence {
+        // position of `idx` in `sequence`
+        fn position(&self, idx: u64) -> usize {
+            match idx.checked_sub(self.compacted_idx) {
+                Some(pos) => pos as usize,
+                None => panic!(
+                    "Index {} was already compacted. Compacted: {}",
+                    idx, self.compacted_idx
+                ),
+            }
+        }
     }
 
     impl SequenceTraits for MemorySequence {}
 
     impl Sequence for MemorySequence {
         fn new() -> Self {
-            MemorySequence { sequence: vec![] }
+            MemorySequence::new_with_sequence(vec![])
         }
 
         fn new_with_sequence(seq: Vec<Entry>) -> Self {
-            MemorySequence { sequence: seq }
+            MemorySequence::new_from_snapshot(0, seq)
+        }
+
+        fn new_from_snapshot(compacted_idx: u64, suffix: Vec<Entry>) -> Self {
+            MemorySequence {
+                sequence: suffix,
+                compacted_idx,
+            }
+        }
+
+        fn open(_config: &DiskConfig) -> Self {
+            MemorySequence::new()
+        }
+
+        fn recover(_config: &DiskConfig) -> Result<Self, String> {
+            Err("MemorySequence is not persisted and can not be recovered".to_string())
         }
 
         fn append_entry(&mut self, entry: Entry) {
@@ -962,24 +1432,25 @@ pub mod paxos {
         }
 
         fn append_on_prefix(&mut self, from_idx: u64, seq: &mut Vec<Entry>) {
-            self.sequence.truncate(from_idx as usize);
+            let pos = self.position(from_idx);
+            self.sequence.truncate(pos);
             self.sequence.append(seq);
         }
 
         fn get_entries(&self, from: u64, to: u64) -> &[Entry] {
-            match self.sequence.get(from as usize..to as usize) {
+            match self.sequence.get(self.position(from)..self.position(to)) {
                 Some(ents) => ents,
                 None => panic!(
                     "get_entries out of bounds. From: {}, To: {}, len: {}",
                     from,
                     to,
-                    self.sequence.len()
+                    self.get_sequence_len()
                 ),
             }
         }
 
         fn get_ser_entries(&self, from: u64, to: u64) -> Option<Vec<u8>> {
-            match self.sequence.get(from as usize..to as usize) {
+            match self.sequence.get(self.position(from)..self.position(to)) {
                 Some(ents) => {
                     let mut bytes = Vec::with_capacity(((to - from) * 8) as usize);
                     PaxosSer::serialise_entries(ents, &mut bytes);
@@ -990,14 +1461,14 @@ pub mod paxos {
         }
 
         fn get_suffix(&self, from: u64) -> Vec<Entry> {
-            match self.sequence.get(from as usize..) {
+            match self.sequence.get(self.position(from)..) {
                 Some(s) => s.to_vec(),
                 None => vec![],
             }
         }
 
         fn get_ser_suffix(&self, from: u64) -> Option<Vec<u8>> {
-            match self.sequence.get(from as usize..) {
+            match self.sequence.get(self.position(from)..) {
                 Some(s) => {
                     let len = s.len();
                     let mut bytes: Vec<u8> = Vec::with_capacity(len * 40);
@@ -1013,7 +1484,7 @@ pub mod paxos {
         }
 
         fn get_sequence_len(&self) -> u64 {
-            self.sequence.len() as u64
+            self.compacted_idx + self.sequence.len() as u64
         }
 
         fn stopped(&self) -> bool {
@@ -1022,6 +1493,22 @@ pub mod paxos {
                 None => false,
             }
         }
+
+        fn io_stats(&self) -> IoStats {
+            IoStats::default()
+        }
+
+        fn compact(&mut self, idx: u64) {
+            if idx > self.compacted_idx {
+                let pos = self.position(idx).min(self.sequence.len());
+                self.sequence.drain(..pos);
+                self.compacted_idx += pos as u64;
+            }
+        }
+
+        fn get_compacted_idx(&self) -> u64 {
+            self.compacted_idx
+        }
     }
 
     #[derive(Debug)]
@@ -1043,6 +1530,14 @@ pub mod paxos {
             }
         }
 
+        fn open(_config: &DiskConfig) -> Self {
+            MemoryState::new()
+        }
+
+        fn recover(_config: &DiskConfig) -> Result<Self, String> {
+            Err("MemoryState is not persisted and can not be recovered".to_string())
+        }
+
         fn set_promise(&mut self, nprom: Ballot) {
             self.n_prom = nprom;
         }
@@ -1066,5 +1561,527 @@ pub mod paxos {
         fn get_promise(&self) -> Ballot {
             self.n_prom
         }
+
+        fn io_stats(&self) -> IoStats {
+            IoStats::default()
+        }
+    }
+
+    /// A file that is synced according to an `FsyncPolicy`.
+    #[derive(Debug)]
+    struct SyncedFile {
+        file: File,
+        policy: FsyncPolicy,
+        unsynced_writes: usize,
+        io_stats: IoStats,
+    }
+
+    impl SyncedFile {
+        fn create(path: &Path, policy: FsyncPolicy) -> SyncedFile {
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(path)
+                .unwrap_or_else(|e| panic!("Failed to create {:?}: {}", path, e));
+            SyncedFile::with(file, policy)
+        }
+
+        fn with(file: File, policy: FsyncPolicy) -> SyncedFile {
+            SyncedFile {
+                file,
+                policy,
+                unsynced_writes: 0,
+                io_stats: IoStats::default(),
+            }
+        }
+
+        /// Records that `bytes` were written and syncs if the policy says so.
+        fn written(&mut self, bytes: usize) {
+            self.io_stats.written(bytes);
+            let sync = match self.policy {
+                FsyncPolicy::Always => true,
+                FsyncPolicy::Batched(n) => {
+                    self.unsynced_writes += 1;
+                    self.unsynced_writes >= n
+                }
+                FsyncPolicy::Never => false,
+            };
+            if sync {
+                self.file.sync_data().expect("Failed to fsync");
+                self.io_stats.synced();
+                self.unsynced_writes = 0;
+            }
+        }
+    }
+
+    fn create_dir(config: &DiskConfig) {
+        create_dir_all(&config.dir)
+            .unwrap_or_else(|e| panic!("Failed to create directory {:?}: {}", config.dir, e));
+    }
+
+    /// Append-only log of entries.
+    ///
+    /// All entries are also kept in memory, so reads never touch the disk.
+    /// Sequences that are not created with `open` (e.g. ones transferred from previous configurations) are only kept in memory.
+    /// The log starts with the compacted index, followed by the retained entries.
+    #[derive(Debug)]
+    pub struct DiskSequence {
+        sequence: MemorySequence,
+        log: Option<LogFile>,
+    }
+
+    #[derive(Debug)]
+    struct LogFile {
+        file: SyncedFile,
+        path: PathBuf,
+        offsets: Vec<u64>, // byte offset of every retained entry in the file
+        len: u64,
+    }
+
+    impl LogFile {
+        const HEADER_SIZE: u64 = 8; // compacted index
+
+        fn create(path: PathBuf, policy: FsyncPolicy, compacted_idx: u64) -> LogFile {
+            let mut file = SyncedFile::create(&path, policy);
+            file.file
+                .write_all(&compacted_idx.to_be_bytes())
+                .expect("Failed to write log header");
+            file.written(LogFile::HEADER_SIZE as usize);
+            LogFile {
+                file,
+                path,
+                offsets: vec![],
+                len: LogFile::HEADER_SIZE,
+            }
+        }
+
+        fn append(&mut self, entries: &[Entry]) {
+            if entries.is_empty() {
+                return;
+            }
+            let mut bytes = Vec::with_capacity(entries.len() * 16);
+            for e in entries {
+                self.offsets.push(self.len + bytes.len() as u64);
+                PaxosSer::serialise_entry(e, &mut bytes);
+            }
+            self.file
+                .file
+                .write_all(&bytes)
+                .expect("Failed to write to log");
+            self.len += bytes.len() as u64;
+            self.file.written(bytes.len());
+        }
+
+        /// Removes all entries from position `pos` on.
+        fn truncate(&mut self, pos: u64) {
+            if let Some(offset) = self.offsets.get(pos as usize).copied() {
+                self.offsets.truncate(pos as usize);
+                self.file
+                    .file
+                    .set_len(offset)
+                    .expect("Failed to truncate log");
+                self.file
+                    .file
+                    .seek(SeekFrom::Start(offset))
+                    .expect("Failed to seek in log");
+                self.len = offset;
+            }
+        }
+
+        /// Replaces the log with one only holding `retained`.
+        /// The new log is written next to the old one and renamed over it, so a crash leaves either of them intact.
+        fn compact(&mut self, compacted_idx: u64, retained: &[Entry]) {
+            let tmp_path = self.path.with_extension("compact");
+            let mut log = LogFile::create(tmp_path.clone(), self.file.policy, compacted_idx);
+            log.append(retained);
+            log.file.file.sync_all().expect("Failed to fsync log");
+            log.file.io_stats.synced();
+            rename(&tmp_path, &self.path)
+                .unwrap_or_else(|e| panic!("Failed to replace log {:?}: {}", self.path, e));
+            log.path = self.path.clone();
+            log.file.io_stats += self.file.io_stats;
+            *self = log;
+        }
+    }
+
+    impl SequenceTraits for DiskSequence {}
+
+    impl Sequence for DiskSequence {
+        fn new() -> Self {
+            DiskSequence {
+                sequence: MemorySequence::new(),
+                log: None,
+            }
+        }
+
+        fn new_with_sequence(seq: Vec<Entry>) -> Self {
+            DiskSequence {
+                sequence: MemorySequence::new_with_sequence(seq),
+                log: None,
+            }
+        }
+
+        fn new_from_snapshot(compacted_idx: u64, suffix: Vec<Entry>) -> Self {
+            DiskSequence {
+                sequence: MemorySequence::new_from_snapshot(compacted_idx, suffix),
+                log: None,
+            }
+        }
+
+        fn open(config: &DiskConfig) -> Self {
+            create_dir(config);
+            DiskSequence {
+                sequence: MemorySequence::new(),
+                log: Some(LogFile::create(
+                    config.dir.join(SEQUENCE_FILE),
+                    config.fsync,
+                    0,
+                )),
+            }
+        }
+
+        fn recover(config: &DiskConfig) -> Result<Self, String> {
+            let path = config.dir.join(SEQUENCE_FILE);
+            let mut bytes = vec![];
+            File::open(&path)
+                .and_then(|mut f| f.read_to_end(&mut bytes))
+                .map_err(|e| format!("Failed to read log {:?}: {}", path, e))?;
+            if (bytes.len() as u64) < LogFile::HEADER_SIZE {
+                return Err(format!("Corrupt log {:?}", path));
+            }
+            let total = bytes.len() as u64;
+            let mut buf = bytes.as_slice();
+            let compacted_idx = buf.get_u64();
+            let mut sequence = vec![];
+            let mut offsets = vec![];
+            while buf.has_remaining() {
+                offsets.push(total - buf.remaining() as u64);
+                let entry = PaxosSer::deserialise_entry(&mut buf)
+                    .map_err(|e| format!("Corrupt entry in log {:?}: {:?}", path, e))?;
+                sequence.push(entry);
+            }
+            let mut file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(&path)
+                .map_err(|e| format!("Failed to open log {:?}: {}", path, e))?;
+            file.seek(SeekFrom::End(0))
+                .map_err(|e| format!("Failed to seek in log {:?}: {}", path, e))?;
+            let log = LogFile {
+                file: SyncedFile::with(file, config.fsync),
+                path,
+                offsets,
+                len: total,
+            };
+            Ok(DiskSequence {
+                sequence: MemorySequence::new_from_snapshot(compacted_idx, sequence),
+                log: Some(log),
+            })
+        }
+
+        fn append_entry(&mut self, entry: Entry) {
+            if let Some(log) = self.log.as_mut() {
+                log.append(std::slice::from_ref(&entry));
+            }
+            self.sequence.append_entry(entry);
+        }
+
+        fn append_sequence(&mut self, seq: &mut Vec<Entry>) {
+            if let Some(log) = self.log.as_mut() {
+                log.append(seq);
+            }
+            self.sequence.append_sequence(seq);
+        }
+
+        fn append_on_prefix(&mut self, from_idx: u64, seq: &mut Vec<Entry>) {
+            if let Some(log) = self.log.as_mut() {
+                log.truncate(from_idx - self.sequence.get_compacted_idx());
+                log.append(seq);
+            }
+            self.sequence.append_on_prefix(from_idx, seq);
+        }
+
+        fn get_entries(&self, from: u64, to: u64) -> &[Entry] {
+            self.sequence.get_entries(from, to)
+        }
+
+        fn get_ser_entries(&self, from: u64, to: u64) -> Option<Vec<u8>> {
+            self.sequence.get_ser_entries(from, to)
+        }
+
+        fn get_suffix(&self, from: u64) -> Vec<Entry> {
+            self.sequence.get_suffix(from)
+        }
+
+        fn get_ser_suffix(&self, from: u64) -> Option<Vec<u8>> {
+            self.sequence.get_ser_suffix(from)
+        }
+
+        fn get_sequence(&self) -> Vec<Entry> {
+            self.sequence.get_sequence()
+        }
+
+        fn get_sequence_len(&self) -> u64 {
+            self.sequence.get_sequence_len()
+        }
+
+        fn stopped(&self) -> bool {
+            self.sequence.stopped()
+        }
+
+        fn io_stats(&self) -> IoStats {
+            self.log
+                .as_ref()
+                .map_or_else(IoStats::default, |l| l.file.io_stats)
+        }
+
+        fn compact(&mut self, idx: u64) {
+            self.sequence.compact(idx);
+            if let Some(log) = self.log.as_mut() {
+                let compacted_idx = self.sequence.get_compacted_idx();
+                let retained = self
+                    .sequence
+                    .get_entries(compacted_idx, self.sequence.get_sequence_len());
+                log.compact(compacted_idx, retained);
+            }
+        }
+
+        fn get_compacted_idx(&self) -> u64 {
+            self.sequence.get_compacted_idx()
+        }
+    }
+
+    /// Promise, accepted ballot and decided length, persisted on every change.
+    #[derive(Debug)]
+    pub struct DiskState {
+        state: MemoryState,
+        file: Option<SyncedFile>,
+    }
+
+    impl DiskState {
+        const SIZE: usize = 5 * 8; // two ballots and ld
+
+        fn persist(&mut self) {
+            if let Some(f) = self.file.as_mut() {
+                let s = &self.state;
+                let mut bytes = Vec::with_capacity(DiskState::SIZE);
+                for v in &[
+                    s.n_prom.n,
+                    s.n_prom.pid,
+                    s.acc_round.n,
+                    s.acc_round.pid,
+                    s.ld,
+                ] {
+                    bytes.extend_from_slice(&v.to_be_bytes());
+                }
+                f.file
+                    .seek(SeekFrom::Start(0))
+                    .expect("Failed to seek in state file");
+                f.file.write_all(&bytes).expect("Failed to write state");
+                f.written(bytes.len());
+            }
+        }
+    }
+
+    impl PaxosStateTraits for DiskState {}
+
+    impl PaxosState for DiskState {
+        fn new() -> Self {
+            DiskState {
+                state: MemoryState::new(),
+                file: None,
+            }
+        }
+
+        fn open(config: &DiskConfig) -> Self {
+            create_dir(config);
+            let mut state = DiskState {
+                state: MemoryState::new(),
+                file: Some(SyncedFile::create(
+                    &config.dir.join(STATE_FILE),
+                    config.fsync,
+                )),
+            };
+            state.persist();
+            state
+        }
+
+        fn recover(config: &DiskConfig) -> Result<Self, String> {
+            let path = config.dir.join(STATE_FILE);
+            let mut bytes = vec![];
+            let mut file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(&path)
+                .map_err(|e| format!("Failed to open state {:?}: {}", path, e))?;
+            file.read_to_end(&mut bytes)
+                .map_err(|e| format!("Failed to read state {:?}: {}", path, e))?;
+            if bytes.len() != DiskState::SIZE {
+                return Err(format!("Corrupt state file {:?}", path));
+            }
+            let mut buf = bytes.as_slice();
+            let n_prom = Ballot::with(buf.get_u64(), buf.get_u64());
+            let acc_round = Ballot::with(buf.get_u64(), buf.get_u64());
+            let ld = buf.get_u64();
+            Ok(DiskState {
+                state: MemoryState {
+                    n_prom,
+                    acc_round,
+                    ld,
+                },
+                file: Some(SyncedFile::with(file, config.fsync)),
+            })
+        }
+
+        fn set_promise(&mut self, nprom: Ballot) {
+            self.state.set_promise(nprom);
+            self.persist();
+        }
+
+        fn set_decided_len(&mut self, ld: u64) {
+            self.state.set_decided_len(ld);
+            self.persist();
+        }
+
+        fn set_accepted_ballot(&mut self, na: Ballot) {
+            self.state.set_accepted_ballot(na);
+            self.persist();
+        }
+
+        fn get_accepted_ballot(&self) -> Ballot {
+            self.state.get_accepted_ballot()
+        }
+
+        fn get_decided_len(&self) -> u64 {
+            self.state.get_decided_len()
+        }
+
+        fn get_promise(&self) -> Ballot {
+            self.state.get_promise()
+        }
+
+        fn io_stats(&self) -> IoStats {
+            self.file
+                .as_ref()
+                .map_or_else(IoStats::default, |f| f.io_stats)
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::super::super::paxos::raw_paxos::StopSign;
+        use super::*;
+        use std::fs::remove_dir_all;
+
+        fn disk_config(dir: &str) -> DiskConfig {
+            DiskConfig::with(dir.into(), FsyncPolicy::Batched(2))
+        }
+
+        fn normal(i: u64) -> Entry {
+            Entry::Normal(i.to_be_bytes().to_vec())
+        }
+
+        #[test]
+        fn disk_sequence_recover_test() {
+            let config = disk_config("paxos_sequence_test");
+            let mut seq = DiskSequence::open(&config);
+            seq.append_entry(normal(1));
+            seq.append_sequence(&mut vec![normal(2), normal(3), normal(4)]);
+            seq.append_on_prefix(2, &mut vec![normal(5)]);
+            let ss = StopSign::with(2, vec![1, 2, 3], None);
+            seq.append_entry(Entry::StopSign(ss));
+            let expected = seq.get_sequence();
+            assert_eq!(4, expected.len());
+            drop(seq);
+
+            let mut recovered = DiskSequence::recover(&config).expect("Failed to recover");
+            assert_eq!(expected, recovered.get_sequence());
+            assert!(recovered.stopped());
+            recovered.append_on_prefix(1, &mut vec![normal(6)]);
+            drop(recovered);
+            let recovered = DiskSequence::recover(&config).expect("Failed to recover");
+            assert_eq!(vec![normal(1), normal(6)], recovered.get_sequence());
+            remove_dir_all("paxos_sequence_test").expect("Failed to remove test storage files");
+        }
+
+        #[test]
+        fn disk_sequence_compaction_test() {
+            let config = disk_config("paxos_compaction_test");
+            let mut seq = DiskSequence::open(&config);
+            seq.append_sequence(&mut (1..=5).map(normal).collect());
+            seq.compact(3);
+            assert_eq!(3, seq.get_compacted_idx());
+            assert_eq!(5, seq.get_sequence_len());
+            assert_eq!(&[normal(4), normal(5)], seq.get_entries(3, 5));
+            assert_eq!(
+                (3, &[normal(4), normal(5)][..]),
+                seq.get_retained_entries(1, 5)
+            );
+            seq.append_on_prefix(4, &mut vec![normal(6)]);
+            drop(seq);
+
+            let mut recovered = DiskSequence::recover(&config).expect("Failed to recover");
+            assert_eq!(3, recovered.get_compacted_idx());
+            assert_eq!(vec![normal(4), normal(6)], recovered.get_sequence());
+            recovered.append_entry(normal(7));
+            drop(recovered);
+            let recovered = DiskSequence::recover(&config).expect("Failed to recover");
+            assert_eq!(6, recovered.get_sequence_len());
+            assert_eq!(&[normal(6), normal(7)], recovered.get_entries(4, 6));
+            remove_dir_all("paxos_compaction_test").expect("Failed to remove test storage files");
+        }
+
+        #[test]
+        fn disk_state_recover_test() {
+            let config = disk_config("paxos_state_test");
+            let mut state = DiskState::open(&config);
+            state.set_promise(Ballot::with(3, 2));
+            state.set_accepted_ballot(Ballot::with(2, 1));
+            state.set_decided_len(7);
+            drop(state);
+
+            let recovered = DiskState::recover(&config).expect("Failed to recover");
+            assert_eq!(Ballot::with(3, 2), recovered.get_promise());
+            assert_eq!(Ballot::with(2, 1), recovered.get_accepted_ballot());
+            assert_eq!(7, recovered.get_decided_len());
+            remove_dir_all("paxos_state_test").expect("Failed to remove test storage files");
+        }
+
+        #[test]
+        fn recover_without_storage_test() {
+            let config = disk_config("paxos_missing_test");
+            assert!(DiskSequence::recover(&config).is_err());
+            assert!(DiskState::recover(&config).is_err());
+            assert!(MemorySequence::recover(&config).is_err());
+            assert!(MemoryState::recover(&config).is_err());
+        }
+
+        #[test]
+        fn disk_io_stats_test() {
+            let config = disk_config("paxos_io_stats_test");
+            let mut seq = DiskSequence::open(&config.sub_dir("sequence"));
+            let mut state = DiskState::open(&config.sub_dir("state"));
+            let header = LogFile::HEADER_SIZE;
+            assert_eq!(header, seq.io_stats().bytes_written);
+            seq.append_sequence(&mut (1..=4).map(normal).collect());
+            let appended = seq.io_stats();
+            assert!(appended.bytes_written > header);
+            assert_eq!(1, appended.fsyncs); // the header and the entries are one batch
+            seq.compact(2);
+            let compacted = seq.io_stats();
+            assert!(compacted.bytes_written > appended.bytes_written);
+            assert!(compacted.fsyncs > appended.fsyncs);
+
+            state.set_promise(Ballot::with(1, 1));
+            let expected = IoStats {
+                bytes_written: 2 * DiskState::SIZE as u64,
+                fsyncs: 1,
+            };
+            assert_eq!(expected, state.io_stats());
+            assert_eq!(IoStats::default(), MemorySequence::new().io_stats());
+            remove_dir_all("paxos_io_stats_test").expect("Failed to remove test storage files");
+        }
     }
 }
@@ -1,6 +1,8 @@
 use super::*;
 
+use crate::kompact_system_provider::WithOverrides;
 use crate::partitioning_actor::*;
+use crate::ser_utils::{get_i8, get_u32, get_u64};
 use benchmark_suite_shared::kompics_benchmarks::benchmarks::AtomicRegisterRequest;
 use benchmark_suite_shared::test_utils::all_linearizable;
 use benchmark_suite_shared::test_utils::KVOperation;
@@ -28,6 +30,39 @@ impl ClientParams {
             write_workload,
         }
     }
+
+    fn parse(str: String) -> Result<ClientParams, BenchmarkError> {
+        let split: Vec<_> = str.split(',').collect();
+        if split.len() != 3 {
+            Err(BenchmarkError::InvalidMessage(format!(
+                "String '{}' does not represent a client conf!",
+                str
+            )))
+        } else {
+            let num_keys_str = split[0];
+            let num_keys = num_keys_str.parse::<u64>().map_err(|e| {
+                BenchmarkError::InvalidMessage(format!(
+                    "String '{}' does not represent a client conf: {:?}",
+                    str, e
+                ))
+            })?;
+            let readwl_str = split[1];
+            let read_workload = readwl_str.parse::<f32>().map_err(|e| {
+                BenchmarkError::InvalidMessage(format!(
+                    "String '{}' does not represent a client conf: {:?}",
+                    str, e
+                ))
+            })?;
+            let writewl_str = split[2];
+            let write_workload = writewl_str.parse::<f32>().map_err(|e| {
+                BenchmarkError::InvalidMessage(format!(
+                    "String '{}' does not represent a client conf: {:?}",
+                    str, e
+                ))
+            })?;
+            Ok(ClientParams::new(num_keys, read_workload, write_workload))
+        }
+    }
 }
 
 pub mod actor_atomicregister {
@@ -38,7 +73,7 @@ pub mod actor_atomicregister {
 
     impl DistributedBenchmark for AtomicRegister {
         type MasterConf = AtomicRegisterRequest;
-        type ClientConf = ClientParams;
+        type ClientConf = WithOverrides<ClientParams>;
         type ClientData = ActorPath;
         type Master = AtomicRegisterMaster;
         type Client = AtomicRegisterClient;
@@ -52,7 +87,7 @@ pub mod actor_atomicregister {
         fn msg_to_master_conf(
             msg: Box<dyn (::protobuf::Message)>,
         ) -> Result<Self::MasterConf, BenchmarkError> {
-            downcast_msg!(msg; AtomicRegisterRequest)
+            downcast_kompact_msg!(msg; AtomicRegisterRequest)
         }
 
         fn new_client() -> Self::Client {
@@ -60,36 +95,7 @@ pub mod actor_atomicregister {
         }
 
         fn str_to_client_conf(str: String) -> Result<Self::ClientConf, BenchmarkError> {
-            let split: Vec<_> = str.split(',').collect();
-            if split.len() != 3 {
-                Err(BenchmarkError::InvalidMessage(format!(
-                    "String '{}' does not represent a client conf!",
-                    str
-                )))
-            } else {
-                let num_keys_str = split[0];
-                let num_keys = num_keys_str.parse::<u64>().map_err(|e| {
-                    BenchmarkError::InvalidMessage(format!(
-                        "String '{}' does not represent a client conf: {:?}",
-                        str, e
-                    ))
-                })?;
-                let readwl_str = split[1];
-                let read_workload = readwl_str.parse::<f32>().map_err(|e| {
-                    BenchmarkError::InvalidMessage(format!(
-                        "String '{}' does not represent a client conf: {:?}",
-                        str, e
-                    ))
-                })?;
-                let writewl_str = split[2];
-                let write_workload = writewl_str.parse::<f32>().map_err(|e| {
-                    BenchmarkError::InvalidMessage(format!(
-                        "String '{}' does not represent a client conf: {:?}",
-                        str, e
-                    ))
-                })?;
-                Ok(ClientParams::new(num_keys, read_workload, write_workload))
-            }
+            WithOverrides::deserialise(str, ClientParams::parse)
         }
 
         fn str_to_client_data(str: String) -> Result<Self::ClientData, BenchmarkError> {
@@ -100,7 +106,7 @@ pub mod actor_atomicregister {
         }
 
         fn client_conf_to_str(c: Self::ClientConf) -> String {
-            format!("{},{},{}", c.num_keys, c.read_workload, c.write_workload)
+            c.serialise(|c| format!("{},{},{}", c.num_keys, c.read_workload, c.write_workload))
         }
 
         fn client_data_to_str(d: Self::ClientData) -> String {
@@ -114,6 +120,7 @@ pub mod actor_atomicregister {
         partition_size: Option<u32>,
         num_keys: Option<u64>,
         system: Option<KompactSystem>,
+        system_config: Option<String>,
         finished_latch: Option<Arc<CountdownEvent>>,
         init_id: u32,
         atomic_register: Option<Arc<Component<AtomicRegisterActor>>>,
@@ -128,6 +135,7 @@ pub mod actor_atomicregister {
                 partition_size: None,
                 num_keys: None,
                 system: None,
+                system_config: None,
                 finished_latch: None,
                 init_id: 0,
                 atomic_register: None,
@@ -138,7 +146,7 @@ pub mod actor_atomicregister {
 
     impl DistributedBenchmarkMaster for AtomicRegisterMaster {
         type MasterConf = AtomicRegisterRequest;
-        type ClientConf = ClientParams;
+        type ClientConf = WithOverrides<ClientParams>;
         type ClientData = ActorPath;
 
         fn setup(
@@ -158,15 +166,20 @@ pub mod actor_atomicregister {
             self.write_workload = Some(c.write_workload);
             self.partition_size = Some(c.partition_size);
             self.num_keys = Some(c.number_of_keys);
-            let system = crate::kompact_system_provider::global()
-                .new_remote_system_with_threads("atomicregister", 4);
+            let provider = crate::kompact_system_provider::for_request(c.get_system_overrides());
+            self.system_config = Some(provider.describe_with(4));
+            let system = provider.new_remote_system_with_threads("atomicregister", 4);
             self.system = Some(system);
             let params = ClientParams {
                 num_keys: c.number_of_keys,
                 read_workload: c.read_workload,
                 write_workload: c.write_workload,
             };
-            Ok(params)
+            Ok(WithOverrides::new(c.get_system_overrides(), params))
+        }
+
+        fn system_config(&self) -> Option<String> {
+            self.system_config.clone()
         }
 
         fn prepare_iteration(&mut self, d: Vec<Self::ClientData>) -> () {
@@ -309,13 +322,15 @@ pub mod actor_atomicregister {
     }
 
     impl DistributedBenchmarkClient for AtomicRegisterClient {
-        type ClientConf = ClientParams;
+        type ClientConf = WithOverrides<ClientParams>;
         type ClientData = ActorPath;
 
         fn setup(&mut self, c: Self::ClientConf) -> Self::ClientData {
             println!("Setting up Atomic Register(client)");
-            let system = crate::kompact_system_provider::global()
+            let system = c
+                .provider()
                 .new_remote_system_with_threads("atomicregister", 4);
+            let c = c.conf;
             let (atomic_register, unique_reg_f) = system.create_and_register(|| {
                 AtomicRegisterActor::with(c.num_keys, c.read_workload, c.write_workload, false)
             });
@@ -789,7 +804,7 @@ pub mod mixed_atomicregister {
 
     impl DistributedBenchmark for AtomicRegister {
         type MasterConf = AtomicRegisterRequest;
-        type ClientConf = ClientParams;
+        type ClientConf = WithOverrides<ClientParams>;
         type ClientData = ActorPath;
         type Master = AtomicRegisterMaster;
         type Client = AtomicRegisterClient;
@@ -803,7 +818,7 @@ pub mod mixed_atomicregister {
         fn msg_to_master_conf(
             msg: Box<dyn (::protobuf::Message)>,
         ) -> Result<Self::MasterConf, BenchmarkError> {
-            downcast_msg!(msg; AtomicRegisterRequest)
+            downcast_kompact_msg!(msg; AtomicRegisterRequest)
         }
 
         fn new_client() -> Self::Client {
@@ -811,37 +826,7 @@ pub mod mixed_atomicregister {
         }
 
         fn str_to_client_conf(str: String) -> Result<Self::ClientConf, BenchmarkError> {
-            let split: Vec<_> = str.split(',').collect();
-            if split.len() != 3 {
-                Err(BenchmarkError::InvalidMessage(format!(
-                    "String '{}' does not represent a client conf!",
-                    str
-                )))
-            } else {
-                let num_keys_str = split[0];
-                let num_keys = num_keys_str.parse::<u64>().map_err(|e| {
-                    BenchmarkError::InvalidMessage(format!(
-                        "String '{}' does not represent a client conf: {:?}",
-                        str, e
-                    ))
-                })?;
-
-                let readwl_str = split[1];
-                let read_workload = readwl_str.parse::<f32>().map_err(|e| {
-                    BenchmarkError::InvalidMessage(format!(
-                        "String '{}' does not represent a client conf: {:?}",
-                        str, e
-                    ))
-                })?;
-                let writewl_str = split[2];
-                let write_workload = writewl_str.parse::<f32>().map_err(|e| {
-                    BenchmarkError::InvalidMessage(format!(
-                        "String '{}' does not represent a client conf: {:?}",
-                        str, e
-                    ))
-                })?;
-                Ok(ClientParams::new(num_keys, read_workload, write_workload))
-            }
+            WithOverrides::deserialise(str, ClientParams::parse)
         }
 
         fn str_to_client_data(str: String) -> Result<Self::ClientData, BenchmarkError> {
@@ -852,7 +837,7 @@ pub mod mixed_atomicregister {
         }
 
         fn client_conf_to_str(c: Self::ClientConf) -> String {
-            format!("{},{},{}", c.num_keys, c.read_workload, c.write_workload)
+            c.serialise(|c| format!("{},{},{}", c.num_keys, c.read_workload, c.write_workload))
         }
 
         fn client_data_to_str(d: Self::ClientData) -> String {
@@ -866,6 +851,7 @@ pub mod mixed_atomicregister {
         partition_size: Option<u32>,
         num_keys: Option<u64>,
         system: Option<KompactSystem>,
+        system_config: Option<String>,
         finished_latch: Option<Arc<CountdownEvent>>,
         init_id: u32,
         atomic_register: Option<Arc<Component<AtomicRegisterComp>>>,
@@ -881,6 +867,7 @@ pub mod mixed_atomicregister {
                 partition_size: None,
                 num_keys: None,
                 system: None,
+                system_config: None,
                 finished_latch: None,
                 init_id: 0,
                 atomic_register: None,
@@ -892,7 +879,7 @@ pub mod mixed_atomicregister {
 
     impl DistributedBenchmarkMaster for AtomicRegisterMaster {
         type MasterConf = AtomicRegisterRequest;
-        type ClientConf = ClientParams;
+        type ClientConf = WithOverrides<ClientParams>;
         type ClientData = ActorPath;
 
         fn setup(
@@ -912,15 +899,20 @@ pub mod mixed_atomicregister {
             self.write_workload = Some(c.write_workload);
             self.partition_size = Some(c.partition_size);
             self.num_keys = Some(c.number_of_keys);
-            let system = crate::kompact_system_provider::global()
-                .new_remote_system_with_threads("atomicregister", 4);
+            let provider = crate::kompact_system_provider::for_request(c.get_system_overrides());
+            self.system_config = Some(provider.describe_with(4));
+            let system = provider.new_remote_system_with_threads("atomicregister", 4);
             self.system = Some(system);
             let params = ClientParams {
                 num_keys: c.number_of_keys,
                 read_workload: c.read_workload,
                 write_workload: c.write_workload,
             };
-            Ok(params)
+            Ok(WithOverrides::new(c.get_system_overrides(), params))
+        }
+
+        fn system_config(&self) -> Option<String> {
+            self.system_config.clone()
         }
 
         fn prepare_iteration(&mut self, d: Vec<Self::ClientData>) -> () {
@@ -1102,13 +1094,15 @@ pub mod mixed_atomicregister {
     }
 
     impl DistributedBenchmarkClient for AtomicRegisterClient {
-        type ClientConf = ClientParams;
+        type ClientConf = WithOverrides<ClientParams>;
         type ClientData = ActorPath;
 
         fn setup(&mut self, c: Self::ClientConf) -> Self::ClientData {
             println!("Setting up Atomic Register(client)");
-            let system = crate::kompact_system_provider::global()
+            let system = c
+                .provider()
                 .new_remote_system_with_threads("atomicregister", 4);
+            let c = c.conf;
             /*** Setup Broadcast component ***/
             let (bcast_comp, unique_reg_f) = system.create_and_register(|| BroadcastComp::new());
             let bcast_comp_f = system.start_notify(&bcast_comp);
@@ -1858,21 +1852,21 @@ impl Deserialiser<AtomicRegisterMessage> for AtomicRegisterSer {
     const SER_ID: SerId = serialiser_ids::ATOMICREG_ID;
 
     fn deserialise(buf: &mut dyn Buf) -> Result<AtomicRegisterMessage, SerError> {
-        match buf.get_i8() {
+        match get_i8(buf)? {
             READ_ID => {
-                let run_id = buf.get_u32();
-                let key = buf.get_u64();
-                let rid = buf.get_u32();
+                let run_id = get_u32(buf)?;
+                let key = get_u64(buf)?;
+                let rid = get_u32(buf)?;
                 Ok(AtomicRegisterMessage::Read(Read { run_id, key, rid }))
             }
             VALUE_ID => {
-                let run_id = buf.get_u32();
-                let key = buf.get_u64();
-                let rid = buf.get_u32();
-                let ts = buf.get_u32();
-                let wr = buf.get_u32();
-                let value = buf.get_u32();
-                let sender_rank = buf.get_u32();
+                let run_id = get_u32(buf)?;
+                let key = get_u64(buf)?;
+                let rid = get_u32(buf)?;
+                let ts = get_u32(buf)?;
+                let wr = get_u32(buf)?;
+                let value = get_u32(buf)?;
+                let sender_rank = get_u32(buf)?;
                 Ok(AtomicRegisterMessage::Value(Value {
                     run_id,
                     key,
@@ -1884,12 +1878,12 @@ impl Deserialiser<AtomicRegisterMessage> for AtomicRegisterSer {
                 }))
             }
             WRITE_ID => {
-                let run_id = buf.get_u32();
-                let key = buf.get_u64();
-                let rid = buf.get_u32();
-                let ts = buf.get_u32();
-                let wr = buf.get_u32();
-                let value = buf.get_u32();
+                let run_id = get_u32(buf)?;
+                let key = get_u64(buf)?;
+                let rid = get_u32(buf)?;
+                let ts = get_u32(buf)?;
+                let wr = get_u32(buf)?;
+                let value = get_u32(buf)?;
                 Ok(AtomicRegisterMessage::Write(Write {
                     run_id,
                     key,
@@ -1900,9 +1894,9 @@ impl Deserialiser<AtomicRegisterMessage> for AtomicRegisterSer {
                 }))
             }
             ACK_ID => {
-                let run_id = buf.get_u32();
-                let key = buf.get_u64();
-                let rid = buf.get_u32();
+                let run_id = get_u32(buf)?;
+                let key = get_u64(buf)?;
+                let rid = get_u32(buf)?;
                 Ok(AtomicRegisterMessage::Ack(Ack { run_id, key, rid }))
             }
             _ => Err(SerError::InvalidType(
@@ -1911,3 +1905,42 @@ impl Deserialiser<AtomicRegisterMessage> for AtomicRegisterSer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ser_utils::testing::check_serialiser;
+
+    #[test]
+    fn atomic_register_ser_test() {
+        check_serialiser(1, &AtomicRegisterSer, |rng| match rng.gen_range(0, 4) {
+            0 => AtomicRegisterMessage::Read(Read {
+                run_id: rng.gen(),
+                key: rng.gen(),
+                rid: rng.gen(),
+            }),
+            1 => AtomicRegisterMessage::Value(Value {
+                run_id: rng.gen(),
+                key: rng.gen(),
+                rid: rng.gen(),
+                ts: rng.gen(),
+                wr: rng.gen(),
+                value: rng.gen(),
+                sender_rank: rng.gen(),
+            }),
+            2 => AtomicRegisterMessage::Write(Write {
+                run_id: rng.gen(),
+                key: rng.gen(),
+                rid: rng.gen(),
+                ts: rng.gen(),
+                wr: rng.gen(),
+                value: rng.gen(),
+            }),
+            _ => AtomicRegisterMessage::Ack(Ack {
+                run_id: rng.gen(),
+                key: rng.gen(),
+                rid: rng.gen(),
+            }),
+        });
+    }
+}
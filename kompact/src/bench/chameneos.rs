@@ -7,6 +7,20 @@ use std::convert::TryInto;
 use std::sync::Arc;
 use synchronoise::CountdownEvent;
 
+/// Every meeting is counted by both of its participants.
+fn check_meetings(num_meetings: u64, sum_meetings: u64) -> Result<(), BenchmarkError> {
+    if sum_meetings == 2 * num_meetings {
+        Ok(())
+    } else {
+        Err(BenchmarkError::ValidationFailed(format!(
+            "Chameneos reported {} meetings in total, but should be 2 * {} = {}",
+            sum_meetings,
+            num_meetings,
+            2 * num_meetings
+        )))
+    }
+}
+
 pub mod actor_chameneos {
     use super::*;
 
@@ -18,7 +32,7 @@ pub mod actor_chameneos {
         type Instance = ChameneosI;
 
         fn msg_to_conf(msg: Box<dyn (::protobuf::Message)>) -> Result<Self::Conf, BenchmarkError> {
-            downcast_msg!(msg; ChameneosRequest)
+            downcast_kompact_msg!(msg; ChameneosRequest)
         }
 
         fn new_instance() -> Self::Instance {
@@ -31,7 +45,9 @@ pub mod actor_chameneos {
     pub struct ChameneosI {
         num_chameneos: Option<usize>,
         num_meetings: Option<u64>,
+        validate: bool,
         system: Option<KompactSystem>,
+        system_config: Option<String>,
         mall: Option<Arc<Component<ChameneosMallActor>>>,
         chameneos: Vec<Arc<Component<ChameneoActor>>>,
         latch: Option<Arc<CountdownEvent>>,
@@ -42,7 +58,9 @@ pub mod actor_chameneos {
             ChameneosI {
                 num_chameneos: None,
                 num_meetings: None,
+                validate: false,
                 system: None,
+                system_config: None,
                 mall: None,
                 chameneos: Vec::new(),
                 latch: None,
@@ -56,10 +74,17 @@ pub mod actor_chameneos {
         fn setup(&mut self, c: &Self::Conf) -> () {
             self.num_chameneos = Some(c.number_of_chameneos.try_into().unwrap());
             self.num_meetings = Some(c.number_of_meetings);
-            let system = crate::kompact_system_provider::global().new_system("chameneos");
+            self.validate = c.validate;
+            let provider = crate::kompact_system_provider::for_request(c.get_system_overrides());
+            self.system_config = Some(provider.describe());
+            let system = provider.new_system("chameneos");
             self.system = Some(system);
         }
 
+        fn system_config(&self) -> Option<String> {
+            self.system_config.clone()
+        }
+
         fn prepare_iteration(&mut self) -> () {
             if let Some(ref system) = self.system {
                 if let Some(num_chameneos) = self.num_chameneos {
@@ -105,6 +130,21 @@ pub mod actor_chameneos {
             }
         }
 
+        fn validate_iteration(&mut self) -> Result<(), BenchmarkError> {
+            if !self.validate {
+                return Ok(());
+            }
+            match (self.num_meetings, self.mall.as_ref()) {
+                (Some(num_meetings), Some(mall)) => {
+                    let sum_meetings = mall.on_definition(|m| m.sum_meetings);
+                    check_meetings(num_meetings, sum_meetings)
+                }
+                _ => Err(BenchmarkError::ValidationFailed(
+                    "No mall to count the meetings of!".to_string(),
+                )),
+            }
+        }
+
         fn cleanup_iteration(&mut self, last_iteration: bool, _exec_time_millis: f64) -> () {
             let system = self.system.take().unwrap();
             // self.chameneos.clear(); // they stop themselves and got drained when run
@@ -308,7 +348,7 @@ pub mod mixed_chameneos {
         type Instance = ChameneosI;
 
         fn msg_to_conf(msg: Box<dyn (::protobuf::Message)>) -> Result<Self::Conf, BenchmarkError> {
-            downcast_msg!(msg; ChameneosRequest)
+            downcast_kompact_msg!(msg; ChameneosRequest)
         }
 
         fn new_instance() -> Self::Instance {
@@ -321,7 +361,9 @@ pub mod mixed_chameneos {
     pub struct ChameneosI {
         num_chameneos: Option<usize>,
         num_meetings: Option<u64>,
+        validate: bool,
         system: Option<KompactSystem>,
+        system_config: Option<String>,
         mall: Option<Arc<Component<ChameneosMallActor>>>,
         chameneos: Vec<Arc<Component<ChameneoActor>>>,
         latch: Option<Arc<CountdownEvent>>,
@@ -332,7 +374,9 @@ pub mod mixed_chameneos {
             ChameneosI {
                 num_chameneos: None,
                 num_meetings: None,
+                validate: false,
                 system: None,
+                system_config: None,
                 mall: None,
                 chameneos: Vec::new(),
                 latch: None,
@@ -346,10 +390,17 @@ pub mod mixed_chameneos {
         fn setup(&mut self, c: &Self::Conf) -> () {
             self.num_chameneos = Some(c.number_of_chameneos.try_into().unwrap());
             self.num_meetings = Some(c.number_of_meetings);
-            let system = crate::kompact_system_provider::global().new_system("chameneos");
+            self.validate = c.validate;
+            let provider = crate::kompact_system_provider::for_request(c.get_system_overrides());
+            self.system_config = Some(provider.describe());
+            let system = provider.new_system("chameneos");
             self.system = Some(system);
         }
 
+        fn system_config(&self) -> Option<String> {
+            self.system_config.clone()
+        }
+
         fn prepare_iteration(&mut self) -> () {
             if let Some(ref system) = self.system {
                 if let Some(num_chameneos) = self.num_chameneos {
@@ -396,6 +447,21 @@ pub mod mixed_chameneos {
             }
         }
 
+        fn validate_iteration(&mut self) -> Result<(), BenchmarkError> {
+            if !self.validate {
+                return Ok(());
+            }
+            match (self.num_meetings, self.mall.as_ref()) {
+                (Some(num_meetings), Some(mall)) => {
+                    let sum_meetings = mall.on_definition(|m| m.sum_meetings);
+                    check_meetings(num_meetings, sum_meetings)
+                }
+                _ => Err(BenchmarkError::ValidationFailed(
+                    "No mall to count the meetings of!".to_string(),
+                )),
+            }
+        }
+
         fn cleanup_iteration(&mut self, last_iteration: bool, _exec_time_millis: f64) -> () {
             let system = self.system.take().unwrap();
             // self.chameneos.clear(); // they stop themselves and got drained when run
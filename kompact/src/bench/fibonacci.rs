@@ -13,7 +13,7 @@ impl Benchmark for Fibonacci {
     type Instance = FibonacciI;
 
     fn msg_to_conf(msg: Box<dyn (::protobuf::Message)>) -> Result<Self::Conf, BenchmarkError> {
-        downcast_msg!(msg; FibonacciRequest)
+        downcast_kompact_msg!(msg; FibonacciRequest)
     }
 
     fn new_instance() -> Self::Instance {
@@ -25,7 +25,9 @@ impl Benchmark for Fibonacci {
 
 pub struct FibonacciI {
     fib_number: Option<u32>,
+    validate: bool,
     system: Option<KompactSystem>,
+    system_config: Option<String>,
     fib: Option<Arc<Component<FibonacciActor>>>,
     latch: Option<Arc<CountdownEvent>>,
 }
@@ -34,7 +36,9 @@ impl FibonacciI {
     fn new() -> FibonacciI {
         FibonacciI {
             fib_number: None,
+            validate: false,
             system: None,
+            system_config: None,
             fib: None,
             latch: None,
         }
@@ -46,10 +50,17 @@ impl BenchmarkInstance for FibonacciI {
 
     fn setup(&mut self, c: &Self::Conf) -> () {
         self.fib_number = Some(c.fib_number);
-        let system = crate::kompact_system_provider::global().new_system("fibonacci");
+        self.validate = c.validate;
+        let provider = crate::kompact_system_provider::for_request(c.get_system_overrides());
+        self.system_config = Some(provider.describe());
+        let system = provider.new_system("fibonacci");
         self.system = Some(system);
     }
 
+    fn system_config(&self) -> Option<String> {
+        self.system_config.clone()
+    }
+
     fn prepare_iteration(&mut self) -> () {
         match self.system {
             Some(ref system) => {
@@ -86,6 +97,29 @@ impl BenchmarkInstance for FibonacciI {
         }
     }
 
+    fn validate_iteration(&mut self) -> Result<(), BenchmarkError> {
+        if !self.validate {
+            return Ok(());
+        }
+        match (self.fib_number, self.fib.as_ref()) {
+            (Some(fib_number), Some(fib)) => {
+                let result = fib.on_definition(|f| f.result);
+                let expected = sequential_fibonacci(fib_number);
+                if result == expected {
+                    Ok(())
+                } else {
+                    Err(BenchmarkError::ValidationFailed(format!(
+                        "Fibonacci({}) was computed as {}, but should be {}",
+                        fib_number, result, expected
+                    )))
+                }
+            }
+            _ => Err(BenchmarkError::ValidationFailed(
+                "No Fibonacci result to validate!".to_string(),
+            )),
+        }
+    }
+
     fn cleanup_iteration(&mut self, last_iteration: bool, _exec_time_millis: f64) -> () {
         let _ = self.fib.take(); // kills itself, so just drop
         std::thread::sleep(Duration::from_millis(500)); // reduce bleed over effect from still dying actors into next test
@@ -99,6 +133,17 @@ impl BenchmarkInstance for FibonacciI {
     }
 }
 
+fn sequential_fibonacci(n: u32) -> u64 {
+    let mut previous = 1u64;
+    let mut current = 1u64;
+    for _i in 2..n {
+        let next = previous + current;
+        previous = current;
+        current = next;
+    }
+    current
+}
+
 enum ResultTarget {
     Latch(Arc<CountdownEvent>),
     Parent(ActorRefStrong<FibonacciMsg>),
@@ -144,6 +189,9 @@ impl FibonacciActor {
                 parent_ref.tell(response);
             }
             ResultTarget::Latch(ref latch) => {
+                if let FibonacciMsg::Response { value } = response {
+                    self.result = value; // keep the final value around for validation
+                }
                 latch.decrement().expect("Should decrement!");
             }
         }
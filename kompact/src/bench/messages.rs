@@ -1,3 +1,4 @@
+use crate::ser_utils::get_u64;
 use crate::serialiser_ids;
 use kompact::prelude::*;
 
@@ -128,7 +129,7 @@ impl Deserialiser<Ping> for Ping {
     const SER_ID: SerId = Ping::SERID;
 
     fn deserialise(buf: &mut dyn Buf) -> Result<Ping, SerError> {
-        let index = buf.get_u64();
+        let index = get_u64(buf)?;
         Ok(Ping::new(index))
     }
 }
@@ -167,7 +168,22 @@ impl Deserialiser<Pong> for Pong {
     const SER_ID: SerId = Pong::SERID;
 
     fn deserialise(buf: &mut dyn Buf) -> Result<Pong, SerError> {
-        let index = buf.get_u64();
+        let index = get_u64(buf)?;
         Ok(Pong::new(index))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ser_utils::testing::check_serialisable;
+    use rand::Rng;
+
+    #[test]
+    fn ping_pong_ser_test() {
+        check_serialisable::<StaticPing, StaticPing>(1, |_| STATIC_PING);
+        check_serialisable::<StaticPong, StaticPong>(2, |_| STATIC_PONG);
+        check_serialisable::<Ping, Ping>(3, |rng| Ping::new(rng.gen()));
+        check_serialisable::<Pong, Pong>(4, |rng| Pong::new(rng.gen()));
+    }
+}
@@ -2,6 +2,17 @@ use super::*;
 use benchmark_suite_shared::benchmark::*;
 use std::time::Duration;
 
+/// Like `downcast_msg!`, but also checks the request's `system_overrides`,
+/// which the benchmark applies to the systems it creates during setup.
+macro_rules! downcast_kompact_msg {
+    ($m:expr; $M:ty) => {{
+        downcast_msg!($m; $M).and_then(|req| {
+            crate::kompact_system_provider::check_overrides(req.get_system_overrides())
+                .map(|_| req)
+        })
+    }};
+}
+
 pub mod all_pairs_shortest_path;
 pub mod atomic_broadcast;
 pub mod atomicregister;
@@ -78,6 +89,10 @@ impl BenchmarkFactory for ComponentFactory {
     ) -> Result<Box<dyn AbstractDistributedBenchmark>, NotImplementedError> {
         Err(NotImplementedError::NotImplementable)
     }
+
+    fn replicated_kv(&self) -> Result<Box<dyn AbstractDistributedBenchmark>, NotImplementedError> {
+        Err(NotImplementedError::NotImplementable)
+    }
 }
 
 pub fn actor() -> Box<dyn BenchmarkFactory> {
@@ -148,6 +163,10 @@ impl BenchmarkFactory for ActorFactory {
     ) -> Result<Box<dyn AbstractDistributedBenchmark>, NotImplementedError> {
         Err(NotImplementedError::NotImplementable)
     }
+
+    fn replicated_kv(&self) -> Result<Box<dyn AbstractDistributedBenchmark>, NotImplementedError> {
+        Err(NotImplementedError::NotImplementable)
+    }
 }
 pub fn mixed() -> Box<dyn BenchmarkFactory> {
     Box::new(MixedFactory {})
@@ -163,6 +182,7 @@ impl BenchmarkFactory for MixedFactory {
             atomic_broadcast::atomic_broadcast::AtomicBroadcast::LABEL => {
                 self.atomic_broadcast().map_into()
             }
+            atomic_broadcast::replicated_kv::ReplicatedKV::LABEL => self.replicated_kv().map_into(),
             _ => Err(NotImplementedError::NotFound),
         }
     }
@@ -213,4 +233,8 @@ impl BenchmarkFactory for MixedFactory {
     ) -> Result<Box<dyn AbstractDistributedBenchmark>, NotImplementedError> {
         Ok(atomic_broadcast::atomic_broadcast::AtomicBroadcast {}.into())
     }
+
+    fn replicated_kv(&self) -> Result<Box<dyn AbstractDistributedBenchmark>, NotImplementedError> {
+        Ok(atomic_broadcast::replicated_kv::ReplicatedKV {}.into())
+    }
 }
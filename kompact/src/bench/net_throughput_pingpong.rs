@@ -1,5 +1,6 @@
 use super::*;
 
+use crate::kompact_system_provider::WithOverrides;
 use benchmark_suite_shared::kompics_benchmarks::benchmarks::ThroughputPingPongRequest;
 use kompact::prelude::*;
 use std::str::FromStr;
@@ -22,6 +23,32 @@ impl ClientParams {
             static_only,
         }
     }
+
+    fn parse(str: String) -> Result<ClientParams, BenchmarkError> {
+        let split: Vec<_> = str.split(',').collect();
+        if split.len() != 2 {
+            Err(BenchmarkError::InvalidMessage(format!(
+                "String '{}' does not represent a client conf!",
+                str
+            )))
+        } else {
+            let num_str = split[0];
+            let num = num_str.parse::<u32>().map_err(|e| {
+                BenchmarkError::InvalidMessage(format!(
+                    "String '{}' does not represent a client conf: {:?}",
+                    str, e
+                ))
+            })?;
+            let static_str = split[1];
+            let static_only = static_str.parse::<bool>().map_err(|e| {
+                BenchmarkError::InvalidMessage(format!(
+                    "String '{}' does not represent a client conf: {:?}",
+                    str, e
+                ))
+            })?;
+            Ok(ClientParams::new(num, static_only))
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -32,7 +59,7 @@ pub struct PingPong;
 
 impl DistributedBenchmark for PingPong {
     type MasterConf = ThroughputPingPongRequest;
-    type ClientConf = ClientParams;
+    type ClientConf = WithOverrides<ClientParams>;
     type ClientData = ClientRefs;
     type Master = PingPongMaster;
     type Client = PingPongClient;
@@ -45,36 +72,14 @@ impl DistributedBenchmark for PingPong {
     fn msg_to_master_conf(
         msg: Box<dyn (::protobuf::Message)>,
     ) -> Result<Self::MasterConf, BenchmarkError> {
-        downcast_msg!(msg; ThroughputPingPongRequest)
+        downcast_kompact_msg!(msg; ThroughputPingPongRequest)
     }
 
     fn new_client() -> Self::Client {
         PingPongClient::new()
     }
     fn str_to_client_conf(str: String) -> Result<Self::ClientConf, BenchmarkError> {
-        let split: Vec<_> = str.split(',').collect();
-        if split.len() != 2 {
-            Err(BenchmarkError::InvalidMessage(format!(
-                "String '{}' does not represent a client conf!",
-                str
-            )))
-        } else {
-            let num_str = split[0];
-            let num = num_str.parse::<u32>().map_err(|e| {
-                BenchmarkError::InvalidMessage(format!(
-                    "String '{}' does not represent a client conf: {:?}",
-                    str, e
-                ))
-            })?;
-            let static_str = split[1];
-            let static_only = static_str.parse::<bool>().map_err(|e| {
-                BenchmarkError::InvalidMessage(format!(
-                    "String '{}' does not represent a client conf: {:?}",
-                    str, e
-                ))
-            })?;
-            Ok(ClientParams::new(num, static_only))
-        }
+        WithOverrides::deserialise(str, ClientParams::parse)
     }
     fn str_to_client_data(str: String) -> Result<Self::ClientData, BenchmarkError> {
         let res: Result<Vec<_>, _> = str.split(',').map(|s| ActorPath::from_str(s)).collect();
@@ -84,7 +89,7 @@ impl DistributedBenchmark for PingPong {
     }
 
     fn client_conf_to_str(c: Self::ClientConf) -> String {
-        format!("{},{}", c.num_pongers, c.static_only)
+        c.serialise(|c| format!("{},{}", c.num_pongers, c.static_only))
     }
     fn client_data_to_str(d: Self::ClientData) -> String {
         d.0.into_iter()
@@ -97,6 +102,7 @@ impl DistributedBenchmark for PingPong {
 pub struct PingPongMaster {
     params: Option<Params>,
     system: Option<KompactSystem>,
+    system_config: Option<String>,
     pingers: EitherComponents<StaticPinger, Pinger>,
     pinger_refs: Vec<ActorRefStrong<&'static Run>>,
     pongers: Vec<ActorPath>,
@@ -108,6 +114,7 @@ impl PingPongMaster {
         PingPongMaster {
             params: None,
             system: None,
+            system_config: None,
             pingers: EitherComponents::Empty,
             pinger_refs: Vec::new(),
             pongers: Vec::new(),
@@ -118,7 +125,7 @@ impl PingPongMaster {
 
 impl DistributedBenchmarkMaster for PingPongMaster {
     type MasterConf = ThroughputPingPongRequest;
-    type ClientConf = ClientParams;
+    type ClientConf = WithOverrides<ClientParams>;
     type ClientData = ClientRefs;
 
     fn setup(
@@ -127,12 +134,17 @@ impl DistributedBenchmarkMaster for PingPongMaster {
         _m: &DeploymentMetaData,
     ) -> Result<Self::ClientConf, BenchmarkError> {
         let params = Params::from_req(&c);
-        let system =
-            crate::kompact_system_provider::global().new_remote_system("throughputpingpong");
+        let provider = crate::kompact_system_provider::for_request(c.get_system_overrides());
+        self.system_config = Some(provider.describe());
+        let system = provider.new_remote_system("throughputpingpong");
         self.system = Some(system);
         let client_conf = ClientParams::new(params.num_pairs, params.static_only);
         self.params = Some(params);
-        Ok(client_conf)
+        Ok(WithOverrides::new(c.get_system_overrides(), client_conf))
+    }
+
+    fn system_config(&self) -> Option<String> {
+        self.system_config.clone()
     }
     fn prepare_iteration(&mut self, d: Vec<Self::ClientData>) -> () {
         self.pongers = d[0].0.clone();
@@ -241,14 +253,14 @@ impl PingPongClient {
 }
 
 impl DistributedBenchmarkClient for PingPongClient {
-    type ClientConf = ClientParams;
+    type ClientConf = WithOverrides<ClientParams>;
     type ClientData = ClientRefs;
 
     fn setup(&mut self, c: Self::ClientConf) -> Self::ClientData {
         println!("Setting up ponger.");
 
-        let system =
-            crate::kompact_system_provider::global().new_remote_system("throughputpingpong");
+        let system = c.provider().new_remote_system("throughputpingpong");
+        let c = c.conf;
         let (pongers, ponger_refs) = if c.static_only {
             let mut vpo = Vec::with_capacity(c.num_pongers as usize);
             let mut vpor = Vec::with_capacity(c.num_pongers as usize);
@@ -541,14 +553,17 @@ mod tests {
     #[test]
     fn test_client_params() {
         let params = ClientParams::new(42, true);
-        let param_string = PingPong::client_conf_to_str(params.clone());
+        let param_string = PingPong::client_conf_to_str(WithOverrides::new("", params.clone()));
         let params_deser = PingPong::str_to_client_conf(param_string).unwrap();
-        assert_eq!(params, params_deser);
+        assert_eq!(params, params_deser.conf);
 
         let params2 = ClientParams::new(42, false);
-        let param_string2 = PingPong::client_conf_to_str(params2.clone());
+        let overrides = "system { throughput = 10 }";
+        let param_string2 =
+            PingPong::client_conf_to_str(WithOverrides::new(overrides, params2.clone()));
         let params_deser2 = PingPong::str_to_client_conf(param_string2).unwrap();
-        assert_eq!(params2, params_deser2);
+        assert_eq!(params2, params_deser2.conf);
+        assert_eq!(overrides, params_deser2.overrides);
     }
 
     #[test]
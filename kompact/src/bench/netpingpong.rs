@@ -1,5 +1,6 @@
 use super::*;
 
+use crate::kompact_system_provider::WithOverrides;
 use benchmark_suite_shared::kompics_benchmarks::benchmarks::PingPongRequest;
 use kompact::prelude::*;
 use messages::{Run, StaticPing, StaticPong, RUN, STATIC_PING, STATIC_PONG};
@@ -13,7 +14,7 @@ pub struct PingPong;
 
 impl DistributedBenchmark for PingPong {
     type MasterConf = PingPongRequest;
-    type ClientConf = ();
+    type ClientConf = WithOverrides<()>;
     type ClientData = ActorPath;
     type Master = PingPongMaster;
     type Client = PingPongClient;
@@ -26,14 +27,14 @@ impl DistributedBenchmark for PingPong {
     fn msg_to_master_conf(
         msg: Box<dyn (::protobuf::Message)>,
     ) -> Result<Self::MasterConf, BenchmarkError> {
-        downcast_msg!(msg; PingPongRequest)
+        downcast_kompact_msg!(msg; PingPongRequest)
     }
 
     fn new_client() -> Self::Client {
         PingPongClient::new()
     }
-    fn str_to_client_conf(_str: String) -> Result<Self::ClientConf, BenchmarkError> {
-        Ok(())
+    fn str_to_client_conf(str: String) -> Result<Self::ClientConf, BenchmarkError> {
+        WithOverrides::deserialise(str, |_| Ok(()))
     }
     fn str_to_client_data(str: String) -> Result<Self::ClientData, BenchmarkError> {
         let res = ActorPath::from_str(&str);
@@ -42,8 +43,8 @@ impl DistributedBenchmark for PingPong {
         })
     }
 
-    fn client_conf_to_str(_c: Self::ClientConf) -> String {
-        String::new()
+    fn client_conf_to_str(c: Self::ClientConf) -> String {
+        c.serialise(|_| String::new())
     }
     fn client_data_to_str(d: Self::ClientData) -> String {
         d.to_string()
@@ -53,6 +54,7 @@ impl DistributedBenchmark for PingPong {
 pub struct PingPongMaster {
     num: Option<u64>,
     system: Option<KompactSystem>,
+    system_config: Option<String>,
     pinger: Option<Arc<Component<Pinger>>>,
     ponger: Option<ActorPath>,
     latch: Option<Arc<CountdownEvent>>,
@@ -63,6 +65,7 @@ impl PingPongMaster {
         PingPongMaster {
             num: None,
             system: None,
+            system_config: None,
             pinger: None,
             ponger: None,
             latch: None,
@@ -72,7 +75,7 @@ impl PingPongMaster {
 
 impl DistributedBenchmarkMaster for PingPongMaster {
     type MasterConf = PingPongRequest;
-    type ClientConf = ();
+    type ClientConf = WithOverrides<()>;
     type ClientData = ActorPath;
 
     fn setup(
@@ -81,10 +84,15 @@ impl DistributedBenchmarkMaster for PingPongMaster {
         _m: &DeploymentMetaData,
     ) -> Result<Self::ClientConf, BenchmarkError> {
         self.num = Some(c.number_of_messages);
-        let system = crate::kompact_system_provider::global()
-            .new_remote_system_with_threads("netpingpong", 1);
+        let provider = crate::kompact_system_provider::for_request(c.get_system_overrides());
+        self.system_config = Some(provider.describe_with(1));
+        let system = provider.new_remote_system_with_threads("netpingpong", 1);
         self.system = Some(system);
-        Ok(())
+        Ok(WithOverrides::new(c.get_system_overrides(), ()))
+    }
+
+    fn system_config(&self) -> Option<String> {
+        self.system_config.clone()
     }
     fn prepare_iteration(&mut self, d: Vec<Self::ClientData>) -> () {
         let ponger_ref = match self.ponger {
@@ -171,13 +179,14 @@ impl PingPongClient {
 }
 
 impl DistributedBenchmarkClient for PingPongClient {
-    type ClientConf = ();
+    type ClientConf = WithOverrides<()>;
     type ClientData = ActorPath;
 
-    fn setup(&mut self, _c: Self::ClientConf) -> Self::ClientData {
+    fn setup(&mut self, c: Self::ClientConf) -> Self::ClientData {
         println!("Setting up ponger.");
 
-        let system = crate::kompact_system_provider::global()
+        let system = c
+            .provider()
             .new_remote_system_with_threads("netpingpong", 1);
         let (ponger, unique_reg_f) = system.create_and_register(|| Ponger::new());
         let named_reg_f = system.register_by_alias(&ponger, "ponger");
@@ -19,7 +19,7 @@ pub mod actor_pingpong {
         type Instance = PingPongI;
 
         fn msg_to_conf(msg: Box<dyn (::protobuf::Message)>) -> Result<Self::Conf, BenchmarkError> {
-            downcast_msg!(msg; PingPongRequest)
+            downcast_kompact_msg!(msg; PingPongRequest)
         }
 
         fn new_instance() -> Self::Instance {
@@ -32,6 +32,7 @@ pub mod actor_pingpong {
     pub struct PingPongI {
         num: Option<u64>,
         system: Option<KompactSystem>,
+        system_config: Option<String>,
         pinger: Option<Arc<Component<Pinger>>>,
         ponger: Option<Arc<Component<Ponger>>>,
         latch: Option<Arc<CountdownEvent>>,
@@ -42,6 +43,7 @@ pub mod actor_pingpong {
             PingPongI {
                 num: None,
                 system: None,
+                system_config: None,
                 pinger: None,
                 ponger: None,
                 latch: None,
@@ -54,11 +56,16 @@ pub mod actor_pingpong {
 
         fn setup(&mut self, c: &Self::Conf) -> () {
             self.num = Some(c.number_of_messages);
-            let system =
-                crate::kompact_system_provider::global().new_system_with_threads("pingpong", 2);
+            let provider = crate::kompact_system_provider::for_request(c.get_system_overrides());
+            self.system_config = Some(provider.describe_with(2));
+            let system = provider.new_system_with_threads("pingpong", 2);
             self.system = Some(system);
         }
 
+        fn system_config(&self) -> Option<String> {
+            self.system_config.clone()
+        }
+
         fn prepare_iteration(&mut self) -> () {
             match self.num {
                 Some(num) => match self.system {
@@ -226,7 +233,7 @@ pub mod component_pingpong {
         type Instance = PingPongI;
 
         fn msg_to_conf(msg: Box<dyn (::protobuf::Message)>) -> Result<Self::Conf, BenchmarkError> {
-            downcast_msg!(msg; PingPongRequest)
+            downcast_kompact_msg!(msg; PingPongRequest)
         }
 
         fn new_instance() -> Self::Instance {
@@ -239,6 +246,7 @@ pub mod component_pingpong {
     pub struct PingPongI {
         num: Option<u64>,
         system: Option<KompactSystem>,
+        system_config: Option<String>,
         pinger: Option<Arc<Component<Pinger>>>,
         ponger: Option<Arc<Component<Ponger>>>,
         latch: Option<Arc<CountdownEvent>>,
@@ -249,6 +257,7 @@ pub mod component_pingpong {
             PingPongI {
                 num: None,
                 system: None,
+                system_config: None,
                 pinger: None,
                 ponger: None,
                 latch: None,
@@ -261,11 +270,16 @@ pub mod component_pingpong {
 
         fn setup(&mut self, c: &Self::Conf) -> () {
             self.num = Some(c.number_of_messages);
-            let system =
-                crate::kompact_system_provider::global().new_system_with_threads("pingpong", 2);
+            let provider = crate::kompact_system_provider::for_request(c.get_system_overrides());
+            self.system_config = Some(provider.describe_with(2));
+            let system = provider.new_system_with_threads("pingpong", 2);
             self.system = Some(system);
         }
 
+        fn system_config(&self) -> Option<String> {
+            self.system_config.clone()
+        }
+
         fn prepare_iteration(&mut self) -> () {
             match self.num {
                 Some(num) => match self.system {
@@ -1,5 +1,7 @@
 use super::*;
 
+use crate::kompact_system_provider::WithOverrides;
+use crate::ser_utils::{get_i64, get_u32, get_u64, get_u8};
 use benchmark_suite_shared::kompics_benchmarks::benchmarks::StreamingWindowsRequest;
 use kompact::prelude::*;
 use parse_duration;
@@ -39,7 +41,7 @@ pub struct StreamingWindows;
 
 impl DistributedBenchmark for StreamingWindows {
     type MasterConf = StreamingWindowsRequest;
-    type ClientConf = WindowerConfig;
+    type ClientConf = WithOverrides<WindowerConfig>;
     type ClientData = WindowerRefs;
 
     type Master = StreamingWindowsMaster;
@@ -54,60 +56,62 @@ impl DistributedBenchmark for StreamingWindows {
     fn msg_to_master_conf(
         msg: Box<dyn (::protobuf::Message)>,
     ) -> Result<Self::MasterConf, BenchmarkError> {
-        downcast_msg!(msg; StreamingWindowsRequest)
+        downcast_kompact_msg!(msg; StreamingWindowsRequest)
     }
 
     fn new_client() -> Self::Client {
         StreamingWindowsClient::new()
     }
     fn str_to_client_conf(str: String) -> Result<Self::ClientConf, BenchmarkError> {
-        let split: Vec<_> = str.split(',').collect();
-        if split.len() != 4 {
-            Err(BenchmarkError::InvalidMessage(format!(
-                "String '{}' does not represent a client conf!",
-                str
-            )))
-        } else {
-            let window_size_str = split[0];
-            // Note: The `as_millis` produces a u128, but `from_millis` takes a u64.
-            // Should not be an issue, though, since JVM implementations can't deal
-            // with u128-sized Durations anyway.
-            let window_size = window_size_str
-                .parse::<u64>()
-                .map(Duration::from_millis)
-                .map_err(|e| {
+        WithOverrides::deserialise(str, |str| {
+            let split: Vec<_> = str.split(',').collect();
+            if split.len() != 4 {
+                Err(BenchmarkError::InvalidMessage(format!(
+                    "String '{}' does not represent a client conf!",
+                    str
+                )))
+            } else {
+                let window_size_str = split[0];
+                // Note: The `as_millis` produces a u128, but `from_millis` takes a u64.
+                // Should not be an issue, though, since JVM implementations can't deal
+                // with u128-sized Durations anyway.
+                let window_size = window_size_str
+                    .parse::<u64>()
+                    .map(Duration::from_millis)
+                    .map_err(|e| {
+                        BenchmarkError::InvalidMessage(format!(
+                            "String '{}' does not represent a client conf: {:?}",
+                            str, e
+                        ))
+                    })?;
+                let batch_size_str = split[1];
+                let batch_size = batch_size_str.parse::<u64>().map_err(|e| {
                     BenchmarkError::InvalidMessage(format!(
                         "String '{}' does not represent a client conf: {:?}",
                         str, e
                     ))
                 })?;
-            let batch_size_str = split[1];
-            let batch_size = batch_size_str.parse::<u64>().map_err(|e| {
-                BenchmarkError::InvalidMessage(format!(
-                    "String '{}' does not represent a client conf: {:?}",
-                    str, e
-                ))
-            })?;
-            let amplification_str = split[2];
-            let amplification = amplification_str.parse::<u64>().map_err(|e| {
-                BenchmarkError::InvalidMessage(format!(
-                    "String '{}' does not represent a client conf: {:?}",
-                    str, e
+                let amplification_str = split[2];
+                let amplification = amplification_str.parse::<u64>().map_err(|e| {
+                    BenchmarkError::InvalidMessage(format!(
+                        "String '{}' does not represent a client conf: {:?}",
+                        str, e
+                    ))
+                })?;
+                let paths = split[3];
+                let actor_paths_res: Result<Vec<ActorPath>, _> =
+                    paths.split(';').map(|s| ActorPath::from_str(s)).collect();
+                let actor_paths = actor_paths_res.map_err(|e| {
+                    BenchmarkError::InvalidMessage(format!("Could not read client conf: {}", e))
+                })?;
+                Ok(WindowerConfig::new(
+                    window_size,
+                    batch_size,
+                    amplification,
+                    actor_paths,
                 ))
-            })?;
-            let paths = split[3];
-            let actor_paths_res: Result<Vec<ActorPath>, _> =
-                paths.split(';').map(|s| ActorPath::from_str(s)).collect();
-            let actor_paths = actor_paths_res.map_err(|e| {
-                BenchmarkError::InvalidMessage(format!("Could not read client conf: {}", e))
-            })?;
-            Ok(WindowerConfig::new(
-                window_size,
-                batch_size,
-                amplification,
-                actor_paths,
-            ))
-        }
+            }
+        })
     }
 
     fn str_to_client_data(str: String) -> Result<Self::ClientData, BenchmarkError> {
@@ -118,19 +122,21 @@ impl DistributedBenchmark for StreamingWindows {
     }
 
     fn client_conf_to_str(c: Self::ClientConf) -> String {
-        let paths = c
-            .upstream_actor_paths
-            .into_iter()
-            .map(|path| path.to_string())
-            .collect::<Vec<String>>()
-            .join(";");
-        format!(
-            "{},{},{},{}",
-            c.window_size.as_millis(),
-            c.batch_size,
-            c.amplification,
-            paths
-        )
+        c.serialise(|c| {
+            let paths = c
+                .upstream_actor_paths
+                .into_iter()
+                .map(|path| path.to_string())
+                .collect::<Vec<String>>()
+                .join(";");
+            format!(
+                "{},{},{},{}",
+                c.window_size.as_millis(),
+                c.batch_size,
+                c.amplification,
+                paths
+            )
+        })
     }
     fn client_data_to_str(d: Self::ClientData) -> String {
         d.0.into_iter()
@@ -146,6 +152,7 @@ struct Params {
     window_size: Duration,
     number_of_windows: u64,
     window_size_amplification: u64,
+    validate: bool,
 }
 impl Params {
     fn from_req(msg: &StreamingWindowsRequest) -> Result<Params, BenchmarkError> {
@@ -161,6 +168,7 @@ impl Params {
             window_size,
             number_of_windows: msg.number_of_windows,
             window_size_amplification: msg.window_size_amplification,
+            validate: msg.validate,
         })
     }
 }
@@ -171,10 +179,12 @@ const FLUSH_TIMEOUT: Duration = Duration::from_secs(60);
 pub struct StreamingWindowsMaster {
     params: Option<Params>,
     system: Option<KompactSystem>,
+    system_config: Option<String>,
     latch: Option<Arc<CountdownEvent>>,
     sources: Vec<(u32, Arc<Component<StreamSource>>)>,
     sinks: Vec<Arc<Component<StreamSink>>>,
     sink_refs: Vec<ActorRefStrong<SinkMsg>>,
+    reference: Vec<Vec<f64>>,
 }
 
 impl StreamingWindowsMaster {
@@ -182,17 +192,19 @@ impl StreamingWindowsMaster {
         StreamingWindowsMaster {
             params: None,
             system: None,
+            system_config: None,
             latch: None,
             sources: Vec::new(),
             sinks: Vec::new(),
             sink_refs: Vec::new(),
+            reference: Vec::new(),
         }
     }
 }
 
 impl DistributedBenchmarkMaster for StreamingWindowsMaster {
     type MasterConf = StreamingWindowsRequest;
-    type ClientConf = WindowerConfig;
+    type ClientConf = WithOverrides<WindowerConfig>;
     type ClientData = WindowerRefs;
 
     fn setup(
@@ -201,11 +213,14 @@ impl DistributedBenchmarkMaster for StreamingWindowsMaster {
         _m: &DeploymentMetaData,
     ) -> Result<Self::ClientConf, BenchmarkError> {
         let params = Params::from_req(&c)?;
-        let system = crate::kompact_system_provider::global().new_remote_system("streamingwindows");
+        let provider = crate::kompact_system_provider::for_request(c.get_system_overrides());
+        self.system_config = Some(provider.describe());
+        let system = provider.new_remote_system("streamingwindows");
 
         let mut sources: Vec<ActorPath> = Vec::new();
         for pid in 0..params.number_of_partitions {
-            let (source, req_f) = system.create_and_register(|| StreamSource::with(pid));
+            let (source, req_f) =
+                system.create_and_register(|| StreamSource::with(pid, params.validate));
             let source_path = req_f.wait_expect(REG_TIMEOUT, "Source failed to register!");
             system
                 .start_notify(&source)
@@ -215,6 +230,19 @@ impl DistributedBenchmarkMaster for StreamingWindowsMaster {
             sources.push(source_path);
         }
         self.system = Some(system);
+        if params.validate {
+            let window_size_ms = params.window_size.as_millis() as u64; // see above for cast explanation
+            self.reference = (0..params.number_of_partitions)
+                .map(|pid| {
+                    reference_medians(
+                        pid,
+                        window_size_ms,
+                        params.number_of_windows,
+                        params.window_size_amplification,
+                    )
+                })
+                .collect();
+        }
         let client_conf = WindowerConfig::new(
             params.window_size,
             params.batch_size,
@@ -222,7 +250,11 @@ impl DistributedBenchmarkMaster for StreamingWindowsMaster {
             sources,
         );
         self.params = Some(params);
-        Ok(client_conf)
+        Ok(WithOverrides::new(c.get_system_overrides(), client_conf))
+    }
+
+    fn system_config(&self) -> Option<String> {
+        self.system_config.clone()
     }
 
     fn prepare_iteration(&mut self, d: Vec<Self::ClientData>) -> () {
@@ -233,7 +265,12 @@ impl DistributedBenchmarkMaster for StreamingWindowsMaster {
                 let latch = Arc::new(CountdownEvent::new(params.number_of_partitions as usize));
                 for windower in windowers.drain(..) {
                     let (sink, req_f) = system.create_and_register(|| {
-                        StreamSink::with(latch.clone(), params.number_of_windows, windower)
+                        StreamSink::with(
+                            latch.clone(),
+                            params.number_of_windows,
+                            windower,
+                            params.validate,
+                        )
                     });
                     let _sink_path = req_f.wait_expect(REG_TIMEOUT, "Sink failed to register!");
 
@@ -264,6 +301,45 @@ impl DistributedBenchmarkMaster for StreamingWindowsMaster {
             unimplemented!()
         }
     }
+    fn validate_iteration(&mut self) -> Result<(), BenchmarkError> {
+        if self.reference.is_empty() {
+            return Ok(());
+        }
+        for sink in self.sinks.iter() {
+            let medians = sink.on_definition(|s| s.medians.take()).unwrap_or_default();
+            let partition_id = match medians.first() {
+                Some((pid, _)) => *pid,
+                None => {
+                    return Err(BenchmarkError::ValidationFailed(
+                        "A sink did not receive any windows!".to_string(),
+                    ))
+                }
+            };
+            let expected = self.reference.get(partition_id as usize).ok_or_else(|| {
+                BenchmarkError::ValidationFailed(format!("Unknown partition {}", partition_id))
+            })?;
+            if medians.len() != expected.len() {
+                return Err(BenchmarkError::ValidationFailed(format!(
+                    "Partition {} produced {} windows, but should be {}",
+                    partition_id,
+                    medians.len(),
+                    expected.len()
+                )));
+            }
+            for (window, ((pid, actual), expected)) in
+                medians.iter().zip(expected.iter()).enumerate()
+            {
+                if *pid != partition_id || actual != expected {
+                    return Err(BenchmarkError::ValidationFailed(format!(
+                        "Window {} of partition {} had median {} (from partition {}), but should be {}",
+                        window, partition_id, actual, pid, expected
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn cleanup_iteration(&mut self, last_iteration: bool, _exec_time_millis: f64) -> () {
         let system = self.system.take().expect("System during cleanup");
         let reset_futures: Vec<KFuture<()>> = self
@@ -302,6 +378,7 @@ impl DistributedBenchmarkMaster for StreamingWindowsMaster {
                 .shutdown()
                 .expect("Kompact didn't shut down properly");
             self.params = None;
+            self.reference.clear();
         } else {
             self.system = Some(system);
         }
@@ -322,13 +399,14 @@ impl StreamingWindowsClient {
 }
 
 impl DistributedBenchmarkClient for StreamingWindowsClient {
-    type ClientConf = WindowerConfig;
+    type ClientConf = WithOverrides<WindowerConfig>;
     type ClientData = WindowerRefs;
 
-    fn setup(&mut self, mut c: Self::ClientConf) -> Self::ClientData {
+    fn setup(&mut self, c: Self::ClientConf) -> Self::ClientData {
         println!("Setting up windowers.");
 
-        let system = crate::kompact_system_provider::global().new_remote_system("streamingwindows");
+        let system = c.provider().new_remote_system("streamingwindows");
+        let mut c = c.conf;
 
         let window_size = c.window_size;
         let batch_size = c.batch_size;
@@ -379,6 +457,53 @@ impl DistributedBenchmarkClient for StreamingWindowsClient {
     }
 }
 
+/// Sorts the (non-empty) `window` in place and returns its median.
+fn median(window: &mut [i64]) -> f64 {
+    window.sort_unstable();
+    let len = window.len();
+    if len % 2 == 0 {
+        let upper_middle = len / 2;
+        let lower_middle = upper_middle - 1;
+        let um_value = window[upper_middle];
+        let lm_value = window[lower_middle];
+        match lm_value.checked_add(um_value) {
+            Some(sum) => (sum as f64) / 2.0,
+            None => {
+                let um_double = (um_value as f64) / 2.0;
+                let lm_double = (lm_value as f64) / 2.0;
+                lm_double + um_double
+            }
+        }
+    } else {
+        let middle = len / 2;
+        window[middle] as f64
+    }
+}
+
+/// Sequentially computes the window medians a single partition should produce in one iteration.
+fn reference_medians(
+    partition_id: u32,
+    window_size_ms: u64,
+    number_of_windows: u64,
+    amplification: u64,
+) -> Vec<f64> {
+    let mut random = StreamSource::random_for(partition_id);
+    let mut window: Vec<i64> = Vec::new();
+    (0..number_of_windows)
+        .map(|_| {
+            window.clear();
+            // sources emit one event per timestamp, starting at 0
+            for _ts in 0..window_size_ms {
+                let value: i64 = random.gen();
+                for _i in 0..amplification {
+                    window.push(value);
+                }
+            }
+            median(&mut window)
+        })
+        .collect()
+}
+
 #[derive(Debug)]
 enum WindowerMsg {
     Start,
@@ -444,14 +569,14 @@ impl Serialisable for WindowerMsg {
 impl Deserialiser<WindowerMsg> for WindowerMsg {
     const SER_ID: SerId = Self::SERID;
     fn deserialise(buf: &mut dyn Buf) -> Result<WindowerMsg, SerError> {
-        let flag = buf.get_u8();
+        let flag = get_u8(buf)?;
         match flag {
             Self::START_FLAG => Ok(WindowerMsg::Start),
             Self::STOP_FLAG => Ok(WindowerMsg::Stop),
             Self::EVENT_FLAG => {
-                let ts = buf.get_u64();
-                let pid = buf.get_u32();
-                let value = buf.get_i64();
+                let ts = get_u64(buf)?;
+                let pid = get_u32(buf)?;
+                let value = get_i64(buf)?;
                 Ok(WindowerMsg::event(ts, pid, value))
             }
             Self::FLUSH_FLAG => Ok(WindowerMsg::Flush),
@@ -516,25 +641,7 @@ impl Windower {
             );
             return;
         }
-        self.current_window.sort_unstable();
-        let len = self.current_window.len();
-        let median = if len % 2 == 0 {
-            let upper_middle = len / 2;
-            let lower_middle = upper_middle - 1;
-            let um_value = self.current_window[upper_middle];
-            let lm_value = self.current_window[lower_middle];
-            match lm_value.checked_add(um_value) {
-                Some(sum) => (sum as f64) / 2.0,
-                None => {
-                    let um_double = (um_value as f64) / 2.0;
-                    let lm_double = (lm_value as f64) / 2.0;
-                    lm_double + um_double
-                }
-            }
-        } else {
-            let middle = len / 2;
-            self.current_window[middle] as f64
-        };
+        let median = median(&mut self.current_window);
         self.current_window.clear();
         if let Some(ref downstream) = self.downstream {
             let msg = SinkMsg::WindowAggregate {
@@ -667,10 +774,10 @@ impl Serialisable for SourceMsg {
 impl Deserialiser<SourceMsg> for SourceMsg {
     const SER_ID: SerId = Self::SERID;
     fn deserialise(buf: &mut dyn Buf) -> Result<SourceMsg, SerError> {
-        let flag = buf.get_u8();
+        let flag = get_u8(buf)?;
         match flag {
             Self::READY_FLAG => {
-                let bs = buf.get_u64();
+                let bs = get_u64(buf)?;
                 Ok(SourceMsg::Ready { batch_size: bs })
             }
             Self::FLUSHED_FLAG => Ok(SourceMsg::Flushed),
@@ -689,10 +796,11 @@ pub struct StreamSource {
     current_ts: u64,
     flushing: bool,
     reply_on_flushed: Option<Ask<(), ()>>,
+    reseed: bool,
 }
 impl StreamSource {
-    pub fn with(partition_id: u32) -> StreamSource {
-        let random = SmallRng::seed_from_u64(partition_id as u64);
+    pub fn with(partition_id: u32, reseed: bool) -> StreamSource {
+        let random = StreamSource::random_for(partition_id);
         StreamSource {
             ctx: ComponentContext::uninitialised(),
             partition_id,
@@ -702,9 +810,14 @@ impl StreamSource {
             current_ts: 0u64,
             flushing: false,
             reply_on_flushed: None,
+            reseed,
         }
     }
 
+    fn random_for(partition_id: u32) -> SmallRng {
+        SmallRng::seed_from_u64(partition_id as u64)
+    }
+
     fn send(&mut self) {
         if self.remaining > 0u64 {
             if let Some(ref downstream) = self.downstream {
@@ -754,6 +867,10 @@ impl NetworkActor for StreamSource {
                 }
                 self.current_ts = 0u64;
                 self.remaining = 0u64;
+                if self.reseed {
+                    // validation needs every iteration to see the same stream as the reference
+                    self.random = StreamSource::random_for(self.partition_id);
+                }
             }
             SourceMsg::Flushed if self.flushing => {
                 debug!(self.ctx().log(), "Got Flushed");
@@ -849,19 +966,23 @@ pub struct StreamSink {
     number_of_windows: u64,
     upstream: ActorPath,
     window_count: u64,
+    medians: Option<Vec<(u32, f64)>>,
 }
 impl StreamSink {
     pub fn with(
         latch: Arc<CountdownEvent>,
         number_of_windows: u64,
         upstream: ActorPath,
+        validate: bool,
     ) -> StreamSink {
+        let medians = if validate { Some(Vec::new()) } else { None };
         StreamSink {
             ctx: ComponentContext::uninitialised(),
             latch,
             number_of_windows,
             upstream,
             window_count: 0u64,
+            medians,
         }
     }
 }
@@ -878,9 +999,18 @@ impl NetworkActor for StreamSink {
                     .tell_serialised(WindowerMsg::Start, self)
                     .expect("Should serialise");
             }
-            SinkMsg::WindowAggregate { value: median, .. } => {
+            SinkMsg::WindowAggregate {
+                partition_id,
+                value: median,
+                ..
+            } => {
                 debug!(self.ctx().log(), "Got window with median={}", median);
                 self.window_count += 1u64;
+                if self.window_count <= self.number_of_windows {
+                    if let Some(ref mut medians) = self.medians {
+                        medians.push((partition_id, median));
+                    }
+                }
                 if self.window_count == self.number_of_windows {
                     self.latch.decrement().expect("Latch should decrement");
                     self.upstream
@@ -898,3 +1028,40 @@ impl NetworkActor for StreamSink {
         Handled::Ok
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ser_utils::testing::check_serialisable;
+
+    #[test]
+    fn windower_msg_ser_test() {
+        check_serialisable::<WindowerMsg, WindowerMsg>(1, |rng| match rng.gen_range(0, 4) {
+            0 => WindowerMsg::Start,
+            1 => WindowerMsg::Stop,
+            2 => WindowerMsg::event(rng.gen(), rng.gen(), rng.gen()),
+            _ => WindowerMsg::Flush,
+        });
+    }
+
+    #[test]
+    fn source_msg_ser_test() {
+        // Reset and Next are local only
+        check_serialisable::<SourceMsg, SourceMsg>(2, |rng| {
+            if rng.gen() {
+                SourceMsg::Ready {
+                    batch_size: rng.gen(),
+                }
+            } else {
+                SourceMsg::Flushed
+            }
+        });
+    }
+
+    #[test]
+    fn sink_msg_ser_test() {
+        check_serialisable::<SinkMsg, SinkMsg>(3, |rng| {
+            SinkMsg::window(rng.gen(), rng.gen(), rng.gen())
+        });
+    }
+}
@@ -138,7 +138,7 @@ pub mod actor_pingpong {
         type Instance = PingPongI;
 
         fn msg_to_conf(msg: Box<dyn (::protobuf::Message)>) -> Result<Self::Conf, BenchmarkError> {
-            downcast_msg!(msg; ThroughputPingPongRequest)
+            downcast_kompact_msg!(msg; ThroughputPingPongRequest)
         }
 
         fn new_instance() -> Self::Instance {
@@ -151,6 +151,7 @@ pub mod actor_pingpong {
     pub struct PingPongI {
         params: Option<Params>,
         system: Option<KompactSystem>,
+        system_config: Option<String>,
         pingers: EitherComponents<StaticPinger, Pinger>,
         pinger_refs: Vec<Recipient<&'static Run>>,
         pongers: EitherComponents<StaticPonger, Ponger>,
@@ -162,6 +163,7 @@ pub mod actor_pingpong {
             PingPongI {
                 params: None,
                 system: None,
+                system_config: None,
                 pingers: EitherComponents::Empty,
                 pinger_refs: Vec::new(),
                 pongers: EitherComponents::Empty,
@@ -175,10 +177,16 @@ pub mod actor_pingpong {
 
         fn setup(&mut self, c: &Self::Conf) -> () {
             self.params = Some(Params::from_req(c));
-            let system = crate::kompact_system_provider::global().new_system("throughputpingpong");
+            let provider = crate::kompact_system_provider::for_request(c.get_system_overrides());
+            self.system_config = Some(provider.describe());
+            let system = provider.new_system("throughputpingpong");
             self.system = Some(system);
         }
 
+        fn system_config(&self) -> Option<String> {
+            self.system_config.clone()
+        }
+
         fn prepare_iteration(&mut self) -> () {
             match self.params {
                 Some(ref params) => match self.system {
@@ -502,7 +510,7 @@ pub mod component_pingpong {
         type Instance = PingPongI;
 
         fn msg_to_conf(msg: Box<dyn (::protobuf::Message)>) -> Result<Self::Conf, BenchmarkError> {
-            downcast_msg!(msg; ThroughputPingPongRequest)
+            downcast_kompact_msg!(msg; ThroughputPingPongRequest)
         }
 
         fn new_instance() -> Self::Instance {
@@ -515,6 +523,7 @@ pub mod component_pingpong {
     pub struct PingPongI {
         params: Option<Params>,
         system: Option<KompactSystem>,
+        system_config: Option<String>,
         pingers: EitherComponents<StaticPinger, Pinger>,
         pongers: EitherComponents<StaticPonger, Ponger>,
         latch: Option<Arc<CountdownEvent>>,
@@ -525,6 +534,7 @@ pub mod component_pingpong {
             PingPongI {
                 params: None,
                 system: None,
+                system_config: None,
                 pingers: EitherComponents::Empty,
                 pongers: EitherComponents::Empty,
                 latch: None,
@@ -537,10 +547,16 @@ pub mod component_pingpong {
 
         fn setup(&mut self, c: &Self::Conf) -> () {
             self.params = Some(Params::from_req(c));
-            let system = crate::kompact_system_provider::global().new_system("throughputpingpong");
+            let provider = crate::kompact_system_provider::for_request(c.get_system_overrides());
+            self.system_config = Some(provider.describe());
+            let system = provider.new_system("throughputpingpong");
             self.system = Some(system);
         }
 
+        fn system_config(&self) -> Option<String> {
+            self.system_config.clone()
+        }
+
         fn prepare_iteration(&mut self) -> () {
             match self.params {
                 Some(ref params) => match self.system {
@@ -1,5 +1,8 @@
 use super::*;
-use benchmark_suite_shared::benchmark_runner::{not_implemented, run, run_async};
+use benchmark_suite_shared::benchmark::{Benchmark, BenchmarkError};
+use benchmark_suite_shared::benchmark_runner::{
+    local_shutdown, not_implemented, run_async, run_to_result,
+};
 use benchmark_suite_shared::kompics_benchmarks::{benchmarks, benchmarks_grpc, messages};
 use futures::future::Future;
 
@@ -29,9 +32,9 @@ impl benchmarks_grpc::BenchmarkRunner for BenchmarkRunnerActorImpl {
     fn shutdown(
         &self,
         _o: grpc::RequestOptions,
-        _p: messages::ShutdownRequest,
+        p: messages::ShutdownRequest,
     ) -> ::grpc::SingleResponse<messages::ShutdownAck> {
-        unimplemented!();
+        local_shutdown(p)
     }
 
     fn ping_pong(
@@ -42,7 +45,7 @@ impl benchmarks_grpc::BenchmarkRunner for BenchmarkRunnerActorImpl {
         println!("Got ping_pong req: {}", p.number_of_messages);
         let f = run_async(move || {
             let b = bench::pingpong::actor_pingpong::PingPong::default();
-            run(&b, &p).into()
+            run_local(&b, &p, p.get_system_overrides())
         })
         .map_err(|e| {
             println!("Converting benchmark error into grpc error: {:?}", e);
@@ -67,7 +70,7 @@ impl benchmarks_grpc::BenchmarkRunner for BenchmarkRunnerActorImpl {
         println!("Got req: {:?}", p);
         let f = run_async(move || {
             let b = bench::throughput_pingpong::actor_pingpong::PingPong::default();
-            run(&b, &p).into()
+            run_local(&b, &p, p.get_system_overrides())
         })
         .map_err(|e| {
             println!("Converting benchmark error into grpc error: {:?}", e);
@@ -108,7 +111,7 @@ impl benchmarks_grpc::BenchmarkRunner for BenchmarkRunnerActorImpl {
         println!("Got fibonacci req: {:?}", p);
         let f = run_async(move || {
             let b = bench::fibonacci::Fibonacci::default();
-            run(&b, &p).into()
+            run_local(&b, &p, p.get_system_overrides())
         })
         .map_err(|e| {
             println!("Converting benchmark error into grpc error: {:?}", e);
@@ -125,7 +128,7 @@ impl benchmarks_grpc::BenchmarkRunner for BenchmarkRunnerActorImpl {
         println!("Got chameneos req: {:?}", p);
         let f = run_async(move || {
             let b = bench::chameneos::actor_chameneos::Chameneos::default();
-            run(&b, &p).into()
+            run_local(&b, &p, p.get_system_overrides())
         })
         .map_err(|e| {
             println!("Converting benchmark error into grpc error: {:?}", e);
@@ -142,7 +145,7 @@ impl benchmarks_grpc::BenchmarkRunner for BenchmarkRunnerActorImpl {
         println!("Got APSP req: {:?}", p);
         let f = run_async(move || {
             let b = bench::all_pairs_shortest_path::actor_apsp::AllPairsShortestPath::default();
-            run(&b, &p).into()
+            run_local(&b, &p, p.get_system_overrides())
         })
         .map_err(|e| {
             println!("Converting benchmark error into grpc error: {:?}", e);
@@ -158,6 +161,14 @@ impl benchmarks_grpc::BenchmarkRunner for BenchmarkRunnerActorImpl {
     ) -> grpc::SingleResponse<messages::TestResult> {
         grpc::SingleResponse::completed(not_implemented())
     }
+
+    fn replicated_kv(
+        &self,
+        _o: grpc::RequestOptions,
+        _p: benchmarks::ReplicatedKVRequest,
+    ) -> grpc::SingleResponse<messages::TestResult> {
+        grpc::SingleResponse::completed(not_implemented())
+    }
 }
 
 #[derive(Clone)]
@@ -185,9 +196,9 @@ impl benchmarks_grpc::BenchmarkRunner for BenchmarkRunnerComponentImpl {
     fn shutdown(
         &self,
         _o: grpc::RequestOptions,
-        _p: messages::ShutdownRequest,
+        p: messages::ShutdownRequest,
     ) -> ::grpc::SingleResponse<messages::ShutdownAck> {
-        unimplemented!();
+        local_shutdown(p)
     }
 
     fn ping_pong(
@@ -198,7 +209,7 @@ impl benchmarks_grpc::BenchmarkRunner for BenchmarkRunnerComponentImpl {
         println!("Got req: {:?}", p);
         let f = run_async(move || {
             let b = bench::pingpong::component_pingpong::PingPong::default();
-            run(&b, &p).into()
+            run_local(&b, &p, p.get_system_overrides())
         })
         .map_err(|e| {
             println!("Converting benchmark error into grpc error: {:?}", e);
@@ -223,7 +234,7 @@ impl benchmarks_grpc::BenchmarkRunner for BenchmarkRunnerComponentImpl {
         println!("Got req: {:?}", p);
         let f = run_async(move || {
             let b = bench::throughput_pingpong::component_pingpong::PingPong::default();
-            run(&b, &p).into()
+            run_local(&b, &p, p.get_system_overrides())
         })
         .map_err(|e| {
             println!("Converting benchmark error into grpc error: {:?}", e);
@@ -281,7 +292,7 @@ impl benchmarks_grpc::BenchmarkRunner for BenchmarkRunnerComponentImpl {
         println!("Got APSP req: {:?}", p);
         let f = run_async(move || {
             let b = bench::all_pairs_shortest_path::component_apsp::AllPairsShortestPath::default();
-            run(&b, &p).into()
+            run_local(&b, &p, p.get_system_overrides())
         })
         .map_err(|e| {
             println!("Converting benchmark error into grpc error: {:?}", e);
@@ -297,6 +308,14 @@ impl benchmarks_grpc::BenchmarkRunner for BenchmarkRunnerComponentImpl {
     ) -> grpc::SingleResponse<messages::TestResult> {
         grpc::SingleResponse::completed(not_implemented())
     }
+
+    fn replicated_kv(
+        &self,
+        _o: grpc::RequestOptions,
+        _p: benchmarks::ReplicatedKVRequest,
+    ) -> grpc::SingleResponse<messages::TestResult> {
+        grpc::SingleResponse::completed(not_implemented())
+    }
 }
 
 #[derive(Clone)]
@@ -322,9 +341,9 @@ impl benchmarks_grpc::BenchmarkRunner for BenchmarkRunnerMixedImpl {
     fn shutdown(
         &self,
         _o: grpc::RequestOptions,
-        _p: messages::ShutdownRequest,
+        p: messages::ShutdownRequest,
     ) -> ::grpc::SingleResponse<messages::ShutdownAck> {
-        unimplemented!();
+        local_shutdown(p)
     }
 
     fn ping_pong(
@@ -392,7 +411,7 @@ impl benchmarks_grpc::BenchmarkRunner for BenchmarkRunnerMixedImpl {
         println!("Got chameneos req: {:?}", p);
         let f = run_async(move || {
             let b = bench::chameneos::mixed_chameneos::Chameneos::default();
-            run(&b, &p).into()
+            run_local(&b, &p, p.get_system_overrides())
         })
         .map_err(|e| {
             println!("Converting benchmark error into grpc error: {:?}", e);
@@ -417,4 +436,20 @@ impl benchmarks_grpc::BenchmarkRunner for BenchmarkRunnerMixedImpl {
     ) -> grpc::SingleResponse<messages::TestResult> {
         grpc::SingleResponse::completed(not_implemented())
     }
+
+    fn replicated_kv(
+        &self,
+        _o: grpc::RequestOptions,
+        _p: benchmarks::ReplicatedKVRequest,
+    ) -> grpc::SingleResponse<messages::TestResult> {
+        grpc::SingleResponse::completed(not_implemented())
+    }
+}
+
+/// Runs a local benchmark, after checking the request's system overrides.
+fn run_local<B: Benchmark>(b: &B, c: &B::Conf, system_overrides: &str) -> messages::TestResult {
+    match kompact_system_provider::check_overrides(system_overrides) {
+        Ok(()) => run_to_result(b, c),
+        Err(e) => Err::<Vec<f64>, BenchmarkError>(e).into(),
+    }
 }
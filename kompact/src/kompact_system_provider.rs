@@ -1,32 +1,325 @@
 //use super::*;
 
+use benchmark_suite_shared::benchmark::BenchmarkError;
+use hocon::{Hocon, HoconLoader};
 use kompact::executors::*;
 use kompact::net::buffers::BufferConfig;
 use kompact::prelude::*;
+use lazy_static::lazy_static;
 use num_cpus;
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::fmt;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
 
-// Would be nicer to be able to declare the default value const -.-
-static mut GLOBAL: KompactSystemProvider = KompactSystemProvider {
-    public_if: IpAddr::V4(Ipv4Addr::LOCALHOST),
-};
+pub const CONFIG_PATH: &str = "./configs/kompact_system.conf";
+
+lazy_static! {
+    /// The provider with the config file settings, which are only changed during startup.
+    static ref GLOBAL: RwLock<KompactSystemProvider> = RwLock::new(KompactSystemProvider::default());
+}
 
 pub(crate) fn set_global_public_if(addr: IpAddr) {
-    unsafe {
-        GLOBAL.set_public_if(addr);
-    }
+    GLOBAL
+        .write()
+        .expect("Kompact system provider lock")
+        .set_public_if(addr);
+}
+
+/// A copy of the provider with only the config file applied.
+pub fn global() -> KompactSystemProvider {
+    GLOBAL.read().expect("Kompact system provider lock").clone()
 }
-pub fn global() -> &'static KompactSystemProvider {
-    unsafe { &GLOBAL }
+
+/// Loads the provider config file, if it exists, and uses it for all systems created afterwards.
+pub(crate) fn load_global_config(path: &'static str) {
+    GLOBAL
+        .write()
+        .expect("Kompact system provider lock")
+        .load_config(path)
+        .unwrap_or_else(|e| panic!("Invalid Kompact system config {}: {:?}", path, e));
+}
+
+/// Checks the HOCON `overrides` of a single request against the config file, without applying them.
+pub(crate) fn check_overrides(overrides: &str) -> Result<(), BenchmarkError> {
+    global().with_overrides(overrides).map(|_| ())
+}
+
+/// The provider for a single request, with its HOCON `overrides` applied on top of the config file.
+///
+/// The overrides must have been checked when the request was parsed.
+pub fn for_request(overrides: &str) -> KompactSystemProvider {
+    global()
+        .with_overrides(overrides)
+        .expect("System overrides should have been checked with the request")
+}
+
+/// A client conf together with the `system_overrides` of the request it belongs to,
+/// so that clients create their systems with the same overrides as the master.
+#[derive(Clone, Debug)]
+pub struct WithOverrides<C> {
+    pub overrides: String,
+    pub conf: C,
+}
+impl<C> WithOverrides<C> {
+    pub fn new<I: Into<String>>(overrides: I, conf: C) -> WithOverrides<C> {
+        WithOverrides {
+            overrides: overrides.into(),
+            conf,
+        }
+    }
+
+    /// The provider for the client's systems, with the overrides applied to the client's config file.
+    pub fn provider(&self) -> KompactSystemProvider {
+        for_request(&self.overrides)
+    }
+
+    /// Prefixes the serialised `conf` with the length of the overrides and the overrides themselves,
+    /// since they may contain any character.
+    pub fn serialise<F>(self, f: F) -> String
+    where
+        F: FnOnce(C) -> String,
+    {
+        format!(
+            "{}:{}{}",
+            self.overrides.len(),
+            self.overrides,
+            f(self.conf)
+        )
+    }
+
+    pub fn deserialise<F>(s: String, f: F) -> Result<WithOverrides<C>, BenchmarkError>
+    where
+        F: FnOnce(String) -> Result<C, BenchmarkError>,
+    {
+        let invalid = || {
+            BenchmarkError::InvalidMessage(format!(
+                "String '{}' does not start with system overrides!",
+                s
+            ))
+        };
+        let colon = s.find(':').ok_or_else(invalid)?;
+        let len: usize = s[..colon].parse().map_err(|_| invalid())?;
+        let start = colon + 1;
+        let end = start.checked_add(len).ok_or_else(invalid)?;
+        if end > s.len() || !s.is_char_boundary(end) {
+            return Err(invalid());
+        }
+        let overrides = s[start..end].to_string();
+        // the client's config file may reject them, even if the master's didn't
+        check_overrides(&overrides)?;
+        let conf = f(s[end..].to_string())?;
+        Ok(WithOverrides { overrides, conf })
+    }
 }
 
+#[derive(Clone, Debug)]
 pub struct KompactSystemProvider {
     public_if: IpAddr,
+    config_file: Option<&'static str>,
+    config: SystemConfig,
 }
 
 mod consts {
     pub const NUM_WORKERS_DEFAULT: usize = 4;
-    pub const NUM_WORKERS_MAX: usize = 64;
+    pub const SMALL_POOL_MAX: usize = 32;
+    pub const LARGE_POOL_MAX: usize = 64;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExecutorKind {
+    /// Picks the smallest pool that supports the number of threads.
+    Auto,
+    /// Work-stealing pool for up to 32 threads.
+    Small,
+    /// Work-stealing pool for up to 64 threads.
+    Large,
+    /// Large work-stealing pools of up to 64 threads each, for any number of threads.
+    Sharded,
+    /// Shared-queue pool without a limit on the number of threads.
+    Channel,
+}
+impl ExecutorKind {
+    fn parse(s: &str) -> Result<ExecutorKind, String> {
+        match s.to_lowercase().as_ref() {
+            "auto" => Ok(ExecutorKind::Auto),
+            "small" => Ok(ExecutorKind::Small),
+            "large" => Ok(ExecutorKind::Large),
+            "sharded" => Ok(ExecutorKind::Sharded),
+            "channel" => Ok(ExecutorKind::Channel),
+            _ => Err(format!("Unknown executor '{}'", s)),
+        }
+    }
+
+    /// The executor that is actually used for `threads` workers.
+    ///
+    /// Fails if the executor was picked explicitly, but doesn't support that many threads.
+    pub fn resolve(self, threads: usize) -> Result<ExecutorKind, String> {
+        match self {
+            ExecutorKind::Auto if threads <= consts::SMALL_POOL_MAX => Ok(ExecutorKind::Small),
+            ExecutorKind::Auto if threads <= consts::LARGE_POOL_MAX => Ok(ExecutorKind::Large),
+            ExecutorKind::Auto => Ok(ExecutorKind::Sharded),
+            kind => match kind.max_threads() {
+                Some(max) if threads > max => Err(format!(
+                    "The {} executor supports at most {} threads, but {} were requested",
+                    kind, max, threads
+                )),
+                _ => Ok(kind),
+            },
+        }
+    }
+
+    fn max_threads(self) -> Option<usize> {
+        match self {
+            ExecutorKind::Small => Some(consts::SMALL_POOL_MAX),
+            ExecutorKind::Large => Some(consts::LARGE_POOL_MAX),
+            ExecutorKind::Auto | ExecutorKind::Sharded | ExecutorKind::Channel => None,
+        }
+    }
+}
+impl fmt::Display for ExecutorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecutorKind::Auto => write!(f, "auto"),
+            ExecutorKind::Small => write!(f, "small"),
+            ExecutorKind::Large => write!(f, "large"),
+            ExecutorKind::Sharded => write!(f, "sharded"),
+            ExecutorKind::Channel => write!(f, "channel"),
+        }
+    }
+}
+
+/// Network buffer settings, `None` leaves Kompact's default.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BufferSettings {
+    pub chunk_size: Option<usize>,
+    pub initial_chunk_count: Option<usize>,
+    pub max_chunk_count: Option<usize>,
+    pub encode_buf_min_free_space: Option<usize>,
+}
+
+/// Settings applied to every `KompactSystem` the provider creates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SystemConfig {
+    /// `None` picks a number based on the available cores.
+    pub threads: Option<usize>,
+    pub throughput: usize,
+    pub msg_priority: f32,
+    pub executor: ExecutorKind,
+    pub tcp_nodelay: bool,
+    pub buffer: BufferSettings,
+}
+
+impl SystemConfig {
+    pub const DEFAULT: SystemConfig = SystemConfig {
+        threads: None,
+        throughput: 50,
+        msg_priority: 1.0,
+        executor: ExecutorKind::Auto,
+        tcp_nodelay: true,
+        buffer: BufferSettings {
+            chunk_size: None,
+            initial_chunk_count: None,
+            max_chunk_count: None,
+            encode_buf_min_free_space: None,
+        },
+    };
+
+    /// Reads the `system` section of `file` (if it exists) with `overrides` applied on top.
+    pub fn load(file: Option<&str>, overrides: &str) -> Result<SystemConfig, String> {
+        let mut loader = HoconLoader::new();
+        if let Some(path) = file {
+            if Path::new(path).exists() {
+                loader = loader
+                    .load_file(path)
+                    .map_err(|e| format!("Could not load {}: {:?}", path, e))?;
+            }
+        }
+        if !overrides.trim().is_empty() {
+            loader = loader
+                .load_str(overrides)
+                .map_err(|e| format!("Could not load overrides '{}': {:?}", overrides, e))?;
+        }
+        let hocon = loader
+            .hocon()
+            .map_err(|e| format!("Config is not valid HOCON: {:?}", e))?;
+        SystemConfig::from_hocon(&hocon["system"])
+    }
+
+    fn from_hocon(h: &Hocon) -> Result<SystemConfig, String> {
+        let d = SystemConfig::DEFAULT;
+        let threads = match &h["threads"] {
+            Hocon::String(s) if s.to_lowercase() == "auto" => None,
+            v if is_missing(v) => d.threads,
+            v => Some(read_positive(v, "threads")?),
+        };
+        let executor = match &h["executor"] {
+            v if is_missing(v) => d.executor,
+            v => v
+                .as_string()
+                .ok_or_else(|| format!("executor should be a string, but was {:?}", v))
+                .and_then(|s| ExecutorKind::parse(&s))?,
+        };
+        let msg_priority = match &h["msg_priority"] {
+            v if is_missing(v) => d.msg_priority,
+            v => v
+                .as_f64()
+                .filter(|p| *p > 0.0)
+                .ok_or_else(|| format!("msg_priority should be positive, but was {:?}", v))?
+                as f32,
+        };
+        let tcp_nodelay = match &h["tcp_nodelay"] {
+            v if is_missing(v) => d.tcp_nodelay,
+            v => v
+                .as_bool()
+                .ok_or_else(|| format!("tcp_nodelay should be a boolean, but was {:?}", v))?,
+        };
+        let b = &h["buffer"];
+        let buffer = BufferSettings {
+            chunk_size: read_optional(&b["chunk_size"], "buffer.chunk_size")?,
+            initial_chunk_count: read_optional(
+                &b["initial_chunk_count"],
+                "buffer.initial_chunk_count",
+            )?,
+            max_chunk_count: read_optional(&b["max_chunk_count"], "buffer.max_chunk_count")?,
+            encode_buf_min_free_space: read_optional(
+                &b["encode_buf_min_free_space"],
+                "buffer.encode_buf_min_free_space",
+            )?,
+        };
+        Ok(SystemConfig {
+            threads,
+            throughput: read_optional(&h["throughput"], "throughput")?.unwrap_or(d.throughput),
+            msg_priority,
+            executor,
+            tcp_nodelay,
+            buffer,
+        })
+    }
+}
+
+pub(crate) fn is_missing(v: &Hocon) -> bool {
+    match v {
+        Hocon::BadValue(_) | Hocon::Null => true,
+        _ => false,
+    }
+}
+
+pub(crate) fn read_positive(v: &Hocon, key: &str) -> Result<usize, String> {
+    v.as_i64()
+        .filter(|i| *i > 0)
+        .map(|i| i as usize)
+        .ok_or_else(|| format!("{} should be a positive integer, but was {:?}", key, v))
+}
+
+pub(crate) fn read_optional(v: &Hocon, key: &str) -> Result<Option<usize>, String> {
+    if is_missing(v) {
+        Ok(None)
+    } else {
+        read_positive(v, key).map(Some)
+    }
 }
 
 impl KompactSystemProvider {
@@ -37,6 +330,95 @@ impl KompactSystemProvider {
         self.public_if = s.into();
     }
 
+    pub fn load_config(&mut self, path: &'static str) -> Result<(), String> {
+        let config = SystemConfig::load(Some(path), "")?;
+        self.validate(&config)?;
+        self.config = config;
+        self.config_file = Some(path);
+        Ok(())
+    }
+
+    /// A copy of this provider with `overrides` applied on top of the config file.
+    ///
+    /// An empty string just reloads the config file.
+    pub fn with_overrides(&self, overrides: &str) -> Result<KompactSystemProvider, BenchmarkError> {
+        let config = SystemConfig::load(self.config_file, overrides).map_err(|e| {
+            BenchmarkError::InvalidTest(format!("Invalid Kompact system overrides: {}", e))
+        })?;
+        self.validate(&config)
+            .map_err(BenchmarkError::InvalidTest)?;
+        Ok(KompactSystemProvider {
+            config,
+            ..self.clone()
+        })
+    }
+
+    pub fn config(&self) -> &SystemConfig {
+        &self.config
+    }
+
+    /// Describes the config of systems created with the configured number of threads.
+    pub fn describe(&self) -> String {
+        self.describe_with(self.get_num_workers())
+    }
+
+    /// Describes the config of a system with `threads` workers,
+    /// including where that differs from what was configured.
+    pub fn describe_with(&self, threads: usize) -> String {
+        self.describe_system(threads, &self.config.buffer, self.config.tcp_nodelay)
+    }
+
+    /// Describes the config of a system with `threads` workers, which was created with
+    /// its own network settings, as in `new_remote_system_with_threads_config`.
+    pub fn describe_system(
+        &self,
+        threads: usize,
+        buffer_settings: &BufferSettings,
+        tcp_nodelay: bool,
+    ) -> String {
+        let c = &self.config;
+        let mut buffer = Vec::new();
+        if let Some(v) = buffer_settings.chunk_size {
+            buffer.push(format!("chunk_size = {}", v));
+        }
+        if let Some(v) = buffer_settings.initial_chunk_count {
+            buffer.push(format!("initial_chunk_count = {}", v));
+        }
+        if let Some(v) = buffer_settings.max_chunk_count {
+            buffer.push(format!("max_chunk_count = {}", v));
+        }
+        if let Some(v) = buffer_settings.encode_buf_min_free_space {
+            buffer.push(format!("encode_buf_min_free_space = {}", v));
+        }
+        let configured_threads = self.get_num_workers();
+        let threads_str = if threads == configured_threads {
+            threads.to_string()
+        } else {
+            format!(
+                "{} (fixed by the benchmark, configured {})",
+                threads, configured_threads
+            )
+        };
+        let executor = self.executor_for(threads);
+        let executor_str = match executor {
+            ExecutorKind::Sharded => format!(
+                "sharded ({} shards)",
+                shard_sizes(threads, consts::LARGE_POOL_MAX).len()
+            ),
+            kind => kind.to_string(),
+        };
+        format!(
+            "system {{ threads = {}, available_cores = {}, throughput = {}, msg_priority = {}, executor = {}, tcp_nodelay = {}, buffer {{ {} }} }}",
+            threads_str,
+            num_cpus::get(),
+            c.throughput,
+            c.msg_priority,
+            executor_str,
+            tcp_nodelay,
+            buffer.join(", ")
+        )
+    }
+
     pub fn new_system<I: Into<String>>(&self, name: I) -> KompactSystem {
         self.new_system_with_threads(name, self.get_num_workers())
     }
@@ -49,11 +431,8 @@ impl KompactSystemProvider {
         let s = name.into();
         let mut conf = KompactConfig::default();
         conf.label(s);
-        conf.threads(threads);
-        Self::set_executor_for_threads(threads, &mut conf);
-        conf.throughput(50);
-        let system = conf.build().expect("KompactSystem");
-        system
+        self.configure(threads, &mut conf);
+        self.build(conf)
     }
 
     pub fn new_remote_system<I: Into<String>>(&self, name: I) -> KompactSystem {
@@ -69,12 +448,11 @@ impl KompactSystemProvider {
         let addr = SocketAddr::new(self.get_public_if(), 0);
         let mut conf = KompactConfig::default();
         conf.label(s);
-        conf.threads(threads);
-        Self::set_executor_for_threads(threads, &mut conf);
-        conf.throughput(50);
-        conf.system_components(DeadletterBox::new, NetworkConfig::new(addr).build());
-        let system = conf.build().expect("KompactSystem");
-        system
+        self.configure(threads, &mut conf);
+        let mut nc = NetworkConfig::with_buffer_config(addr, self.buffer_config());
+        nc.set_tcp_nodelay(self.config.tcp_nodelay);
+        conf.system_components(DeadletterBox::new, nc.build());
+        self.build(conf)
     }
 
     pub fn new_remote_system_with_threads_config<I: Into<String>>(
@@ -88,47 +466,264 @@ impl KompactSystemProvider {
         let s = name.into();
         let addr = SocketAddr::new(self.get_public_if(), 0);
         conf.label(s);
-        conf.threads(threads);
-        Self::set_executor_for_threads(threads, &mut conf);
-        conf.throughput(50);
+        self.configure(threads, &mut conf);
         let mut nc = NetworkConfig::with_buffer_config(addr, buf_conf);
         nc.set_tcp_nodelay(tcp_no_delay);
         conf.system_components(DeadletterBox::new, nc.build());
-        let system = conf.build().expect("KompactSystem");
-        system
+        self.build(conf)
     }
 
+    fn build(&self, conf: KompactConfig) -> KompactSystem {
+        conf.build().expect("KompactSystem")
+    }
+
+    /// The configured number of threads, or one per core (but at least `NUM_WORKERS_DEFAULT`).
     pub fn get_num_workers(&self) -> usize {
-        let n = num_cpus::get();
-        if (n >= consts::NUM_WORKERS_DEFAULT) && (n <= consts::NUM_WORKERS_MAX) {
-            n
-        } else if n >= consts::NUM_WORKERS_DEFAULT {
-            consts::NUM_WORKERS_MAX
-        } else {
-            consts::NUM_WORKERS_DEFAULT
-        }
+        self.get_num_workers_for(&self.config)
+    }
+
+    fn get_num_workers_for(&self, config: &SystemConfig) -> usize {
+        config
+            .threads
+            .unwrap_or_else(|| num_cpus::get().max(consts::NUM_WORKERS_DEFAULT))
     }
 
     pub fn get_public_if(&self) -> IpAddr {
         self.public_if
     }
 
-    fn set_executor_for_threads(threads: usize, conf: &mut KompactConfig) -> () {
-        if threads <= 32 {
-            conf.executor(|t| crossbeam_workstealing_pool::small_pool(t))
-        } else if threads <= 64 {
-            conf.executor(|t| crossbeam_workstealing_pool::large_pool(t))
-        } else {
-            panic!("DynPool doesn't work atm!");
-            //conf.scheduler(|t| crossbeam_workstealing_pool::dyn_pool(t))
+    fn configure(&self, threads: usize, conf: &mut KompactConfig) -> () {
+        conf.threads(threads);
+        self.set_executor_for_threads(threads, conf);
+        conf.throughput(self.config.throughput);
+        conf.msg_priority(self.config.msg_priority);
+    }
+
+    fn buffer_config(&self) -> BufferConfig {
+        let settings = &self.config.buffer;
+        let mut bc = BufferConfig::default();
+        if let Some(v) = settings.chunk_size {
+            bc.chunk_size(v);
+        }
+        if let Some(v) = settings.initial_chunk_count {
+            bc.initial_chunk_count(v);
+        }
+        if let Some(v) = settings.max_chunk_count {
+            bc.max_chunk_count(v);
+        }
+        if let Some(v) = settings.encode_buf_min_free_space {
+            bc.encode_buf_min_free_space(v);
+        }
+        bc.validate();
+        bc
+    }
+
+    fn validate(&self, config: &SystemConfig) -> Result<(), String> {
+        let threads = self.get_num_workers_for(config);
+        config.executor.resolve(threads).map(|_| ())
+    }
+
+    /// The executor for a system with `threads` workers.
+    ///
+    /// The configured number of threads has been validated, so this only fails
+    /// for benchmarks with a fixed number of threads that the configured executor can't run.
+    fn executor_for(&self, threads: usize) -> ExecutorKind {
+        self.config
+            .executor
+            .resolve(threads)
+            .unwrap_or_else(|e| panic!("Can't create a system with fixed threads: {}", e))
+    }
+
+    fn set_executor_for_threads(&self, threads: usize, conf: &mut KompactConfig) -> () {
+        match self.executor_for(threads) {
+            ExecutorKind::Small => conf.executor(|t| crossbeam_workstealing_pool::small_pool(t)),
+            ExecutorKind::Large => conf.executor(|t| crossbeam_workstealing_pool::large_pool(t)),
+            ExecutorKind::Sharded => conf.executor(|t| {
+                ShardedPool::new(t, consts::LARGE_POOL_MAX, |shard_threads| {
+                    crossbeam_workstealing_pool::large_pool(shard_threads)
+                })
+            }),
+            ExecutorKind::Channel | ExecutorKind::Auto => {
+                conf.executor(|t| crossbeam_channel_pool::ThreadPool::new(t))
+            }
         };
     }
 }
 
+/// Splits `threads` into as few shards of at most `max_shard_threads` as possible,
+/// with sizes that differ by at most one.
+fn shard_sizes(threads: usize, max_shard_threads: usize) -> Vec<usize> {
+    let shards = ((threads + max_shard_threads - 1) / max_shard_threads).max(1);
+    (0..shards)
+        .map(|i| threads / shards + if i < threads % shards { 1 } else { 0 })
+        .collect()
+}
+
+/// Several work-stealing pools that together run more threads than a single pool supports.
+///
+/// Jobs are handed to the shards round-robin and are only stolen by threads of the same shard.
+pub struct ShardedPool<E> {
+    shards: Arc<Vec<E>>,
+    next: Arc<AtomicUsize>,
+}
+impl<E: Executor + Sync + 'static> ShardedPool<E> {
+    pub fn new<F>(threads: usize, max_shard_threads: usize, new_shard: F) -> ShardedPool<E>
+    where
+        F: Fn(usize) -> E,
+    {
+        let shards = shard_sizes(threads, max_shard_threads)
+            .into_iter()
+            .map(new_shard)
+            .collect();
+        ShardedPool {
+            shards: Arc::new(shards),
+            next: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    fn next_shard(&self) -> &E {
+        let i = self.next.fetch_add(1, Ordering::Relaxed) % self.shards.len();
+        &self.shards[i]
+    }
+}
+impl<E> Clone for ShardedPool<E> {
+    fn clone(&self) -> Self {
+        ShardedPool {
+            shards: self.shards.clone(),
+            next: self.next.clone(),
+        }
+    }
+}
+impl<E: Executor + Sync + 'static> CanExecute for ShardedPool<E> {
+    fn execute_job(&self, job: Box<dyn FnOnce() + Send + 'static>) {
+        self.next_shard().execute_job(job);
+    }
+}
+impl<E: Executor + Sync + 'static> Executor for ShardedPool<E> {
+    fn execute<F>(&self, job: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.next_shard().execute(job);
+    }
+
+    fn shutdown_async(&self) {
+        self.shards.iter().for_each(|s| s.shutdown_async());
+    }
+
+    fn shutdown_borrowed(&self) -> Result<(), String> {
+        let errors: Vec<String> = self
+            .shards
+            .iter()
+            .filter_map(|s| s.shutdown_borrowed().err())
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+}
+impl<E: FuturesExecutor + Sync + 'static> FuturesExecutor for ShardedPool<E> {
+    fn spawn<R: Send + 'static>(
+        &self,
+        future: impl Future<Output = R> + 'static + Send,
+    ) -> JoinHandle<R> {
+        self.next_shard().spawn(future)
+    }
+}
+
 impl Default for KompactSystemProvider {
     fn default() -> Self {
         KompactSystemProvider {
             public_if: "127.0.0.1".parse().unwrap(),
+            config_file: None,
+            config: SystemConfig::DEFAULT,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_apply_on_top_of_defaults() {
+        let config = SystemConfig::load(None, "").expect("defaults");
+        assert_eq!(SystemConfig::DEFAULT, config);
+
+        let overrides =
+            "system { threads = 8, throughput = 10, executor = large, buffer.chunk_size = 64000 }";
+        let config = SystemConfig::load(None, overrides).expect("overrides");
+        assert_eq!(Some(8), config.threads);
+        assert_eq!(10, config.throughput);
+        assert_eq!(ExecutorKind::Large, config.executor);
+        assert_eq!(Some(64000), config.buffer.chunk_size);
+        assert_eq!(None, config.buffer.max_chunk_count);
+        assert_eq!(SystemConfig::DEFAULT.msg_priority, config.msg_priority);
+
+        assert!(SystemConfig::load(None, "system.executor = fancy").is_err());
+        assert!(SystemConfig::load(None, "system.threads = -1").is_err());
+    }
+
+    #[test]
+    fn executor_supports_any_number_of_threads() {
+        assert_eq!(Ok(ExecutorKind::Small), ExecutorKind::Auto.resolve(32));
+        assert_eq!(Ok(ExecutorKind::Large), ExecutorKind::Auto.resolve(64));
+        assert_eq!(Ok(ExecutorKind::Sharded), ExecutorKind::Auto.resolve(128));
+        assert_eq!(Ok(ExecutorKind::Small), ExecutorKind::Small.resolve(4));
+        assert_eq!(
+            Ok(ExecutorKind::Channel),
+            ExecutorKind::Channel.resolve(128)
+        );
+        assert!(
+            ExecutorKind::Small.resolve(48).is_err(),
+            "Pools that are too small must be rejected"
+        );
+        assert_eq!(vec![64, 64], shard_sizes(128, 64));
+        assert_eq!(vec![50, 50], shard_sizes(100, 64));
+        assert_eq!(vec![33, 32], shard_sizes(65, 64));
+        assert_eq!(vec![4], shard_sizes(4, 64));
+
+        let base = KompactSystemProvider::default();
+        let provider = base
+            .with_overrides("system { threads = 128 }")
+            .expect("auto executor");
+        assert_eq!(128, provider.get_num_workers());
+        assert!(provider
+            .describe()
+            .contains("executor = sharded (2 shards)"));
+        let small = base
+            .with_overrides("system { threads = 8, executor = small }")
+            .expect("small executor");
+        let fixed = small.describe_with(4);
+        assert!(fixed.contains("threads = 4 (fixed by the benchmark, configured 8)"));
+        assert!(fixed.contains("executor = small"));
+        assert!(base
+            .with_overrides("system { threads = 48, executor = small }")
+            .is_err());
+        assert!(provider
+            .with_overrides("system { threads = 128, executor = large }")
+            .is_err());
+        assert_eq!(
+            base.config(),
+            &SystemConfig::DEFAULT,
+            "Overrides must not change the provider they are applied to"
+        );
+    }
+
+    #[test]
+    fn overrides_survive_client_conf_serialisation() {
+        let overrides = "system { threads = 8, buffer { chunk_size = 64000 } }";
+        let conf = WithOverrides::new(overrides, "a,b:c".to_string());
+        let s = conf.serialise(|c| c);
+        let conf = WithOverrides::deserialise(s, Ok).expect("client conf");
+        assert_eq!(overrides, conf.overrides);
+        assert_eq!("a,b:c", conf.conf);
+        assert_eq!(Some(8), conf.provider().config().threads);
+
+        assert!(WithOverrides::deserialise("a,b".to_string(), Ok).is_err());
+        assert!(WithOverrides::deserialise("100:short".to_string(), Ok).is_err());
+        assert!(WithOverrides::deserialise("19:system.threads = -1".to_string(), Ok).is_err());
+    }
+}
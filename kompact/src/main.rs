@@ -11,6 +11,7 @@ mod bench;
 mod benchmark_runner;
 pub mod kompact_system_provider;
 pub mod partitioning_actor;
+pub mod ser_utils;
 pub mod serialiser_ids;
 
 fn main() {
@@ -32,6 +33,7 @@ fn main() {
         .expect("No bench mode was provided!");
 
     args.remove(1);
+    kompact_system_provider::load_global_config(kompact_system_provider::CONFIG_PATH);
     match mode {
         BenchMode::ACTOR => {
             BenchmarkMain::run_with(
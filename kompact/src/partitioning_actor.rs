@@ -1,4 +1,5 @@
 use super::*;
+use crate::ser_utils::{get_actor_path, get_bytes, get_i64, get_len, get_u32, get_u64, get_u8};
 use benchmark_suite_shared::test_utils::{KVOperation, KVTimestamp};
 use kompact::prelude::*;
 use std::sync::Arc;
@@ -261,25 +262,19 @@ impl Deserialiser<PartitioningActorMsg> for PartitioningActorSer {
     const SER_ID: u64 = serialiser_ids::PARTITIONING_ID;
 
     fn deserialise(buf: &mut dyn Buf) -> Result<PartitioningActorMsg, SerError> {
-        match buf.get_u8() {
+        match get_u8(buf)? {
             INIT_ID => {
-                let pid: u32 = buf.get_u32();
-                let init_id: u32 = buf.get_u32();
-                let data_len: u64 = buf.get_u64();
+                let pid: u32 = get_u32(buf)?;
+                let init_id: u32 = get_u32(buf)?;
+                let data_len: u64 = get_u64(buf)?;
                 let init_data = match data_len {
                     0 => None,
-                    _ => {
-                        let mut data = vec![];
-                        for _ in 0..data_len {
-                            data.push(buf.get_u8());
-                        }
-                        Some(data)
-                    }
+                    _ => Some(get_bytes(buf, data_len as usize)?),
                 };
-                let nodes_len: u32 = buf.get_u32();
+                let nodes_len: u32 = get_u32(buf)?;
                 let mut nodes: Vec<ActorPath> = Vec::new();
                 for _ in 0..nodes_len {
-                    let actorpath = ActorPath::deserialise(buf)?;
+                    let actorpath = get_actor_path(buf)?;
                     nodes.push(actorpath);
                 }
                 let init = Init {
@@ -291,25 +286,30 @@ impl Deserialiser<PartitioningActorMsg> for PartitioningActorSer {
                 Ok(PartitioningActorMsg::Init(init))
             }
             INITACK_ID => {
-                let init_id = buf.get_u32();
+                let init_id = get_u32(buf)?;
                 Ok(PartitioningActorMsg::InitAck(init_id))
             }
             RUN_ID => Ok(PartitioningActorMsg::Run),
             DONE_ID => Ok(PartitioningActorMsg::Done),
             TESTDONE_ID => {
-                let n: u32 = buf.get_u32();
-                let mut timestamps: Vec<KVTimestamp> = Vec::new();
+                // a timestamp takes at least 21 bytes
+                let n = get_len(buf, 21)?;
+                let mut timestamps: Vec<KVTimestamp> = Vec::with_capacity(n);
                 for _ in 0..n {
-                    let key = buf.get_u64();
-                    let (operation, value) = match buf.get_u8() {
+                    let key = get_u64(buf)?;
+                    let (operation, value) = match get_u8(buf)? {
                         READ_INV => (KVOperation::ReadInvokation, None),
-                        READ_RESP => (KVOperation::ReadResponse, Some(buf.get_u32())),
-                        WRITE_INV => (KVOperation::WriteInvokation, Some(buf.get_u32())),
-                        WRITE_RESP => (KVOperation::WriteResponse, Some(buf.get_u32())),
-                        _ => panic!("Found unknown KVOperation id"),
+                        READ_RESP => (KVOperation::ReadResponse, Some(get_u32(buf)?)),
+                        WRITE_INV => (KVOperation::WriteInvokation, Some(get_u32(buf)?)),
+                        WRITE_RESP => (KVOperation::WriteResponse, Some(get_u32(buf)?)),
+                        _ => {
+                            return Err(SerError::InvalidType(
+                                "Found unknown KVOperation id".into(),
+                            ))
+                        }
                     };
-                    let time = buf.get_i64();
-                    let sender = buf.get_u32();
+                    let time = get_i64(buf)?;
+                    let sender = get_u32(buf)?;
                     let ts = KVTimestamp {
                         key,
                         operation,
@@ -329,3 +329,93 @@ impl Deserialiser<PartitioningActorMsg> for PartitioningActorSer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ser_utils::testing::*;
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+    use std::net::IpAddr;
+    use uuid::Uuid;
+
+    fn actor_path(rng: &mut SmallRng) -> ActorPath {
+        let ip = IpAddr::from([127, 0, 0, rng.gen()]);
+        let port = rng.gen();
+        if rng.gen() {
+            ActorPath::Unique(UniquePath::new(
+                Transport::TCP,
+                ip,
+                port,
+                Uuid::from_u128(rng.gen()),
+            ))
+        } else {
+            let path = (0..rng.gen_range(1, 4))
+                .map(|i| format!("partitioning{}", i))
+                .collect();
+            ActorPath::Named(NamedPath::new(Transport::TCP, ip, port, path))
+        }
+    }
+
+    fn timestamp(rng: &mut SmallRng) -> KVTimestamp {
+        let (operation, value) = match rng.gen_range(0, 4) {
+            0 => (KVOperation::ReadInvokation, None),
+            1 => (KVOperation::ReadResponse, Some(rng.gen())),
+            2 => (KVOperation::WriteInvokation, Some(rng.gen())),
+            _ => (KVOperation::WriteResponse, Some(rng.gen())),
+        };
+        KVTimestamp {
+            key: rng.gen(),
+            operation,
+            value,
+            time: rng.gen(),
+            sender: rng.gen(),
+        }
+    }
+
+    fn partitioning_msg(rng: &mut SmallRng) -> PartitioningActorMsg {
+        match rng.gen_range(0, 7) {
+            0 => {
+                let init_data = if rng.gen() {
+                    Some((0..rng.gen_range(1, 64)).map(|_| rng.gen()).collect())
+                } else {
+                    None
+                };
+                PartitioningActorMsg::Init(Init {
+                    pid: rng.gen(),
+                    init_id: rng.gen(),
+                    nodes: (0..rng.gen_range(0, 6)).map(|_| actor_path(rng)).collect(),
+                    init_data,
+                })
+            }
+            1 => PartitioningActorMsg::InitAck(rng.gen()),
+            2 => PartitioningActorMsg::Run,
+            3 => PartitioningActorMsg::Done,
+            4 => PartitioningActorMsg::TestDone(
+                (0..rng.gen_range(0, 100)).map(|_| timestamp(rng)).collect(),
+            ),
+            5 => PartitioningActorMsg::Stop,
+            _ => PartitioningActorMsg::StopAck,
+        }
+    }
+
+    #[test]
+    fn partitioning_actor_ser_test() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let valid: Vec<Vec<u8>> = (0..CASES)
+            .map(|_| {
+                let msg = partitioning_msg(&mut rng);
+                let bytes = roundtrip::<PartitioningActorMsg, PartitioningActorSer>(&msg);
+                match msg {
+                    // the size of the actor paths and the timestamps isn't known up front
+                    PartitioningActorMsg::Init(_) | PartitioningActorMsg::TestDone(_) => {
+                        assert_hint_bound(msg.size_hint(), &bytes)
+                    }
+                    _ => assert_exact_hint(&msg, &bytes),
+                }
+                assert_prefixes_rejected::<PartitioningActorMsg, PartitioningActorSer>(&bytes);
+                bytes
+            })
+            .collect();
+        fuzz::<PartitioningActorMsg, PartitioningActorSer>(&mut rng, &valid);
+    }
+}
@@ -0,0 +1,301 @@
+//! Bounds-checked reads for the hand-written deserialisers.
+//!
+//! `Buf::get_*` panics when the buffer is too short, so a truncated or corrupted message would take down the
+//! receiving component. These helpers return a `SerError` instead.
+use kompact::prelude::{ActorPath, Buf, Deserialiser, SerError};
+
+/// Fails unless at least `n` bytes remain in `buf`.
+pub fn ensure_remaining(buf: &dyn Buf, n: usize) -> Result<(), SerError> {
+    if buf.remaining() < n {
+        Err(SerError::InvalidData(format!(
+            "Need {} bytes, but only {} remain in buffer!",
+            n,
+            buf.remaining()
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+pub fn get_u8(buf: &mut dyn Buf) -> Result<u8, SerError> {
+    ensure_remaining(buf, 1)?;
+    Ok(buf.get_u8())
+}
+
+pub fn get_i8(buf: &mut dyn Buf) -> Result<i8, SerError> {
+    ensure_remaining(buf, 1)?;
+    Ok(buf.get_i8())
+}
+
+pub fn get_u32(buf: &mut dyn Buf) -> Result<u32, SerError> {
+    ensure_remaining(buf, 4)?;
+    Ok(buf.get_u32())
+}
+
+pub fn get_u64(buf: &mut dyn Buf) -> Result<u64, SerError> {
+    ensure_remaining(buf, 8)?;
+    Ok(buf.get_u64())
+}
+
+pub fn get_i64(buf: &mut dyn Buf) -> Result<i64, SerError> {
+    ensure_remaining(buf, 8)?;
+    Ok(buf.get_i64())
+}
+
+pub fn get_f64(buf: &mut dyn Buf) -> Result<f64, SerError> {
+    ensure_remaining(buf, 8)?;
+    Ok(buf.get_f64())
+}
+
+/// Reads a flag that was written as 0 or 1.
+pub fn get_bool(buf: &mut dyn Buf) -> Result<bool, SerError> {
+    match get_u8(buf)? {
+        0 => Ok(false),
+        1 => Ok(true),
+        flag => Err(SerError::InvalidData(format!("Unknown flag: {}", flag))),
+    }
+}
+
+/// Reads the u32 length of a sequence whose elements take at least `min_elem_size` bytes each. The length is checked
+/// against the remaining bytes, so a corrupted length can't make the caller allocate gigabytes.
+pub fn get_len(buf: &mut dyn Buf, min_elem_size: usize) -> Result<usize, SerError> {
+    let len = get_u32(buf)? as usize;
+    ensure_remaining(buf, len.saturating_mul(min_elem_size))?;
+    Ok(len)
+}
+
+/// Reads `len` bytes of payload.
+pub fn get_bytes(buf: &mut dyn Buf, len: usize) -> Result<Vec<u8>, SerError> {
+    ensure_remaining(buf, len)?;
+    let mut data = vec![0; len];
+    buf.copy_to_slice(&mut data);
+    Ok(data)
+}
+
+/// Kompact reads actor paths by slicing the buffer without checking its length, so the length of the path is checked
+/// here first. The header byte of Kompact's path format is `path type (1 bit) | protocol (5 bits) | address type
+/// (2 bits)`, followed by the address, the port and a UUID or a u16-prefixed name.
+pub fn get_actor_path(buf: &mut dyn Buf) -> Result<ActorPath, SerError> {
+    ensure_path_len(buf.bytes())?;
+    ActorPath::deserialise(buf)
+}
+
+fn ensure_path_len(bytes: &[u8]) -> Result<(), SerError> {
+    let truncated = || {
+        SerError::InvalidData(format!(
+            "Truncated ActorPath, only {} bytes remain in buffer!",
+            bytes.len()
+        ))
+    };
+    let header = *bytes.first().ok_or_else(truncated)?;
+    let address_len = match header & 0b11 {
+        0 => 4,  // IPv4
+        1 => 16, // IPv6
+        address_type => {
+            return Err(SerError::InvalidData(format!(
+                "Unsupported ActorPath address type: {}",
+                address_type
+            )))
+        }
+    };
+    let system_len = 1 + address_len + 2; // header, address and port
+    let path_len = if header >> 7 == 0 {
+        16 // unique path
+    } else {
+        let name_len = bytes
+            .get(system_len..system_len + 2)
+            .ok_or_else(truncated)?;
+        2 + u16::from_be_bytes([name_len[0], name_len[1]]) as usize
+    };
+    if bytes.len() < system_len + path_len {
+        Err(truncated())
+    } else {
+        Ok(())
+    }
+}
+
+/// Property and fuzz checks shared by the tests of the serialisers.
+#[cfg(test)]
+pub mod testing {
+    use kompact::prelude::{Deserialiser, SerError, Serialisable, Serialiser};
+    use rand::{rngs::SmallRng, Rng, SeedableRng};
+    use std::fmt::Debug;
+    use std::panic;
+
+    /// Number of random messages or inputs generated per property.
+    pub const CASES: usize = 500;
+
+    /// Serialises `msg` and checks that `D` reads back the same message, comparing the `Debug` output since most
+    /// messages don't implement `PartialEq`. Returns the bytes for further checks.
+    pub fn roundtrip<M, D>(msg: &M) -> Vec<u8>
+    where
+        M: Serialisable + Debug,
+        D: Deserialiser<M>,
+    {
+        let mut bytes = Vec::new();
+        msg.serialise(&mut bytes).expect("Should serialise");
+        assert_reads_back::<M, D>(msg, &bytes);
+        bytes
+    }
+
+    /// Like `roundtrip`, for messages that are serialised by a separate `Serialiser`.
+    pub fn roundtrip_with<M, S>(ser: &S, msg: &M) -> Vec<u8>
+    where
+        M: Debug,
+        S: Serialiser<M> + Deserialiser<M>,
+    {
+        let mut bytes = Vec::new();
+        ser.serialise(msg, &mut bytes).expect("Should serialise");
+        assert_reads_back::<M, S>(msg, &bytes);
+        bytes
+    }
+
+    fn assert_reads_back<M: Debug, D: Deserialiser<M>>(msg: &M, bytes: &[u8]) {
+        let mut buf = bytes;
+        let deser = D::deserialise(&mut buf)
+            .unwrap_or_else(|e| panic!("Failed to deserialise {:?}: {:?}", msg, e));
+        assert_eq!(format!("{:?}", msg), format!("{:?}", deser));
+        assert!(
+            buf.is_empty(),
+            "{} bytes left after deserialising {:?}",
+            buf.len(),
+            msg
+        );
+    }
+
+    /// The size hint of `msg` must be its serialised size.
+    pub fn assert_exact_hint<M: Serialisable + Debug>(msg: &M, bytes: &[u8]) {
+        assert_eq!(
+            Some(bytes.len()),
+            msg.size_hint(),
+            "Wrong size hint for {:?}",
+            msg
+        );
+    }
+
+    /// A size hint that can't depend on the message must at least cover it, so the buffer never has to grow.
+    pub fn assert_hint_bound(hint: Option<usize>, bytes: &[u8]) {
+        let hint = hint.expect("Serialised messages should have a size hint");
+        assert!(
+            hint >= bytes.len(),
+            "Size hint {} is less than the {} serialised bytes",
+            hint,
+            bytes.len()
+        );
+    }
+
+    /// Deserialises `input`, failing the test with the input if `D` panics instead of returning an error.
+    pub fn deserialise_no_panic<M, D>(input: &[u8]) -> Result<M, SerError>
+    where
+        D: Deserialiser<M>,
+    {
+        let res = panic::catch_unwind(|| D::deserialise(&mut &input[..]));
+        res.unwrap_or_else(|_| panic!("Deserialiser panicked on {:?}", input))
+    }
+
+    /// Every strict prefix of a serialised message is missing some field and must be rejected.
+    pub fn assert_prefixes_rejected<M: Debug, D: Deserialiser<M>>(bytes: &[u8]) {
+        for len in 0..bytes.len() {
+            if let Ok(msg) = deserialise_no_panic::<M, D>(&bytes[..len]) {
+                panic!("Accepted the prefix {:?} as {:?}", &bytes[..len], msg);
+            }
+        }
+    }
+
+    /// Feeds `D` with random bytes and with copies of the `valid` serialisations that have a few random bytes
+    /// overwritten. The result may be anything but a panic. The first byte of random input is kept small to hit the
+    /// message tags.
+    pub fn fuzz<M, D>(rng: &mut SmallRng, valid: &[Vec<u8>])
+    where
+        D: Deserialiser<M>,
+    {
+        for _ in 0..CASES {
+            let len = rng.gen_range(0, 64);
+            let mut input: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            if let Some(tag) = input.first_mut() {
+                *tag = rng.gen_range(0, 16);
+            }
+            let _ = deserialise_no_panic::<M, D>(&input);
+        }
+        if valid.is_empty() {
+            return;
+        }
+        for _ in 0..CASES {
+            let mut input = valid[rng.gen_range(0, valid.len())].clone();
+            if input.is_empty() {
+                continue;
+            }
+            for _ in 0..rng.gen_range(1, 4) {
+                let idx = rng.gen_range(0, input.len());
+                input[idx] = rng.gen();
+            }
+            let _ = deserialise_no_panic::<M, D>(&input);
+        }
+    }
+
+    /// Checks the round trip and exact size hint of `CASES` messages from `gen`, checks that their prefixes are
+    /// rejected and fuzzes `D` with them.
+    pub fn check_serialisable<M, D>(seed: u64, gen: impl Fn(&mut SmallRng) -> M)
+    where
+        M: Serialisable + Debug,
+        D: Deserialiser<M>,
+    {
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let valid: Vec<Vec<u8>> = (0..CASES)
+            .map(|_| {
+                let msg = gen(&mut rng);
+                let bytes = roundtrip::<M, D>(&msg);
+                assert_exact_hint(&msg, &bytes);
+                assert_prefixes_rejected::<M, D>(&bytes);
+                bytes
+            })
+            .collect();
+        fuzz::<M, D>(&mut rng, &valid);
+    }
+
+    /// Like `check_serialisable`, for a `Serialiser` whose size hint only bounds the messages.
+    pub fn check_serialiser<M, S>(seed: u64, ser: &S, gen: impl Fn(&mut SmallRng) -> M)
+    where
+        M: Debug,
+        S: Serialiser<M> + Deserialiser<M>,
+    {
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let valid: Vec<Vec<u8>> = (0..CASES)
+            .map(|_| {
+                let msg = gen(&mut rng);
+                let bytes = roundtrip_with(ser, &msg);
+                assert_hint_bound(ser.size_hint(), &bytes);
+                assert_prefixes_rejected::<M, S>(&bytes);
+                bytes
+            })
+            .collect();
+        fuzz::<M, S>(&mut rng, &valid);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kompact::prelude::Serialisable;
+    use std::str::FromStr;
+
+    #[test]
+    fn get_actor_path_test() {
+        for path in &[
+            "tcp://127.0.0.1:8080/atomicbroadcast/paxos_replica",
+            "tcp://[::1]:8080/client",
+            "tcp://127.0.0.1:8080#4a6b3a2e-2f8d-4b4e-9d55-6f3c7a1e0b2d",
+        ] {
+            let path = ActorPath::from_str(path).expect("Invalid path");
+            let mut bytes = Vec::new();
+            path.serialise(&mut bytes).expect("Should serialise");
+            assert_eq!(path, get_actor_path(&mut bytes.as_slice()).unwrap());
+            for len in 0..bytes.len() {
+                assert!(get_actor_path(&mut &bytes[..len]).is_err());
+            }
+            let mut domain = bytes.clone();
+            domain[0] = (domain[0] & !0b11) | 2;
+            assert!(get_actor_path(&mut domain.as_slice()).is_err());
+        }
+    }
+}
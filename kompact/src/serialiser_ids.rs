@@ -21,3 +21,5 @@ pub const PAXOS_ID: SerId = 60;
 pub const RECONFIG_ID: SerId = 61;
 pub const TEST_SEQ_ID: SerId = 62;
 pub const STOP_ID: SerId = 63;
+pub const FAULT_ID: SerId = 64;
+pub const CLIENT_HOST_ID: SerId = 65;
@@ -69,4 +69,9 @@ impl BenchmarkFactory for Factory {
     ) -> Result<Box<dyn AbstractDistributedBenchmark>, NotImplementedError> {
         Err(NotImplementedError::FutureWork)
     }
+    fn replicated_kv(
+        &self,
+    ) -> Result<Box<dyn AbstractDistributedBenchmark>, NotImplementedError> {
+        Err(NotImplementedError::FutureWork)
+    }
 }
@@ -1,5 +1,5 @@
 use super::*;
-use benchmark_suite_shared::benchmark_runner::{not_implemented, run, run_async};
+use benchmark_suite_shared::benchmark_runner::{local_shutdown, not_implemented, run, run_async};
 use benchmark_suite_shared::kompics_benchmarks::{benchmarks, benchmarks_grpc, messages};
 use futures::future::Future;
 
@@ -27,9 +27,9 @@ impl benchmarks_grpc::BenchmarkRunner for BenchmarkRunnerImpl {
     fn shutdown(
         &self,
         _o: grpc::RequestOptions,
-        _p: messages::ShutdownRequest,
+        p: messages::ShutdownRequest,
     ) -> ::grpc::SingleResponse<messages::ShutdownAck> {
-        unimplemented!();
+        local_shutdown(p)
     }
 
     fn ping_pong(
@@ -156,4 +156,12 @@ impl benchmarks_grpc::BenchmarkRunner for BenchmarkRunnerImpl {
     ) -> grpc::SingleResponse<messages::TestResult> {
         grpc::SingleResponse::completed(not_implemented())
     }
+
+    fn replicated_kv(
+        &self,
+        _o: grpc::RequestOptions,
+        _p: benchmarks::ReplicatedKVRequest,
+    ) -> grpc::SingleResponse<messages::TestResult> {
+        grpc::SingleResponse::completed(not_implemented())
+    }
 }
@@ -380,6 +380,232 @@ object Benchmarks extends ParameterDescriptionImplicits {
       }
   );
 
+  /*** every combination of the Paxos optimisations ***/
+  private val paxosOptionCombinations = (0 until 32).map { bits =>
+    PaxosOptions(
+      batchAccept = (bits & 1) != 0,
+      latestDecide = (bits & 2) != 0,
+      latestAccepted = (bits & 4) != 0,
+      maxAccsync = (bits & 8) != 0,
+      headstartBle = (bits & 16) != 0,
+    )
+  }.toList;
+
+  private val paxosOptionsTestSpace = ParameterSpacePB
+    .cross(
+      atomicBroadcastTestNodes,
+      atomicBroadcastTestProposals,
+      List(1L.k),
+      List(PaxosOptions(), PaxosOptions(batchAccept = true, latestDecide = true, latestAccepted = true, maxAccsync = true)),
+    );
+
+  private val paxosOptionsSpace = ParameterSpacePB
+    .cross(
+      atomicBroadcastNodes,
+      atomicBroadcastProposals,
+      atomicBroadcastConcurrentProposals,
+      paxosOptionCombinations,
+    );
+
+  val paxosOptions = Benchmark(
+    name = "Paxos Options",
+    symbol = "PAXOSOPTIONS",
+    invoke = (stub, request: AtomicBroadcastRequest) => {
+      stub.atomicBroadcast(request)
+    },
+    space = paxosOptionsSpace
+      .msg[AtomicBroadcastRequest] {
+        case (nn, np, cp, o) =>
+          AtomicBroadcastRequest(
+            algorithm = "paxos",
+            numberOfNodes = nn,
+            numberOfProposals = np,
+            concurrentProposals = cp,
+            reconfiguration = "off",
+            reconfigPolicy = "none",
+            paxosOptions = Some(o),
+          )
+      },
+    testSpace = paxosOptionsTestSpace
+      .msg[AtomicBroadcastRequest] {
+        case (nn, np, cp, o) =>
+          AtomicBroadcastRequest(
+            algorithm = "paxos",
+            numberOfNodes = nn,
+            numberOfProposals = np,
+            concurrentProposals = cp,
+            reconfiguration = "off",
+            reconfigPolicy = "none",
+            paxosOptions = Some(o),
+          )
+      }
+  );
+
+  /*** phase 2 quorums from a single replica up to a majority, each with the smallest intersecting phase 1 quorum ***/
+  private def phase2Quorums(nodes: Int): List[Long] = (1 to nodes / 2 + 1).map(_.toLong).toList;
+
+  private def flexibleQuorumSpace(nodes: List[Int], proposals: List[Long], concurrentProposals: List[Long]) =
+    nodes
+      .map(nn => ParameterSpacePB.cross(List(nn), phase2Quorums(nn), proposals, concurrentProposals))
+      .reduce(_ append _);
+
+  val flexiblePaxos = Benchmark(
+    name = "Flexible Paxos",
+    symbol = "FLEXIBLEPAXOS",
+    invoke = (stub, request: AtomicBroadcastRequest) => {
+      stub.atomicBroadcast(request)
+    },
+    space = flexibleQuorumSpace(atomicBroadcastNodes, atomicBroadcastProposals, atomicBroadcastConcurrentProposals)
+      .msg[AtomicBroadcastRequest] {
+        case (nn, q2, np, cp) =>
+          AtomicBroadcastRequest(
+            algorithm = "paxos",
+            numberOfNodes = nn,
+            numberOfProposals = np,
+            concurrentProposals = cp,
+            reconfiguration = "off",
+            reconfigPolicy = "none",
+            phase2Quorum = q2,
+          )
+      },
+    testSpace = flexibleQuorumSpace(atomicBroadcastTestNodes, atomicBroadcastTestProposals, List(1L.k))
+      .msg[AtomicBroadcastRequest] {
+        case (nn, q2, np, cp) =>
+          AtomicBroadcastRequest(
+            algorithm = "paxos",
+            numberOfNodes = nn,
+            numberOfProposals = np,
+            concurrentProposals = cp,
+            reconfiguration = "off",
+            reconfigPolicy = "none",
+            phase2Quorum = q2,
+          )
+      }
+  );
+
+  /*** node 1 leads first, then hands over halfway through the run or keeps the leadership ***/
+  private val leaderTransfers = List("none", "next@0.5");
+
+  val leaderHandover = Benchmark(
+    name = "Leader Handover",
+    symbol = "LEADERHANDOVER",
+    invoke = (stub, request: AtomicBroadcastRequest) => {
+      stub.atomicBroadcast(request)
+    },
+    space = ParameterSpacePB
+      .cross(List("paxos", "raft"), atomicBroadcastNodes, atomicBroadcastProposals, atomicBroadcastConcurrentProposals, leaderTransfers)
+      .msg[AtomicBroadcastRequest] {
+        case (a, nn, np, cp, lt) =>
+          AtomicBroadcastRequest(
+            algorithm = a,
+            numberOfNodes = nn,
+            numberOfProposals = np,
+            concurrentProposals = cp,
+            reconfiguration = "off",
+            reconfigPolicy = "none",
+            initialLeader = 1L,
+            leaderTransfers = lt,
+          )
+      },
+    testSpace = ParameterSpacePB
+      .cross(List("paxos", "raft"), atomicBroadcastTestNodes, atomicBroadcastTestProposals, List(1L.k), leaderTransfers)
+      .msg[AtomicBroadcastRequest] {
+        case (a, nn, np, cp, lt) =>
+          AtomicBroadcastRequest(
+            algorithm = a,
+            numberOfNodes = nn,
+            numberOfProposals = np,
+            concurrentProposals = cp,
+            reconfiguration = "off",
+            reconfigPolicy = "none",
+            initialLeader = 1L,
+            leaderTransfers = lt,
+          )
+      }
+  );
+
+  private val replicatedKVTestOperations = List(5L.k);
+  private val replicatedKVTestConcurrentOperations = List(500L);
+
+  private val replicatedKVOperations = List(1L.mio);
+  private val replicatedKVConcurrentOperations = List(1L.k, 10L.k);
+  private val replicatedKVKeys = 10L.k;
+  private val replicatedKVWorkloads = List("a", "b", "c", "f");
+
+  private val paxosKVTestSpace = ParameterSpacePB // paxos can only serve reads through the log
+    .cross(
+      paxos,
+      atomicBroadcastTestNodes,
+      replicatedKVTestOperations,
+      replicatedKVTestConcurrentOperations,
+      replicatedKVWorkloads,
+      List("log"),
+    );
+
+  private val raftKVTestSpace = ParameterSpacePB
+    .cross(
+      raft,
+      atomicBroadcastTestNodes,
+      replicatedKVTestOperations,
+      replicatedKVTestConcurrentOperations,
+      replicatedKVWorkloads,
+      List("log", "read_index", "lease"),
+    );
+
+  private val paxosKVSpace = ParameterSpacePB
+    .cross(
+      paxos,
+      atomicBroadcastNodes,
+      replicatedKVOperations,
+      replicatedKVConcurrentOperations,
+      replicatedKVWorkloads,
+      List("log"),
+    );
+
+  private val raftKVSpace = ParameterSpacePB
+    .cross(
+      raft,
+      atomicBroadcastNodes,
+      replicatedKVOperations,
+      replicatedKVConcurrentOperations,
+      replicatedKVWorkloads,
+      List("log", "read_index", "lease"),
+    );
+
+  val replicatedKV = Benchmark(
+    name = "Replicated KV",
+    symbol = "REPLICATEDKV",
+    invoke = (stub, request: ReplicatedKVRequest) => {
+      stub.replicatedKV(request)
+    },
+    space = paxosKVSpace.append(raftKVSpace)
+      .msg[ReplicatedKVRequest] {
+        case (a, nn, no, co, w, rm) =>
+          ReplicatedKVRequest(
+            algorithm = a,
+            numberOfNodes = nn,
+            numberOfOperations = no,
+            concurrentOperations = co,
+            numberOfKeys = replicatedKVKeys,
+            workload = w,
+            readMode = rm,
+          )
+      },
+    testSpace = paxosKVTestSpace.append(raftKVTestSpace)
+      .msg[ReplicatedKVRequest] {
+        case (a, nn, no, co, w, rm) =>
+          ReplicatedKVRequest(
+            algorithm = a,
+            numberOfNodes = nn,
+            numberOfOperations = no,
+            concurrentOperations = co,
+            numberOfKeys = replicatedKVKeys,
+            workload = w,
+            readMode = rm,
+          )
+      }
+  );
+
   val benchmarks: List[Benchmark] = Macros.memberList[Benchmark];
   lazy val benchmarkLookup: Map[String, Benchmark] = benchmarks.map(b => (b.symbol -> b)).toMap;
 }
@@ -1,3 +1,4 @@
+use crate::kompics_benchmarks::messages;
 use std::{convert::From, marker::PhantomData, panic::UnwindSafe};
 
 pub use self::{distributed_benchmark::*, local_benchmark::*};
@@ -21,18 +22,23 @@ mod local_benchmark {
         fn setup(&mut self, c: &Self::Conf) -> ();
         fn prepare_iteration(&mut self) -> () {}
         fn run_iteration(&mut self) -> ();
+        // called before every `cleanup_iteration` to check the iteration's output, if requested
+        fn validate_iteration(&mut self) -> Result<(), BenchmarkError> { Ok(()) }
         fn cleanup_iteration(&mut self, _last_iteration: bool, _exec_time_millis: f64) -> () {}
+        // describes the runtime config of the systems created in `setup`, attached to the result
+        fn system_config(&self) -> Option<String> { None }
     }
 
     pub trait AbstractBenchmark: Send + Sync + UnwindSafe {
         fn new_instance(&self) -> Box<dyn AbstractBenchmarkInstance>;
-        fn run(&self, msg: Box<dyn (::protobuf::Message)>) -> Result<Vec<f64>, BenchmarkError>;
+        fn run(&self, msg: Box<dyn (::protobuf::Message)>) -> messages::TestResult;
         fn label(&self) -> &'static str;
     }
     pub trait AbstractBenchmarkInstance {
         fn setup(&mut self, msg: Box<dyn (::protobuf::Message)>) -> Result<(), BenchmarkError>;
         fn prepare_iteration(&mut self) -> () {}
         fn run_iteration(&mut self) -> ();
+        fn validate_iteration(&mut self) -> Result<(), BenchmarkError> { Ok(()) }
         fn cleanup_iteration(&mut self, _last_iteration: bool, _exec_time_millis: f64) -> () {}
     }
 
@@ -57,11 +63,12 @@ mod local_benchmark {
             Box::new(bio)
         }
 
-        fn run(&self, msg: Box<dyn (::protobuf::Message)>) -> Result<Vec<f64>, BenchmarkError> {
-            let conf_res = B::msg_to_conf(msg);
+        fn run(&self, msg: Box<dyn (::protobuf::Message)>) -> messages::TestResult {
             let b = B::default();
-            let res = conf_res.and_then(|conf| crate::benchmark_runner::run(&b, &conf).into());
-            res
+            match B::msg_to_conf(msg) {
+                Ok(conf) => crate::benchmark_runner::run_to_result(&b, &conf),
+                Err(e) => crate::benchmark_runner::test_result(Err(e), None),
+            }
         }
 
         fn label(&self) -> &'static str { B::LABEL }
@@ -97,6 +104,10 @@ mod local_benchmark {
 
         fn run_iteration(&mut self) -> () { self.bi.run_iteration() }
 
+        fn validate_iteration(&mut self) -> Result<(), BenchmarkError> {
+            self.bi.validate_iteration()
+        }
+
         fn cleanup_iteration(&mut self, last_iteration: bool, exec_time_millis: f64) -> () {
             self.bi.cleanup_iteration(last_iteration, exec_time_millis)
         }
@@ -159,7 +170,11 @@ mod distributed_benchmark {
         ) -> Result<Self::ClientConf, BenchmarkError>;
         fn prepare_iteration(&mut self, _d: Vec<Self::ClientData>) -> () {}
         fn run_iteration(&mut self) -> ();
+        // called before every `cleanup_iteration` to check the iteration's output, if requested
+        fn validate_iteration(&mut self) -> Result<(), BenchmarkError> { Ok(()) }
         fn cleanup_iteration(&mut self, _last_iteration: bool, _exec_time_millis: f64) -> () {}
+        // describes the runtime config of the systems created in `setup`, attached to the result
+        fn system_config(&self) -> Option<String> { None }
     }
 
     pub trait DistributedBenchmarkClient {
@@ -205,7 +220,9 @@ mod distributed_benchmark {
             Ok(())
         }
         fn run_iteration(&mut self) -> ();
+        fn validate_iteration(&mut self) -> Result<(), BenchmarkError> { Ok(()) }
         fn cleanup_iteration(&mut self, _last_iteration: bool, _exec_time_millis: f64) -> () {}
+        fn system_config(&self) -> Option<String> { None }
     }
     pub trait AbstractBenchmarkClient {
         fn setup(&mut self, c: ClientConfHolder) -> Result<ClientDataHolder, BenchmarkError>;
@@ -292,9 +309,15 @@ mod distributed_benchmark {
 
         fn run_iteration(&mut self) -> () { self.bm.run_iteration(); }
 
+        fn validate_iteration(&mut self) -> Result<(), BenchmarkError> {
+            self.bm.validate_iteration()
+        }
+
         fn cleanup_iteration(&mut self, last_iteration: bool, exec_time_millis: f64) -> () {
             self.bm.cleanup_iteration(last_iteration, exec_time_millis);
         }
+
+        fn system_config(&self) -> Option<String> { self.bm.system_config() }
     }
 
     struct BenchmarkClientObject<B>
@@ -382,6 +405,7 @@ pub trait BenchmarkFactory: Send + Sync {
     fn chameneos(&self) -> Result<Box<dyn AbstractBenchmark>, NotImplementedError>;
     fn all_pairs_shortest_path(&self) -> Result<Box<dyn AbstractBenchmark>, NotImplementedError>;
     fn atomic_broadcast(&self) -> Result<Box<dyn AbstractDistributedBenchmark>, NotImplementedError>;
+    fn replicated_kv(&self) -> Result<Box<dyn AbstractDistributedBenchmark>, NotImplementedError>;
 }
 
 impl Clone for Box<dyn BenchmarkFactory> {
@@ -423,6 +447,8 @@ pub enum BenchmarkError {
     InvalidTest(String),
     NotImplemented(NotImplementedError),
     InvalidDeployment(String),
+    ValidationFailed(String),
+    Interrupted,
 }
 
 impl From<grpc::Error> for BenchmarkError {
@@ -509,6 +535,50 @@ pub(crate) mod tests {
         bi.setup(&c);
     }
 
+    struct Test4B;
+    struct Test4BI {
+        n_runs: usize,
+    }
+
+    impl Benchmark for Test4B {
+        type Conf = PingPongRequest;
+        type Instance = Test4BI;
+
+        const LABEL: &'static str = "Test4B";
+
+        fn msg_to_conf(msg: Box<dyn (::protobuf::Message)>) -> Result<Self::Conf, BenchmarkError> {
+            downcast_msg!(msg; PingPongRequest)
+        }
+
+        fn new_instance() -> Self::Instance { Test4BI { n_runs: 0 } }
+    }
+
+    impl BenchmarkInstance for Test4BI {
+        type Conf = PingPongRequest;
+
+        fn setup(&mut self, _c: &Self::Conf) -> () {}
+
+        fn run_iteration(&mut self) -> () { self.n_runs += 1; }
+
+        fn validate_iteration(&mut self) -> Result<(), BenchmarkError> {
+            if self.n_runs < 3 {
+                Ok(())
+            } else {
+                Err(BenchmarkError::ValidationFailed(format!("run {}", self.n_runs)))
+            }
+        }
+    }
+
+    #[test]
+    fn failed_validation_aborts_local_benchmark() -> () {
+        let msg = PingPongRequest::new();
+        let c = Test4B::msg_to_conf(Box::new(msg)).unwrap();
+        match crate::benchmark_runner::run(&Test4B {}, &c) {
+            Err(BenchmarkError::ValidationFailed(reason)) => assert_eq!("run 3", reason),
+            res => panic!("Expected validation failure, but got {:?}", res),
+        }
+    }
+
     struct Test3B;
     struct Test3Conf;
     struct Test3BM;
@@ -669,6 +739,12 @@ pub(crate) mod tests {
         ) -> Result<Box<dyn AbstractDistributedBenchmark>, NotImplementedError> {
             Ok(Test3B {}.into())
         }
+
+        fn replicated_kv(
+            &self,
+        ) -> Result<Box<dyn AbstractDistributedBenchmark>, NotImplementedError> {
+            Ok(Test3B {}.into())
+        }
     }
 
     #[test]
@@ -1,6 +1,7 @@
 use crate::{
     benchmark::*,
     kompics_benchmarks::{distributed, distributed_grpc, messages},
+    shutdown,
 };
 use crossbeam::channel as cbchannel;
 use futures::{future, sync::oneshot, Future};
@@ -195,7 +196,12 @@ impl BenchmarkClient {
                                     format!("Test {} is local!", test_label),
                                 )),
                                 AbstractBench::Distributed(db) => {
-                                    let mut active_bench = ActiveBench::new(db);
+                                    let active = shutdown::try_begin().ok_or_else(|| {
+                                        BenchmarkError::InvalidTest(
+                                            "Client is shutting down.".to_string(),
+                                        )
+                                    })?;
+                                    let mut active_bench = ActiveBench::new(db, active);
                                     let client_data_res = active_bench.setup(sc);
                                     client_data_res.map(|client_data| {
                                         active_bench.prepare();
@@ -247,6 +253,15 @@ impl BenchmarkClient {
                     },
                     ClientCommand::Shutdown => {
                         info!(self.logger, "Shutting down...");
+                        if let State::Running(active_bench) = state {
+                            warn!(
+                                self.logger,
+                                "Interrupting {} to shut down.",
+                                active_bench.label()
+                            );
+                            active_bench.cleanup(true);
+                            shutdown::mark_interrupted();
+                        }
                         *state = State::Stopped;
                         thread::sleep(Duration::from_millis(500)); // give it some time to send the response
                     },
@@ -330,10 +345,10 @@ impl distributed_grpc::BenchmarkClient for ClientHandler {
     {
         info!(self.logger, "Got shutdown request: {:?}", p);
         if p.force {
-            crate::force_shutdown();
-        } else {
-            self.command_queue.send(ClientCommand::Shutdown).expect("Command channel broke!");
+            shutdown::request(true);
+            shutdown::exit_after_timeout();
         }
+        self.command_queue.send(ClientCommand::Shutdown).expect("Command channel broke!");
         grpc::SingleResponse::completed(messages::ShutdownAck::new())
     }
 }
@@ -392,11 +407,12 @@ enum StateError {
 struct ActiveBench {
     b:        Box<dyn AbstractDistributedBenchmark>,
     instance: Box<dyn AbstractBenchmarkClient>,
+    _active:  shutdown::ActiveGuard, // the process waits for the final cleanup before exiting
 }
 impl ActiveBench {
-    fn new(b: Box<dyn AbstractDistributedBenchmark>) -> ActiveBench {
+    fn new(b: Box<dyn AbstractDistributedBenchmark>, active: shutdown::ActiveGuard) -> ActiveBench {
         let instance = b.new_client();
-        ActiveBench { b, instance }
+        ActiveBench { b, instance, _active: active }
     }
 
     fn setup(&mut self, sc: distributed::SetupConfig) -> Result<String, BenchmarkError> {
@@ -6,6 +6,7 @@ use crate::{
         distributed_grpc::{self, BenchmarkClient},
         messages,
     },
+    shutdown,
 };
 use crossbeam::channel as cbchannel;
 use futures::{future, sync::oneshot, Future};
@@ -16,7 +17,10 @@ use slog::{crit, debug, error, info, o, warn, Drain, Logger};
 use std::{
     convert::TryInto,
     panic::UnwindSafe,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
     thread,
     time::Duration,
 };
@@ -31,6 +35,7 @@ pub fn run(
 {
     let (check_in_sender, check_in_receiver) = cbchannel::unbounded();
     let (bench_sender, bench_receiver) = cbchannel::unbounded();
+    let stopping = Arc::new(AtomicBool::new(false));
     let mut inst = BenchmarkMaster::new(
         logger.new(
             o!("runner-port" => runner_port, "wait-for" => wait_for, "ty" => "BenchmarkMaster"),
@@ -94,6 +99,7 @@ pub fn run(
             benchmarks.clone(),
             bench_sender.clone(),
             inst.state(),
+            stopping.clone(),
         );
         let runner_address = format!("0.0.0.0:{}", runner_port);
         match std::net::TcpListener::bind(runner_address.clone()) {
@@ -358,6 +364,17 @@ impl BenchmarkMaster {
         invocation: BenchInvocation,
     ) -> ()
     {
+        // keeps the process alive until the benchmark has cleaned up, even during a forced shutdown
+        let _active = match shutdown::try_begin() {
+            Some(guard) => guard,
+            None => {
+                warn!(self.logger, "Not running queued test, since the master is shutting down.");
+                promise
+                    .send(crate::benchmark_runner::shutting_down())
+                    .expect("Receiver was closed?!?");
+                return;
+            },
+        };
         let msg = invocation.msg;
         let (res, label) = match invocation.benchmark {
             AbstractBench::Local(b) => {
@@ -392,7 +409,7 @@ impl BenchmarkMaster {
         self.state.cas(State::READY, State::RUN).expect("Wasn't ready to run!");
         let blogger = self.logger.new(o!("benchmark" => b.label()));
         info!(blogger, "Starting local test {}", b.label());
-        let f = run_async(move || b.run(msg).into());
+        let f = run_async(move || b.run(msg));
         let state_copy = self.state.clone();
         f.then(move |res| {
             info!(blogger, "Completed local test.");
@@ -465,7 +482,7 @@ impl BenchmarkMaster {
                         state_copy2
                             .cas(State::CLEANUP, State::FINISHED)
                             .expect("Wasn't cleanup before run!");
-                        let tr: messages::TestResult = Ok(it.results()).into();
+                        let tr = it.result();
                         future::Loop::Break(tr)
                     } else {
                         state_copy2
@@ -526,6 +543,7 @@ struct RunnerHandler {
     benchmarks:  Box<dyn BenchmarkFactory>,
     bench_queue: cbchannel::Sender<BenchRequest>,
     state:       StateHolder,
+    stopping:    Arc<AtomicBool>,
 }
 
 impl RunnerHandler {
@@ -534,11 +552,14 @@ impl RunnerHandler {
         benchmarks: Box<dyn BenchmarkFactory>,
         bench_queue: cbchannel::Sender<BenchRequest>,
         state: StateHolder,
+        stopping: Arc<AtomicBool>,
     ) -> RunnerHandler
     {
-        RunnerHandler { logger, benchmarks, bench_queue, state }
+        RunnerHandler { logger, benchmarks, bench_queue, state, stopping }
     }
 
+    fn is_stopping(&self) -> bool { self.stopping.load(Ordering::SeqCst) }
+
     fn enqeue(
         &self,
         inv: BenchInvocation,
@@ -564,6 +585,10 @@ impl RunnerHandler {
     where
         F: FnOnce(B) -> BenchInvocation,
     {
+        if self.is_stopping() {
+            warn!(self.logger, "Rejecting test, since the master is shutting down.");
+            return grpc::SingleResponse::completed(crate::benchmark_runner::shutting_down());
+        }
         match res {
             Ok(b) => {
                 let br = f(b);
@@ -589,7 +614,7 @@ impl benchmarks_grpc::BenchmarkRunner for RunnerHandler {
     {
         info!(self.logger, "Got ready? req.");
         let mut msg = messages::ReadyResponse::new();
-        if self.state.get() == State::READY {
+        if self.state.get() == State::READY && !self.is_stopping() {
             msg.set_status(true);
         } else {
             msg.set_status(false);
@@ -604,10 +629,12 @@ impl benchmarks_grpc::BenchmarkRunner for RunnerHandler {
     ) -> ::grpc::SingleResponse<messages::ShutdownAck>
     {
         info!(self.logger, "Got shutdown request: {:?}", p);
-        self.bench_queue.send(BenchRequest::Shutdown(p.force)).expect("Command channel broke!"); // make sure children get shut down
+        self.stopping.store(true, Ordering::SeqCst); // reject new tests
         if p.force {
-            crate::force_shutdown();
+            shutdown::request(true); // cancel the running test at the next iteration
+            shutdown::exit_after_timeout();
         }
+        self.bench_queue.send(BenchRequest::Shutdown(p.force)).expect("Command channel broke!"); // make sure children get shut down
         grpc::SingleResponse::completed(messages::ShutdownAck::new())
     }
 
@@ -719,6 +746,16 @@ impl benchmarks_grpc::BenchmarkRunner for RunnerHandler {
         let b_res = self.benchmarks.atomic_broadcast();
         self.enqueue_if_implemented(b_res, |b| BenchInvocation::new(b.into(), p))
     }
+
+    fn replicated_kv(&self,
+                     _o: grpc::RequestOptions,
+                     p: benchmarks::ReplicatedKVRequest
+    ) -> grpc::SingleResponse<messages::TestResult>
+    {
+        info!(self.logger, "Got Replicated KV req: {:?}", p);
+        let b_res = self.benchmarks.replicated_kv();
+        self.enqueue_if_implemented(b_res, |b| BenchInvocation::new(b.into(), p))
+    }
 }
 
 #[derive(Clone)]
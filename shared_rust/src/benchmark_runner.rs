@@ -2,6 +2,7 @@ use crate::{
     benchmark::{Benchmark, BenchmarkError, BenchmarkInstance, *},
     benchmark_master::ClientEntry,
     kompics_benchmarks::*,
+    shutdown,
 };
 use futures::future::{self, Future};
 //use slog::{crit, debug, error, info, o, warn, Drain, Logger};
@@ -67,8 +68,12 @@ where
 
 pub fn run_async<F>(f: F) -> impl Future<Item = messages::TestResult, Error = BenchmarkError>
 where F: FnOnce() -> messages::TestResult + std::panic::UnwindSafe {
-    let lf = future::lazy(|| {
-        let r = f();
+    let guard_opt = shutdown::try_begin(); // register right away, so shutdown waits for queued runs
+    let lf = future::lazy(move || {
+        let r = match guard_opt {
+            Some(_guard) => f(),
+            None => shutting_down(),
+        };
         future::ok::<messages::TestResult, ()>(r)
     });
     let fcu = lf.catch_unwind();
@@ -87,6 +92,27 @@ where F: FnOnce() -> messages::TestResult + std::panic::UnwindSafe {
     f
 }
 
+pub fn shutting_down() -> messages::TestResult {
+    let mut tf = messages::TestFailure::new();
+    tf.set_reason("Runner is shutting down and does not accept new benchmarks.".to_string());
+    let mut rm = messages::TestResult::new();
+    rm.set_failure(tf);
+    rm
+}
+
+/// Shutdown handling for runners in local mode.
+///
+/// Acknowledges right away and exits the process once all active benchmarks are done,
+/// which includes shutting down their systems and writing their results.
+pub fn local_shutdown(
+    request: messages::ShutdownRequest,
+) -> grpc::SingleResponse<messages::ShutdownAck> {
+    slog::info!(slog_scope::logger(), "Got shutdown request: {:?}", request);
+    shutdown::request(request.force);
+    shutdown::exit_when_idle();
+    grpc::SingleResponse::completed(messages::ShutdownAck::new())
+}
+
 pub fn not_implemented() -> messages::TestResult {
     let ni = messages::NotImplemented::new();
     let mut rm = messages::TestResult::new();
@@ -101,6 +127,17 @@ pub(crate) const NS_TO_MS: f64 = 1.0 / (1000.0 * 1000.0);
 
 pub fn run<B: Benchmark>(_b: &B, c: &B::Conf) -> Result<Vec<f64>, BenchmarkError> {
     let mut bi = B::new_instance();
+    run_instance(&mut bi, c)
+}
+
+/// Runs the benchmark like `run` and attaches the config its instance reports to the result.
+pub fn run_to_result<B: Benchmark>(_b: &B, c: &B::Conf) -> messages::TestResult {
+    let mut bi = B::new_instance();
+    let res = run_instance(&mut bi, c);
+    test_result(res, bi.system_config())
+}
+
+fn run_instance<I: BenchmarkInstance>(bi: &mut I, c: &I::Conf) -> Result<Vec<f64>, BenchmarkError> {
     bi.setup(c);
     let mut results = Vec::with_capacity(MIN_RUNS);
     let mut n_runs = 0;
@@ -109,17 +146,20 @@ pub fn run<B: Benchmark>(_b: &B, c: &B::Conf) -> Result<Vec<f64>, BenchmarkError
     n_runs += 1;
     // run at least 20 to be able to calculate RSE
     while n_runs < MIN_RUNS {
+        check_iteration(&mut bi, &results)?;
         bi.cleanup_iteration(false, *results.last().unwrap());
         bi.prepare_iteration();
         results.push(measure(|| bi.run_iteration()));
         n_runs += 1;
     }
     while (n_runs < MAX_RUNS) && (rse(&results) > RSE_TARGET) {
+        check_iteration(&mut bi, &results)?;
         bi.cleanup_iteration(false, *results.last().unwrap());
         bi.prepare_iteration();
         results.push(measure(|| bi.run_iteration()));
         n_runs += 1;
     }
+    check_iteration(&mut bi, &results)?;
     bi.cleanup_iteration(true, *results.last().unwrap());
     let result_rse = rse(&results);
     if result_rse > RSE_TARGET {
@@ -142,27 +182,60 @@ pub fn run<B: Benchmark>(_b: &B, c: &B::Conf) -> Result<Vec<f64>, BenchmarkError
     // }
 }
 
-impl From<Result<Vec<f64>, BenchmarkError>> for messages::TestResult {
-    fn from(res: Result<Vec<f64>, BenchmarkError>) -> Self {
-        match res {
-            Ok(data) => {
-                let len = data.len();
-                let mut ts = messages::TestSuccess::new();
-                ts.set_number_of_runs(len as u32);
-                ts.set_run_results(data);
-                let mut rm = messages::TestResult::new();
-                rm.set_success(ts);
-                rm
-            },
-            Err(e) => {
-                let msg = format!("{:?}", e);
-                let mut tf = messages::TestFailure::new();
-                tf.set_reason(msg);
-                let mut rm = messages::TestResult::new();
-                rm.set_failure(tf);
-                rm
-            },
+/// Validates the last iteration and checks for a forced shutdown.
+///
+/// If either fails, the instance is cleaned up as if it was the last iteration.
+fn check_iteration<I: BenchmarkInstance>(
+    bi: &mut I,
+    results: &[f64],
+) -> Result<(), BenchmarkError>
+{
+    let res = bi.validate_iteration().and_then(|_| {
+        if shutdown::is_cancelled() {
+            shutdown::mark_interrupted();
+            Err(BenchmarkError::Interrupted)
+        } else {
+            Ok(())
         }
+    });
+    res.map_err(|e| {
+        eprintln!("Stopping after {} runs: {:?}", results.len(), e);
+        bi.cleanup_iteration(true, *results.last().unwrap());
+        e
+    })
+}
+
+impl From<Result<Vec<f64>, BenchmarkError>> for messages::TestResult {
+    fn from(res: Result<Vec<f64>, BenchmarkError>) -> Self { test_result(res, None) }
+}
+
+/// Converts the outcome of a run into a result, with the runtime config it was executed with.
+pub fn test_result(
+    res: Result<Vec<f64>, BenchmarkError>,
+    system_config: Option<String>,
+) -> messages::TestResult
+{
+    match res {
+        Ok(data) => {
+            let len = data.len();
+            let mut ts = messages::TestSuccess::new();
+            ts.set_number_of_runs(len as u32);
+            ts.set_run_results(data);
+            if let Some(config) = system_config {
+                ts.set_system_config(config);
+            }
+            let mut rm = messages::TestResult::new();
+            rm.set_success(ts);
+            rm
+        },
+        Err(e) => {
+            let msg = format!("{:?}", e);
+            let mut tf = messages::TestFailure::new();
+            tf.set_reason(msg);
+            let mut rm = messages::TestResult::new();
+            rm.set_failure(tf);
+            rm
+        },
     }
 }
 
@@ -216,6 +289,7 @@ pub(crate) struct DistributedIteration {
     client_data_l: Vec<(ClientEntry, ClientDataHolder)>,
     n_runs:        usize,
     results:       Vec<f64>,
+    failure:       Option<BenchmarkError>,
 }
 
 impl DistributedIteration {
@@ -224,12 +298,25 @@ impl DistributedIteration {
         client_data_l: Vec<(ClientEntry, ClientDataHolder)>,
     ) -> DistributedIteration
     {
-        DistributedIteration { master, client_data_l, n_runs: 0, results: Vec::new() }
+        DistributedIteration {
+            master,
+            client_data_l,
+            n_runs: 0,
+            results: Vec::new(),
+            failure: None,
+        }
     }
 
     pub(crate) fn n_runs(&self) -> usize { self.n_runs }
 
-    pub(crate) fn results(self) -> Vec<f64> { self.results }
+    pub(crate) fn result(self) -> messages::TestResult {
+        let system_config = self.master.system_config();
+        let res = match self.failure {
+            Some(e) => Err(e),
+            None => Ok(self.results),
+        };
+        test_result(res, system_config)
+    }
 
     pub fn prepare(mut self) -> Self {
         self.master
@@ -247,8 +334,17 @@ impl DistributedIteration {
 
     pub fn cleanup(mut self) -> impl Future<Item = (Self, bool), Error = grpc::Error> {
         let clients: Vec<_> = self.client_data_l.iter().map(|(c, _)| c.clone()).collect();
-        let is_final: bool = if (self.n_runs < MIN_RUNS)
-            || (self.n_runs < MAX_RUNS) && (rse(&self.results) > RSE_TARGET)
+        if let Err(e) = self.master.validate_iteration() {
+            eprintln!("Validation failed after {} runs: {:?}", self.n_runs, e);
+            self.failure = Some(e);
+        } else if shutdown::is_cancelled() {
+            eprintln!("Cancelled after {} runs due to forced shutdown.", self.n_runs);
+            shutdown::mark_interrupted();
+            self.failure = Some(BenchmarkError::Interrupted);
+        }
+        let is_final: bool = if self.failure.is_none()
+            && ((self.n_runs < MIN_RUNS)
+                || (self.n_runs < MAX_RUNS) && (rse(&self.results) > RSE_TARGET))
         {
             self.master.cleanup_iteration(false, *self.results.last().unwrap());
             false
@@ -7,6 +7,7 @@ pub mod benchmark_master;
 pub mod benchmark_runner;
 pub mod helpers;
 pub mod kompics_benchmarks;
+pub mod shutdown;
 
 pub use self::benchmark::*;
 use self::kompics_benchmarks::*;
@@ -16,7 +17,6 @@ use slog_scope;
 use slog_stdlog;
 use std::{
     net::{IpAddr, Ipv4Addr, SocketAddr},
-    thread,
     time::Duration,
 };
 //pub(crate) type BenchLogger = Logger;
@@ -62,7 +62,7 @@ impl BenchmarkMain {
                 benchmarks,
                 logger.new(o!("ty" => "benchmark_client::run")),
             );
-            unreachable!("This should not return!");
+            shutdown::exit();
         } else if args.len() == 4 {
             // master mode
             let bench_runner_addr: SocketAddr =
@@ -84,20 +84,13 @@ impl BenchmarkMain {
                 benchmarks,
                 logger.new(o!("ty" => "benchmark_master::run")),
             );
-            unreachable!("This should not return!");
+            shutdown::exit();
         } else {
             panic!("Too many args={} provided!", args.len());
         };
     }
 }
 
-fn force_shutdown() {
-    std::thread::spawn(|| {
-        thread::sleep(Duration::from_millis(500));
-        std::process::exit(0);
-    });
-}
-
 pub mod test_utils {
     use super::*;
     use arraymap::ArrayMap;
@@ -107,6 +100,8 @@ pub mod test_utils {
     use itertools::Itertools;
     use std::{
         clone::Clone,
+        cmp::Reverse,
+        collections::{HashMap, HashSet},
         net::SocketAddr,
         sync::{
             atomic::{AtomicBool, Ordering},
@@ -664,6 +659,194 @@ pub mod test_utils {
         assert!(cloned.len() < trace.len());
         cloned
     }
+
+    /// An entry in the decided sequence of an atomic broadcast replica.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub enum DecidedEntry {
+        Normal(u64),
+        /// Ends the configuration with the given id.
+        StopSign(u32),
+    }
+
+    /// The decided sequence a replica returned. If the replica compacted its log, the sequence
+    /// only holds the decided entries after the compacted prefix.
+    #[derive(Clone, Debug, Default, PartialEq)]
+    pub struct DecidedSequence {
+        /// The log index the replica compacted up to, 0 if it compacted nothing. It bounds
+        /// the number of decided entries that are missing in front of `entries`.
+        pub compacted_idx: u64,
+        pub entries:       Vec<DecidedEntry>,
+    }
+
+    impl From<Vec<DecidedEntry>> for DecidedSequence {
+        fn from(entries: Vec<DecidedEntry>) -> Self {
+            DecidedSequence { compacted_idx: 0, entries }
+        }
+    }
+
+    /// The proposals a client issued and the ones it received a response for.
+    #[derive(Clone, Debug, Default)]
+    pub struct ProposalLog {
+        pub proposed:         Vec<u64>,
+        pub acked:            Vec<u64>,
+        /// For each reconfiguration the client got a response for, in order: the number of
+        /// proposals in `acked` and the latest proposal id when the response arrived.
+        pub reconfigs:        Vec<(usize, u64)>,
+        /// The proposals that were pending at a leader when it crashed and were proposed again.
+        /// They may be decided twice: from the log of the crashed leader and once more after.
+        pub pending_at_crash: Vec<u64>,
+    }
+
+    impl ProposalLog {
+        /// Adds the proposals of another client of the same run. The reconfigurations are the ones
+        /// of this (primary) client, whose acked proposals stay in front of the added ones.
+        pub fn merge(&mut self, other: ProposalLog) {
+            self.proposed.extend(other.proposed);
+            self.acked.extend(other.acked);
+            self.pending_at_crash.extend(other.pending_at_crash);
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum TotalOrderViolation {
+        /// The sequence of `pid` diverges at its `index` from the sequence the others are checked
+        /// against, which starts with the one of `longest_pid`.
+        NotPrefix { pid: u64, longest_pid: u64, index: usize },
+        Duplicate { pid: u64, id: u64 },
+        NotProposed { pid: u64, id: u64 },
+        AckedNotDecided { id: u64 },
+        MisplacedStopSign { pid: u64, index: usize, config_id: u32 },
+    }
+
+    /// Checks the decided sequences of all replicas (including the ones removed by a
+    /// reconfiguration or crashed) against each other and against the clients' proposal log.
+    ///
+    /// Verifies that every sequence is a prefix of the longest one, that no proposal is decided
+    /// twice or decided without being proposed, that every acked proposal is decided and that the
+    /// stopsigns of consecutive configurations appear in order. A stopsign has to come after the
+    /// proposals that were acked before its reconfiguration was, and before the proposals that were
+    /// issued after that.
+    ///
+    /// The sequence of a compacted replica is only a suffix. It is aligned by its first entry
+    /// against the entries of the less compacted sequences, or appended to them if they do not
+    /// reach it yet. Acked proposals may then be missing, but not more of them than the largest
+    /// compacted index, and the stopsigns are only checked in uncompacted sequences.
+    pub fn check_total_order(
+        sequences: &HashMap<u64, DecidedSequence>,
+        log: &ProposalLog,
+    ) -> Result<(), TotalOrderViolation>
+    {
+        let mut ordered: Vec<(&u64, &DecidedSequence)> = sequences.iter().collect();
+        // the least compacted and longest sequences first, so the others can be aligned to them
+        ordered.sort_by_key(|(pid, s)| (s.compacted_idx, Reverse(s.entries.len()), **pid));
+        let longest_pid = ordered.first().map_or(0, |(pid, _)| **pid);
+        let mut merged: Vec<DecidedEntry> = vec![];
+        let mut max_compacted_idx = 0;
+        for (pid, sequence) in ordered {
+            let entries = &sequence.entries;
+            let offset = if sequence.compacted_idx == 0 || entries.is_empty() {
+                0
+            } else {
+                max_compacted_idx = max_compacted_idx.max(sequence.compacted_idx);
+                match merged.iter().position(|e| *e == entries[0]) {
+                    Some(offset) => offset,
+                    // the other sequences do not reach the suffix yet
+                    None if !entries.iter().any(|e| merged.contains(e)) => merged.len(),
+                    None => {
+                        return Err(TotalOrderViolation::NotPrefix {
+                            pid: *pid,
+                            longest_pid,
+                            index: 0,
+                        });
+                    },
+                }
+            };
+            let overlap = merged.get(offset..).unwrap_or(&[]);
+            if let Some(index) = entries.iter().zip(overlap).position(|(a, b)| a != b) {
+                return Err(TotalOrderViolation::NotPrefix { pid: *pid, longest_pid, index });
+            }
+            if entries.len() > overlap.len() {
+                merged.extend_from_slice(&entries[overlap.len()..]);
+            }
+            if sequence.compacted_idx == 0 {
+                check_stopsigns(*pid, entries, log)?;
+            }
+        }
+        let proposed: HashSet<u64> = log.proposed.iter().copied().collect();
+        let pending_at_crash: HashSet<u64> = log.pending_at_crash.iter().copied().collect();
+        let mut decided: HashSet<u64> = HashSet::with_capacity(merged.len());
+        for entry in &merged {
+            if let DecidedEntry::Normal(id) = entry {
+                if !proposed.contains(id) {
+                    return Err(TotalOrderViolation::NotProposed { pid: longest_pid, id: *id });
+                }
+                if !decided.insert(*id) && !pending_at_crash.contains(id) {
+                    return Err(TotalOrderViolation::Duplicate { pid: longest_pid, id: *id });
+                }
+            }
+        }
+        // every missing acked proposal has to fit into a compacted log index
+        let missing: Vec<&u64> = log.acked.iter().filter(|id| !decided.contains(id)).collect();
+        match missing.first() {
+            Some(id) if missing.len() as u64 > max_compacted_idx => {
+                Err(TotalOrderViolation::AckedNotDecided { id: **id })
+            },
+            _ => Ok(()),
+        }
+    }
+
+    fn check_stopsigns(
+        pid: u64,
+        sequence: &[DecidedEntry],
+        log: &ProposalLog,
+    ) -> Result<(), TotalOrderViolation>
+    {
+        let positions: HashMap<u64, usize> = sequence
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| match entry {
+                DecidedEntry::Normal(id) => Some((*id, index)),
+                DecidedEntry::StopSign(_) => None,
+            })
+            .collect();
+        let stopsigns: Vec<(usize, u32)> = sequence
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| match entry {
+                DecidedEntry::StopSign(config_id) => Some((index, *config_id)),
+                DecidedEntry::Normal(_) => None,
+            })
+            .collect();
+        let mut prev_config = 0;
+        for (index, config_id) in &stopsigns {
+            if *config_id != prev_config + 1 {
+                return Err(TotalOrderViolation::MisplacedStopSign {
+                    pid,
+                    index: *index,
+                    config_id: *config_id,
+                });
+            }
+            prev_config = *config_id;
+        }
+        for ((index, config_id), (num_acked, latest)) in stopsigns.iter().zip(&log.reconfigs) {
+            // a log that claims more acked proposals than it holds can not place the stopsign
+            let acked_after = log.acked.get(..*num_acked).map_or(true, |acked| {
+                acked.iter().any(|id| positions.get(id).map_or(true, |i| i > index))
+            });
+            let issued_before = sequence[..*index].iter().any(|e| match e {
+                DecidedEntry::Normal(id) => id > latest,
+                DecidedEntry::StopSign(_) => false,
+            });
+            if acked_after || issued_before {
+                return Err(TotalOrderViolation::MisplacedStopSign {
+                    pid,
+                    index: *index,
+                    config_id: *config_id,
+                });
+            }
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -893,8 +1076,8 @@ mod tests {
             println!("Got ping_pong req: {}", p.number_of_messages);
             let bench = benchmark::BenchmarkFactory::ping_pong(self);
             let f = benchmark_runner::run_async(move || match bench {
-                Ok(b) => b.run(Box::new(p)).into(),
-                Err(e) => Err(BenchmarkError::NotImplemented(e)).into(),
+                Ok(b) => b.run(Box::new(p)),
+                Err(e) => Err::<Vec<f64>, _>(BenchmarkError::NotImplemented(e)).into(),
             })
             .map_err(|e| {
                 println!("Converting benchmark error into grpc error: {:?}", e);
@@ -921,8 +1104,8 @@ mod tests {
             println!("Got req: {:?}", p);
             let bench = benchmark::BenchmarkFactory::throughput_ping_pong(self);
             let f = benchmark_runner::run_async(move || match bench {
-                Ok(b) => b.run(Box::new(p)).into(),
-                Err(e) => Err(BenchmarkError::NotImplemented(e)).into(),
+                Ok(b) => b.run(Box::new(p)),
+                Err(e) => Err::<Vec<f64>, _>(BenchmarkError::NotImplemented(e)).into(),
             })
             .map_err(|e| {
                 println!("Converting benchmark error into grpc error: {:?}", e);
@@ -967,8 +1150,8 @@ mod tests {
             println!("Got fibonacci req: {:?}", p);
             let bench = benchmark::BenchmarkFactory::fibonacci(self);
             let f = benchmark_runner::run_async(move || match bench {
-                Ok(b) => b.run(Box::new(p)).into(),
-                Err(e) => Err(BenchmarkError::NotImplemented(e)).into(),
+                Ok(b) => b.run(Box::new(p)),
+                Err(e) => Err::<Vec<f64>, _>(BenchmarkError::NotImplemented(e)).into(),
             })
             .map_err(|e| {
                 println!("Converting benchmark error into grpc error: {:?}", e);
@@ -986,8 +1169,8 @@ mod tests {
             println!("Got chameneos req: {:?}", p);
             let bench = benchmark::BenchmarkFactory::chameneos(self);
             let f = benchmark_runner::run_async(move || match bench {
-                Ok(b) => b.run(Box::new(p)).into(),
-                Err(e) => Err(BenchmarkError::NotImplemented(e)).into(),
+                Ok(b) => b.run(Box::new(p)),
+                Err(e) => Err::<Vec<f64>, _>(BenchmarkError::NotImplemented(e)).into(),
             })
             .map_err(|e| {
                 println!("Converting benchmark error into grpc error: {:?}", e);
@@ -1005,8 +1188,8 @@ mod tests {
             println!("Got APSP req: {:?}", p);
             let bench = benchmark::BenchmarkFactory::all_pairs_shortest_path(self);
             let f = benchmark_runner::run_async(move || match bench {
-                Ok(b) => b.run(Box::new(p)).into(),
-                Err(e) => Err(BenchmarkError::NotImplemented(e)).into(),
+                Ok(b) => b.run(Box::new(p)),
+                Err(e) => Err::<Vec<f64>, _>(BenchmarkError::NotImplemented(e)).into(),
             })
             .map_err(|e| {
                 println!("Converting benchmark error into grpc error: {:?}", e);
@@ -1027,4 +1210,168 @@ mod tests {
         let runner = TestFactory {};
         test_local_implementation(runner);
     }
+
+    #[test]
+    fn test_total_order_checker() {
+        use std::collections::HashMap;
+        use test_utils::{
+            check_total_order, DecidedEntry::*, DecidedSequence, ProposalLog, TotalOrderViolation,
+        };
+
+        let log = ProposalLog {
+            proposed:  vec![1, 2, 3, 4],
+            acked:     vec![1, 2, 3],
+            reconfigs: vec![(2, 2)],
+            ..ProposalLog::default()
+        };
+        let mut sequences: HashMap<u64, DecidedSequence> = HashMap::new();
+        sequences.insert(1, vec![Normal(1), Normal(2), StopSign(1), Normal(3)].into());
+        // 2 was removed by the reconfiguration
+        sequences.insert(2, vec![Normal(1), Normal(2), StopSign(1)].into());
+        sequences.insert(3, vec![Normal(1), Normal(2), StopSign(1), Normal(3), Normal(4)].into());
+        assert_eq!(check_total_order(&sequences, &log), Ok(()));
+
+        let mut diverged = sequences.clone();
+        diverged.insert(2, vec![Normal(2), Normal(1)].into());
+        assert_eq!(
+            check_total_order(&diverged, &log),
+            Err(TotalOrderViolation::NotPrefix { pid: 2, longest_pid: 3, index: 0 })
+        );
+
+        let mut duplicated: HashMap<u64, DecidedSequence> = HashMap::new();
+        duplicated.insert(1, vec![Normal(1), Normal(2), Normal(3), Normal(1)].into());
+        assert_eq!(
+            check_total_order(&duplicated, &log),
+            Err(TotalOrderViolation::Duplicate { pid: 1, id: 1 })
+        );
+
+        let mut invented: HashMap<u64, DecidedSequence> = HashMap::new();
+        invented.insert(1, vec![Normal(1), Normal(2), Normal(3), Normal(5)].into());
+        assert_eq!(
+            check_total_order(&invented, &log),
+            Err(TotalOrderViolation::NotProposed { pid: 1, id: 5 })
+        );
+
+        let mut missing: HashMap<u64, DecidedSequence> = HashMap::new();
+        missing.insert(1, vec![Normal(1), Normal(3)].into());
+        assert_eq!(
+            check_total_order(&missing, &log),
+            Err(TotalOrderViolation::AckedNotDecided { id: 2 })
+        );
+
+        let mut stopsigns: HashMap<u64, DecidedSequence> = HashMap::new();
+        stopsigns
+            .insert(1, vec![Normal(1), StopSign(1), Normal(2), StopSign(3), Normal(3)].into());
+        assert_eq!(
+            check_total_order(&stopsigns, &log),
+            Err(TotalOrderViolation::MisplacedStopSign { pid: 1, index: 3, config_id: 3 })
+        );
+
+        // 2 was acked before the reconfiguration was, so it has to be decided before the stopsign
+        let mut acked_late: HashMap<u64, DecidedSequence> = HashMap::new();
+        acked_late.insert(1, vec![Normal(1), StopSign(1), Normal(2), Normal(3)].into());
+        assert_eq!(
+            check_total_order(&acked_late, &log),
+            Err(TotalOrderViolation::MisplacedStopSign { pid: 1, index: 1, config_id: 1 })
+        );
+
+        // 3 was issued after the reconfiguration was decided, so it can not be decided before it
+        let mut issued_late: HashMap<u64, DecidedSequence> = HashMap::new();
+        issued_late.insert(1, vec![Normal(1), Normal(2), Normal(3), StopSign(1)].into());
+        assert_eq!(
+            check_total_order(&issued_late, &log),
+            Err(TotalOrderViolation::MisplacedStopSign { pid: 1, index: 3, config_id: 1 })
+        );
+
+        // the proposals of a second client are checked once its log is merged into the primary's
+        let primary = ProposalLog {
+            proposed:  vec![1, 2],
+            acked:     vec![1, 2],
+            reconfigs: vec![],
+            ..ProposalLog::default()
+        };
+        let mut merged = primary.clone();
+        merged.merge(ProposalLog {
+            proposed:  vec![1 << 48, (1 << 48) + 1],
+            acked:     vec![1 << 48],
+            reconfigs: vec![],
+            ..ProposalLog::default()
+        });
+        let mut interleaved: HashMap<u64, DecidedSequence> = HashMap::new();
+        interleaved.insert(1, vec![Normal(1), Normal(1 << 48), Normal(2)].into());
+        interleaved.insert(2, vec![Normal(1), Normal(1 << 48)].into());
+        assert_eq!(
+            check_total_order(&interleaved, &primary),
+            Err(TotalOrderViolation::NotProposed { pid: 1, id: 1 << 48 })
+        );
+        assert_eq!(check_total_order(&interleaved, &merged), Ok(()));
+        let mut unacked: HashMap<u64, DecidedSequence> = HashMap::new();
+        unacked.insert(1, vec![Normal(1), Normal(2), Normal((1 << 48) + 1)].into());
+        assert_eq!(
+            check_total_order(&unacked, &merged),
+            Err(TotalOrderViolation::AckedNotDecided { id: 1 << 48 })
+        );
+
+        // a log that claims more acked proposals before a reconfiguration than it holds
+        let truncated = ProposalLog {
+            proposed:  vec![1, 2],
+            acked:     vec![1, 2],
+            reconfigs: vec![(5, 2)],
+            ..ProposalLog::default()
+        };
+        let mut reconfigured: HashMap<u64, DecidedSequence> = HashMap::new();
+        reconfigured.insert(1, vec![Normal(1), Normal(2), StopSign(1)].into());
+        assert_eq!(
+            check_total_order(&reconfigured, &truncated),
+            Err(TotalOrderViolation::MisplacedStopSign { pid: 1, index: 2, config_id: 1 })
+        );
+
+        // the proposals pending at a crashed leader may be decided again after they were retried
+        let mut crashed = ProposalLog {
+            proposed:  vec![1, 2, 3],
+            acked:     vec![1, 2, 3],
+            reconfigs: vec![],
+            ..ProposalLog::default()
+        };
+        let mut retried: HashMap<u64, DecidedSequence> = HashMap::new();
+        retried.insert(1, vec![Normal(1), Normal(2), Normal(3), Normal(2)].into());
+        retried.insert(2, DecidedSequence::default()); // still crashed
+        assert_eq!(
+            check_total_order(&retried, &crashed),
+            Err(TotalOrderViolation::Duplicate { pid: 1, id: 2 })
+        );
+        crashed.pending_at_crash = vec![2];
+        assert_eq!(check_total_order(&retried, &crashed), Ok(()));
+
+        // compacted replicas only return the suffix after their compacted index
+        let compacted_log = ProposalLog {
+            proposed:  vec![1, 2, 3, 4, 5, 6],
+            acked:     vec![1, 2, 3, 4, 5],
+            reconfigs: vec![],
+            ..ProposalLog::default()
+        };
+        let suffix = |compacted_idx, entries| DecidedSequence { compacted_idx, entries };
+        let mut compacted: HashMap<u64, DecidedSequence> = HashMap::new();
+        compacted.insert(1, suffix(2, vec![Normal(3), Normal(4), Normal(5)]));
+        compacted.insert(2, suffix(4, vec![Normal(5), Normal(6)]));
+        compacted.insert(3, vec![Normal(1), Normal(2)].into()); // lagging behind
+        assert_eq!(check_total_order(&compacted, &compacted_log), Ok(()));
+
+        let mut diverged_suffix = compacted.clone();
+        diverged_suffix.insert(2, suffix(4, vec![Normal(6), Normal(5)]));
+        assert_eq!(
+            check_total_order(&diverged_suffix, &compacted_log),
+            Err(TotalOrderViolation::NotPrefix { pid: 2, longest_pid: 3, index: 0 })
+        );
+
+        // 1, 2 and 3 can not all have been in the two compacted entries
+        let mut lost: HashMap<u64, DecidedSequence> = HashMap::new();
+        lost.insert(1, suffix(2, vec![Normal(4), Normal(5)]));
+        assert_eq!(
+            check_total_order(&lost, &compacted_log),
+            Err(TotalOrderViolation::AckedNotDecided { id: 1 })
+        );
+        lost.insert(1, suffix(3, vec![Normal(4), Normal(5)]));
+        assert_eq!(check_total_order(&lost, &compacted_log), Ok(()));
+    }
 }
\ No newline at end of file
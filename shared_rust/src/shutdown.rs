@@ -0,0 +1,201 @@
+//! Process-wide coordination of a graceful shutdown.
+//!
+//! Once a shutdown has been requested no new benchmarks are accepted.
+//! Benchmarks that are already running are allowed to finish,
+//! unless the shutdown was forced, in which case they are cancelled at the next iteration boundary.
+//!
+//! Every benchmark owns the Kompact, Actix or Riker systems it runs on
+//! and shuts them down (and writes its results) in its final cleanup.
+//! The process therefore only exits once no benchmark is active anymore,
+//! except if a forced shutdown could not get them to finish within `FORCE_TIMEOUT`.
+use slog::{error, info, warn};
+use std::{
+    io::Write,
+    process,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Exit code used when the process stopped without interrupting anything.
+pub const EXIT_OK: i32 = 0;
+/// Exit code used when a running benchmark was cancelled (or abandoned) during shutdown.
+pub const EXIT_INTERRUPTED: i32 = 2;
+
+/// How long a forced shutdown waits for cancelled benchmarks to clean up, before giving up on them.
+pub const FORCE_TIMEOUT: Duration = Duration::from_secs(30);
+/// Time given to in-flight RPC responses before the process exits.
+const GRACE_PERIOD: Duration = Duration::from_millis(500);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+static GLOBAL: ShutdownState = ShutdownState::new();
+
+struct ShutdownState {
+    requested:   AtomicBool,
+    forced:      AtomicBool,
+    interrupted: AtomicBool,
+    active:      AtomicUsize,
+}
+
+impl ShutdownState {
+    const fn new() -> ShutdownState {
+        ShutdownState {
+            requested:   AtomicBool::new(false),
+            forced:      AtomicBool::new(false),
+            interrupted: AtomicBool::new(false),
+            active:      AtomicUsize::new(0),
+        }
+    }
+
+    fn request(&self, force: bool) {
+        if force {
+            self.forced.store(true, Ordering::SeqCst);
+        }
+        self.requested.store(true, Ordering::SeqCst);
+    }
+
+    fn is_requested(&self) -> bool { self.requested.load(Ordering::SeqCst) }
+
+    fn is_cancelled(&self) -> bool { self.forced.load(Ordering::SeqCst) }
+
+    fn mark_interrupted(&self) { self.interrupted.store(true, Ordering::SeqCst); }
+
+    fn exit_code(&self) -> i32 {
+        if self.interrupted.load(Ordering::SeqCst) {
+            EXIT_INTERRUPTED
+        } else {
+            EXIT_OK
+        }
+    }
+
+    fn try_begin(&'static self) -> Option<ActiveGuard> {
+        self.active.fetch_add(1, Ordering::SeqCst);
+        let guard = ActiveGuard { state: self };
+        if self.is_requested() {
+            None // dropping the guard deregisters again
+        } else {
+            Some(guard)
+        }
+    }
+
+    fn num_active(&self) -> usize { self.active.load(Ordering::SeqCst) }
+}
+
+/// Marks a benchmark as active until dropped.
+pub struct ActiveGuard {
+    state: &'static ShutdownState,
+}
+impl Drop for ActiveGuard {
+    fn drop(&mut self) { self.state.active.fetch_sub(1, Ordering::SeqCst); }
+}
+
+pub fn request(force: bool) { GLOBAL.request(force) }
+
+pub fn is_requested() -> bool { GLOBAL.is_requested() }
+
+/// Running benchmarks should stop at the next opportunity.
+pub fn is_cancelled() -> bool { GLOBAL.is_cancelled() }
+
+pub fn mark_interrupted() { GLOBAL.mark_interrupted() }
+
+pub fn exit_code() -> i32 { GLOBAL.exit_code() }
+
+/// Registers a new benchmark run, unless a shutdown has already been requested.
+pub fn try_begin() -> Option<ActiveGuard> { GLOBAL.try_begin() }
+
+/// Blocks until no benchmark is active anymore.
+///
+/// Forced shutdowns stop waiting after `FORCE_TIMEOUT` and count as interrupted.
+pub fn await_idle() {
+    let start = Instant::now();
+    while GLOBAL.num_active() > 0 {
+        if is_cancelled() && start.elapsed() > FORCE_TIMEOUT {
+            error!(
+                slog_scope::logger(),
+                "{} benchmark(s) did not clean up within {:?} of a forced shutdown.",
+                GLOBAL.num_active(),
+                FORCE_TIMEOUT
+            );
+            mark_interrupted();
+            return;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Flushes the log and the standard streams and exits with `exit_code()`,
+/// after a short grace period.
+///
+/// Callers must have waited for active benchmarks (see `await_idle`),
+/// since their systems are not shut down anymore after this.
+pub fn exit() -> ! {
+    thread::sleep(GRACE_PERIOD);
+    let code = exit_code();
+    let active = GLOBAL.num_active();
+    let logger = slog_scope::logger();
+    if active > 0 {
+        warn!(logger, "Abandoning {} benchmark(s) that are still active.", active);
+    }
+    info!(logger, "Shutting down with exit code {}.", code);
+    let _ = std::io::stdout().flush();
+    let _ = std::io::stderr().flush();
+    process::exit(code);
+}
+
+/// Waits for active benchmarks in the background and then exits the process.
+pub fn exit_when_idle() {
+    thread::Builder::new()
+        .name("shutdown".to_string())
+        .spawn(|| {
+            await_idle();
+            exit();
+        })
+        .expect("shutdown thread");
+}
+
+/// Exits the process as interrupted, if it hasn't shut down by itself
+/// within `FORCE_TIMEOUT` after the active benchmarks finished cleaning up.
+///
+/// The benchmarks themselves are only abandoned,
+/// if they don't clean up within `FORCE_TIMEOUT` either.
+pub fn exit_after_timeout() {
+    thread::Builder::new()
+        .name("shutdown-watchdog".to_string())
+        .spawn(|| {
+            await_idle();
+            thread::sleep(FORCE_TIMEOUT);
+            error!(
+                slog_scope::logger(),
+                "Process did not shut down within {:?}, exiting anyway.", FORCE_TIMEOUT
+            );
+            mark_interrupted();
+            exit();
+        })
+        .expect("shutdown watchdog thread");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shutdown_rejects_new_benchmarks() {
+        // leaked, so it doesn't interfere with the process-wide state other tests use
+        let state: &'static ShutdownState = Box::leak(Box::new(ShutdownState::new()));
+        assert!(!state.is_requested());
+        let guard = state.try_begin().expect("Should accept before shutdown");
+        assert_eq!(1, state.num_active());
+        state.request(false);
+        assert!(state.is_requested());
+        assert!(!state.is_cancelled());
+        assert!(state.try_begin().is_none(), "Should reject after shutdown");
+        assert_eq!(1, state.num_active());
+        drop(guard);
+        assert_eq!(0, state.num_active());
+        assert_eq!(EXIT_OK, state.exit_code());
+        state.request(true);
+        assert!(state.is_cancelled());
+        state.mark_interrupted();
+        assert_eq!(EXIT_INTERRUPTED, state.exit_code());
+    }
+}
@@ -117,6 +117,10 @@ class BenchmarkMaster(val runnerPort: Int, val masterPort: Int, val waitFor: Int
       val b = benchmarks.atomicBroadcast;
       runBenchmark(b, request)
     }
+    override def replicatedKV(request: ReplicatedKVRequest): Future[TestResult] = queueIfNotReady {
+      val b = benchmarks.replicatedKV;
+      runBenchmark(b, request)
+    }
 
     override def shutdown(request: ShutdownRequest): Future[ShutdownAck] = {
       logger.info(s"Got shutdown request with force=${request.force}");
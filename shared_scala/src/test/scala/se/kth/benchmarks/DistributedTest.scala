@@ -285,6 +285,7 @@ object TestFactory extends BenchmarkFactory {
   override def chameneos(): Benchmark = TestLocalBench;
   override def fibonacci: Benchmark = TestLocalBench;
   override def atomicBroadcast(): DistributedBenchmark = TestDistributedBench;
+  override def replicatedKV(): DistributedBenchmark = TestDistributedBench;
 }
 
 class FailFactory(val masterFailAt: Option[Stage], val clientFailAt: Option[Stage]) extends BenchmarkFactory {
@@ -303,4 +304,5 @@ class FailFactory(val masterFailAt: Option[Stage], val clientFailAt: Option[Stag
   override def chameneos(): Benchmark = ???;
   override def fibonacci: Benchmark = ???;
   override def atomicBroadcast(): DistributedBenchmark = ???;
+  override def replicatedKV(): DistributedBenchmark = ???;
 }
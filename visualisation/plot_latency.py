@@ -6,12 +6,13 @@ import matplotlib.pyplot as plt
 import numpy as np
 import sys
 import argparse
+import json
 import os
 
 parser = argparse.ArgumentParser()
 
-parser.add_argument('-s', required=True, help='Directory of raw results')
-parser.add_argument('-n', nargs='?', default=-1, help='Number of lines to read per file')
+parser.add_argument('-s', required=True, help='meta_results_path of the experiments')
+parser.add_argument('-n', nargs='?', default=-1, help='Number of proposals to read per experiment')
 parser.add_argument('-t', nargs='?', default='./', help='Output directory')
 
 legends = []
@@ -20,25 +21,27 @@ all_plots = []
 args = parser.parse_args()
 print("Plotting with args:",args)
 n = int(args.n)
-data_files = [f for f in os.listdir(args.s) if f.endswith('.data')]
-for filename in data_files :
-    count = 0
-    x = []
-    y = []
+# each experiment has one JSON record per iteration in results/ and the latency of each proposal in latency/
+results_dir = os.path.join(args.s, 'results')
+latency_dir = os.path.join(args.s, 'latency')
+result_files = [f for f in os.listdir(results_dir) if f.endswith('.jsonl')]
+for filename in result_files :
+    latency_file = os.path.join(latency_dir, filename)
+    if not os.path.exists(latency_file):
+        print("No latencies for", filename, ", skipping")
+        continue
     print("Reading", filename, "...")
-    for line in open(args.s + filename, 'r'):
-        count += 1
-        #print(line)
-        x.append(count)
-        y.append(float(line)/1000)
-        if count == n:
-            break
+    with open(os.path.join(results_dir, filename), 'r') as f:
+        record = json.loads(f.readline())
+    with open(latency_file, 'r') as f:
+        # only the first iteration of an experiment is plotted
+        latencies = json.loads(f.readline())
+    y = [l/1000 for l in latencies['latency_micros']]
+    if n >= 0:
+        y = y[:n]
+    x = list(range(1, len(y) + 1))
     all_plots.append((x, y))
-    if "paxos" in filename:
-    	legends.append("paxos")
-    else:
-    	legends.append("raft")
-    #legends.append(filename)
+    legends.append(record['params']['algorithm'])
     
 print("Plotting",len(all_plots),"series")    
 for (x, y) in all_plots: